@@ -1,22 +1,114 @@
+import * as http from 'http';
 import WebSocket, { WebSocketServer } from 'ws';
+import { handleGifSearch } from './gifs';
+import { fetchPreview, findUrls } from './preview';
+import { URL } from 'url';
+import { randomBytes, timingSafeEqual } from 'crypto';
 
 const PORT = process.env.PORT ? parseInt(process.env.PORT) : 8080;
+const DEFAULT_ROOM = 'general';
+// Messages kept per room for clients that join later.
+const HISTORY_LIMIT = 1000;
+// Page size for history requests that don't ask for a valid one.
+const HISTORY_PAGE = 50;
+// Largest page a history request may ask for.
+const HISTORY_PAGE_MAX = 200;
+// Links per message that get a preview pushed to the room.
+const MAX_PREVIEWS = 2;
+// Names new rooms may have; clients check the same rule before asking.
+const ROOM_NAME = /^[a-z0-9-]{3,32}$/;
+const MAX_DESCRIPTION = 200;
+// Matches per page of a user search.
+const SEARCH_PAGE = 20;
+// Different people it takes reporting a message to hide it until a moderator looks.
+const REPORT_THRESHOLD = 3;
+const REPORT_CATEGORIES = ['spam', 'harassment', 'inappropriate'];
+// Nicks given roles, comma-separated.
+const ADMINS = (process.env.ADMINS ?? '').split(',').map((nick) => nick.trim()).filter((nick) => nick);
+const MODERATORS = (process.env.MODERATORS ?? '').split(',').map((nick) => nick.trim()).filter((nick) => nick);
+// Nicks aren't authenticated, so those given roles can only be taken by registering
+// with this secret; with none set, nobody can take them.
+const STAFF_TOKEN = process.env.STAFF_TOKEN ?? '';
+// Longest ban an admin can hand out.
+const MAX_BAN_MINUTES = 7 * 24 * 60;
+type Role = 'user' | 'moderator' | 'admin';
+const ROLE_RANK: { [role in Role]: number } = { user: 0, moderator: 1, admin: 2 };
 interface User {
     ws: WebSocket;
     nick: String;
     isAlive: boolean;
+    room: String;
+    status: String;
+    // DiceBear style of the avatar; clients fall back to their default.
+    avatar?: String;
+    // Key for encrypting direct messages to this user, sent on 'register'.
+    publicKey?: String;
+    role: Role;
+    // Whether they registered with STAFF_TOKEN, so may use the nicks given roles.
+    staff: boolean;
+    // IANA time zone and BCP 47 locale of their browser, sent on 'register'.
+    timezone?: String;
+    locale?: String;
+}
+
+interface Room {
+    description: string;
+    // Only listed to, and joinable by, those with the invite.
+    private: boolean;
+    maxMembers?: number;
+    invite?: string;
 }
 
 interface Message {
     messageType: String;
     data: String;
     dataArray: String[];
+    to?: String;
+    room?: String;
+    // Id of the room message a 'message' frame replies to.
+    replyTo?: number | null;
+    publicKey?: String;
+    // Invite to the private room a 'register' frame joins.
+    invite?: String;
+    timezone?: String;
+    locale?: String;
+    // Whether the data of a 'directmessage' frame is encrypted.
+    encrypted?: boolean;
+    // STAFF_TOKEN, on a 'register' frame for a nick given a role.
+    token?: String;
 }
 
 let users: User[] = [];
+const rooms = new Map<String, Room>(
+    [DEFAULT_ROOM, 'random', 'help'].map((name): [String, Room] => [name, { description: '', private: false }])
+);
+// Seeded from the clock so ids stay unique across server restarts.
+let nextMessageId = Date.now();
+// message id -> emoji -> nicks that reacted with it
+const reactions = new Map<number, Map<string, Set<String>>>();
+// room -> latest messages as serialized MessageData, oldest first; hidden ones
+// keep what they said in `original`, for moderators only. `sender` is the
+// connection it came from, which a rename doesn't change.
+const roomHistory = new Map<String, { id: number; data: string; original?: string; sender?: User }[]>();
+// room -> ids of its pinned messages, in the order they were pinned
+const pins = new Map<String, number[]>();
+// message id -> reporter's connection -> category, so a rename doesn't count twice
+const reports = new Map<number, Map<User, string>>();
+// nick -> room -> id of the last message they have read there
+const readCursors = new Map<String, Map<String, number>>();
+// nick -> when their ban runs out, in ms since the epoch
+const bans = new Map<String, number>();
 
-console.log(`Listening on port ${PORT}`);
-const wss = new WebSocketServer({ port: PORT });
+// Plain HTTP on the same port is only for the GIF search proxy.
+const server = http.createServer((req, res) => {
+    if (req.method === 'GET' && new URL(req.url ?? '', 'http://localhost').pathname === '/gifs') {
+        handleGifSearch(req, res);
+        return;
+    }
+    res.writeHead(404).end();
+});
+const wss = new WebSocketServer({ server });
+server.listen(PORT, () => console.log(`Listening on port ${PORT}`));
 
 wss.on('connection', (ws: WebSocket) => {
     console.log('ws connected');
@@ -26,40 +118,648 @@ wss.on('connection', (ws: WebSocket) => {
         try {
             const parsed_data: Message = JSON.parse(raw_data);
             switch (parsed_data.messageType) {
-                case 'register':
-                    users.push({ ws, nick: parsed_data.data, isAlive: true });
-                    broadcast(JSON.stringify({ messageType: 'users', dataArray: users.map((u) => u.nick) }));
+                case 'register': {
+                    // Registering again from the same socket is how clients switch rooms.
+                    const room = parsed_data.room || DEFAULT_ROOM;
+                    const settings = rooms.get(room);
+                    if (!settings) {
+                        ws.send(roomErrorFrame(room, 'not_found'));
+                        break;
+                    }
+                    if (settings.private && parsed_data.invite !== settings.invite) {
+                        ws.send(roomErrorFrame(room, 'private'));
+                        break;
+                    }
+                    const members = users.filter((u) => u.room === room && u.ws !== ws).length;
+                    if (settings.maxMembers !== undefined && members >= settings.maxMembers) {
+                        ws.send(roomErrorFrame(room, 'full'));
+                        break;
+                    }
+                    const banned = banMinutesLeft(parsed_data.data);
+                    if (banned > 0) {
+                        ws.send(moderationFrame('userbanned', parsed_data.data, banned));
+                        ws.close();
+                        break;
+                    }
+                    const existing = users.find((u) => u.ws === ws);
+                    const staff = isStaffToken(parsed_data.token);
+                    const role = roleOf(parsed_data.data);
+                    if (role !== 'user' && !staff) {
+                        ws.send(nameReservedFrame(parsed_data.data, existing?.nick));
+                        break;
+                    }
+                    const publicKey = typeof parsed_data.publicKey === 'string' ? parsed_data.publicKey : undefined;
+                    const user: User = existing ?? { ws, nick: parsed_data.data, isAlive: true, room, status: 'online', role, staff };
+                    if (existing) {
+                        const previous = existing.room;
+                        existing.nick = parsed_data.data;
+                        existing.role = role;
+                        existing.staff = staff;
+                        existing.room = room;
+                        if (previous !== room) {
+                            broadcastUsers(previous);
+                        }
+                    } else {
+                        users.push(user);
+                    }
+                    user.timezone = shortString(parsed_data.timezone);
+                    user.locale = shortString(parsed_data.locale);
+                    // Keys are for direct messages, which cross rooms, so everyone hears of them.
+                    if (publicKey && publicKey !== user.publicKey) {
+                        user.publicKey = publicKey;
+                        users.filter((u) => u.ws !== ws).forEach((u) => u.ws.send(keyFrame(user)));
+                    }
+                    users.filter((u) => u.ws !== ws && u.publicKey).forEach((u) => ws.send(keyFrame(u)));
+                    ws.send(roomsFrame());
+                    ws.send(roomInfoFrame('roominfo', room, settings));
+                    // Before the users list, after which clients ask for the history it applies to.
+                    ws.send(readSyncFrame(user.nick));
+                    broadcastUsers(room);
+                    // Roles and time zones go out with profiles, which the newcomer only sends with an avatar of their own.
+                    if ((user.role !== 'user' || user.timezone) && !user.avatar) {
+                        broadcastToRoom(room, profileFrame(user));
+                    }
+                    // Users are listed as online unless told otherwise.
+                    users
+                        .filter((u) => u.room === room && u.status !== 'online')
+                        .forEach((u) => ws.send(presenceFrame(u)));
+                    users
+                        .filter((u) => u.room === room && (u.avatar || u.role !== 'user' || u.timezone))
+                        .forEach((u) => ws.send(profileFrame(u)));
+                    // Pins of messages that have since dropped out of the history go with them.
+                    const history = roomHistory.get(room) ?? [];
+                    (pins.get(room) ?? []).forEach((id) => {
+                        const entry = history.find((m) => m.id === id);
+                        if (entry) {
+                            ws.send(pinFrame(room, entry.data));
+                        }
+                    });
                     break;
+                }
                 case 'message':
                     const sender = users.find((u) => u.ws === ws);
                     if (sender) {
-                        broadcast(
+                        const id = nextMessageId++;
+                        const message = JSON.stringify({
+                            id,
+                            from: sender.nick,
+                            message: parsed_data.data,
+                            time: Date.now(),
+                            replyTo: typeof parsed_data.replyTo === 'number' ? parsed_data.replyTo : undefined,
+                        });
+                        const history = roomHistory.get(sender.room) ?? [];
+                        history.push({ id, data: message, sender });
+                        roomHistory.set(sender.room, history.slice(-HISTORY_LIMIT));
+                        broadcastToRoom(
+                            sender.room,
                             JSON.stringify({
                                 messageType: 'message',
+                                room: sender.room,
+                                data: message,
+                            })
+                        );
+                        const room = sender.room;
+                        findUrls(parsed_data.data as string, MAX_PREVIEWS).forEach((url) =>
+                            fetchPreview(url).then((preview) => {
+                                if (preview) {
+                                    broadcastToRoom(
+                                        room,
+                                        JSON.stringify({
+                                            messageType: 'linkpreview',
+                                            room,
+                                            data: JSON.stringify({ id, ...preview }),
+                                        })
+                                    );
+                                }
+                            })
+                        );
+                    }
+                    break;
+                case 'directmessage': {
+                    const sender = users.find((u) => u.ws === ws);
+                    const recipient = users.find((u) => u.nick === parsed_data.to);
+                    if (sender && recipient) {
+                        const payload = JSON.stringify({
+                            messageType: 'directmessage',
+                            to: recipient.nick,
+                            data: JSON.stringify({
+                                from: sender.nick,
+                                message: parsed_data.data,
+                                time: Date.now(),
+                                encrypted: parsed_data.encrypted === true || undefined,
+                            }),
+                        });
+                        recipient.ws.send(payload);
+                        if (recipient.ws !== ws) {
+                            ws.send(payload);
+                        }
+                    }
+                    break;
+                }
+                case 'calloffer':
+                case 'callanswer':
+                case 'icecandidate':
+                case 'callend': {
+                    // Only passed on, to the other side of the call.
+                    const sender = users.find((u) => u.ws === ws);
+                    const recipient = users.find((u) => u.nick === parsed_data.to);
+                    if (sender && recipient && recipient !== sender) {
+                        recipient.ws.send(
+                            JSON.stringify({
+                                messageType: parsed_data.messageType,
                                 data: JSON.stringify({
+                                    ...JSON.parse((parsed_data.data as string) || '{}'),
                                     from: sender.nick,
-                                    message: parsed_data.data,
-                                    time: Date.now(),
+                                }),
+                            }),
+                        );
+                    }
+                    break;
+                }
+                case 'createroom': {
+                    const { name, description, private: isPrivate, max_members } = JSON.parse(parsed_data.data as string);
+                    const valid =
+                        typeof name === 'string' &&
+                        ROOM_NAME.test(name) &&
+                        (description === undefined || (typeof description === 'string' && description.length <= MAX_DESCRIPTION)) &&
+                        (max_members === undefined || (Number.isInteger(max_members) && max_members >= 2));
+                    if (!valid) {
+                        ws.send(roomErrorFrame(String(name), 'invalid'));
+                    } else if (rooms.has(name)) {
+                        ws.send(roomErrorFrame(name, 'exists'));
+                    } else {
+                        const room: Room = {
+                            description: description ?? '',
+                            private: isPrivate === true,
+                            maxMembers: max_members,
+                            invite: isPrivate === true ? randomBytes(12).toString('hex') : undefined,
+                        };
+                        rooms.set(name, room);
+                        if (!room.private) {
+                            broadcast(roomsFrame());
+                        }
+                        // Only the creator gets the invite, to hand out.
+                        ws.send(roomInfoFrame('roomcreated', name, room));
+                    }
+                    break;
+                }
+                case 'reaction': {
+                    // Reacting again with the same emoji takes the reaction back.
+                    const reactor = users.find((u) => u.ws === ws);
+                    const { id, emoji } = JSON.parse(parsed_data.data as string);
+                    if (reactor && typeof id === 'number' && typeof emoji === 'string' && emoji) {
+                        const byEmoji = reactions.get(id) ?? new Map<string, Set<String>>();
+                        reactions.set(id, byEmoji);
+                        const nicks = byEmoji.get(emoji) ?? new Set<String>();
+                        if (nicks.has(reactor.nick)) {
+                            nicks.delete(reactor.nick);
+                        } else {
+                            nicks.add(reactor.nick);
+                        }
+                        if (nicks.size > 0) {
+                            byEmoji.set(emoji, nicks);
+                        } else {
+                            byEmoji.delete(emoji);
+                        }
+                        const summary: { [emoji: string]: String[] } = {};
+                        byEmoji.forEach((reacted, e) => {
+                            summary[e] = Array.from(reacted);
+                        });
+                        broadcastToRoom(
+                            reactor.room,
+                            JSON.stringify({
+                                messageType: 'reaction',
+                                room: reactor.room,
+                                data: JSON.stringify({ id, reactions: summary }),
+                            })
+                        );
+                    }
+                    break;
+                }
+                case 'edit': {
+                    // Only the sender may edit, and only messages still in the room's history.
+                    const editor = users.find((u) => u.ws === ws);
+                    const { id, message } = JSON.parse(parsed_data.data as string);
+                    const entry = editor && (roomHistory.get(editor.room) ?? []).find((m) => m.id === id);
+                    if (editor && entry && typeof message === 'string' && message) {
+                        const stored = JSON.parse(entry.data);
+                        if (stored.from === editor.nick && !stored.hidden) {
+                            entry.data = JSON.stringify({ ...stored, message, edited: true });
+                            broadcastToRoom(
+                                editor.room,
+                                JSON.stringify({
+                                    messageType: 'edit',
+                                    room: editor.room,
+                                    data: JSON.stringify({ id, message }),
+                                })
+                            );
+                        }
+                    }
+                    break;
+                }
+                case 'delete': {
+                    // A tombstone stays behind so the timeline doesn't shift. Admins may delete anyone's.
+                    const deleter = users.find((u) => u.ws === ws);
+                    const { id } = JSON.parse(parsed_data.data as string);
+                    const history = deleter ? roomHistory.get(deleter.room) ?? [] : [];
+                    const index = history.findIndex((m) => m.id === id);
+                    const stored = index >= 0 ? JSON.parse(history[index].data) : undefined;
+                    if (deleter && stored && (stored.from === deleter.nick || deleter.role === 'admin')) {
+                        history[index].original = undefined;
+                        history[index].data = JSON.stringify({
+                            ...stored,
+                            message: '',
+                            file: undefined,
+                            deleted: true,
+                        });
+                        reactions.delete(id);
+                        unpin(deleter.room, id);
+                        broadcastToRoom(
+                            deleter.room,
+                            JSON.stringify({
+                                messageType: 'delete',
+                                room: deleter.room,
+                                data: JSON.stringify({ id }),
+                            })
+                        );
+                    }
+                    break;
+                }
+                case 'report': {
+                    // Who reports is whoever is on this socket, whatever the frame says.
+                    const reporter = users.find((u) => u.ws === ws);
+                    const { message_id, category } = JSON.parse(parsed_data.data as string);
+                    const history = reporter ? roomHistory.get(reporter.room) ?? [] : [];
+                    const entry = history.find((m) => m.id === message_id);
+                    const stored = entry ? JSON.parse(entry.data) : undefined;
+                    // Nobody reports their own message, whatever they've been called since.
+                    const own = entry && (entry.sender === reporter || stored.from === reporter?.nick);
+                    if (!reporter || !entry || own || !REPORT_CATEGORIES.includes(category)) {
+                        break;
+                    }
+                    const byReporter = reports.get(message_id) ?? new Map<User, string>();
+                    byReporter.set(reporter, category);
+                    reports.set(message_id, byReporter);
+                    console.log(
+                        `report: ${reporter.nick} reported message ${message_id} by ${stored.from} in #${reporter.room} ` +
+                            `as ${category} (${byReporter.size}/${REPORT_THRESHOLD}): ${stored.message}`
+                    );
+                    if (byReporter.size >= REPORT_THRESHOLD && !stored.hidden) {
+                        // Only moderators get to see what it said from now on.
+                        entry.original = entry.data;
+                        entry.data = JSON.stringify({ ...stored, message: '', file: undefined, hidden: true });
+                        broadcastToRoom(
+                            reporter.room,
+                            JSON.stringify({
+                                messageType: 'messagehidden',
+                                room: reporter.room,
+                                data: JSON.stringify({ id: message_id }),
+                            })
+                        );
+                    }
+                    break;
+                }
+                case 'pin': {
+                    // Pinning and unpinning are for moderators and admins.
+                    const member = users.find((u) => u.ws === ws && isModerator(u));
+                    const { id } = JSON.parse(parsed_data.data as string);
+                    const entry = member && (roomHistory.get(member.room) ?? []).find((m) => m.id === id);
+                    const pinned = member ? pins.get(member.room) ?? [] : [];
+                    if (member && entry && !pinned.includes(id)) {
+                        pins.set(member.room, [...pinned, id]);
+                        broadcastToRoom(member.room, pinFrame(member.room, entry.data));
+                    }
+                    break;
+                }
+                case 'unpin': {
+                    const member = users.find((u) => u.ws === ws && isModerator(u));
+                    const { id } = JSON.parse(parsed_data.data as string);
+                    if (member) {
+                        unpin(member.room, id);
+                    }
+                    break;
+                }
+                case 'filechunk':
+                case 'audio': {
+                    // Pieces of a shared file or voice message go straight
+                    // to the room; they are too big to keep in the history.
+                    const sender = users.find((u) => u.ws === ws);
+                    if (sender) {
+                        const chunk = JSON.parse(parsed_data.data as string);
+                        broadcastToRoom(
+                            sender.room,
+                            JSON.stringify({
+                                messageType: parsed_data.messageType,
+                                room: sender.room,
+                                data: JSON.stringify({ ...chunk, from: sender.nick }),
+                            })
+                        );
+                    }
+                    break;
+                }
+                case 'presence': {
+                    const member = users.find((u) => u.ws === ws);
+                    const { status } = JSON.parse(parsed_data.data as string);
+                    if (member && typeof status === 'string') {
+                        member.status = status;
+                        broadcastToRoom(member.room, presenceFrame(member));
+                    }
+                    break;
+                }
+                case 'profileupdate': {
+                    const member = users.find((u) => u.ws === ws);
+                    const { name, avatar } = JSON.parse(parsed_data.data as string);
+                    if (member && typeof name === 'string' && typeof avatar === 'string' && banMinutesLeft(name) === 0) {
+                        if (roleOf(name) !== 'user' && !member.staff) {
+                            ws.send(nameReservedFrame(name, member.nick));
+                            break;
+                        }
+                        const previous = member.nick;
+                        member.nick = name;
+                        member.avatar = avatar;
+                        member.role = roleOf(name);
+                        broadcastToRoom(member.room, profileFrame(member, previous));
+                        if (previous !== name) {
+                            broadcastUsers(member.room);
+                        }
+                    }
+                    break;
+                }
+                case 'read': {
+                    const reader = users.find((u) => u.ws === ws);
+                    const { id } = JSON.parse(parsed_data.data as string);
+                    if (reader && typeof id === 'number') {
+                        const cursors = readCursors.get(reader.nick) ?? new Map<String, number>();
+                        if (id > (cursors.get(reader.room) ?? 0)) {
+                            cursors.set(reader.room, id);
+                            readCursors.set(reader.nick, cursors);
+                        }
+                        broadcastToRoom(
+                            reader.room,
+                            JSON.stringify({
+                                messageType: 'read',
+                                room: reader.room,
+                                data: JSON.stringify({ name: reader.nick, id }),
+                            })
+                        );
+                    }
+                    break;
+                }
+                case 'history': {
+                    const member = users.find((u) => u.ws === ws);
+                    if (member) {
+                        // Without a cursor this is the latest page.
+                        const { before, limit } = parsed_data.data ? JSON.parse(parsed_data.data as string) : ({} as any);
+                        const history = roomHistory.get(member.room) ?? [];
+                        const older = before === undefined ? history : history.filter((m) => m.id < before);
+                        const valid = Number.isInteger(limit) && limit >= 1;
+                        const page = older.slice(-(valid ? Math.min(limit, HISTORY_PAGE_MAX) : HISTORY_PAGE));
+                        ws.send(
+                            JSON.stringify({
+                                messageType: 'history',
+                                room: member.room,
+                                dataArray: page.map((m) => (isModerator(member) ? m.original ?? m.data : m.data)),
+                                data: JSON.stringify({ before, more: older.length > page.length }),
+                            })
+                        );
+                    }
+                    break;
+                }
+                case 'kick':
+                case 'ban': {
+                    const moderator = users.find((u) => u.ws === ws);
+                    const { target, duration_minutes } = JSON.parse(parsed_data.data as string);
+                    const banning = parsed_data.messageType === 'ban';
+                    if (!moderator || typeof target !== 'string' || !mayModerate(moderator, target, banning)) {
+                        break;
+                    }
+                    if (banning) {
+                        if (!Number.isInteger(duration_minutes) || duration_minutes < 1 || duration_minutes > MAX_BAN_MINUTES) {
+                            break;
+                        }
+                        bans.set(target, Date.now() + duration_minutes * 60 * 1000);
+                    }
+                    const targets = users.filter((u) => u.nick === target);
+                    const affected = new Set(targets.map((u) => u.room));
+                    // Told first, so the target hears why before being disconnected.
+                    affected.forEach((room) =>
+                        broadcastToRoom(
+                            room,
+                            moderationFrame(banning ? 'userbanned' : 'userkicked', target, banning ? duration_minutes : undefined, room)
+                        )
+                    );
+                    users = users.filter((u) => u.nick !== target);
+                    targets.forEach((u) => u.ws.close());
+                    affected.forEach((room) => broadcastUsers(room));
+                    console.log(`${moderator.nick} ${banning ? `banned ${target} for ${duration_minutes} minutes` : `kicked ${target}`}`);
+                    break;
+                }
+                case 'searchusers': {
+                    const searcher = users.find((u) => u.ws === ws);
+                    if (searcher) {
+                        const request = JSON.parse(parsed_data.data as string);
+                        const query = String(request.query ?? '');
+                        const offset = request.offset;
+                        const needle = query.trim().toLowerCase();
+                        const start = Number.isInteger(offset) && offset > 0 ? offset : 0;
+                        // Everyone connected, in any room; the same nick twice is the same person.
+                        const matches = [...new Set(users.map((u) => u.nick))]
+                            .filter((nick) => nick !== searcher.nick && nick.toLowerCase().includes(needle))
+                            .sort((a, b) => a.localeCompare(b as string));
+                        const page = needle ? matches.slice(start, start + SEARCH_PAGE) : [];
+                        ws.send(
+                            JSON.stringify({
+                                messageType: 'searchresults',
+                                data: JSON.stringify({
+                                    query,
+                                    offset: start,
+                                    users: page,
+                                    more: needle !== '' && matches.length > start + SEARCH_PAGE,
                                 }),
                             })
                         );
                     }
+                    break;
+                }
+                case 'ping':
+                    ws.send(JSON.stringify({ messageType: 'pong', data: parsed_data.data }));
+                    break;
+                case 'typing': {
+                    const typist = users.find((u) => u.ws === ws);
+                    if (typist) {
+                        broadcastToRoom(typist.room, JSON.stringify({ messageType: 'typing', room: typist.room, data: typist.nick }));
+                    }
+                    break;
+                }
             }
         } catch (e) {
             console.log('Error in message', e);
         }
     });
+
+    ws.on('close', () => {
+        const member = users.find((u) => u.ws === ws);
+        if (member) {
+            member.status = 'offline';
+            broadcastToRoom(member.room, presenceFrame(member));
+        }
+    });
 });
 
 const interval = setInterval(function ping() {
     const current_clients = Array.from(wss.clients);
     const updated_users = users.filter((u) => current_clients.includes(u.ws));
     if (updated_users.length !== users.length) {
+        const affected_rooms = new Set(users.filter((u) => !updated_users.includes(u)).map((u) => u.room));
         users = updated_users;
-        broadcast(JSON.stringify({ messageType: 'users', dataArray: users.map((u) => u.nick) }));
+        affected_rooms.forEach((room) => broadcastUsers(room));
     }
 }, 5000);
 
+// Public rooms only; private ones are known to those with an invite.
+const roomsFrame = () => {
+    const names: String[] = [];
+    rooms.forEach((room, name) => {
+        if (!room.private) {
+            names.push(name);
+        }
+    });
+    return JSON.stringify({ messageType: 'rooms', dataArray: names });
+};
+
+// A room's settings, with its invite for 'roomcreated' only.
+const roomInfoFrame = (messageType: 'roominfo' | 'roomcreated', name: String, room: Room) =>
+    JSON.stringify({
+        messageType,
+        room: name,
+        data: JSON.stringify({
+            name,
+            description: room.description,
+            private: room.private,
+            max_members: room.maxMembers,
+            invite: messageType === 'roomcreated' ? room.invite : undefined,
+        }),
+    });
+
+const roomErrorFrame = (room: String, reason: 'exists' | 'not_found' | 'invalid' | 'private' | 'full') =>
+    JSON.stringify({ messageType: 'roomerror', data: JSON.stringify({ room, reason }) });
+
+// Where `nick` had read up to in each room, for picking up there after reconnecting.
+const readSyncFrame = (nick: String) => {
+    const cursors: { [room: string]: number } = {};
+    (readCursors.get(nick) ?? new Map<String, number>()).forEach((id, room) => (cursors[room as string] = id));
+    return JSON.stringify({ messageType: 'readsync', data: JSON.stringify(cursors) });
+};
+
+const presenceFrame = (user: User) =>
+    JSON.stringify({
+        messageType: 'presence',
+        room: user.room,
+        data: JSON.stringify({ name: user.nick, status: user.status }),
+    });
+
+const profileFrame = (user: User, previous?: String) =>
+    JSON.stringify({
+        messageType: 'profileupdate',
+        room: user.room,
+        data: JSON.stringify({
+            previous,
+            name: user.nick,
+            avatar: user.avatar,
+            role: user.role,
+            timezone: user.timezone,
+            locale: user.locale,
+        }),
+    });
+
+// `value` if it's a string of a sensible length for a name like a time zone.
+const shortString = (value: unknown): String | undefined =>
+    typeof value === 'string' && value.length > 0 && value.length <= 64 ? value : undefined;
+
+const keyFrame = (user: User) =>
+    JSON.stringify({
+        messageType: 'publickey',
+        data: JSON.stringify({ name: user.nick, key: user.publicKey }),
+    });
+
+const pinFrame = (room: String, data: string) =>
+    JSON.stringify({
+        messageType: 'pin',
+        room,
+        data,
+    });
+
+// Tells the room when message `id` stops being pinned.
+const unpin = (room: String, id: number) => {
+    const pinned = pins.get(room) ?? [];
+    if (pinned.includes(id)) {
+        pins.set(room, pinned.filter((p) => p !== id));
+        broadcastToRoom(
+            room,
+            JSON.stringify({
+                messageType: 'unpin',
+                room,
+                data: JSON.stringify({ id }),
+            })
+        );
+    }
+};
+
+const roleOf = (nick: String): Role =>
+    ADMINS.includes(nick as string) ? 'admin' : MODERATORS.includes(nick as string) ? 'moderator' : 'user';
+
+// Moderators may kick, admins may also ban, and only those ranked below them.
+const mayModerate = (moderator: User, target: String, banning: boolean) => {
+    const rank = ROLE_RANK[moderator.role];
+    return rank >= ROLE_RANK[banning ? 'admin' : 'moderator'] && rank > ROLE_RANK[roleOf(target)];
+};
+
+// Whether `token` is STAFF_TOKEN, compared in constant time.
+const isStaffToken = (token: unknown) => {
+    if (!STAFF_TOKEN || typeof token !== 'string') {
+        return false;
+    }
+    const given = Buffer.from(token);
+    const expected = Buffer.from(STAFF_TOKEN);
+    return given.length === expected.length && timingSafeEqual(given, expected);
+};
+
+// Turns down `name`, which has a role, for lack of STAFF_TOKEN; `previous` is the nick kept, if any.
+const nameReservedFrame = (name: String, previous?: String) =>
+    JSON.stringify({ messageType: 'namereserved', data: JSON.stringify({ name, previous }) });
+
+const isModerator = (user: User) => ROLE_RANK[user.role] >= ROLE_RANK.moderator;
+
+// Whole minutes left of `nick`'s ban, 0 if they aren't banned.
+const banMinutesLeft = (nick: String) => {
+    const left = (bans.get(nick) ?? 0) - Date.now();
+    if (left <= 0) {
+        bans.delete(nick);
+        return 0;
+    }
+    return Math.ceil(left / (60 * 1000));
+};
+
+const moderationFrame = (messageType: 'userkicked' | 'userbanned', target: String, duration_minutes?: number, room?: String) =>
+    JSON.stringify({
+        messageType,
+        room,
+        data: JSON.stringify({ target, duration_minutes }),
+    });
+
+const broadcastUsers = (room: String) => {
+    const members = users.filter((u) => u.room === room).map((u) => u.nick);
+    broadcastToRoom(room, JSON.stringify({ messageType: 'users', room, dataArray: members }));
+};
+
+const broadcastToRoom = (room: String, data: any) => {
+    users.forEach((u) => {
+        if (u.room === room && u.ws.readyState === WebSocket.OPEN) {
+            u.ws.send(data);
+        }
+    });
+};
+
 const broadcast = (data: any) => {
     wss.clients.forEach((client) => {
         if (client.readyState === WebSocket.OPEN) {
@@ -0,0 +1,91 @@
+import * as http from 'http';
+import * as https from 'https';
+import { URL } from 'url';
+
+// GIF search for clients, proxied so the Giphy API key stays on the server.
+// Answers GET /gifs?q=... with { data: [{ url, preview_url }] }.
+
+const API_KEY = process.env.GIPHY_API_KEY;
+const SEARCH_URL = 'https://api.giphy.com/v1/gifs/search';
+const LIMIT = 24;
+const TIMEOUT_MS = 5000;
+const MAX_QUERY = 50;
+
+export interface Gif {
+    url: string;
+    preview_url: string;
+}
+
+interface GiphyImage {
+    url?: string;
+}
+
+interface GiphyResult {
+    images?: { original?: GiphyImage; fixed_width_small?: GiphyImage; fixed_width?: GiphyImage };
+}
+
+export const handleGifSearch = (req: http.IncomingMessage, res: http.ServerResponse) => {
+    const query = (new URL(req.url ?? '', 'http://localhost').searchParams.get('q') ?? '').trim().slice(0, MAX_QUERY);
+    if (!API_KEY) {
+        respond(res, 503, { error: 'GIF search is not configured' });
+        return;
+    }
+    if (!query) {
+        respond(res, 200, { data: [] });
+        return;
+    }
+    searchGiphy(query).then(
+        (data) => respond(res, 200, { data }),
+        (e) => {
+            console.log(`GIF search for "${query}" failed: ${e}`);
+            respond(res, 502, { error: 'GIF search failed' });
+        }
+    );
+};
+
+const searchGiphy = (query: string): Promise<Gif[]> =>
+    new Promise((resolve, reject) => {
+        const target = new URL(SEARCH_URL);
+        target.searchParams.set('api_key', API_KEY ?? '');
+        target.searchParams.set('q', query);
+        target.searchParams.set('limit', String(LIMIT));
+        target.searchParams.set('rating', 'g');
+        const request = https.get(target, { timeout: TIMEOUT_MS }, (response) => {
+            if (response.statusCode !== 200) {
+                response.resume();
+                reject(new Error(`Giphy answered ${response.statusCode}`));
+                return;
+            }
+            let body = '';
+            response.setEncoding('utf8');
+            response.on('data', (chunk: string) => (body += chunk));
+            response.on('end', () => {
+                try {
+                    const results: GiphyResult[] = JSON.parse(body).data ?? [];
+                    const gifs: Gif[] = [];
+                    for (const { images } of results) {
+                        const url = images?.original?.url;
+                        const preview_url = images?.fixed_width_small?.url ?? images?.fixed_width?.url ?? url;
+                        if (url && preview_url) {
+                            gifs.push({ url, preview_url });
+                        }
+                    }
+                    resolve(gifs);
+                } catch (e) {
+                    reject(e);
+                }
+            });
+            response.on('error', reject);
+        });
+        request.on('timeout', () => request.destroy(new Error('timed out')));
+        request.on('error', reject);
+    });
+
+const respond = (res: http.ServerResponse, status: number, body: object) => {
+    res.writeHead(status, {
+        'Content-Type': 'application/json',
+        // The client is served from elsewhere.
+        'Access-Control-Allow-Origin': '*',
+    });
+    res.end(JSON.stringify(body));
+};
@@ -0,0 +1,200 @@
+import { lookup, LookupAddress } from 'dns';
+import * as http from 'http';
+import * as https from 'https';
+import { isIPv4, LookupFunction } from 'net';
+import { URL } from 'url';
+
+// Open Graph details of a web page, pushed to clients as a 'linkpreview' frame.
+export interface LinkPreview {
+    url: string;
+    title: string;
+    description?: string;
+    image_url?: string;
+}
+
+const TIMEOUT_MS = 5000;
+// Only the start of a page is read; the <head> is all that matters.
+const MAX_BYTES = 256 * 1024;
+const MAX_REDIRECTS = 3;
+// url -> preview, or null for pages without one
+const cache = new Map<string, LinkPreview | null>();
+const CACHE_LIMIT = 500;
+
+// The http(s) links in `text`, without trailing punctuation, at most `limit` of them.
+export const findUrls = (text: string, limit: number): string[] => {
+    const urls = (text.match(/https?:\/\/[^\s<>"]+/gi) ?? []).map((url) => url.replace(/[.,!?;:'*_)]+$/, ''));
+    return Array.from(new Set(urls)).slice(0, limit);
+};
+
+// Fetches the Open Graph title, description and image of `url`, falling back to
+// its <title>. Resolves to undefined when there's nothing to show.
+export const fetchPreview = async (url: string): Promise<LinkPreview | undefined> => {
+    const cached = cache.get(url);
+    if (cached !== undefined) {
+        return cached ?? undefined;
+    }
+    let preview: LinkPreview | null = null;
+    try {
+        preview = parsePreview(url, await fetchPage(url, MAX_REDIRECTS));
+    } catch (e) {
+        console.log(`no preview for ${url}: ${e}`);
+    }
+    if (cache.size >= CACHE_LIMIT) {
+        cache.delete(cache.keys().next().value);
+    }
+    cache.set(url, preview);
+    return preview ?? undefined;
+};
+
+// Every hop, redirects included, is resolved and checked before connecting,
+// and the connection goes to the address checked.
+const fetchPage = async (url: string, redirects: number): Promise<string> => {
+    const target = new URL(url);
+    if (!['http:', 'https:'].includes(target.protocol)) {
+        throw new Error('not a web address');
+    }
+    // IPv6 literals come in brackets.
+    const [{ address, family }] = await resolvePublic(target.hostname.replace(/^\[|\]$/g, ''));
+    return new Promise((resolve, reject) => {
+        const client = target.protocol === 'https:' ? https : http;
+        const options = {
+            headers: { 'User-Agent': 'YewChat link preview', Accept: 'text/html' },
+            timeout: TIMEOUT_MS,
+            lookup: pinnedLookup(address, family),
+        };
+        const request = client.get(target, options, (response) => {
+            const status = response.statusCode ?? 0;
+            const location = response.headers.location;
+            if (status >= 300 && status < 400 && location && redirects > 0) {
+                response.resume();
+                fetchPage(new URL(location, target).toString(), redirects - 1).then(resolve, reject);
+                return;
+            }
+            if (status !== 200 || !(response.headers['content-type'] ?? '').includes('text/html')) {
+                response.resume();
+                reject(new Error(`unusable response (${status})`));
+                return;
+            }
+            let body = '';
+            response.setEncoding('utf8');
+            response.on('data', (chunk: string) => {
+                body += chunk;
+                if (body.length > MAX_BYTES) {
+                    response.destroy();
+                    resolve(body);
+                }
+            });
+            response.on('end', () => resolve(body));
+            response.on('error', reject);
+        });
+        request.on('timeout', () => request.destroy(new Error('timed out')));
+        request.on('error', reject);
+    });
+};
+
+// The addresses `hostname` resolves to, as long as every one of them is public.
+// Keeps previews from being used to probe the server's own network.
+const resolvePublic = (hostname: string): Promise<LookupAddress[]> =>
+    new Promise((resolve, reject) => {
+        lookup(hostname, { all: true }, (error, addresses) => {
+            if (error) {
+                reject(error);
+            } else if (addresses.length === 0 || !addresses.every(({ address }) => isPublicAddress(address))) {
+                reject(new Error('not a public web address'));
+            } else {
+                resolve(addresses);
+            }
+        });
+    });
+
+// Has a request connect to `address` instead of looking its host name up again,
+// which could give a different answer the second time.
+const pinnedLookup = (address: string, family: number): LookupFunction =>
+    ((_hostname: string, options: { all?: boolean }, callback: (...args: unknown[]) => void) =>
+        options.all ? callback(null, [{ address, family }]) : callback(null, address, family)) as LookupFunction;
+
+// Whether `address` is outside the unspecified, loopback, private, shared,
+// link-local and multicast ranges.
+const isPublicAddress = (address: string): boolean => {
+    if (isIPv4(address)) {
+        const [a, b] = address.split('.').map(Number);
+        return !(
+            a === 0 ||
+            a === 10 ||
+            a === 127 ||
+            a >= 224 ||
+            (a === 100 && b >= 64 && b < 128) ||
+            (a === 169 && b === 254) ||
+            (a === 172 && b >= 16 && b < 32) ||
+            (a === 192 && b === 168)
+        );
+    }
+    const groups = ipv6Groups(address);
+    // ::ffff:a.b.c.d goes by the IPv4 address in it.
+    if (groups.slice(0, 5).every((group) => group === 0) && groups[5] === 0xffff) {
+        return isPublicAddress([groups[6] >> 8, groups[6] & 0xff, groups[7] >> 8, groups[7] & 0xff].join('.'));
+    }
+    return !(
+        // :: and ::1
+        (groups.slice(0, 7).every((group) => group === 0) && groups[7] <= 1) ||
+        // fc00::/7, unique local
+        (groups[0] & 0xfe00) === 0xfc00 ||
+        // fe80::/10, link-local
+        (groups[0] & 0xffc0) === 0xfe80 ||
+        // ff00::/8, multicast
+        (groups[0] & 0xff00) === 0xff00
+    );
+};
+
+// The eight 16-bit groups of an IPv6 address, with `::` and a trailing IPv4
+// part spelled out.
+const ipv6Groups = (address: string): number[] => {
+    let text = address.toLowerCase().split('%')[0];
+    const ipv4 = /(\d+)\.(\d+)\.(\d+)\.(\d+)$/.exec(text);
+    if (ipv4) {
+        const [a, b, c, d] = ipv4.slice(1).map(Number);
+        text = `${text.slice(0, ipv4.index)}${((a << 8) | b).toString(16)}:${((c << 8) | d).toString(16)}`;
+    }
+    const [head, tail] = text.split('::');
+    const parse = (part: string) => (part ? part.split(':').map((group) => parseInt(group, 16)) : []);
+    const start = parse(head);
+    const end = tail === undefined ? [] : parse(tail);
+    const zeros = tail === undefined ? [] : new Array(8 - start.length - end.length).fill(0);
+    return [...start, ...zeros, ...end];
+};
+
+const parsePreview = (url: string, html: string): LinkPreview | null => {
+    const meta = new Map<string, string>();
+    for (const tag of html.match(/<meta\b[^>]*>/gi) ?? []) {
+        const key = attribute(tag, 'property') ?? attribute(tag, 'name');
+        const content = attribute(tag, 'content');
+        if (key && content && !meta.has(key.toLowerCase())) {
+            meta.set(key.toLowerCase(), decodeEntities(content.trim()));
+        }
+    }
+    const titleTag = /<title[^>]*>([^<]*)<\/title>/i.exec(html)?.[1];
+    const title = meta.get('og:title') ?? (titleTag ? decodeEntities(titleTag.trim()) : undefined);
+    if (!title) {
+        return null;
+    }
+    const image = meta.get('og:image');
+    return {
+        url,
+        title,
+        description: meta.get('og:description') ?? meta.get('description'),
+        image_url: image ? new URL(image, url).toString() : undefined,
+    };
+};
+
+const attribute = (tag: string, name: string): string | undefined => {
+    const match = new RegExp(`\\b${name}\\s*=\\s*(?:"([^"]*)"|'([^']*)')`, 'i').exec(tag);
+    return match ? match[1] ?? match[2] : undefined;
+};
+
+const decodeEntities = (text: string): string =>
+    text
+        .replace(/&quot;/g, '"')
+        .replace(/&#0?39;|&apos;/g, "'")
+        .replace(/&lt;/g, '<')
+        .replace(/&gt;/g, '>')
+        .replace(/&amp;/g, '&');
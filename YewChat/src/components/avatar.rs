@@ -0,0 +1,74 @@
+use yew::prelude::*;
+
+pub enum Msg {
+    Loaded,
+}
+
+#[derive(Properties, PartialEq)]
+pub struct Props {
+    pub src: String,
+    /// Size and spacing, e.g. `w-8 h-8 mr-2`.
+    #[prop_or_default]
+    pub class: Classes,
+    #[prop_or_default]
+    pub alt: String,
+    /// Tooltip, if any.
+    #[prop_or_default]
+    pub title: Option<String>,
+    #[prop_or_default]
+    pub oncontextmenu: Option<Callback<MouseEvent>>,
+}
+
+/// A round avatar that shows a grey skeleton until the image has loaded,
+/// instead of a broken image, and only loads once it's near the screen.
+pub struct Avatar {
+    /// The image `loaded` is about; a new one starts out loading again.
+    src: String,
+    loaded: bool,
+}
+
+impl Component for Avatar {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        Self {
+            src: ctx.props().src.clone(),
+            loaded: false,
+        }
+    }
+
+    fn update(&mut self, _ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::Loaded => !std::mem::replace(&mut self.loaded, true),
+        }
+    }
+
+    fn changed(&mut self, ctx: &Context<Self>) -> bool {
+        if ctx.props().src != self.src {
+            self.src = ctx.props().src.clone();
+            self.loaded = false;
+        }
+        true
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let props = ctx.props();
+        html! {
+            <div
+                class={classes!("relative", "flex-none", "rounded-full", "overflow-hidden", props.class.clone())}
+                title={props.title.clone()}
+                oncontextmenu={props.oncontextmenu.clone()}
+            >
+                <div class={classes!("absolute", "inset-0", "rounded-full", "bg-gray-300", "dark:bg-gray-600", "animate-pulse", self.loaded.then_some("hidden"))} />
+                <img
+                    class={classes!("w-full", "h-full", "rounded-full", "transition-opacity", if self.loaded { "opacity-100" } else { "opacity-0" })}
+                    src={props.src.clone()}
+                    alt={props.alt.clone()}
+                    loading="lazy"
+                    onload={ctx.link().callback(|_| Msg::Loaded)}
+                />
+            </div>
+        }
+    }
+}
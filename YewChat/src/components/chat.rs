@@ -1,233 +1,5880 @@
-use serde::{Deserialize, Serialize};
-use web_sys::HtmlInputElement;
+use std::collections::{HashMap, HashSet};
+
+use gloo_timers::callback::{Interval, Timeout};
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{
+    Blob, CryptoKey, HtmlCanvasElement, HtmlInputElement, HtmlSelectElement, HtmlTextAreaElement,
+    HtmlVideoElement, IntersectionObserver, IntersectionObserverEntry, MediaStream, Url,
+};
+use yew::context::ContextHandle;
 use yew::prelude::*;
 use yew_agent::{Bridge, Bridged};
 
-use crate::services::event_bus::EventBus;
-use crate::{services::websocket::WebsocketService, User};
+use crate::components::avatar::Avatar;
+use crate::components::code_block::copy_to_clipboard;
+use crate::components::emoji_picker::EmojiPicker;
+use crate::components::forward_dialog::{Destination, ForwardDialog};
+use crate::components::gif_picker::GifPicker;
+use crate::components::mention_popup::{Candidate, MentionPopup};
+use crate::components::mentions_inbox::{Mention, MentionsInbox};
+use crate::components::message_bubble::{MessageBubble, Quote};
+use crate::components::profile_panel::{avatar_url, ProfilePanel, AVATAR_STYLES};
+use crate::components::room_dialog::RoomDialog;
+use crate::components::settings::Settings;
+use crate::components::user_popover::{UserPopover, POPOVER_HEIGHT_PX, POPOVER_WIDTH_PX};
+use crate::components::user_search::UserSearch;
+use crate::services::blocklist;
+use crate::services::call::Connection;
+use crate::services::crypto::{self, KeyPair};
+use crate::services::event_bus::{BusEvent, EventBus};
+use crate::services::export::{self, ExportFormat};
+use crate::services::favorites::{self, Favorite};
+use crate::services::history;
+use crate::services::notify::{self, NotificationPrefs};
+use crate::services::preview::{self, Preview, PreviewState};
+use crate::services::protocol::{
+    CallSignal, EditData, FileChunk, HistoryQuery, IceCandidateData, MessageData, MessageId,
+    MessageKind, ModerationData, MsgTypes, PresenceData, PresenceStatus, ProfileData,
+    PublicKeyData, ReactionData, ReadReceipt, ReportCategory, ReportData, RoomData,
+    RoomErrorReason, UserRole, UserSearchData, WebSocketMessage,
+};
+use crate::services::unread::TabBadge;
+use crate::services::voice::{self, Recorder};
+use crate::services::websocket::{self, ConnectionStatus, WebsocketService, OUTBOX_CAPACITY};
+use crate::utils::commands::{parse_command, Command};
+use crate::utils::content::{
+    classify_message, forwarded, normalize_outgoing, safe_image_url, split_forwarded,
+    MessageContent,
+};
+use crate::utils::dedup::{self, RecentKeys};
+use crate::utils::grouping::group_messages;
+use crate::utils::linkify::{split_into_segments, Segment};
+use crate::utils::mentions;
+use crate::utils::rate_limit::RateLimiter;
+use crate::utils::shortcodes::replace_shortcodes;
+use crate::utils::theme::Theme;
+use crate::utils::translations::{self, t, t_args, Language};
+use crate::utils::{search, time};
+use crate::{ThemeContext, User};
 
 pub enum Msg {
-    HandleMsg(String),
+    HandleBus(BusEvent),
     SubmitMessage,
+    InputChanged,
+    TypingDebounceElapsed,
+    /// Sending is allowed again after going too fast.
+    RateLimitElapsed,
+    /// Drop typists we haven't heard from in a while.
+    ExpireTyping,
+    /// Put this at the caret in the message box: an emoji, or a mention.
+    InsertText(String),
+    ShowGifs(bool),
+    /// A GIF was picked: its URL becomes the message.
+    PickGif(String),
+    /// Open the file chooser for an image upload.
+    PickImage,
+    ImagePicked,
+    /// An image was read in as a `data:image/...` URL.
+    ImageLoaded(String),
+    /// Open the file chooser for sharing a file of any kind.
+    PickFile,
+    FilePicked,
+    /// A file to share was read in as a `data:` URL. `voice` marks a voice
+    /// message, which goes out as `Audio` frames.
+    FileLoaded {
+        name: String,
+        mime: String,
+        url: String,
+        voice: bool,
+    },
+    /// The voice message button was pressed (`true`) or let go.
+    Record(bool),
+    /// The microphone is open and recording, or couldn't be opened.
+    RecorderReady(Result<Recorder, String>),
+    /// A voice message was recorded and is ready to send.
+    VoiceRecorded(Blob),
+    /// Draw the next frame of the waveform while recording.
+    DrawWaveform,
+    /// Switch the main panel to the DM thread with the given user, or back to
+    /// the public room with `None`.
+    SelectThread(Option<String>),
+    /// My key pair for encrypted DMs is ready, or couldn't be made.
+    KeysReady(Option<KeyPair>),
+    /// The key shared with `peer` was worked out from their `public` key.
+    KeyDerived {
+        peer: String,
+        public: String,
+        key: CryptoKey,
+    },
+    /// A direct message to `to` was encrypted, or failed to be.
+    SendEncrypted {
+        to: String,
+        data: Option<String>,
+    },
+    /// An encrypted direct message was opened; if it couldn't be, its text
+    /// says so.
+    Decrypted {
+        to: Option<String>,
+        message: MessageData,
+    },
+    /// Call the user whose DM thread is open.
+    StartCall,
+    /// Pick up the incoming call.
+    AcceptCall,
+    /// Decline, cancel or hang up the call.
+    EndCall,
+    /// The microphone and camera for the call with `peer` are open and my
+    /// offer or answer is ready, or that failed.
+    CallReady {
+        peer: String,
+        result: Result<(Connection, String), String>,
+    },
+    /// The other side's offer or answer for the call with `peer` is in, or
+    /// couldn't be taken up.
+    CallDescribed {
+        peer: String,
+        ok: bool,
+    },
+    /// My side of the call found a way the other side could reach me.
+    CallCandidate(IceCandidateData),
+    /// The other side's media started coming in.
+    RemoteStream(MediaStream),
+    JoinRoom(String),
+    /// Toggle my `emoji` reaction on room message `id`.
+    React {
+        id: u64,
+        emoji: String,
+    },
+    /// Stored history for `room` has been read back from the browser.
+    HistoryLoaded {
+        room: String,
+        messages: Vec<MessageData>,
+    },
+    /// The preview of `url` came back, or failed with `None`.
+    PreviewLoaded {
+        url: String,
+        preview: Option<Preview>,
+    },
+    DismissError(usize),
+    /// The message list was scrolled by the user.
+    Scrolled,
+    ScrollToBottom,
+    /// The search box changed. Filtering waits until the typing stops,
+    /// see `SEARCH_DEBOUNCE_MS`; an empty query shows everything again.
+    Search(String),
+    /// Filter by what's in the search box now.
+    ApplySearch,
+    /// Enter in the search box: move to the next older result, or with
+    /// `false` (Shift+Enter) back to the next newer one.
+    SearchStep(bool),
+    /// Expand or collapse the search box. Collapsing clears the search.
+    ShowSearch(bool),
+    /// Show only the room messages from this user, or everything again.
+    SetFilter(Option<String>),
+    /// The window was resized.
+    Resized,
+    /// Slide the sidebar in or out, on narrow screens.
+    ShowSidebar(bool),
+    /// Room messages up to this id have been on screen.
+    MessagesSeen(u64),
+    /// The message from `MessagesSeen` has been on screen for
+    /// `READ_DWELL_MS`.
+    ReadDwelled,
+    /// The tab was hidden or shown again.
+    VisibilityChanged,
+    /// I picked a status for myself.
+    SetStatus(PresenceStatus),
+    /// The window gained (`true`) or lost focus.
+    FocusChanged(bool),
+    ClearUnreadDivider,
+    /// The "+1"/"−1" next to the online count has had its time.
+    ClearMemberDelta,
+    ToggleDarkMode,
+    /// The theme provided at the root changed.
+    ThemeChanged(ThemeContext),
+    SetLanguage(Language),
+    /// New notification preferences from the settings.
+    SetNotificationPrefs(NotificationPrefs),
+    /// The user answered the browser's notification permission prompt.
+    DesktopPermission(bool),
+    /// A desktop notification was clicked: show the direct-message thread
+    /// with `peer`, or room message `id`.
+    ShowNotified {
+        peer: Option<String>,
+        id: Option<u64>,
+    },
+    /// Start replying to room message `id` by `from`, or stop with `None`.
+    ReplyTo(Option<(u64, String)>),
+    /// Start editing my room message `id` in place, or stop with `None`.
+    EditMessage(Option<u64>),
+    SubmitEdit,
+    /// Ask before deleting my room message `id`, or stop asking with `None`.
+    ConfirmDelete(Option<u64>),
+    /// Open the report popover on room message `id`, or close it with
+    /// `None`.
+    Report(Option<u64>),
+    PickReportCategory(ReportCategory),
+    SubmitReport,
+    /// Delete my room message `id`.
+    DeleteMessage(u64),
+    /// Copy the text of the message with this `MessageData::dedup_key`.
+    CopyMessage {
+        key: String,
+        text: String,
+    },
+    /// Copying finished, successfully or not.
+    MessageCopied {
+        key: String,
+        ok: bool,
+    },
+    ResetCopied,
+    /// Pin or unpin room message `id`.
+    SetPinned {
+        id: u64,
+        pinned: bool,
+    },
+    /// Open or close the list of pinned messages.
+    ShowPins(bool),
+    /// Scroll the message list to room message `id` and flash it.
+    JumpTo(u64),
+    StopFlash,
+    /// Move the highlighted `@name` suggestion by `offset`.
+    MoveMention(isize),
+    /// Complete the `@name` being typed with the given name, or the
+    /// highlighted suggestion.
+    CompleteMention(Option<String>),
+    CloseMention,
+    /// Escape was pressed.
+    CloseOverlays,
+    FocusSearch,
+    /// Show or hide the messages that mentioned me.
+    ShowMentions(bool),
+    /// Scroll to the mention of me in room message `id`, marking it read.
+    OpenMention {
+        room: String,
+        id: u64,
+    },
+    /// Show or hide the profile editor.
+    ShowProfile(bool),
+    /// Show or hide the notification settings.
+    ShowSettings(bool),
+    /// Show or hide the dialog for creating and joining rooms.
+    ShowRoomDialog(bool),
+    /// Ask the server for a new room, joined once it exists.
+    CreateRoom(RoomData),
+    /// Join a room picked in the room dialog, with the invite if it's
+    /// private.
+    EnterRoom {
+        room: String,
+        invite: Option<String>,
+    },
+    /// Open or close the menu of formats to export the thread in.
+    ShowExport(bool),
+    /// Download the thread on screen in this format.
+    Export(ExportFormat),
+    /// Narrow the sidebar's user list to names containing this.
+    SetUserFilter(String),
+    /// Open or close the search for users in every room.
+    ShowUserSearch(bool),
+    /// Ask the server for the users matching `query`, skipping `offset`.
+    SearchUsers {
+        query: String,
+        offset: usize,
+    },
+    /// Invite a user found by searching to the room I'm in, by DM.
+    InviteUser(String),
+    /// Save a new display name and avatar style.
+    SaveProfile(String, String),
+    /// Open the menu for a user, right-clicked in the sidebar or on a
+    /// message, or close it with `None`.
+    ShowUserMenu(Option<UserMenu>),
+    /// Open the card of a user clicked in the sidebar, or close it with
+    /// `None`.
+    ShowUserCard(Option<UserMenu>),
+    /// Stop (`true`) or start again showing what this user says.
+    Block(String, bool),
+    /// Kick a user out, or ban them if there's a duration.
+    Moderate(ModerationData),
+    /// Star the message, or unstar it if it's starred already.
+    ToggleFavorite(Favorite),
+    /// Scroll to room message `id`, switching to its room if need be.
+    ShowRoomMessage {
+        room: String,
+        id: u64,
+    },
+    /// Pick where to forward this message, or stop with `None`.
+    Forward(Option<MessageData>),
+    /// Send the message being forwarded to `Destination` and go there.
+    ForwardTo(Destination),
+}
+
+/// How long the search box has to be left alone before the messages are
+/// filtered, so typing stays smooth in long rooms.
+const SEARCH_DEBOUNCE_MS: u32 = 200;
+/// How long to wait before sending another `Typing` frame while the user
+/// keeps typing.
+const TYPING_DEBOUNCE_MS: u32 = 2_000;
+/// How long someone counts as typing after their last `Typing` frame.
+const TYPING_TIMEOUT_MS: f64 = 3_000.0;
+/// How often stale typists are swept out.
+const TYPING_SWEEP_MS: u32 = 500;
+/// Largest image accepted for upload, before base64 encoding.
+const MAX_IMAGE_BYTES: f64 = 200.0 * 1024.0;
+/// Longest message that can be sent, in characters, unless
+/// `YEWCHAT_CONFIG.maxMessageLength` says otherwise.
+const DEFAULT_MAX_MESSAGE_CHARS: usize = 2000;
+/// Messages that can be sent in a row within `SEND_WINDOW_MS`.
+const SEND_LIMIT: usize = 5;
+const SEND_WINDOW_MS: f64 = 3_000.0;
+/// Largest file that can be shared, before base64 encoding.
+const MAX_FILE_BYTES: f64 = 5.0 * 1024.0 * 1024.0;
+/// Length of the base64 pieces a shared file is sent in.
+const FILE_CHUNK_LEN: usize = 64 * 1024;
+/// Most pieces a file is accepted in, enough for `MAX_FILE_BYTES`.
+const MAX_FILE_CHUNKS: usize = 128;
+/// Voice messages shorter than this are taken for a slip and dropped.
+const MIN_VOICE_MS: f64 = 500.0;
+/// How often the waveform is redrawn while recording.
+const WAVEFORM_MS: u32 = 50;
+/// Room every client joins right after logging in.
+const DEFAULT_ROOM: &str = "general";
+/// How close to the bottom of the message list (in px) still counts as
+/// "at the bottom" for auto-scrolling.
+const SCROLL_STICKY_PX: i32 = 100;
+/// Windows narrower than this get the phone layout, with the sidebar in
+/// a drawer. Tailwind's `sm:` breakpoint, which the markup also uses.
+const MOBILE_WIDTH_PX: f64 = 640.0;
+/// The message box grows with its content up to this many lines.
+const MAX_INPUT_ROWS: usize = 6;
+/// How long the "new messages" divider stays once I'm back and have seen it.
+const UNREAD_DIVIDER_MS: u32 = 4000;
+/// How long the "+1"/"−1" next to the online count shows; matches the
+/// `delta` animation.
+const MEMBER_DELTA_MS: u32 = 2_000;
+/// How long a copy button says how copying went.
+const COPIED_MS: u32 = 1_500;
+/// How long a message jumped to stays highlighted.
+const FLASH_MS: u32 = 1_500;
+/// How long the newest message has to stay on screen before it counts as
+/// read.
+const READ_DWELL_MS: u32 = 1_000;
+/// How many recently received messages are remembered to drop re-deliveries.
+const RECENT_MESSAGES: usize = 500;
+/// How many messages each history request asks the server for.
+const HISTORY_PAGE: usize = 50;
+/// Longest excerpt shown when quoting a message.
+const QUOTE_CHARS: usize = 80;
+/// Links per message that get a preview card.
+const MAX_PREVIEWS: usize = 2;
+/// Most names suggested while typing an `@name`.
+const MAX_MENTION_SUGGESTIONS: usize = 8;
+/// Mentions kept in the inbox, the latest ones.
+const MAX_MENTIONS: usize = 100;
+/// Reactions offered when hovering a message, in display order.
+const QUICK_REACTIONS: [&str; 6] = ["👍", "❤️", "😂", "😮", "😢", "🎉"];
+/// How long admins can ban someone for from the user menu, in minutes.
+const BAN_DURATIONS: [(u32, &str); 3] = [
+    (60, "moderate.ban_hour"),
+    (24 * 60, "moderate.ban_day"),
+    (7 * 24 * 60, "moderate.ban_week"),
+];
+
+/// Where the connection to the server stands, as shown to the user.
+#[derive(Clone, Debug, PartialEq)]
+enum ReconnectState {
+    /// Opening the first connection since the page loaded.
+    Connecting,
+    Connected,
+    /// The connection dropped and the next attempt starts in `delay_ms`.
+    Reconnecting {
+        attempt: u32,
+        delay_ms: u32,
+    },
+    /// The service gave up on the endpoint for good.
+    Failed(String),
+}
+
+impl From<ConnectionStatus> for ReconnectState {
+    fn from(status: ConnectionStatus) -> Self {
+        match status {
+            ConnectionStatus::Connected => Self::Connected,
+            ConnectionStatus::Reconnecting { attempt, delay_ms } => {
+                Self::Reconnecting { attempt, delay_ms }
+            }
+            ConnectionStatus::Failed(e) => Self::Failed(e),
+        }
+    }
+}
+
+/// A DOM event handler that ignores the event itself.
+type Listener = Closure<dyn Fn()>;
+
+/// The `@name` being typed in the message box and who it could be.
+struct MentionState {
+    /// UTF-16 offsets of the `@` in the message box, as the DOM counts,
+    /// and of the end of the word it starts.
+    start: u32,
+    end: u32,
+    /// What's typed of the name before the caret.
+    query: String,
+    candidates: Vec<String>,
+    selected_idx: usize,
+}
+
+/// A shared file coming in piece by piece.
+struct FileTransfer {
+    mime: String,
+    /// Base64 pieces received so far, by index.
+    chunks: Vec<Option<String>>,
+    received: usize,
+    total: usize,
+    /// The whole file as a `data:` URL once every piece is in, or for
+    /// audio, as a `blob:` URL.
+    url: Option<String>,
+}
+
+impl FileTransfer {
+    fn new(mime: &str, total: usize) -> Self {
+        // It ends up in a `data:` URL, so don't trust it to be well-formed.
+        let valid = mime.contains('/')
+            && mime
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "/+.-".contains(c));
+        Self {
+            mime: if valid {
+                mime.to_string()
+            } else {
+                "application/octet-stream".to_string()
+            },
+            chunks: vec![None; total],
+            received: 0,
+            total,
+            url: None,
+        }
+    }
+
+    /// Stores piece `index`, putting the file together if it was the last
+    /// one missing. Repeated and out-of-range pieces are ignored.
+    fn add(&mut self, index: usize, data: String) {
+        match self.chunks.get_mut(index) {
+            Some(slot) if slot.is_none() => *slot = Some(data),
+            _ => return,
+        }
+        self.received += 1;
+        if self.received == self.total {
+            let data: String = self.chunks.drain(..).flatten().collect();
+            let blob = if self.mime.starts_with("audio/") {
+                voice::blob_url(&self.mime, &data)
+                    .map_err(|e| log::debug!("no blob URL for audio: {:?}", e))
+                    .ok()
+            } else {
+                None
+            };
+            self.url = Some(blob.unwrap_or_else(|| format!("data:{};base64,{}", self.mime, data)));
+        }
+    }
+}
+
+impl Drop for FileTransfer {
+    fn drop(&mut self) {
+        if let Some(url) = self.url.as_ref().filter(|url| url.starts_with("blob:")) {
+            let _ = Url::revoke_object_url(url);
+        }
+    }
+}
+
+/// Where a call stands.
+enum CallState {
+    /// They're calling me with this SDP offer.
+    Ringing(String),
+    /// I'm calling them and they haven't picked up yet.
+    Calling,
+    /// Picked up; waiting for the media to flow.
+    Connecting,
+    Active,
 }
 
-#[derive(Deserialize)]
-struct MessageData {
-    from: String,
-    message: String,
+/// The call with `peer`. There's only ever one.
+struct Call {
+    peer: String,
+    state: CallState,
+    /// Set once the microphone and camera are open.
+    connection: Option<Connection>,
+    /// Whether the other side's offer or answer is in, without which their
+    /// candidates can't be added.
+    described: bool,
+    /// Candidates from the other side that came before that.
+    pending: Vec<IceCandidateData>,
+    remote: Option<MediaStream>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
-#[serde(rename_all = "lowercase")]
-pub enum MsgTypes {
-    Users,
-    Register,
-    Message,
+impl Call {
+    fn new(peer: String, state: CallState) -> Self {
+        Self {
+            peer,
+            state,
+            connection: None,
+            described: false,
+            pending: Vec::new(),
+            remote: None,
+        }
+    }
 }
 
-#[derive(Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct WebSocketMessage {
-    message_type: MsgTypes,
-    data_array: Option<Vec<String>>,
-    data: Option<String>,
+/// A user whose menu or card is open, and where on screen it opens: where
+/// they were right-clicked, or next to where they were clicked.
+pub struct UserMenu {
+    name: String,
+    x: i32,
+    y: i32,
 }
 
 #[derive(Clone)]
 struct UserProfile {
     name: String,
     avatar: String,
+    status: PresenceStatus,
+    role: UserRole,
+    /// When they showed up in the room, in epoch ms; `None` for those who
+    /// were already there when I came.
+    online_since: Option<u64>,
+    /// Their IANA time zone and locale, empty until their profile says.
+    timezone: String,
+    locale: String,
+}
+
+/// Who joined and who left between two member lists, see
+/// `membership_change`.
+struct MembershipChange<'a> {
+    joined: Vec<&'a String>,
+    left: Vec<&'a String>,
+}
+
+impl MembershipChange<'_> {
+    /// How many more are in the room now.
+    fn delta(&self) -> isize {
+        self.joined.len() as isize - self.left.len() as isize
+    }
 }
 
 pub struct Chat {
     users: Vec<UserProfile>,
     chat_input: NodeRef,
-    _producer: Box<dyn Bridge<EventBus>>,
+    /// Lines the message box is tall, following what's typed into it.
+    input_rows: usize,
+    /// Characters in the message box, and how many a message may have.
+    input_chars: usize,
+    max_chars: usize,
+    /// The last attempt to send was refused; cleared by typing.
+    input_rejected: bool,
+    /// Id and sender of the room message the next one replies to.
+    replying_to: Option<(u64, String)>,
+    mention: Option<MentionState>,
+    /// Pinned messages of the current room, in the order they were pinned.
+    pinned: Vec<MessageData>,
+    pins_open: bool,
+    /// The message last copied, see `MessageData::dedup_key`, and whether
+    /// that worked; shown on its copy button for a moment.
+    copied: Option<(String, bool)>,
+    copied_reset: Option<Timeout>,
+    /// Room message just jumped to, highlighted for `FLASH_MS`.
+    flashing: Option<u64>,
+    flash_reset: Option<Timeout>,
+    /// Room messages that mentioned me since logging in, in any room,
+    /// oldest first and at most `MAX_MENTIONS`.
+    mentions: Vec<Mention>,
+    mentions_open: bool,
+    /// Link previews by URL, so each link is only fetched once.
+    previews: HashMap<String, PreviewState>,
+    /// Id of my room message being edited in place.
+    editing: Option<u64>,
+    /// Id of my room message whose delete button asks to be sure.
+    confirm_delete: Option<u64>,
+    /// Room message with the report popover open, and the category
+    /// picked so far.
+    reporting: Option<(u64, Option<ReportCategory>)>,
+    /// Room messages I've reported, which can't be reported again.
+    reported: HashSet<u64>,
+    edit_input: NodeRef,
+    /// Focus `edit_input` once it has been rendered.
+    edit_focus_pending: bool,
+    search_input: NodeRef,
+    image_input: NodeRef,
+    file_input: NodeRef,
+    /// Files shared in rooms, by id, complete or still arriving.
+    transfers: HashMap<String, FileTransfer>,
+    /// The voice message being recorded.
+    recorder: Option<Recorder>,
+    /// Whether the voice message button is held down; the microphone may
+    /// still be opening.
+    recording: bool,
+    /// Redraws the waveform on `waveform_canvas` while recording.
+    waveform: Option<Interval>,
+    waveform_canvas: NodeRef,
+    /// Where events from the server come in, and where mine go out.
+    bus: Box<dyn Bridge<EventBus>>,
     wss: WebsocketService,
     messages: Vec<MessageData>,
+    /// Rooms advertised by the server.
+    rooms: Vec<String>,
+    room: String,
+    /// History of rooms we've left, restored when switching back.
+    room_history: HashMap<String, Vec<MessageData>>,
+    /// Direct-message history keyed by the other participant's name.
+    dm_threads: HashMap<String, Vec<MessageData>>,
+    active_dm: Option<String>,
+    /// Peers whose DM thread got messages I haven't opened yet.
+    dm_unread: HashSet<String>,
+    /// My key pair for encrypting DMs; `None` until it's made, or if the
+    /// browser can't.
+    keys: Option<KeyPair>,
+    /// Public keys peers announced, by name.
+    peer_keys: HashMap<String, String>,
+    /// Keys agreed with peers, by name. DMs to them go out encrypted.
+    shared_keys: HashMap<String, CryptoKey>,
+    call: Option<Call>,
+    remote_video: NodeRef,
+    local_video: NodeRef,
+    /// Who is typing, with when (epoch ms) we last heard from them.
+    typists: HashMap<String, f64>,
+    typing_debounce: Option<Timeout>,
+    limiter: RateLimiter,
+    /// Runs while sending is held back for going too fast.
+    slow_down: Option<Timeout>,
+    /// Runs while `typists` is non-empty.
+    typing_sweep: Option<Interval>,
+    connection: ReconnectState,
+    /// Non-fatal problems shown as dismissible banners.
+    errors: Vec<String>,
+    /// Round-trip time of the last heartbeat, once one has been measured.
+    latency_ms: Option<u32>,
+    /// Messages submitted while disconnected that are waiting in the
+    /// service's outbox.
+    pending: usize,
+    message_list: NodeRef,
+    /// Whether the message list is scrolled (nearly) all the way down, in
+    /// which case new messages scroll into view on their own.
+    at_bottom: bool,
+    /// Messages that arrived while scrolled up, shown on the badge.
+    unseen: usize,
+    /// Scroll to the newest message after the next render.
+    scroll_pending: bool,
+    /// `visibilitychange` handler, removed again in `destroy`.
+    visibility_listener: Closure<dyn Fn()>,
+    /// Window `focus` and `blur` handlers, removed again in `destroy`.
+    focus_listeners: Vec<(&'static str, Listener)>,
+    /// Whether the window has focus, as of the last focus/blur event.
+    focused: bool,
+    /// Window `resize` handler, removed again in `destroy`.
+    resize_listener: Listener,
+    /// Whether the window is narrower than `MOBILE_WIDTH_PX`.
+    is_mobile: bool,
+    /// Whether the sidebar drawer is out, in the phone layout.
+    sidebar_open: bool,
+    /// First room message that arrived while I was away, see
+    /// `MessageData::dedup_key`. The "new messages" divider goes above it.
+    unread_marker: Option<String>,
+    unread_divider: NodeRef,
+    /// Clears the divider once it has been seen, see `UNREAD_DIVIDER_MS`.
+    unread_timeout: Option<Timeout>,
+    /// How many more (or fewer) are in the room than a moment ago, shown
+    /// next to the online count until the timeout clears it.
+    member_delta: Option<(isize, Timeout)>,
+    /// Messages that arrived while I was away, counted in the tab's title
+    /// and favicon until I'm back at the bottom of the list.
+    tab_badge: TabBadge,
+    /// Only messages matching this (case-insensitively) are shown.
+    search_query: String,
+    search_open: bool,
+    /// What's typed in the search box, until it becomes `search_query`.
+    search_draft: String,
+    search_debounce: Option<Timeout>,
+    /// Focus `search_input` once it has been rendered.
+    search_focus_pending: bool,
+    /// The result moved to with Enter, counted from the newest.
+    search_match: usize,
+    /// Bring that result into view after the next render.
+    search_scroll_pending: bool,
+    /// Marks the element right before that result.
+    search_target: NodeRef,
+    /// `scroll_anchor` of the list before searching, to go back to after.
+    search_anchor: Option<(i32, i32)>,
+    /// Only room messages from this user are shown.
+    active_filter: Option<String>,
+    /// Messages that arrived since `active_filter` was set but don't match.
+    filter_hidden: usize,
+    /// Messages received lately, see `MessageData::dedup_key`.
+    recent: RecentKeys,
+    /// How far each member of the room has read, by message id.
+    read_up_to: HashMap<String, u64>,
+    /// The last id we told the room we've read.
+    last_read_sent: u64,
+    /// The newest message seen that we haven't told the room about yet,
+    /// waiting out `read_timeout`.
+    read_pending: u64,
+    read_timeout: Option<Timeout>,
+    /// How far I had read in the current room as of the last `ReadSync`,
+    /// until the history page it applies to comes in.
+    synced_read: Option<u64>,
+    /// Bring the "new messages" divider into view after the next render.
+    unread_scroll_pending: bool,
+    /// Watches room messages coming on screen to send read receipts.
+    read_observer: Option<IntersectionObserver>,
+    _on_messages_seen: Closure<dyn FnMut(js_sys::Array)>,
+    /// How many of `messages` are history (stored or fetched from the
+    /// server) rather than live traffic.
+    history_len: usize,
+    /// Whether the server has been asked for the room's history since we
+    /// last registered.
+    history_requested: bool,
+    /// Whether the server has older messages than the ones loaded, as of
+    /// its last history reply.
+    history_more: bool,
+    /// An older page has been asked for and hasn't arrived yet.
+    loading_older: bool,
+    /// Scroll height and offset of the message list before a page was
+    /// prepended, to keep the view in place after the next render.
+    scroll_anchor: Option<(i32, i32)>,
+    /// Alerts for messages arriving while the tab is hidden.
+    notify: NotificationPrefs,
+    /// Unavailable where the browser can't play audio.
+    sound: Option<notify::Sound>,
+    /// Whether my first message has prompted for notification permission.
+    permission_asked: bool,
+    /// Jump to this room message once the room timeline shows it again,
+    /// after switching rooms or back from a direct-message thread.
+    jump_pending: Option<u64>,
+    theme: ThemeContext,
+    _theme_listener: ContextHandle<ThemeContext>,
+    /// What `t` translates to, handed down to the components that show
+    /// text of their own so they render again when it changes.
+    language: Language,
+    /// My avatar style, see `AVATAR_STYLES`.
+    avatar_style: String,
+    /// The status I picked. While it's `Online`, hiding the tab shows me as
+    /// away.
+    chosen_status: PresenceStatus,
+    profile_open: bool,
+    settings_open: bool,
+    room_dialog_open: bool,
+    gifs_open: bool,
+    /// Why the server turned down the room I last asked for, shown in the
+    /// room dialog.
+    room_error: Option<String>,
+    /// Invites to the private rooms I created or was let into, by room.
+    invites: HashMap<String, String>,
+    /// The staff key given on the login page, if any.
+    staff_token: Option<String>,
+    /// Settings of the room I'm in, once the server has told.
+    room_info: Option<RoomData>,
+    export_open: bool,
+    /// What the sidebar's user list is narrowed to, see `filter_users`.
+    user_filter: String,
+    user_search_open: bool,
+    /// The users search I last sent, and the matches for it so far.
+    user_query: String,
+    found_users: Vec<String>,
+    /// Whether the server has more matches than `found_users`.
+    more_users: bool,
+    /// Whether an answer to `user_query` is still on its way.
+    searching_users: bool,
+    /// Users whose messages, typing and calls I don't want to see, saved
+    /// with `blocklist::save`.
+    blocked: HashSet<String>,
+    /// Starred room messages, saved with `favorites::save`.
+    favorites: Vec<Favorite>,
+    user_menu: Option<UserMenu>,
+    /// The user whose card is open, see `UserPopover`.
+    user_card: Option<UserMenu>,
+    /// The message the forward dialog is open for.
+    forwarding: Option<MessageData>,
 }
 impl Component for Chat {
     type Message = Msg;
     type Properties = ();
 
     fn create(ctx: &Context<Self>) -> Self {
-        let (user, _) = ctx
+        let wss = WebsocketService::new(websocket::resolve_url());
+        let (theme, theme_listener) = ctx
             .link()
-            .context::<User>(Callback::noop())
-            .expect("context to be set");
-        let wss = WebsocketService::new();
-        let username = user.username.borrow().clone();
+            .context::<ThemeContext>(ctx.link().callback(Msg::ThemeChanged))
+            .expect("No theme context found.");
 
-        let message = WebSocketMessage {
-            message_type: MsgTypes::Register,
-            data: Some(username.to_string()),
-            data_array: None,
-        };
+        let link = ctx.link().clone();
+        let visibility_listener =
+            Closure::wrap(
+                Box::new(move || link.send_message(Msg::VisibilityChanged)) as Box<dyn Fn()>
+            );
+        if let Some(document) = web_sys::window().and_then(|w| w.document()) {
+            let _ = document.add_event_listener_with_callback(
+                "visibilitychange",
+                visibility_listener.as_ref().unchecked_ref(),
+            );
+        }
 
-        if let Ok(_) = wss
-            .tx
-            .clone()
-            .try_send(serde_json::to_string(&message).unwrap())
-        {
-            log::debug!("message sent successfully");
+        let focus_listeners: Vec<(&'static str, Listener)> = [("focus", true), ("blur", false)]
+            .into_iter()
+            .map(|(event, focused)| {
+                let link = ctx.link().clone();
+                let listener =
+                    Closure::wrap(
+                        Box::new(move || link.send_message(Msg::FocusChanged(focused)))
+                            as Box<dyn Fn()>,
+                    );
+                if let Some(window) = web_sys::window() {
+                    let _ = window
+                        .add_event_listener_with_callback(event, listener.as_ref().unchecked_ref());
+                }
+                (event, listener)
+            })
+            .collect();
+        let focused = web_sys::window()
+            .and_then(|w| w.document())
+            .and_then(|d| d.has_focus().ok())
+            .unwrap_or(true);
+
+        let link = ctx.link().clone();
+        let resize_listener =
+            Closure::wrap(Box::new(move || link.send_message(Msg::Resized)) as Box<dyn Fn()>);
+        if let Some(window) = web_sys::window() {
+            let _ = window.add_event_listener_with_callback(
+                "resize",
+                resize_listener.as_ref().unchecked_ref(),
+            );
         }
 
-        Self {
+        let link = ctx.link().clone();
+        let on_messages_seen = Closure::wrap(Box::new(move |entries: js_sys::Array| {
+            let newest = entries
+                .iter()
+                .map(JsCast::unchecked_into::<IntersectionObserverEntry>)
+                .filter(|entry| entry.is_intersecting())
+                .filter_map(|entry| entry.target().get_attribute("data-id")?.parse().ok())
+                .max();
+            if let Some(id) = newest {
+                link.send_message(Msg::MessagesSeen(id));
+            }
+        }) as Box<dyn FnMut(js_sys::Array)>);
+        let read_observer = IntersectionObserver::new(on_messages_seen.as_ref().unchecked_ref())
+            .map_err(|e| log::warn!("read receipts disabled: {:?}", e))
+            .ok();
+
+        let mut chat = Self {
             users: vec![],
             messages: vec![],
+            rooms: vec![],
+            room: DEFAULT_ROOM.to_string(),
+            room_history: HashMap::new(),
+            dm_threads: HashMap::new(),
+            dm_unread: HashSet::new(),
+            keys: None,
+            peer_keys: HashMap::new(),
+            shared_keys: HashMap::new(),
+            call: None,
+            remote_video: NodeRef::default(),
+            local_video: NodeRef::default(),
+            active_dm: None,
+            typists: HashMap::new(),
+            typing_debounce: None,
+            limiter: RateLimiter::new(SEND_LIMIT, SEND_WINDOW_MS),
+            slow_down: None,
+            typing_sweep: None,
+            connection: ReconnectState::Connecting,
+            errors: vec![],
+            latency_ms: None,
+            pending: 0,
+            message_list: NodeRef::default(),
+            at_bottom: true,
+            unseen: 0,
+            scroll_pending: false,
+            visibility_listener,
+            focus_listeners,
+            focused,
+            resize_listener,
+            is_mobile: narrow_window(),
+            sidebar_open: false,
+            unread_marker: None,
+            unread_divider: NodeRef::default(),
+            unread_timeout: None,
+            member_delta: None,
+            tab_badge: TabBadge::new(),
+            search_query: String::new(),
+            search_open: false,
+            search_draft: String::new(),
+            search_debounce: None,
+            search_focus_pending: false,
+            search_match: 0,
+            search_scroll_pending: false,
+            search_target: NodeRef::default(),
+            search_anchor: None,
+            active_filter: None,
+            filter_hidden: 0,
+            recent: RecentKeys::new(RECENT_MESSAGES),
+            read_up_to: HashMap::new(),
+            last_read_sent: 0,
+            read_pending: 0,
+            read_timeout: None,
+            synced_read: None,
+            unread_scroll_pending: false,
+            read_observer,
+            _on_messages_seen: on_messages_seen,
+            history_len: 0,
+            history_requested: false,
+            history_more: false,
+            loading_older: false,
+            scroll_anchor: None,
+            notify: NotificationPrefs::load(),
+            sound: notify::Sound::new()
+                .map_err(|e| log::warn!("notification sound disabled: {:?}", e))
+                .ok(),
+            permission_asked: false,
+            jump_pending: None,
+            theme,
+            _theme_listener: theme_listener,
+            language: translations::language(),
+            avatar_style: AVATAR_STYLES[0].to_string(),
+            chosen_status: PresenceStatus::Online,
+            profile_open: false,
+            settings_open: false,
+            room_dialog_open: false,
+            gifs_open: false,
+            room_error: None,
+            invites: HashMap::new(),
+            staff_token: ctx
+                .link()
+                .context::<User>(Callback::noop())
+                .map(|(user, _)| user.token.borrow().clone())
+                .filter(|token| !token.is_empty()),
+            room_info: None,
+            export_open: false,
+            user_filter: String::new(),
+            user_search_open: false,
+            user_query: String::new(),
+            found_users: vec![],
+            more_users: false,
+            searching_users: false,
+            blocked: blocklist::load(),
+            favorites: favorites::load(),
+            user_menu: None,
+            user_card: None,
+            forwarding: None,
             chat_input: NodeRef::default(),
+            input_rows: 1,
+            input_chars: 0,
+            max_chars: max_message_chars(),
+            input_rejected: false,
+            replying_to: None,
+            mention: None,
+            pinned: vec![],
+            pins_open: false,
+            copied: None,
+            copied_reset: None,
+            flashing: None,
+            flash_reset: None,
+            mentions: vec![],
+            mentions_open: false,
+            previews: HashMap::new(),
+            editing: None,
+            confirm_delete: None,
+            reporting: None,
+            reported: HashSet::new(),
+            edit_input: NodeRef::default(),
+            edit_focus_pending: false,
+            search_input: NodeRef::default(),
+            image_input: NodeRef::default(),
+            file_input: NodeRef::default(),
+            transfers: HashMap::new(),
+            recorder: None,
+            recording: false,
+            waveform: None,
+            waveform_canvas: NodeRef::default(),
             wss,
-            _producer: EventBus::bridge(ctx.link().callback(Msg::HandleMsg)),
-        }
+            bus: EventBus::bridge(ctx.link().callback(Msg::HandleBus)),
+        };
+        // Registering waits for the stored history, so that it ends up
+        // above anything the server sends once we've joined.
+        chat.load_history(ctx);
+        chat.bus.send(BusEvent::RoomChanged(chat.room.clone()));
+        let link = ctx.link().clone();
+        wasm_bindgen_futures::spawn_local(async move {
+            let keys = crypto::generate()
+                .await
+                .map_err(|e| log::warn!("encrypted DMs disabled: {:?}", e))
+                .ok();
+            link.send_message(Msg::KeysReady(keys));
+        });
+        chat
     }
 
-    fn update(&mut self, _ctx: &Context<Self>, msg: Self::Message) -> bool {
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
         match msg {
-            Msg::HandleMsg(s) => {
-                let msg: WebSocketMessage = serde_json::from_str(&s).unwrap();
-                match msg.message_type {
-                    MsgTypes::Users => {
-                        let users_from_message = msg.data_array.unwrap_or_default();
-                        self.users = users_from_message
-                            .iter()
-                            .map(|u| UserProfile {
+            Msg::HandleBus(event) => match event {
+                BusEvent::Users { room, users } => {
+                    if room.is_some_and(|room| room != self.room) {
+                        return false;
+                    }
+                    // Being listed means the server has taken our Register.
+                    if !self.history_requested {
+                        self.history_requested = true;
+                        self.request_history(None);
+                        // The server forgets avatars along with the socket.
+                        if self.avatar_style != AVATAR_STYLES[0] {
+                            self.send_profile(current_username(ctx));
+                        }
+                        // Likewise statuses, which start out as online.
+                        if self.my_status() != PresenceStatus::Online {
+                            self.send_presence(ctx);
+                        }
+                    }
+                    let mut known: HashMap<String, UserProfile> =
+                        self.users.drain(..).map(|u| (u.name.clone(), u)).collect();
+                    let current_user = current_username(ctx);
+                    // The first list after joining is everyone already here,
+                    // not news.
+                    let first_list = known.is_empty();
+                    if !first_list {
+                        let change = membership_change(&known, &users, &current_user);
+                        self.announce_membership(&change, &current_user);
+                        self.show_member_delta(ctx, change.delta());
+                    }
+                    self.users = users
+                        .iter()
+                        .map(|u| {
+                            known.remove(u).unwrap_or_else(|| UserProfile {
                                 name: u.into(),
-                                avatar: format!(
-                                    "https://avatars.dicebear.com/api/adventurer-neutral/{}.svg",
-                                    u
-                                )
-                                .into(),
+                                avatar: avatar_url(AVATAR_STYLES[0], u),
+                                status: PresenceStatus::Online,
+                                role: UserRole::User,
+                                online_since: (!first_list || *u == current_user)
+                                    .then(|| js_sys::Date::now() as u64),
+                                timezone: String::new(),
+                                locale: String::new(),
                             })
-                            .collect();
-                        return true;
+                        })
+                        .collect();
+                    self.sort_users();
+                    if self
+                        .user_card
+                        .as_ref()
+                        .is_some_and(|card| !users.contains(&card.name))
+                    {
+                        self.user_card = None;
                     }
-                    MsgTypes::Message => {
-                        let message_data: MessageData =
-                            serde_json::from_str(&msg.data.unwrap()).unwrap();
-                        self.messages.push(message_data);
-                        return true;
+                    // Nothing more would show up.
+                    if self
+                        .active_filter
+                        .as_ref()
+                        .is_some_and(|from| !users.contains(from))
+                    {
+                        self.active_filter = None;
+                        self.filter_hidden = 0;
                     }
-                    _ => {
+                    true
+                }
+                BusEvent::History {
+                    room,
+                    before,
+                    more,
+                    messages,
+                } => {
+                    if room.is_some_and(|room| room != self.room) {
+                        return false;
+                    }
+                    self.history_more = more;
+                    match before {
+                        Some(_) => {
+                            self.loading_older = false;
+                            self.scroll_anchor = self
+                                .message_list
+                                .cast::<web_sys::Element>()
+                                .map(|list| (list.scroll_height(), list.scroll_top()));
+                            self.prepend_history(messages, &current_username(ctx));
+                        }
+                        None => {
+                            self.merge_history(messages, &current_username(ctx));
+                            if let Some(cursor) = self.synced_read.take() {
+                                self.restore_read_position(cursor, &current_username(ctx));
+                            }
+                            // Not in the latest page either; `JumpTo` says so.
+                            if let Some(id) = self.jump_pending {
+                                if !self.messages.iter().any(|m| m.id == Some(id)) {
+                                    self.jump_pending = None;
+                                    ctx.link().send_message(Msg::JumpTo(id));
+                                }
+                            }
+                        }
+                    }
+                    self.active_dm.is_none()
+                }
+                BusEvent::ReadSync(cursors) => {
+                    // Applied to the latest history page, which is asked for
+                    // once the server has registered us and so comes next.
+                    self.synced_read = cursors.get(&self.room).copied();
+                    false
+                }
+                BusEvent::Read { room, receipt } => {
+                    if room.is_some_and(|room| room != self.room) {
+                        return false;
+                    }
+                    let read = self.read_up_to.entry(receipt.name).or_default();
+                    if receipt.id <= *read {
                         return false;
                     }
+                    *read = receipt.id;
+                    self.active_dm.is_none()
                 }
-            }
+                BusEvent::Presence(PresenceData { name, status }) => {
+                    match self.users.iter_mut().find(|u| u.name == name) {
+                        Some(user) if user.status != status => user.status = status,
+                        _ => return false,
+                    }
+                    self.sort_users();
+                    true
+                }
+                BusEvent::Message { room, message } => {
+                    if !self.recent.insert(message.dedup_key()) {
+                        log::debug!("dropping duplicate message {:?}", message.id);
+                        return false;
+                    }
+                    if let Some(room) = room.filter(|room| *room != self.room) {
+                        // Arrived just as we switched away; keep it for later.
+                        let messages = self.room_history.entry(room.clone()).or_default();
+                        messages.push(message);
+                        history::save(history::key(&room, &current_username(ctx)), messages);
+                        return false;
+                    }
+                    self.typists.remove(&message.from);
+                    let from_self = message.from == current_username(ctx);
+                    let blocked = self.blocked.contains(&message.from);
+                    let hidden = self
+                        .active_filter
+                        .as_ref()
+                        .is_some_and(|from| *from != message.from);
+                    if self.active_dm.is_none() && !blocked {
+                        if hidden {
+                            self.filter_hidden += 1;
+                        } else {
+                            self.on_new_message(from_self);
+                        }
+                    }
+                    if !from_self && !blocked {
+                        let mentioned =
+                            mentions::mentions(&message.message, &current_username(ctx));
+                        let id = message.id;
+                        self.alert(
+                            &message,
+                            &t_args("notify.in_room", &[&message.from, &self.room]),
+                            mentioned,
+                            ctx.link()
+                                .callback(move |_| Msg::ShowNotified { peer: None, id }),
+                        );
+                        if let (true, Some(id)) = (mentioned, message.id) {
+                            self.mentions.push(Mention {
+                                room: self.room.clone(),
+                                id,
+                                from: message.from.clone(),
+                                sent_at: message.sent_at,
+                                excerpt: excerpt(&message.message),
+                                read: false,
+                            });
+                            let excess = self.mentions.len().saturating_sub(MAX_MENTIONS);
+                            self.mentions.drain(..excess);
+                        }
+                        if self.away() {
+                            self.tab_badge.increment();
+                            if self.unread_marker.is_none() {
+                                self.unread_marker = Some(message.dedup_key());
+                            }
+                        }
+                    }
+                    self.messages.push(message);
+                    history::save(
+                        history::key(&self.room, &current_username(ctx)),
+                        &self.messages,
+                    );
+                    true
+                }
+                BusEvent::Reactions {
+                    room,
+                    id,
+                    reactions,
+                } => {
+                    let room = room.unwrap_or_else(|| self.room.clone());
+                    let is_current = room == self.room;
+                    let messages = if is_current {
+                        &mut self.messages
+                    } else {
+                        match self.room_history.get_mut(&room) {
+                            Some(messages) => messages,
+                            None => return false,
+                        }
+                    };
+                    match find_message(messages, id) {
+                        Some(message) => message.reactions = reactions,
+                        None => return false,
+                    }
+                    history::save(history::key(&room, &current_username(ctx)), messages);
+                    is_current && self.active_dm.is_none()
+                }
+                BusEvent::Edited { room, edit } => {
+                    let room = room.unwrap_or_else(|| self.room.clone());
+                    let is_current = room == self.room;
+                    let pin = self.pinned.iter_mut().find(|p| p.id == Some(edit.id));
+                    if let (true, Some(pin)) = (is_current, pin) {
+                        pin.message = edit.message.clone();
+                        pin.edited = true;
+                    }
+                    let messages = if is_current {
+                        &mut self.messages
+                    } else {
+                        match self.room_history.get_mut(&room) {
+                            Some(messages) => messages,
+                            None => return false,
+                        }
+                    };
+                    match find_message(messages, edit.id) {
+                        Some(message) => {
+                            message.message = edit.message;
+                            message.edited = true;
+                        }
+                        None => return false,
+                    }
+                    history::save(history::key(&room, &current_username(ctx)), messages);
+                    is_current && self.active_dm.is_none()
+                }
+                BusEvent::Deleted { room, id } => {
+                    let room = room.unwrap_or_else(|| self.room.clone());
+                    let is_current = room == self.room;
+                    if is_current {
+                        self.pinned.retain(|p| p.id != Some(id));
+                    }
+                    let messages = if is_current {
+                        &mut self.messages
+                    } else {
+                        match self.room_history.get_mut(&room) {
+                            Some(messages) => messages,
+                            None => return false,
+                        }
+                    };
+                    // Left in place, so neither the timeline nor replies to
+                    // it lose their footing.
+                    let message = match find_message(messages, id) {
+                        Some(message) => message,
+                        None => return false,
+                    };
+                    message.deleted = true;
+                    message.message.clear();
+                    message.reactions.clear();
+                    if let Some(file) = message.file.take() {
+                        self.transfers.remove(&file);
+                    }
+                    history::save(history::key(&room, &current_username(ctx)), messages);
+                    if is_current && self.editing == Some(id) {
+                        self.editing = None;
+                    }
+                    if self.confirm_delete == Some(id) {
+                        self.confirm_delete = None;
+                    }
+                    is_current && self.active_dm.is_none()
+                }
+                BusEvent::Hidden { room, id } => {
+                    let room = room.unwrap_or_else(|| self.room.clone());
+                    let is_current = room == self.room;
+                    if is_current {
+                        if let Some(pin) = self.pinned.iter_mut().find(|p| p.id == Some(id)) {
+                            pin.hidden = true;
+                            pin.message.clear();
+                        }
+                    }
+                    let messages = if is_current {
+                        &mut self.messages
+                    } else {
+                        match self.room_history.get_mut(&room) {
+                            Some(messages) => messages,
+                            None => return false,
+                        }
+                    };
+                    // Nothing of it is kept, on disk least of all.
+                    let message = match find_message(messages, id) {
+                        Some(message) => message,
+                        None => return false,
+                    };
+                    message.hidden = true;
+                    message.message.clear();
+                    if let Some(file) = message.file.take() {
+                        self.transfers.remove(&file);
+                    }
+                    history::save(history::key(&room, &current_username(ctx)), messages);
+                    if self.reporting.is_some_and(|(reporting, _)| reporting == id) {
+                        self.reporting = None;
+                    }
+                    is_current && self.active_dm.is_none()
+                }
+                BusEvent::Pinned { room, message } => {
+                    if room.is_some_and(|room| room != self.room)
+                        || self.pinned.iter().any(|p| p.id == message.id)
+                    {
+                        return false;
+                    }
+                    self.pinned.push(message);
+                    true
+                }
+                BusEvent::Unpinned { room, id } => {
+                    if room.is_some_and(|room| room != self.room) {
+                        return false;
+                    }
+                    let before = self.pinned.len();
+                    self.pinned.retain(|p| p.id != Some(id));
+                    self.pinned.len() != before
+                }
+                BusEvent::LinkPreview(data) => {
+                    if !self.messages.iter().any(|m| m.id == Some(data.id)) {
+                        return false;
+                    }
+                    // The server's Open Graph details beat whatever the
+                    // oEmbed endpoint had, or didn't have.
+                    self.previews
+                        .insert(data.url.clone(), PreviewState::Loaded(data.into()));
+                    self.active_dm.is_none()
+                }
+                BusEvent::FileChunk { room, chunk } => {
+                    if room.is_some_and(|room| room != self.room) {
+                        return false;
+                    }
+                    if chunk.total_chunks == 0
+                        || chunk.total_chunks > MAX_FILE_CHUNKS
+                        || chunk.chunk_index >= chunk.total_chunks
+                    {
+                        log::warn!(
+                            "dropping piece {}/{} of file {}",
+                            chunk.chunk_index,
+                            chunk.total_chunks,
+                            chunk.file_id
+                        );
+                        return false;
+                    }
+                    let first = !self.transfers.contains_key(&chunk.file_id);
+                    self.transfers
+                        .entry(chunk.file_id.clone())
+                        .or_insert_with(|| FileTransfer::new(&chunk.mime, chunk.total_chunks))
+                        .add(chunk.chunk_index, chunk.data);
+                    if first {
+                        // The first piece to arrive announces the file.
+                        let message = MessageData::file(chunk.from, chunk.name, chunk.file_id);
+                        return self.update(
+                            ctx,
+                            Msg::HandleBus(BusEvent::Message {
+                                room: None,
+                                message,
+                            }),
+                        );
+                    }
+                    true
+                }
+                BusEvent::Rooms(rooms) => {
+                    self.rooms = rooms;
+                    // Private rooms aren't listed.
+                    for room in self.invites.keys() {
+                        if !self.rooms.contains(room) {
+                            self.rooms.push(room.clone());
+                        }
+                    }
+                    true
+                }
+                BusEvent::RoomCreated(room) => self.update(
+                    ctx,
+                    Msg::EnterRoom {
+                        room: room.name,
+                        invite: room.invite,
+                    },
+                ),
+                BusEvent::UserSearchResults(results) => {
+                    // Answers to searches typed over since don't count.
+                    if results.query != self.user_query || results.offset != self.found_users.len()
+                    {
+                        return false;
+                    }
+                    self.found_users.extend(results.users);
+                    self.more_users = results.more;
+                    self.searching_users = false;
+                    true
+                }
+                BusEvent::RoomInfo(room) => {
+                    if room.name != self.room {
+                        return false;
+                    }
+                    self.room_info = Some(room);
+                    true
+                }
+                BusEvent::RoomError(error) => {
+                    let message = match error.reason {
+                        RoomErrorReason::Exists => t_args("room.exists", &[&error.room]),
+                        RoomErrorReason::NotFound => t_args("room.not_found", &[&error.room]),
+                        RoomErrorReason::Invalid => t("room.invalid").to_string(),
+                        RoomErrorReason::Private => {
+                            // It didn't work, so it's no use trying again.
+                            self.invites.remove(&error.room);
+                            t_args("room.private_error", &[&error.room])
+                        }
+                        RoomErrorReason::Full => t_args("room.full", &[&error.room]),
+                    };
+                    // The room I'm in is gone, e.g. after the server restarted,
+                    // or it won't have me.
+                    let refused = matches!(
+                        error.reason,
+                        RoomErrorReason::NotFound
+                            | RoomErrorReason::Private
+                            | RoomErrorReason::Full
+                    );
+                    if refused && error.room == self.room {
+                        self.update(ctx, Msg::JoinRoom(DEFAULT_ROOM.to_string()));
+                    }
+                    if self.room_dialog_open {
+                        self.room_error = Some(message);
+                        true
+                    } else {
+                        self.report_error(&message)
+                    }
+                }
+                BusEvent::NameReserved(reserved) => {
+                    // A rename the server turned down: back to the old name,
+                    // also for the next reconnect.
+                    if let Some(previous) = reserved.previous {
+                        if let Some((user, _)) = ctx.link().context::<User>(Callback::noop()) {
+                            *user.username.borrow_mut() = previous.clone();
+                        }
+                        self.set_hello(previous);
+                    }
+                    self.report_error(&t_args("error.name_reserved", &[&reserved.name]))
+                }
+                BusEvent::DirectMessage { to, message } => {
+                    if !self.recent.insert(message.dedup_key()) {
+                        log::debug!("dropping duplicate direct message");
+                        return false;
+                    }
+                    if message.encrypted {
+                        self.decrypt(ctx, to, message);
+                        return false;
+                    }
+                    self.receive_direct_message(ctx, to, message)
+                }
+                BusEvent::CallOffer(CallSignal { from, sdp, .. }) => {
+                    let sdp = match sdp {
+                        Some(sdp) => sdp,
+                        None => return false,
+                    };
+                    if self.call.is_some() || self.blocked.contains(&from) {
+                        // Busy, as far as they can tell.
+                        self.send_call_signal(MsgTypes::CallEnd, &from, CallSignal::default());
+                        return false;
+                    }
+                    self.call = Some(Call::new(from, CallState::Ringing(sdp)));
+                    true
+                }
+                BusEvent::CallAnswer(CallSignal { from, sdp, .. }) => {
+                    let (call, sdp) = match (&mut self.call, sdp) {
+                        (Some(call), Some(sdp))
+                            if call.peer == from && matches!(call.state, CallState::Calling) =>
+                        {
+                            (call, sdp)
+                        }
+                        _ => return false,
+                    };
+                    let connection = match &call.connection {
+                        Some(connection) => connection.clone(),
+                        None => return false,
+                    };
+                    call.state = CallState::Connecting;
+                    let link = ctx.link().clone();
+                    wasm_bindgen_futures::spawn_local(async move {
+                        let result = connection.accept(&sdp).await;
+                        if let Err(e) = &result {
+                            log::warn!("call: couldn't take up the answer: {:?}", e);
+                        }
+                        link.send_message(Msg::CallDescribed {
+                            peer: from,
+                            ok: result.is_ok(),
+                        });
+                    });
+                    true
+                }
+                BusEvent::IceCandidate(CallSignal {
+                    from,
+                    candidate: Some(candidate),
+                    ..
+                }) => {
+                    if let Some(call) = self.call.as_mut().filter(|call| call.peer == from) {
+                        match &call.connection {
+                            Some(connection) if call.described => {
+                                add_candidate(connection.clone(), candidate)
+                            }
+                            _ => call.pending.push(candidate),
+                        }
+                    }
+                    false
+                }
+                BusEvent::IceCandidate(_) => false,
+                BusEvent::CallEnd(CallSignal { from, .. }) => {
+                    let call = match self.call.take() {
+                        Some(call) if call.peer == from => call,
+                        other => {
+                            self.call = other;
+                            return false;
+                        }
+                    };
+                    if let Some(connection) = &call.connection {
+                        connection.hang_up();
+                    }
+                    if matches!(call.state, CallState::Calling) {
+                        self.report_error(&t_args("call.declined", &[&from]));
+                    }
+                    true
+                }
+                BusEvent::PublicKey(PublicKeyData { name, key }) => {
+                    if name == current_username(ctx) || self.peer_keys.get(&name) == Some(&key) {
+                        return false;
+                    }
+                    // Whatever was agreed with their old key is no good now.
+                    self.shared_keys.remove(&name);
+                    self.peer_keys.insert(name.clone(), key);
+                    self.derive_key(ctx, name);
+                    true
+                }
+                BusEvent::Profile(profile) => {
+                    let previous = profile.previous.unwrap_or_else(|| profile.name.clone());
+                    let user = match self.users.iter_mut().find(|u| u.name == previous) {
+                        Some(user) => user,
+                        None => return false,
+                    };
+                    user.role = profile.role;
+                    if !profile.timezone.is_empty() {
+                        user.timezone = profile.timezone.clone();
+                        user.locale = profile.locale.clone();
+                    }
+                    if !profile.avatar.is_empty() {
+                        user.avatar = avatar_url(&profile.avatar, &profile.name);
+                    }
+                    if previous != profile.name {
+                        user.name = profile.name.clone();
+                        if let Some(read) = self.read_up_to.remove(&previous) {
+                            self.read_up_to.insert(profile.name.clone(), read);
+                        }
+                        if let Some(key) = self.peer_keys.remove(&previous) {
+                            self.peer_keys.insert(profile.name.clone(), key);
+                        }
+                        if let Some(key) = self.shared_keys.remove(&previous) {
+                            self.shared_keys.insert(profile.name.clone(), key);
+                        }
+                        self.typists.remove(&previous);
+                        if self.active_filter.as_ref() == Some(&previous) {
+                            self.active_filter = Some(profile.name.clone());
+                        }
+                        self.messages.push(MessageData::system(t_args(
+                            "notice.renamed",
+                            &[&previous, &profile.name],
+                        )));
+                        if self.active_dm.is_none() {
+                            self.on_new_message(false);
+                        }
+                    }
+                    true
+                }
+                BusEvent::Moderated { room, action } => {
+                    if action.target == current_username(ctx) {
+                        // The server closes the connection next, and
+                        // reconnecting would only register us again.
+                        let now = js_sys::Date::now();
+                        self.wss.hold(
+                            action
+                                .duration_minutes
+                                .map(|minutes| now + f64::from(minutes) * 60_000.0),
+                        );
+                        let error = match action.duration_minutes {
+                            Some(minutes) => t_args("error.banned", &[&minutes]),
+                            None => t("error.kicked").to_string(),
+                        };
+                        return self.report_error(&error);
+                    }
+                    if room.is_some_and(|room| room != self.room) {
+                        return false;
+                    }
+                    // Before the new users list, so they aren't announced
+                    // as having left on top of this.
+                    self.users.retain(|u| u.name != action.target);
+                    self.typists.remove(&action.target);
+                    let notice = match action.duration_minutes {
+                        Some(minutes) => t_args("notice.banned", &[&action.target, &minutes]),
+                        None => t_args("notice.kicked", &[&action.target]),
+                    };
+                    self.messages.push(MessageData::system(notice));
+                    if self.active_dm.is_none() {
+                        self.on_new_message(false);
+                    }
+                    true
+                }
+                BusEvent::Typing(typist) => {
+                    if typist.is_empty()
+                        || typist == current_username(ctx)
+                        || self.blocked.contains(&typist)
+                    {
+                        return false;
+                    }
+                    let is_new = self.typists.insert(typist, js_sys::Date::now()).is_none();
+                    if self.typing_sweep.is_none() {
+                        let link = ctx.link().clone();
+                        self.typing_sweep = Some(Interval::new(TYPING_SWEEP_MS, move || {
+                            link.send_message(Msg::ExpireTyping)
+                        }));
+                    }
+                    is_new
+                }
+                BusEvent::Latency(ms) => {
+                    self.latency_ms = Some(ms);
+                    true
+                }
+                BusEvent::ConnectionChanged(status) => {
+                    // The service re-registers us on its own after a
+                    // reconnect, see `register`.
+                    self.connection = status.into();
+                    if self.is_connected() {
+                        self.pending = 0;
+                        // Catch up on whatever was said while we were away.
+                        self.history_requested = false;
+                        self.loading_older = false;
+                    } else {
+                        self.latency_ms = None;
+                    }
+                    true
+                }
+                BusEvent::Unreadable => self.report_error(t("error.unreadable")),
+                // Nothing is sent as binary yet.
+                BusEvent::Binary(bytes) => {
+                    log::debug!("ignoring a binary frame of {} bytes", bytes.len());
+                    false
+                }
+                // Published from here, for other components to follow.
+                BusEvent::RoomChanged(_) => false,
+            },
             Msg::SubmitMessage => {
-                let input = self.chat_input.cast::<HtmlInputElement>();
+                let input = self.chat_input.cast::<HtmlTextAreaElement>();
                 if let Some(input) = input {
-                    let message = WebSocketMessage {
-                        message_type: MsgTypes::Message,
-                        data: Some(input.value()),
-                        data_array: None,
+                    let text = match normalize_outgoing(&input.value()) {
+                        Some(text) => text,
+                        None => {
+                            self.input_rejected = true;
+                            let _ = input.focus();
+                            return true;
+                        }
                     };
-                    if let Err(e) = self
-                        .wss
-                        .tx
-                        .clone()
-                        .try_send(serde_json::to_string(&message).unwrap())
-                    {
-                        log::debug!("error sending to channel: {:?}", e);
+                    self.mention = None;
+                    if text.chars().count() > self.max_chars {
+                        self.input_rejected = true;
+                        self.report_error(&t_args("error.too_long", &[&self.max_chars]));
+                        return true;
+                    }
+                    if self.slow_down.is_some() {
+                        return false;
+                    }
+                    if !self.limiter.try_send() {
+                        let link = ctx.link().clone();
+                        let wait = self.limiter.wait_ms().ceil() as u32;
+                        self.slow_down = Some(Timeout::new(wait, move || {
+                            link.send_message(Msg::RateLimitElapsed)
+                        }));
+                        return true;
+                    }
+                    if let Some(command) = parse_command(&text) {
+                        input.set_value("");
+                        self.input_chars = 0;
+                        let shrunk = self.reset_input_rows();
+                        return self.run_command(ctx, command) || shrunk;
+                    }
+                    let text = match text.strip_prefix("//") {
+                        Some(rest) => format!("/{}", rest),
+                        None => text,
+                    };
+                    let text = replace_shortcodes(&text);
+                    if !self.send_message(ctx, text) {
+                        return false;
                     }
                     input.set_value("");
+                    self.input_chars = 0;
+                    // Sending with the button would leave focus on it.
+                    let _ = input.focus();
+                    return self.reset_input_rows() || !self.is_connected();
                 };
                 false
             }
-        }
-    }
-
-    fn view(&self, ctx: &Context<Self>) -> Html {
-        let submit = ctx.link().callback(|_| Msg::SubmitMessage);
-        let current_user = ctx.link().context::<User>(Callback::noop()).unwrap().0.username.borrow().clone();
-
-        html! {
-            <div class="flex w-screen">
-                <div class="flex-none w-56 h-screen bg-blue-100"> // <- updated to lighter blue
-                    <div class="text-xl p-3 font-semibold text-blue-800">{"👥 Active Users"}</div>
-                    {
-                        self.users.clone().iter().map(|u| {
-                            html!{
-                                <div class="flex m-3 bg-white rounded-lg p-2 hover:bg-blue-200 transition-all cursor-pointer">
-                                    <img class="w-10 h-10 rounded-full" src={u.avatar.clone()} alt="avatar"/>
-                                    <div class="flex-grow pl-3 pt-1">
-                                        <div class="text-sm font-medium text-gray-700">{u.name.clone()}</div>
-                                        <div class="text-xs text-gray-400">{"Hi there!"}</div>
-                                    </div>
-                                </div>
-                            }
-                        }).collect::<Html>()
+            Msg::ShowGifs(open) => std::mem::replace(&mut self.gifs_open, open) != open,
+            Msg::PickGif(url) => {
+                self.gifs_open = false;
+                if let Some(input) = self.chat_input.cast::<HtmlTextAreaElement>() {
+                    input.set_value(&url);
+                    let _ = input.focus();
+                }
+                self.count_input();
+                true
+            }
+            Msg::PickImage => {
+                if let Some(input) = self.image_input.cast::<HtmlInputElement>() {
+                    input.click();
+                }
+                false
+            }
+            Msg::ImagePicked => {
+                let input = match self.image_input.cast::<HtmlInputElement>() {
+                    Some(input) => input,
+                    None => return false,
+                };
+                let file = input.files().and_then(|files| files.get(0));
+                // Clear it so that picking the same file again still fires.
+                input.set_value("");
+                let file = match file {
+                    Some(file) => file,
+                    None => return false,
+                };
+                if !file.type_().starts_with("image/") {
+                    return self.report_error(t("error.images_only"));
+                }
+                if file.size() > MAX_IMAGE_BYTES {
+                    return self.report_error(&t_args(
+                        "error.image_too_large",
+                        &[&file.name(), &(MAX_IMAGE_BYTES / 1024.0)],
+                    ));
+                }
+                if let Err(e) = read_as_data_url(&file, ctx.link().callback(Msg::ImageLoaded)) {
+                    log::warn!("failed to read {}: {:?}", file.name(), e);
+                    return self.report_error(t("error.image_unreadable"));
+                }
+                false
+            }
+            Msg::ImageLoaded(url) => {
+                if safe_image_url(&url).is_none() {
+                    return self.report_error(t("error.image_type"));
+                }
+                self.send_message(ctx, url) && !self.is_connected()
+            }
+            Msg::PickFile => {
+                if let Some(input) = self.file_input.cast::<HtmlInputElement>() {
+                    input.click();
+                }
+                false
+            }
+            Msg::FilePicked => {
+                let input = match self.file_input.cast::<HtmlInputElement>() {
+                    Some(input) => input,
+                    None => return false,
+                };
+                let file = input.files().and_then(|files| files.get(0));
+                input.set_value("");
+                let file = match file {
+                    Some(file) => file,
+                    None => return false,
+                };
+                if self.active_dm.is_some() {
+                    return self.report_error(t("error.files_in_rooms"));
+                }
+                if !self.is_connected() {
+                    return self.report_error(t("error.files_offline"));
+                }
+                if file.size() > MAX_FILE_BYTES {
+                    return self.report_error(&t_args(
+                        "error.file_too_large",
+                        &[&file.name(), &(MAX_FILE_BYTES / 1024.0 / 1024.0)],
+                    ));
+                }
+                let (name, mime) = (file.name(), file.type_());
+                let on_load = ctx.link().callback(move |url| Msg::FileLoaded {
+                    name: name.clone(),
+                    mime: mime.clone(),
+                    url,
+                    voice: false,
+                });
+                if let Err(e) = read_as_data_url(&file, on_load) {
+                    log::warn!("failed to read {}: {:?}", file.name(), e);
+                    return self.report_error(t("error.file_unreadable"));
+                }
+                false
+            }
+            Msg::FileLoaded {
+                name,
+                mime,
+                url,
+                voice,
+            } => {
+                let data = match url.split_once(',') {
+                    Some((_, data)) => data,
+                    None => return false,
+                };
+                let file_id = format!(
+                    "{:x}-{:x}",
+                    js_sys::Date::now() as u64,
+                    (js_sys::Math::random() * f64::from(u32::MAX)) as u32
+                );
+                // An empty file still takes one (empty) piece.
+                let total_chunks = data.len().div_ceil(FILE_CHUNK_LEN).max(1);
+                for chunk_index in 0..total_chunks {
+                    // base64 is ASCII, so any byte offset is a char boundary.
+                    let start = chunk_index * FILE_CHUNK_LEN;
+                    let chunk = FileChunk {
+                        file_id: file_id.clone(),
+                        chunk_index,
+                        total_chunks,
+                        data: data[start..(start + FILE_CHUNK_LEN).min(data.len())].to_string(),
+                        name: name.clone(),
+                        mime: mime.clone(),
+                        from: String::new(),
+                    };
+                    let kind = if voice {
+                        MsgTypes::Audio
+                    } else {
+                        MsgTypes::FileChunk
+                    };
+                    let sent = self.send(&WebSocketMessage {
+                        data: serde_json::to_string(&chunk).ok(),
+                        room: Some(self.room.clone()),
+                        ..WebSocketMessage::new(kind)
+                    });
+                    if !sent {
+                        return self.report_error(&t_args("error.file_send", &[&name]));
                     }
-                </div>
+                }
+                self.jump_to_bottom();
+                false
+            }
+            Msg::Record(true) => {
+                if self.recording {
+                    return false;
+                }
+                if self.active_dm.is_some() {
+                    return self.report_error(t("error.voice_in_rooms"));
+                }
+                if !self.is_connected() {
+                    return self.report_error(t("error.voice_offline"));
+                }
+                self.recording = true;
+                let on_done = ctx.link().callback(Msg::VoiceRecorded);
+                let link = ctx.link().clone();
+                wasm_bindgen_futures::spawn_local(async move {
+                    let result = Recorder::start(on_done)
+                        .await
+                        .map_err(|e| format!("{:?}", e));
+                    link.send_message(Msg::RecorderReady(result));
+                });
+                true
+            }
+            Msg::Record(false) => {
+                self.recording = false;
+                self.waveform = None;
+                if let Some(recorder) = self.recorder.take() {
+                    let kept = recorder.elapsed_ms() >= MIN_VOICE_MS;
+                    recorder.stop(kept);
+                }
+                true
+            }
+            Msg::RecorderReady(Ok(recorder)) => {
+                // Let go before the microphone even opened.
+                if !self.recording {
+                    recorder.stop(false);
+                    return false;
+                }
+                self.recorder = Some(recorder);
+                let link = ctx.link().clone();
+                self.waveform = Some(Interval::new(WAVEFORM_MS, move || {
+                    link.send_message(Msg::DrawWaveform)
+                }));
+                true
+            }
+            Msg::RecorderReady(Err(e)) => {
+                log::warn!("voice: couldn't record: {}", e);
+                self.recording = false;
+                self.report_error(t("error.voice_failed"))
+            }
+            Msg::VoiceRecorded(blob) => {
+                if blob.size() > MAX_FILE_BYTES {
+                    return self.report_error(t("error.voice_too_long"));
+                }
+                // Drop parameters like `;codecs=opus`, which a shared file's
+                // type can't have.
+                let mime = blob.type_();
+                let mime = mime.split(';').next().unwrap_or_default().to_string();
+                let name = format!(
+                    "{}.{}",
+                    t("voice.name"),
+                    mime.rsplit('/').next().unwrap_or("webm")
+                );
+                let on_load = ctx.link().callback(move |url| Msg::FileLoaded {
+                    name: name.clone(),
+                    mime: mime.clone(),
+                    url,
+                    voice: true,
+                });
+                if let Err(e) = read_as_data_url(&blob, on_load) {
+                    log::warn!("voice: couldn't read the recording: {:?}", e);
+                    return self.report_error(t("error.voice_failed"));
+                }
+                false
+            }
+            Msg::DrawWaveform => {
+                if let (Some(recorder), Some(canvas)) = (
+                    &self.recorder,
+                    self.waveform_canvas.cast::<HtmlCanvasElement>(),
+                ) {
+                    recorder.draw(&canvas);
+                }
+                false
+            }
+            Msg::InputChanged => {
+                let input = self.chat_input.cast::<HtmlTextAreaElement>();
+                let rows = input
+                    .as_ref()
+                    .map_or(1, |input| input.value().split('\n').count())
+                    .min(MAX_INPUT_ROWS);
+                let resized = std::mem::replace(&mut self.input_rows, rows) != rows;
+                let counted = self.count_input();
+                let rejected = std::mem::take(&mut self.input_rejected);
+                let mention =
+                    input.and_then(|input| self.find_mention(&input, &current_username(ctx)));
+                let suggesting = self.mention.is_some() || mention.is_some();
+                self.mention = mention;
+                if self.typing_debounce.is_none() {
+                    self.send(&WebSocketMessage {
+                        data: Some(current_username(ctx)),
+                        room: Some(self.room.clone()),
+                        ..WebSocketMessage::new(MsgTypes::Typing)
+                    });
+                    let link = ctx.link().clone();
+                    self.typing_debounce = Some(Timeout::new(TYPING_DEBOUNCE_MS, move || {
+                        link.send_message(Msg::TypingDebounceElapsed)
+                    }));
+                }
+                resized || counted || rejected || suggesting
+            }
+            Msg::RateLimitElapsed => self.slow_down.take().is_some(),
+            Msg::TypingDebounceElapsed => {
+                self.typing_debounce = None;
+                false
+            }
+            Msg::ExpireTyping => {
+                let now = js_sys::Date::now();
+                let before = self.typists.len();
+                self.typists
+                    .retain(|_, last_seen| now - *last_seen < TYPING_TIMEOUT_MS);
+                if self.typists.is_empty() {
+                    self.typing_sweep = None;
+                }
+                self.typists.len() != before
+            }
+            Msg::DismissError(index) => {
+                if index < self.errors.len() {
+                    self.errors.remove(index);
+                }
+                true
+            }
+            Msg::JoinRoom(room) => {
+                self.sidebar_open = false;
+                self.room_dialog_open = false;
+                if room == self.room && self.active_dm.is_none() {
+                    return true;
+                }
+                self.active_dm = None;
+                self.jump_to_bottom();
+                if room != self.room {
+                    let left = std::mem::replace(&mut self.room, room);
+                    let history = std::mem::take(&mut self.messages);
+                    self.room_history.insert(left, history);
+                    self.bus.send(BusEvent::RoomChanged(self.room.clone()));
+                    self.users.clear();
+                    self.typists.clear();
+                    self.read_up_to.clear();
+                    self.unread_marker = None;
+                    self.unread_timeout = None;
+                    self.replying_to = None;
+                    self.editing = None;
+                    // The server sends the new room's pins on registering.
+                    self.pinned.clear();
+                    self.pins_open = false;
+                    self.room_info = None;
+                    self.active_filter = None;
+                    self.filter_hidden = 0;
+                    self.last_read_sent = 0;
+                    self.read_pending = 0;
+                    self.read_timeout = None;
+                    self.synced_read = None;
+                    match self.room_history.remove(&self.room) {
+                        Some(messages) => {
+                            self.messages = messages;
+                            self.history_len = 0;
+                            self.register(current_username(ctx));
+                        }
+                        None => self.load_history(ctx),
+                    }
+                }
+                true
+            }
+            Msg::React { id, emoji } => {
+                let reaction = ReactionData {
+                    id,
+                    emoji,
+                    from: current_username(ctx),
+                };
+                self.send(&WebSocketMessage {
+                    data: Some(serde_json::to_string(&reaction).unwrap()),
+                    room: Some(self.room.clone()),
+                    ..WebSocketMessage::new(MsgTypes::Reaction)
+                });
+                false
+            }
+            Msg::PreviewLoaded { url, preview } => {
+                let state = match preview {
+                    Some(preview) => PreviewState::Loaded(preview),
+                    None => PreviewState::Failed,
+                };
+                self.previews.insert(url, state);
+                true
+            }
+            Msg::HistoryLoaded { room, mut messages } => {
+                if room != self.room {
+                    // Switched rooms again before the read finished; that
+                    // room's own load takes care of registering.
+                    let newer = self.room_history.entry(room).or_default();
+                    messages.append(newer);
+                    *newer = messages;
+                    return false;
+                }
+                self.history_len = messages.len();
+                messages.append(&mut self.messages);
+                self.messages = messages;
+                self.jump_to_bottom();
+                self.register(current_username(ctx));
+                true
+            }
+            Msg::KeysReady(keys) => {
+                self.keys = keys;
+                if self.keys.is_none() {
+                    return false;
+                }
+                let peers: Vec<String> = self.peer_keys.keys().cloned().collect();
+                for peer in peers {
+                    self.derive_key(ctx, peer);
+                }
+                // Already registered without a key; the hello has it from
+                // now on either way.
+                let register = self.set_hello(current_username(ctx));
+                if self.history_requested {
+                    self.send(&register);
+                }
+                false
+            }
+            Msg::KeyDerived { peer, public, key } => {
+                // Superseded by a newer key while it was worked out.
+                if self.peer_keys.get(&peer) != Some(&public) {
+                    return false;
+                }
+                self.shared_keys.insert(peer, key);
+                true
+            }
+            Msg::SendEncrypted { to, data } => match data {
+                Some(data) => {
+                    self.send(&WebSocketMessage {
+                        data: Some(data),
+                        to: Some(to),
+                        encrypted: true,
+                        ..WebSocketMessage::new(MsgTypes::DirectMessage)
+                    });
+                    false
+                }
+                None => self.report_error(t("error.encrypt_failed")),
+            },
+            Msg::Decrypted { to, message } => self.receive_direct_message(ctx, to, message),
+            Msg::StartCall => {
+                let peer = match &self.active_dm {
+                    Some(peer) if self.call.is_none() => peer.clone(),
+                    _ => return false,
+                };
+                self.call = Some(Call::new(peer.clone(), CallState::Calling));
+                self.open_call(ctx, peer, None);
+                true
+            }
+            Msg::AcceptCall => {
+                let call = match &mut self.call {
+                    Some(call) => call,
+                    None => return false,
+                };
+                let offer = match std::mem::replace(&mut call.state, CallState::Connecting) {
+                    CallState::Ringing(offer) => offer,
+                    state => {
+                        call.state = state;
+                        return false;
+                    }
+                };
+                let peer = call.peer.clone();
+                self.open_call(ctx, peer, Some(offer));
+                true
+            }
+            Msg::EndCall => match self.call.take() {
+                Some(call) => {
+                    if let Some(connection) = &call.connection {
+                        connection.hang_up();
+                    }
+                    self.send_call_signal(MsgTypes::CallEnd, &call.peer, CallSignal::default());
+                    true
+                }
+                None => false,
+            },
+            Msg::CallReady { peer, result } => {
+                let call = match self.call.as_mut().filter(|call| call.peer == peer) {
+                    Some(call) if call.connection.is_none() => call,
+                    // Hung up while the microphone and camera were opening.
+                    _ => {
+                        if let Ok((connection, _)) = result {
+                            connection.hang_up();
+                        }
+                        return false;
+                    }
+                };
+                let (connection, sdp) = match result {
+                    Ok(ready) => ready,
+                    Err(e) => {
+                        log::warn!("call: couldn't start the call with {}: {}", peer, e);
+                        self.call = None;
+                        self.send_call_signal(MsgTypes::CallEnd, &peer, CallSignal::default());
+                        return self.report_error(t("call.failed"));
+                    }
+                };
+                // Answering means their offer is in already.
+                let kind = if matches!(call.state, CallState::Calling) {
+                    MsgTypes::CallOffer
+                } else {
+                    call.described = true;
+                    for candidate in call.pending.drain(..) {
+                        add_candidate(connection.clone(), candidate);
+                    }
+                    MsgTypes::CallAnswer
+                };
+                call.connection = Some(connection);
+                let signal = CallSignal {
+                    sdp: Some(sdp),
+                    ..CallSignal::default()
+                };
+                self.send_call_signal(kind, &peer, signal);
+                true
+            }
+            Msg::CallDescribed { peer, ok } => {
+                let call = match self.call.as_mut().filter(|call| call.peer == peer) {
+                    Some(call) => call,
+                    None => return false,
+                };
+                if !ok {
+                    return self.update(ctx, Msg::EndCall) | self.report_error(t("call.failed"));
+                }
+                call.described = true;
+                if let Some(connection) = &call.connection {
+                    for candidate in call.pending.drain(..) {
+                        add_candidate(connection.clone(), candidate);
+                    }
+                }
+                false
+            }
+            Msg::CallCandidate(candidate) => {
+                if let Some(call) = &self.call {
+                    let signal = CallSignal {
+                        candidate: Some(candidate),
+                        ..CallSignal::default()
+                    };
+                    self.send_call_signal(MsgTypes::IceCandidate, &call.peer, signal);
+                }
+                false
+            }
+            Msg::RemoteStream(stream) => match &mut self.call {
+                Some(call) if call.connection.is_some() => {
+                    call.state = CallState::Active;
+                    call.remote = Some(stream);
+                    true
+                }
+                _ => false,
+            },
+            Msg::SelectThread(peer) => {
+                self.sidebar_open = false;
+                if let Some(peer) = &peer {
+                    self.dm_threads.entry(peer.clone()).or_default();
+                    self.dm_unread.remove(peer);
+                }
+                self.active_dm = peer;
+                self.jump_to_bottom();
+                true
+            }
+            Msg::Scrolled => {
+                let list = match self.message_list.cast::<web_sys::Element>() {
+                    Some(list) => list,
+                    None => return false,
+                };
+                let loading = list.scroll_top() == 0 && self.load_older();
+                self.start_unread_countdown(ctx);
+                let from_bottom = list.scroll_height() - list.scroll_top() - list.client_height();
+                let at_bottom = from_bottom <= SCROLL_STICKY_PX;
+                if at_bottom && !self.away() {
+                    self.tab_badge.clear();
+                }
+                if at_bottom == self.at_bottom {
+                    return loading;
+                }
+                self.at_bottom = at_bottom;
+                if at_bottom {
+                    self.unseen = 0;
+                }
+                true
+            }
+            Msg::Search(query) => {
+                self.search_draft = query;
+                if self.search_draft.trim().is_empty() {
+                    return self.update(ctx, Msg::ApplySearch);
+                }
+                let link = ctx.link().clone();
+                self.search_debounce = Some(Timeout::new(SEARCH_DEBOUNCE_MS, move || {
+                    link.send_message(Msg::ApplySearch)
+                }));
+                false
+            }
+            Msg::ApplySearch => {
+                self.search_debounce = None;
+                if self.search_draft == self.search_query {
+                    return false;
+                }
+                let was_searching = !self.search_query.trim().is_empty();
+                self.search_query = self.search_draft.clone();
+                self.search_match = 0;
+                if self.search_query.trim().is_empty() {
+                    // Back to where the timeline was, as far from the bottom
+                    // as before in case messages arrived meanwhile.
+                    self.scroll_anchor = self.search_anchor.take();
+                } else {
+                    if !was_searching {
+                        self.search_anchor = self
+                            .message_list
+                            .cast::<web_sys::Element>()
+                            .map(|list| (list.scroll_height(), list.scroll_top()));
+                    }
+                    self.search_scroll_pending = true;
+                }
+                true
+            }
+            Msg::SearchStep(older) => {
+                if self.search_debounce.is_some() {
+                    return self.update(ctx, Msg::ApplySearch);
+                }
+                let results = self.visible_messages().len();
+                if self.search_query.trim().is_empty() || results == 0 {
+                    return false;
+                }
+                self.search_match = if older {
+                    (self.search_match + 1) % results
+                } else {
+                    (self.search_match + results - 1) % results
+                };
+                self.search_scroll_pending = true;
+                true
+            }
+            Msg::SetFilter(from) => {
+                if from.is_some() && self.active_dm.is_some() {
+                    self.update(ctx, Msg::SelectThread(None));
+                }
+                self.active_filter = from;
+                self.filter_hidden = 0;
+                self.jump_to_bottom();
+                true
+            }
+            Msg::Resized => {
+                // The on-screen keyboard shrinks the window too; keep the
+                // newest message above it.
+                self.scroll_pending |= self.at_bottom;
+                let is_mobile = narrow_window();
+                let relaid = is_mobile != self.is_mobile;
+                if relaid {
+                    self.is_mobile = is_mobile;
+                    self.sidebar_open = false;
+                }
+                relaid || self.scroll_pending
+            }
+            Msg::ShowSidebar(open) => {
+                self.sidebar_open = open;
+                true
+            }
+            Msg::ShowSearch(open) => {
+                self.search_open = open;
+                self.search_focus_pending = open;
+                if !open {
+                    self.search_draft.clear();
+                    self.update(ctx, Msg::ApplySearch);
+                }
+                true
+            }
+            Msg::MessagesSeen(id) => {
+                if id <= self.last_read_sent.max(self.read_pending) || self.active_dm.is_some() {
+                    return false;
+                }
+                self.read_pending = id;
+                // Newer ones start the wait over.
+                self.read_timeout = None;
+                self.start_read_dwell(ctx);
+                false
+            }
+            Msg::ReadDwelled => {
+                self.read_timeout = None;
+                let id = self.read_pending;
+                // Gone by now: it'll be reported again when it comes back.
+                if self.away() || id <= self.last_read_sent || !self.on_screen(id) {
+                    return false;
+                }
+                self.last_read_sent = id;
+                let receipt = ReadReceipt {
+                    name: current_username(ctx),
+                    id,
+                };
+                self.send(&WebSocketMessage {
+                    data: Some(serde_json::to_string(&receipt).unwrap()),
+                    room: Some(self.room.clone()),
+                    ..WebSocketMessage::new(MsgTypes::Read)
+                });
+                false
+            }
+            Msg::VisibilityChanged => {
+                let hidden = page_hidden();
+                self.on_focus_change(ctx);
+                if !hidden {
+                    // Whatever arrived while hidden is on screen now, but the
+                    // observer only reports changes; start it over to get
+                    // the current picture.
+                    if let Some(observer) = &self.read_observer {
+                        observer.disconnect();
+                    }
+                    self.observe_messages();
+                }
+                self.send_presence(ctx);
+                false
+            }
+            Msg::SetStatus(status) => {
+                self.chosen_status = status;
+                self.send_presence(ctx);
+                false
+            }
+            Msg::FocusChanged(focused) => {
+                self.focused = focused;
+                self.on_focus_change(ctx)
+            }
+            Msg::ClearMemberDelta => self.member_delta.take().is_some(),
+            Msg::ClearUnreadDivider => {
+                self.unread_timeout = None;
+                self.unread_marker.take().is_some()
+            }
+            Msg::ToggleDarkMode => {
+                let theme = self.theme.toggled();
+                theme.save();
+                // Comes back as `ThemeChanged`.
+                self.theme.set(theme);
+                false
+            }
+            Msg::ThemeChanged(theme) => {
+                self.theme = theme;
+                true
+            }
+            Msg::SetLanguage(language) => {
+                translations::set_language(language);
+                language.save();
+                self.language = language;
+                true
+            }
+            Msg::ShowUserCard(card) => {
+                let changed = card.is_some() || self.user_card.is_some();
+                self.user_card = card;
+                changed
+            }
+            Msg::ShowUserMenu(menu) => {
+                let changed = menu.is_some() || self.user_menu.is_some();
+                self.user_menu = menu;
+                changed
+            }
+            Msg::Moderate(action) => {
+                self.user_menu = None;
+                let message_type = match action.duration_minutes {
+                    Some(_) => MsgTypes::Ban,
+                    None => MsgTypes::Kick,
+                };
+                self.send(&WebSocketMessage {
+                    data: Some(serde_json::to_string(&action).unwrap()),
+                    ..WebSocketMessage::new(message_type)
+                });
+                true
+            }
+            Msg::Block(name, block) => {
+                self.user_menu = None;
+                self.user_card = None;
+                if block {
+                    self.blocked.insert(name.clone());
+                    self.typists.remove(&name);
+                    if self.active_filter.as_ref() == Some(&name) {
+                        self.active_filter = None;
+                    }
+                    if self.call.as_ref().is_some_and(|call| call.peer == name) {
+                        self.update(ctx, Msg::EndCall);
+                    }
+                } else {
+                    self.blocked.remove(&name);
+                }
+                blocklist::save(&self.blocked);
+                true
+            }
+            Msg::ToggleFavorite(favorite) => {
+                let before = self.favorites.len();
+                self.favorites
+                    .retain(|f| !f.is(&favorite.room, favorite.message.id));
+                if self.favorites.len() == before {
+                    self.favorites.push(favorite);
+                }
+                favorites::save(&self.favorites);
+                true
+            }
+            Msg::ShowRoomMessage { room, id } => {
+                if room == self.room && self.active_dm.is_none() {
+                    self.sidebar_open = false;
+                    return self.update(ctx, Msg::JumpTo(id));
+                }
+                self.update(ctx, Msg::JoinRoom(room));
+                self.jump_pending = Some(id);
+                true
+            }
+            Msg::Forward(message) => {
+                let changed = message.is_some() || self.forwarding.is_some();
+                self.forwarding = message;
+                changed
+            }
+            Msg::ForwardTo(destination) => {
+                let message = match self.forwarding.take() {
+                    Some(message) => message,
+                    None => return false,
+                };
+                // Forwarding a forward credits whoever said it first.
+                let (author, body) =
+                    split_forwarded(&message.message).unwrap_or((&message.from, &message.message));
+                let text = forwarded(author, body);
+                if text.chars().count() > self.max_chars {
+                    return self.report_error(t("error.forward_too_long"));
+                }
+                match destination {
+                    Destination::Room(room) => self.update(ctx, Msg::JoinRoom(room)),
+                    Destination::User(peer) => self.update(ctx, Msg::SelectThread(Some(peer))),
+                };
+                // It isn't an answer to whatever I was replying to.
+                self.replying_to = None;
+                self.send_message(ctx, text);
+                true
+            }
+            Msg::SetNotificationPrefs(prefs) => {
+                if prefs.desktop_enabled && !self.notify.desktop_enabled {
+                    // Only turned on once the browser allows it, see
+                    // `Msg::DesktopPermission`.
+                    let link = ctx.link().clone();
+                    wasm_bindgen_futures::spawn_local(async move {
+                        let granted = notify::request_permission().await;
+                        link.send_message(Msg::DesktopPermission(granted));
+                    });
+                }
+                self.notify = NotificationPrefs {
+                    desktop_enabled: prefs.desktop_enabled && self.notify.desktop_enabled,
+                    ..prefs
+                };
+                self.notify.save();
+                true
+            }
+            Msg::DesktopPermission(granted) => {
+                if !granted {
+                    return self.report_error(t("error.notifications_blocked"));
+                }
+                self.notify.desktop_enabled = true;
+                self.notify.save();
+                true
+            }
+            Msg::ShowNotified { peer, id } => {
+                let switching = self.active_dm != peer;
+                if switching {
+                    self.update(ctx, Msg::SelectThread(peer.clone()));
+                }
+                match (peer, id) {
+                    // The room's messages aren't on screen until it renders.
+                    (None, Some(id)) if switching => {
+                        self.jump_pending = Some(id);
+                        true
+                    }
+                    (None, Some(id)) => self.update(ctx, Msg::JumpTo(id)),
+                    _ => true,
+                }
+            }
+            Msg::ReplyTo(target) => {
+                self.replying_to = target;
+                if let Some(input) = self.chat_input.cast::<HtmlTextAreaElement>() {
+                    let _ = input.focus();
+                }
+                true
+            }
+            Msg::EditMessage(id) => {
+                self.editing = id;
+                self.edit_focus_pending = id.is_some();
+                true
+            }
+            Msg::SubmitEdit => {
+                let id = match self.editing.take() {
+                    Some(id) => id,
+                    None => return false,
+                };
+                let text = match self.edit_input.cast::<HtmlInputElement>() {
+                    Some(input) => normalize_outgoing(&input.value())
+                        .map(|text| replace_shortcodes(&text))
+                        .unwrap_or_default(),
+                    None => return true,
+                };
+                if text.chars().count() > self.max_chars {
+                    return self.report_error(&t_args("error.too_long", &[&self.max_chars]));
+                }
+                let unchanged = self
+                    .messages
+                    .iter()
+                    .any(|m| m.id == Some(id) && m.message == text);
+                if !text.is_empty() && !unchanged {
+                    let edit = EditData { id, message: text };
+                    self.send(&WebSocketMessage {
+                        data: Some(serde_json::to_string(&edit).unwrap()),
+                        room: Some(self.room.clone()),
+                        ..WebSocketMessage::new(MsgTypes::Edit)
+                    });
+                }
+                true
+            }
+            Msg::ConfirmDelete(id) => std::mem::replace(&mut self.confirm_delete, id) != id,
+            Msg::Report(id) => {
+                let reporting = id.map(|id| (id, None));
+                std::mem::replace(&mut self.reporting, reporting) != reporting
+            }
+            Msg::PickReportCategory(category) => match &mut self.reporting {
+                Some((_, picked)) => {
+                    *picked = Some(category);
+                    true
+                }
+                None => false,
+            },
+            Msg::SubmitReport => {
+                let (id, category) = match self.reporting {
+                    Some((id, Some(category))) => (id, category),
+                    _ => return false,
+                };
+                let report = ReportData {
+                    message_id: id,
+                    reporter: current_username(ctx),
+                    category,
+                };
+                if self.send(&WebSocketMessage {
+                    data: Some(serde_json::to_string(&report).unwrap()),
+                    room: Some(self.room.clone()),
+                    ..WebSocketMessage::new(MsgTypes::Report)
+                }) {
+                    self.reporting = None;
+                    self.reported.insert(id);
+                }
+                true
+            }
+            Msg::DeleteMessage(id) => {
+                self.confirm_delete = None;
+                self.send(&WebSocketMessage {
+                    data: Some(serde_json::to_string(&MessageId { id }).unwrap()),
+                    room: Some(self.room.clone()),
+                    ..WebSocketMessage::new(MsgTypes::Delete)
+                });
+                false
+            }
+            Msg::MoveMention(offset) => match &mut self.mention {
+                Some(mention) => {
+                    let len = mention.candidates.len() as isize;
+                    mention.selected_idx =
+                        (mention.selected_idx as isize + offset).rem_euclid(len) as usize;
+                    true
+                }
+                None => false,
+            },
+            Msg::CompleteMention(name) => {
+                let mention = match self.mention.take() {
+                    Some(mention) => mention,
+                    None => return false,
+                };
+                let name =
+                    match name.or_else(|| mention.candidates.get(mention.selected_idx).cloned()) {
+                        Some(name) => name,
+                        None => return true,
+                    };
+                if let Some(input) = self.chat_input.cast::<HtmlTextAreaElement>() {
+                    let completed = format!("@{} ", name);
+                    if let Err(e) = input.set_range_text_with_start_and_end_and_mode(
+                        &completed,
+                        mention.start,
+                        mention.end,
+                        "end",
+                    ) {
+                        log::debug!("failed to complete mention: {:?}", e);
+                    }
+                    let _ = input.focus();
+                }
+                self.count_input();
+                true
+            }
+            Msg::CloseMention => self.mention.take().is_some(),
+            Msg::CopyMessage { key, text } => {
+                let link = ctx.link().clone();
+                wasm_bindgen_futures::spawn_local(async move {
+                    let copied = copy_to_clipboard(&text).await;
+                    if let Err(e) = &copied {
+                        log::warn!("failed to copy message: {:?}", e);
+                    }
+                    link.send_message(Msg::MessageCopied {
+                        key,
+                        ok: copied.is_ok(),
+                    });
+                });
+                false
+            }
+            Msg::MessageCopied { key, ok } => {
+                self.copied = Some((key, ok));
+                let link = ctx.link().clone();
+                self.copied_reset = Some(Timeout::new(COPIED_MS, move || {
+                    link.send_message(Msg::ResetCopied)
+                }));
+                true
+            }
+            Msg::StopFlash => {
+                self.flash_reset = None;
+                self.flashing.take().is_some()
+            }
+            Msg::ResetCopied => {
+                self.copied_reset = None;
+                self.copied.take().is_some()
+            }
+            Msg::SetPinned { id, pinned } => {
+                let message_type = if pinned {
+                    MsgTypes::Pin
+                } else {
+                    MsgTypes::Unpin
+                };
+                self.send(&WebSocketMessage {
+                    data: Some(serde_json::to_string(&MessageId { id }).unwrap()),
+                    room: Some(self.room.clone()),
+                    ..WebSocketMessage::new(message_type)
+                });
+                false
+            }
+            Msg::ShowPins(open) => std::mem::replace(&mut self.pins_open, open) != open,
+            Msg::JumpTo(id) => {
+                self.pins_open = false;
+                let list = match self.message_list.cast::<web_sys::Element>() {
+                    Some(list) => list,
+                    None => return true,
+                };
+                match list.query_selector(&format!("[data-id=\"{}\"]", id)) {
+                    Ok(Some(target)) => {
+                        // Scrolling the list itself, as for new messages.
+                        let offset = target.get_bounding_client_rect().top()
+                            - list.get_bounding_client_rect().top();
+                        list.set_scroll_top(list.scroll_top() + offset as i32 - 16);
+                        self.flashing = Some(id);
+                        let link = ctx.link().clone();
+                        self.flash_reset = Some(Timeout::new(FLASH_MS, move || {
+                            link.send_message(Msg::StopFlash)
+                        }));
+                        true
+                    }
+                    _ => {
+                        self.report_error(t("error.not_loaded"));
+                        true
+                    }
+                }
+            }
+            Msg::ShowMentions(open) => {
+                self.mentions_open = open;
+                true
+            }
+            Msg::OpenMention { room, id } => {
+                self.mentions_open = false;
+                if let Some(mention) = self
+                    .mentions
+                    .iter_mut()
+                    .find(|m| m.room == room && m.id == id)
+                {
+                    mention.read = true;
+                }
+                self.update(ctx, Msg::ShowRoomMessage { room, id });
+                true
+            }
+            Msg::CloseOverlays => {
+                std::mem::take(&mut self.profile_open)
+                    | std::mem::take(&mut self.settings_open)
+                    | std::mem::take(&mut self.room_dialog_open)
+                    | std::mem::take(&mut self.user_search_open)
+                    | std::mem::take(&mut self.export_open)
+                    | std::mem::take(&mut self.gifs_open)
+                    | std::mem::take(&mut self.pins_open)
+                    | std::mem::take(&mut self.mentions_open)
+                    | std::mem::take(&mut self.sidebar_open)
+                    | self.mention.take().is_some()
+                    | self.replying_to.take().is_some()
+                    | self.editing.take().is_some()
+                    | self.user_menu.take().is_some()
+                    | self.user_card.take().is_some()
+                    | self.forwarding.take().is_some()
+                    | self.confirm_delete.take().is_some()
+                    | self.reporting.take().is_some()
+            }
+            Msg::FocusSearch => self.update(ctx, Msg::ShowSearch(true)),
+            Msg::ShowProfile(open) => {
+                self.profile_open = open;
+                true
+            }
+            Msg::ShowSettings(open) => std::mem::replace(&mut self.settings_open, open) != open,
+            Msg::ShowRoomDialog(open) => {
+                self.room_error = None;
+                std::mem::replace(&mut self.room_dialog_open, open) != open
+            }
+            Msg::CreateRoom(room) => {
+                self.room_error = None;
+                self.send(&WebSocketMessage {
+                    data: Some(serde_json::to_string(&room).unwrap()),
+                    ..WebSocketMessage::new(MsgTypes::CreateRoom)
+                });
+                true
+            }
+            Msg::EnterRoom { room, invite } => {
+                if let Some(invite) = invite {
+                    self.invites.insert(room.clone(), invite);
+                    if !self.rooms.contains(&room) {
+                        self.rooms.push(room.clone());
+                    }
+                }
+                self.update(ctx, Msg::JoinRoom(room))
+            }
+            Msg::ShowExport(open) => std::mem::replace(&mut self.export_open, open) != open,
+            Msg::Export(format) => {
+                self.export_open = false;
+                let name = match &self.active_dm {
+                    Some(peer) => format!("yewchat-dm-{}.{}", peer, format.extension()),
+                    None => format!("yewchat-{}.{}", self.room, format.extension()),
+                };
+                let shown: Vec<MessageData> =
+                    self.shown_messages().map(|(_, m)| m.clone()).collect();
+                let contents = format.export(&shown);
+                if let Err(e) = export::download(&name, format, &contents) {
+                    log::warn!("export failed: {:?}", e);
+                    return self.report_error(t("error.export_failed"));
+                }
+                true
+            }
+            Msg::SetUserFilter(filter) => {
+                std::mem::replace(&mut self.user_filter, filter) != self.user_filter
+            }
+            Msg::ShowUserSearch(open) => {
+                if open {
+                    // The drawer would cover it.
+                    self.sidebar_open = false;
+                    self.user_query.clear();
+                    self.found_users.clear();
+                    self.more_users = false;
+                    self.searching_users = false;
+                }
+                std::mem::replace(&mut self.user_search_open, open) != open
+            }
+            Msg::SearchUsers { query, offset } => {
+                if offset == 0 {
+                    self.found_users.clear();
+                    self.more_users = false;
+                }
+                let data = UserSearchData {
+                    query: query.clone(),
+                    offset,
+                };
+                self.user_query = query;
+                self.searching_users = self.send(&WebSocketMessage {
+                    data: Some(serde_json::to_string(&data).unwrap()),
+                    ..WebSocketMessage::new(MsgTypes::SearchUsers)
+                });
+                true
+            }
+            Msg::InviteUser(peer) => {
+                let text = match self.invites.get(&self.room) {
+                    Some(invite) => t_args("search_users.invite_private", &[&self.room, invite]),
+                    None => t_args("search_users.invite_message", &[&self.room]),
+                };
+                self.user_search_open = false;
+                self.update(ctx, Msg::SelectThread(Some(peer)));
+                self.send_message(ctx, text);
+                true
+            }
+            Msg::SaveProfile(name, avatar) => {
+                let current = current_username(ctx);
+                if name == current && avatar == self.avatar_style {
+                    self.profile_open = false;
+                    return true;
+                }
+                if let Err(e) = self.check_name(&name, &current) {
+                    return self.report_error(&e);
+                }
+                self.avatar_style = avatar;
+                self.rename(ctx, name);
+                self.profile_open = false;
+                true
+            }
+            Msg::ScrollToBottom => {
+                self.jump_to_bottom();
+                true
+            }
+            Msg::InsertText(text) => {
+                if let Some(input) = self.chat_input.cast::<HtmlTextAreaElement>() {
+                    let len = input.value().encode_utf16().count() as u32;
+                    let start = input.selection_start().ok().flatten().unwrap_or(len);
+                    let end = input.selection_end().ok().flatten().unwrap_or(start);
+                    if let Err(e) =
+                        input.set_range_text_with_start_and_end_and_mode(&text, start, end, "end")
+                    {
+                        log::debug!("failed to insert {:?}: {:?}", text, e);
+                    }
+                    let _ = input.focus();
+                }
+                self.count_input()
+            }
+        }
+    }
+
+    fn rendered(&mut self, ctx: &Context<Self>, _first_render: bool) {
+        if let Some((height, top)) = self.scroll_anchor.take() {
+            if let Some(list) = self.message_list.cast::<web_sys::Element>() {
+                list.set_scroll_top(top + list.scroll_height() - height);
+            }
+        }
+        if std::mem::take(&mut self.scroll_pending) {
+            // Scrolling the list itself rather than bringing the last message
+            // into view, which would also scroll the page around it.
+            if let Some(list) = self.message_list.cast::<web_sys::Element>() {
+                list.set_scroll_top(list.scroll_height());
+            }
+        }
+        // Waits for the message to be loaded, see `BusEvent::History`.
+        if let Some(id) = self.jump_pending {
+            if self.messages.iter().any(|m| m.id == Some(id)) {
+                self.jump_pending = None;
+                ctx.link().send_message(Msg::JumpTo(id));
+            }
+        }
+        if std::mem::take(&mut self.unread_scroll_pending) {
+            if let (Some(list), Some(divider)) = (
+                self.message_list.cast::<web_sys::Element>(),
+                self.unread_divider.cast::<web_sys::Element>(),
+            ) {
+                let offset = divider.get_bounding_client_rect().top()
+                    - list.get_bounding_client_rect().top();
+                list.set_scroll_top(list.scroll_top() + offset as i32 - 16);
+            }
+            self.start_unread_countdown(ctx);
+        }
+        if std::mem::take(&mut self.edit_focus_pending) {
+            if let Some(input) = self.edit_input.cast::<HtmlInputElement>() {
+                let _ = input.focus();
+                input.select();
+            }
+        }
+        if std::mem::take(&mut self.search_focus_pending) {
+            if let Some(input) = self.search_input.cast::<HtmlInputElement>() {
+                let _ = input.focus();
+                input.select();
+            }
+        }
+        if std::mem::take(&mut self.search_scroll_pending) {
+            let list = self.message_list.cast::<web_sys::Element>();
+            let result = self
+                .search_target
+                .cast::<web_sys::Element>()
+                .and_then(|marker| marker.next_element_sibling());
+            if let (Some(list), Some(result)) = (list, result) {
+                // Centred in the list, scrolling only the list as `JumpTo` does.
+                let rect = result.get_bounding_client_rect();
+                let offset = rect.top() - list.get_bounding_client_rect().top();
+                let centre = (list.client_height() as f64 - rect.height()) / 2.0;
+                list.set_scroll_top(list.scroll_top() + (offset - centre) as i32);
+            }
+        }
+        // A list too short to scroll never gets scrolled to the top, so
+        // keep fetching older pages until it fills up.
+        let short = self
+            .message_list
+            .cast::<web_sys::Element>()
+            .is_some_and(|list| list.scroll_height() <= list.client_height());
+        if short && self.search_query.is_empty() && self.active_filter.is_none() {
+            self.load_older();
+        }
+        if let Some(call) = &self.call {
+            let local = call.connection.as_ref().map(|c| &c.local);
+            attach_stream(&self.local_video, local, true);
+            attach_stream(&self.remote_video, call.remote.as_ref(), false);
+        }
+        self.observe_messages();
+        self.fetch_previews(ctx);
+    }
+
+    fn destroy(&mut self, _ctx: &Context<Self>) {
+        // Dropping a `Timeout` cancels it, so no callback fires into a
+        // component that no longer exists.
+        self.typing_debounce.take();
+        self.typing_sweep.take();
+        self.search_debounce.take();
+        self.slow_down.take();
+        self.copied_reset.take();
+        self.flash_reset.take();
+        self.waveform.take();
+        if let Some(recorder) = self.recorder.take() {
+            recorder.stop(false);
+        }
+        if let Some(connection) = self.call.take().and_then(|call| call.connection) {
+            connection.hang_up();
+        }
+        if let Some(observer) = &self.read_observer {
+            observer.disconnect();
+        }
+        if let Some(document) = web_sys::window().and_then(|w| w.document()) {
+            let _ = document.remove_event_listener_with_callback(
+                "visibilitychange",
+                self.visibility_listener.as_ref().unchecked_ref(),
+            );
+        }
+        if let Some(window) = web_sys::window() {
+            for (event, listener) in &self.focus_listeners {
+                let _ = window
+                    .remove_event_listener_with_callback(event, listener.as_ref().unchecked_ref());
+            }
+            let _ = window.remove_event_listener_with_callback(
+                "resize",
+                self.resize_listener.as_ref().unchecked_ref(),
+            );
+        }
+        self.unread_timeout.take();
+        self.tab_badge.clear();
+    }
+
+    #[allow(clippy::unnecessary_operation)]
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let dark = *self.theme == Theme::Dark;
+        let submit = ctx.link().callback(|_| Msg::SubmitMessage);
+        let oninput = ctx.link().callback(|_: InputEvent| Msg::InputChanged);
+        let suggesting = self.mention.is_some();
+        let on_input_key = ctx.link().batch_callback(move |e: KeyboardEvent| {
+            if !suggesting {
+                return None;
+            }
+            let msg = match e.key().as_str() {
+                "ArrowDown" => Msg::MoveMention(1),
+                "ArrowUp" => Msg::MoveMention(-1),
+                "Enter" | "Tab" => Msg::CompleteMention(None),
+                "Escape" => Msg::CloseMention,
+                _ => return None,
+            };
+            // Enter completes instead of sending, and Escape only closes the
+            // suggestions.
+            e.prevent_default();
+            e.stop_propagation();
+            Some(msg)
+        });
+        let onkeydown = ctx.link().batch_callback(|e: KeyboardEvent| {
+            let msg = shortcut(&e);
+            if msg.is_some() {
+                e.prevent_default();
+            }
+            msg
+        });
+        let current_user = current_username(ctx);
+        let receipts = self.receipts(&current_user);
+        let follow_ups = self.follow_ups();
+        let mut previous = None;
+        let search_open = self.search_open;
+        let prefs = self.notify;
+        let recording = self.recording;
+        let searching = !self.search_query.trim().is_empty();
+        let visible = self.visible_messages();
+        let results = visible.len();
+        // Position in the thread of the result moved to with Enter.
+        let active_result = visible
+            .iter()
+            .rev()
+            .nth(self.search_match)
+            .filter(|_| searching)
+            .map(|(i, _)| *i);
+        let search_keys = ctx.link().batch_callback(|e: KeyboardEvent| {
+            let msg = match e.key().as_str() {
+                "Enter" => Some(Msg::SearchStep(!e.shift_key())),
+                "Escape" => Some(Msg::ShowSearch(false)),
+                _ => None,
+            };
+            if msg.is_some() {
+                e.prevent_default();
+                e.stop_propagation();
+            }
+            msg
+        });
+
+        let shown_users = filter_users(&self.users, &self.user_filter);
+        let clearing = !self.user_filter.is_empty();
+        let user_filter_keys = ctx.link().batch_callback(move |e: KeyboardEvent| {
+            // Only clears the filter, unless there's none to clear.
+            (e.key() == "Escape" && clearing).then(|| {
+                e.stop_propagation();
+                Msg::SetUserFilter(String::new())
+            })
+        });
+
+        let chat = html! {
+            <div class="flex w-screen" {onkeydown}>
+                {
+                    if self.is_mobile && self.sidebar_open {
+                        html! {
+                            <div
+                                onclick={ctx.link().callback(|_| Msg::ShowSidebar(false))}
+                                class="fixed inset-0 z-30 bg-black/30 backdrop-blur-sm"
+                            />
+                        }
+                    } else {
+                        html! {}
+                    }
+                }
+                // In the phone layout, a drawer sliding in over the chat.
+                <div class={classes!(
+                    "flex-none", "w-56", "h-dvh", "bg-blue-100", "dark:bg-gray-800", // <- updated to lighter blue
+                    self.is_mobile.then_some("fixed inset-y-0 left-0 z-40 overflow-y-auto shadow-xl transition-transform"),
+                    (self.is_mobile && !self.sidebar_open).then_some("-translate-x-full"),
+                )}>
+                    {
+                        if self.is_mobile {
+                            html! {
+                                <button
+                                    onclick={ctx.link().callback(|_| Msg::ShowSidebar(false))}
+                                    class="float-right m-2 px-2 rounded text-gray-500 hover:bg-blue-200 dark:hover:bg-gray-700"
+                                    title={t("sidebar.close")}
+                                >
+                                    {"✕"}
+                                </button>
+                            }
+                        } else {
+                            html! {}
+                        }
+                    }
+                    <div class="flex items-center text-xl p-3 font-semibold text-blue-800 dark:text-blue-300">
+                        {format!("🏠 {}", t("sidebar.rooms"))}
+                        <button
+                            onclick={ctx.link().callback(|_| Msg::ShowRoomDialog(true))}
+                            class="ml-auto px-2 rounded text-lg text-gray-500 hover:bg-blue-200 dark:hover:bg-gray-700"
+                            title={t("sidebar.new_room")}
+                        >
+                            {"＋"}
+                        </button>
+                    </div>
+                    {
+                        self.rooms.iter().map(|room| {
+                            let is_current = *room == self.room;
+                            let class = if is_current {
+                                "mx-3 my-1 px-3 py-1 rounded-lg bg-blue-600 text-white font-medium cursor-pointer"
+                            } else {
+                                "mx-3 my-1 px-3 py-1 rounded-lg text-blue-900 hover:bg-blue-200 dark:text-gray-200 dark:hover:bg-gray-700 cursor-pointer"
+                            };
+                            let target = room.clone();
+                            html! {
+                                <div {class} onclick={ctx.link().callback(move |_| Msg::JoinRoom(target.clone()))}>
+                                    {format!("# {}", room)}
+                                </div>
+                            }
+                        }).collect::<Html>()
+                    }
+                    <div class="text-xl p-3 font-semibold text-blue-800 dark:text-blue-300">{format!("⭐ {}", t("sidebar.favorites"))}</div>
+                    { self.view_favorites(ctx) }
+                    <div class="flex items-center text-xl p-3 font-semibold text-blue-800 dark:text-blue-300">
+                        {format!("👥 {} ({})", t("sidebar.users"), self.users.len())}
+                        <button
+                            onclick={ctx.link().callback(|_| Msg::ShowUserSearch(true))}
+                            class="ml-auto px-2 rounded text-base text-gray-500 hover:bg-blue-200 dark:hover:bg-gray-700"
+                            title={t("sidebar.find_users")}
+                        >
+                            {"🔍"}
+                        </button>
+                    </div>
+                    <input
+                        type="search"
+                        value={self.user_filter.clone()}
+                        placeholder={t("sidebar.filter_users")}
+                        oninput={ctx.link().callback(|e: InputEvent| Msg::SetUserFilter(e.target_unchecked_into::<HtmlInputElement>().value()))}
+                        onkeydown={user_filter_keys}
+                        class="block w-[calc(100%-1.5rem)] mx-3 py-1 px-3 text-sm rounded-md border border-blue-200 dark:border-gray-600 bg-white dark:bg-gray-900 outline-none focus:ring-2 focus:ring-blue-300"
+                    />
+                    {
+                        if shown_users.is_empty() && !self.user_filter.trim().is_empty() {
+                            html! {
+                                <div class="mx-3 px-3 py-2 text-xs text-gray-500 dark:text-gray-400">{t("sidebar.no_users_match")}</div>
+                            }
+                        } else {
+                            html! {}
+                        }
+                    }
+                    {
+                        shown_users.into_iter().map(|u| {
+                            let onclick = if u.name == current_user {
+                                ctx.link().callback(|_| Msg::ShowProfile(true))
+                            } else {
+                                user_card(ctx, &u.name)
+                            };
+                            let filtered = self.active_filter.as_ref() == Some(&u.name);
+                            let blocked = self.blocked.contains(&u.name);
+                            let oncontextmenu = (u.name != current_user).then(|| user_menu(ctx, &u.name));
+                            let toggle_filter = {
+                                let name = (!filtered).then(|| u.name.clone());
+                                // Filtering shouldn't also open a DM.
+                                ctx.link().callback(move |e: MouseEvent| {
+                                    e.stop_propagation();
+                                    Msg::SetFilter(name.clone())
+                                })
+                            };
+                            html!{
+                                <div {onclick} {oncontextmenu} data-user-card="" class={classes!("group", "flex", "m-3", "bg-white", "rounded-lg", "p-2", "hover:bg-blue-200", "dark:bg-gray-700", "dark:hover:bg-gray-600", "transition-all", "cursor-pointer", filtered.then_some("ring-2 ring-blue-400"), blocked.then_some("opacity-50"))}>
+                                    <div class="relative flex-none">
+                                        <Avatar class="w-10 h-10" src={u.avatar.clone()} alt="avatar" title={local_time(u)}/>
+                                        <span
+                                            class={format!("absolute bottom-0 right-0 w-3 h-3 rounded-full border-2 border-white dark:border-gray-700 {}", presence_dot_class(u.status))}
+                                            title={presence_label(u.status)}
+                                        />
+                                    </div>
+                                    <div class="flex-grow pl-3 pt-1">
+                                        <div class="text-sm font-medium text-gray-700 dark:text-gray-100">
+                                            {u.name.clone()}
+                                            { unread_dot(self.dm_unread.contains(&u.name)) }
+                                        </div>
+                                        {
+                                            if u.name == current_user {
+                                                self.view_status_picker(ctx)
+                                            } else if blocked {
+                                                html! { <div class="text-xs text-gray-400">{format!("🚫 {}", t("sidebar.blocked"))}</div> }
+                                            } else {
+                                                html! { <div class="text-xs text-gray-400">{t("sidebar.greeting")}</div> }
+                                            }
+                                        }
+                                    </div>
+                                    <button
+                                        onclick={toggle_filter}
+                                        class={classes!("self-center", "px-1", "rounded", "text-sm", "hover:bg-blue-300", "dark:hover:bg-gray-500", (!filtered && !self.is_mobile).then_some("opacity-0 group-hover:opacity-100"))}
+                                        title={if filtered { t("filter.show_all").to_string() } else { t_args("filter.show_only", &[&u.name]) }}
+                                    >
+                                        {"🔎"}
+                                    </button>
+                                </div>
+                            }
+                        }).collect::<Html>()
+                    }
+                </div>
+
+                <div class="grow min-w-0 h-dvh flex flex-col bg-white dark:bg-gray-900 dark:text-gray-100">
+                    <div class="w-full h-14 flex items-end gap-1 px-2 border-b-2 border-blue-200 dark:border-gray-700">
+                        {
+                            if self.is_mobile {
+                                html! {
+                                    <button
+                                        onclick={ctx.link().callback(|_| Msg::ShowSidebar(true))}
+                                        class="self-center px-2 text-xl rounded hover:bg-gray-100 dark:hover:bg-gray-700"
+                                        title={t("sidebar.open")}
+                                    >
+                                        {"☰"}
+                                    </button>
+                                }
+                            } else {
+                                html! {}
+                            }
+                        }
+                        <div
+                            onclick={ctx.link().callback(|_| Msg::SelectThread(None))}
+                            class={tab_class(self.active_dm.is_none())}
+                            title={self.room_info.as_ref().map(|room| room.description.clone()).filter(|d| !d.is_empty())}
+                        >
+                            {format!("💬 #{}", self.room)}
+                            {
+                                if self.room_info.as_ref().is_some_and(|room| room.private) {
+                                    html! { <span class="ml-1 text-xs" title={t("room.private")}>{"🔒"}</span> }
+                                } else {
+                                    html! {}
+                                }
+                            }
+                            { self.view_invite_button(ctx) }
+                            <span
+                                class={format!("inline-block w-2 h-2 ml-2 mb-0.5 rounded-full {}", connection_dot_class(&self.connection))}
+                                title={connection_label(&self.connection)}
+                            />
+                        </div>
+                        {
+                            self.dm_peers().into_iter().map(|peer| {
+                                let is_active = self.active_dm.as_ref() == Some(peer);
+                                let target = peer.clone();
+                                html! {
+                                    <div
+                                        onclick={ctx.link().callback(move |_| Msg::SelectThread(Some(target.clone())))}
+                                        class={tab_class(is_active)}
+                                    >
+                                        {format!("✉️ {}", peer)}
+                                        { lock_badge(self.shared_keys.contains_key(peer)) }
+                                        { unread_dot(self.dm_unread.contains(peer)) }
+                                    </div>
+                                }
+                            }).collect::<Html>()
+                        }
+                        <div class="ml-auto self-center flex items-center gap-1">
+                            { self.view_online_count() }
+                            <button
+                                onclick={ctx.link().callback(|_| Msg::ShowMentions(true))}
+                                class="relative p-1 rounded text-sm font-semibold text-gray-600 hover:bg-gray-100 dark:text-gray-300 dark:hover:bg-gray-700"
+                                title={t("header.mentions")}
+                            >
+                                {"@"}
+                                {
+                                    match self.mentions.iter().filter(|m| !m.read).count() {
+                                        0 => html! {},
+                                        unread => html! {
+                                            <span class="absolute -top-1 -right-1 min-w-[1rem] px-1 rounded-full text-[10px] leading-4 bg-yellow-300 text-gray-900">
+                                                {unread}
+                                            </span>
+                                        },
+                                    }
+                                }
+                            </button>
+                            {
+                                if self.active_dm.is_some() && self.call.is_none() {
+                                    html! {
+                                        <button
+                                            onclick={ctx.link().callback(|_| Msg::StartCall)}
+                                            class="p-1 rounded text-sm hover:bg-gray-100 dark:hover:bg-gray-700"
+                                            title={t("call.start")}
+                                        >
+                                            {"📞"}
+                                        </button>
+                                    }
+                                } else {
+                                    html! {}
+                                }
+                            }
+                            { self.view_export_menu(ctx) }
+                            <button
+                                onclick={ctx.link().callback(move |_| Msg::ShowSearch(!search_open))}
+                                class={classes!("p-1", "rounded", "text-sm", "hover:bg-gray-100", "dark:hover:bg-gray-700", search_open.then_some("bg-gray-100 dark:bg-gray-700"))}
+                                title={t("header.search")}
+                            >
+                                {"🔍"}
+                            </button>
+                            {
+                                if let Some(ms) = self.latency_ms {
+                                    html! {
+                                        <div class="mr-2 text-xs text-gray-400" title={t("header.latency")}>
+                                            {format!("{}ms", ms)}
+                                        </div>
+                                    }
+                                } else {
+                                    html! {}
+                                }
+                            }
+                            <button
+                                onclick={ctx.link().callback(move |_| Msg::SetNotificationPrefs(NotificationPrefs { sound_enabled: !prefs.sound_enabled, ..prefs }))}
+                                class="p-1 rounded text-sm hover:bg-gray-100 dark:hover:bg-gray-700"
+                                title={t(if prefs.sound_enabled { "header.mute" } else { "header.unmute" })}
+                            >
+                                {if prefs.sound_enabled { "🔔" } else { "🔕" }}
+                            </button>
+                            <button
+                                onclick={ctx.link().callback(|_| Msg::ToggleDarkMode)}
+                                class="p-1 rounded text-sm hover:bg-gray-100 dark:hover:bg-gray-700"
+                                title={t(if dark { "header.light_mode" } else { "header.dark_mode" })}
+                            >
+                                {if dark { "☀️" } else { "🌙" }}
+                            </button>
+                            <button
+                                onclick={ctx.link().callback(|_| Msg::ShowProfile(true))}
+                                class="p-1 rounded text-sm hover:bg-gray-100 dark:hover:bg-gray-700"
+                                title={t("header.profile")}
+                            >
+                                {"👤"}
+                            </button>
+                            <button
+                                onclick={ctx.link().callback(|_| Msg::ShowSettings(true))}
+                                class="p-1 rounded text-sm hover:bg-gray-100 dark:hover:bg-gray-700"
+                                title={t("header.settings")}
+                            >
+                                {"⚙️"}
+                            </button>
+                            { self.view_language_picker(ctx) }
+                        </div>
+                    </div>
+                    { self.view_pins(ctx) }
+
+                    {
+                        self.errors.iter().enumerate().map(|(i, e)| {
+                            html! {
+                                <div class="w-full px-6 py-2 flex items-center text-sm text-amber-800 bg-amber-100 border-b border-amber-200 dark:text-amber-200 dark:bg-amber-900/40 dark:border-amber-800">
+                                    <span class="grow">{e}</span>
+                                    <button
+                                        onclick={ctx.link().callback(move |_| Msg::DismissError(i))}
+                                        class="ml-3 px-2 rounded hover:bg-amber-200 dark:hover:bg-amber-800"
+                                        title={t("common.dismiss")}
+                                    >
+                                        {"✕"}
+                                    </button>
+                                </div>
+                            }
+                        }).collect::<Html>()
+                    }
+                    {
+                        match &self.connection {
+                            ReconnectState::Reconnecting { attempt, delay_ms } => html! {
+                                <div class="w-full px-6 py-2 text-sm text-amber-800 bg-amber-100 border-b border-amber-200 dark:text-amber-200 dark:bg-amber-900/40 dark:border-amber-800">
+                                    {t_args("connection.reconnecting_in", &[&delay_ms.div_ceil(1000), attempt])}
+                                </div>
+                            },
+                            ReconnectState::Failed(e) => html! {
+                                <div class="w-full px-6 py-2 text-sm text-red-700 bg-red-100 border-b border-red-200 dark:text-red-200 dark:bg-red-900/40 dark:border-red-800">
+                                    {e}
+                                </div>
+                            },
+                            ReconnectState::Connecting | ReconnectState::Connected => html! {},
+                        }
+                    }
+
+                    {
+                        if search_open {
+                            html! {
+                                <div class="w-full px-6 py-2 flex items-center gap-2 border-b border-gray-100 dark:border-gray-800">
+                                    <input
+                                        ref={self.search_input.clone()}
+                                        type="search"
+                                        placeholder={t("search.placeholder")}
+                                        value={self.search_draft.clone()}
+                                        oninput={ctx.link().callback(|e: InputEvent| {
+                                            Msg::Search(e.target_unchecked_into::<HtmlInputElement>().value())
+                                        })}
+                                        onkeydown={search_keys}
+                                        class="grow py-1 px-3 text-sm bg-gray-50 border border-gray-200 dark:bg-gray-800 dark:border-gray-700 rounded-full outline-none focus:ring-2 focus:ring-blue-200"
+                                    />
+                                    {
+                                        if searching {
+                                            html! {
+                                                <>
+                                                    <span class="text-xs text-gray-400 whitespace-nowrap">
+                                                        {t_args(if results == 1 { "search.result" } else { "search.results" }, &[&results])}
+                                                    </span>
+                                                    <button
+                                                        onclick={ctx.link().callback(|_| Msg::Search(String::new()))}
+                                                        class="px-2 text-sm text-gray-400 hover:text-gray-600"
+                                                        title={t("search.clear")}
+                                                    >
+                                                        {"✕"}
+                                                    </button>
+                                                </>
+                                            }
+                                        } else {
+                                            html! {}
+                                        }
+                                    }
+                                </div>
+                            }
+                        } else {
+                            html! {}
+                        }
+                    }
+
+                    {
+                        match self.active_filter.as_ref().filter(|_| self.active_dm.is_none()) {
+                            Some(from) => html! {
+                                <div class="w-full px-6 py-1 flex items-center gap-1 text-xs text-blue-800 bg-blue-50 border-b border-blue-100 dark:text-blue-200 dark:bg-gray-800 dark:border-gray-700">
+                                    <span>{t_args("filter.banner", &[from])}{" — "}</span>
+                                    <button
+                                        onclick={ctx.link().callback(|_| Msg::SetFilter(None))}
+                                        class="underline hover:text-blue-600 dark:hover:text-blue-300"
+                                    >
+                                        {t("filter.clear")}
+                                    </button>
+                                    {
+                                        if self.filter_hidden > 0 {
+                                            html! {
+                                                <span class="ml-auto text-gray-500 dark:text-gray-400">
+                                                    {t_args("filter.hidden", &[&self.filter_hidden])}
+                                                </span>
+                                            }
+                                        } else {
+                                            html! {}
+                                        }
+                                    }
+                                </div>
+                            },
+                            None => html! {},
+                        }
+                    }
+
+                    <div class="relative w-full grow min-h-0">
+                        <div
+                            ref={self.message_list.clone()}
+                            onscroll={ctx.link().callback(|_| Msg::Scrolled)}
+                            class="h-full overflow-auto px-6 py-4 space-y-4"
+                        >
+                            {
+                                if self.loading_older && self.active_dm.is_none() {
+                                    html! {
+                                        <div class="flex justify-center">
+                                            <div class="w-5 h-5 rounded-full border-2 border-blue-300 border-t-transparent animate-spin"></div>
+                                        </div>
+                                    }
+                                } else {
+                                    html! {}
+                                }
+                            }
+                            {
+                                visible.into_iter().map(|(i, m)| {
+                                    let earlier = self.active_dm.is_none() && i > 0 && i == self.history_len;
+                                    let unread = self.active_dm.is_none() && self.unread_marker.as_ref().is_some_and(|key| *key == m.dedup_key());
+                                    // Search results and dividers break groups up.
+                                    let adjacent = previous.replace(i).is_some_and(|p| p + 1 == i);
+                                    let continued = follow_ups[i] && adjacent && !earlier && !unread;
+                                    html! {
+                                        <>
+                                            {
+                                                if earlier {
+                                                    html! {
+                                                        <div class="text-center text-xs text-gray-300">{t("list.earlier")}</div>
+                                                    }
+                                                } else {
+                                                    html! {}
+                                                }
+                                            }
+                                            {
+                                                if unread {
+                                                    html! {
+                                                        <div ref={self.unread_divider.clone()} class="flex items-center gap-2 text-xs text-red-500">
+                                                            <div class="grow border-t border-red-400"></div>
+                                                            {t("list.new_messages")}
+                                                            <div class="grow border-t border-red-400"></div>
+                                                        </div>
+                                                    }
+                                                } else {
+                                                    html! {}
+                                                }
+                                            }
+                                            {
+                                                if active_result == Some(i) {
+                                                    html! { <div ref={self.search_target.clone()} class="hidden"></div> }
+                                                } else {
+                                                    html! {}
+                                                }
+                                            }
+                                            { self.view_message(ctx, m, &current_user, &receipts, continued, active_result == Some(i)) }
+                                        </>
+                                    }
+                                }).collect::<Html>()
+                            }
+                        </div>
+                        {
+                            if self.unseen > 0 {
+                                html! {
+                                    <button
+                                        onclick={ctx.link().callback(|_| Msg::ScrollToBottom)}
+                                        class="absolute bottom-3 left-1/2 -translate-x-1/2 px-4 py-1 rounded-full bg-blue-600 text-white text-sm shadow-lg hover:bg-blue-700"
+                                    >
+                                        {t_args(if self.unseen == 1 { "list.jump_one" } else { "list.jump_many" }, &[&self.unseen])}
+                                    </button>
+                                }
+                            } else {
+                                html! {}
+                            }
+                        }
+                    </div>
+
+                    <div class="w-full h-6 px-6 flex justify-between text-xs italic text-gray-400">
+                        {
+                            match typing_notice(&self.typists) {
+                                Some(notice) => html! { <span>{notice}</span> },
+                                None => html! { <span /> },
+                            }
+                        }
+                        {
+                            if self.pending > 0 {
+                                html! {
+                                    <span class="text-amber-600">
+                                        {t_args("composer.pending", &[&self.pending])}
+                                    </span>
+                                }
+                            } else {
+                                html! {}
+                            }
+                        }
+                    </div>
+
+                    {
+                        match &self.replying_to {
+                            Some((id, from)) if self.active_dm.is_none() => html! {
+                                <div class="w-full px-6 py-1 flex items-center gap-2 text-xs bg-blue-50 dark:bg-gray-800 border-t border-blue-100 dark:border-gray-700">
+                                    <span class="grow truncate border-l-2 border-blue-400 pl-2">
+                                        <span class="font-semibold">{t_args("composer.replying_to", &[from])}</span>
+                                        {self.quoted_text(*id)}
+                                    </span>
+                                    <button
+                                        onclick={ctx.link().callback(|_| Msg::ReplyTo(None))}
+                                        class="px-2 text-gray-400 hover:text-gray-600"
+                                        title={t("composer.cancel_reply")}
+                                    >
+                                        {"✕"}
+                                    </button>
+                                </div>
+                            },
+                            _ => html! {},
+                        }
+                    }
+                    <div class="relative w-full h-16 flex px-4 py-2 items-center border-t-2 border-blue-100 bg-gray-50 dark:border-gray-700 dark:bg-gray-800">
+                        {
+                            match &self.mention {
+                                Some(mention) => html! {
+                                    <MentionPopup
+                                        candidates={mention.candidates.iter().map(|name| Candidate {
+                                            name: name.clone(),
+                                            avatar: self.avatar_of(name),
+                                        }).collect::<Vec<_>>()}
+                                        query={mention.query.clone()}
+                                        selected={mention.selected_idx}
+                                        on_select={ctx.link().callback(|name| Msg::CompleteMention(Some(name)))}
+                                    />
+                                },
+                                None => html! {},
+                            }
+                        }
+                        <textarea
+                            ref={self.chat_input.clone()}
+                            {oninput}
+                            onkeydown={on_input_key}
+                            rows={self.input_rows.to_string()}
+                            placeholder={t("composer.placeholder")}
+                            class={classes!(
+                                "flex-grow", "py-2", "px-4", "bg-white", "border", "dark:bg-gray-900", "rounded-2xl", "outline-none", "resize-none",
+                                if self.input_rejected {
+                                    "border-red-500 ring-2 ring-red-300 animate-shake"
+                                } else {
+                                    "border-gray-300 dark:border-gray-600 focus:ring-2 focus:ring-blue-300"
+                                }
+                            )}
+                            required=true
+                        />
+                        <EmojiPicker on_select={ctx.link().callback(Msg::InsertText)} />
+                        <input
+                            ref={self.image_input.clone()}
+                            type="file"
+                            accept="image/png,image/gif,image/jpeg,image/webp"
+                            class="hidden"
+                            onchange={ctx.link().callback(|_| Msg::ImagePicked)}
+                        />
+                        <button
+                            type="button"
+                            onclick={ctx.link().callback(|_| Msg::PickImage)}
+                            title={t("composer.attach_image")}
+                            class="ml-1 p-2 text-xl rounded-full hover:bg-gray-200 dark:hover:bg-gray-700"
+                        >
+                            {"🖼️"}
+                        </button>
+                        <button
+                            type="button"
+                            onclick={ctx.link().callback(|_| Msg::ShowGifs(true))}
+                            title={t("composer.gif")}
+                            class="p-2 text-xs font-bold rounded-full hover:bg-gray-200 dark:hover:bg-gray-700"
+                        >
+                            {"GIF"}
+                        </button>
+                        <input
+                            ref={self.file_input.clone()}
+                            type="file"
+                            class="hidden"
+                            onchange={ctx.link().callback(|_| Msg::FilePicked)}
+                        />
+                        <button
+                            type="button"
+                            onclick={ctx.link().callback(|_| Msg::PickFile)}
+                            title={t("composer.share_file")}
+                            class="p-2 text-xl rounded-full hover:bg-gray-200 dark:hover:bg-gray-700"
+                        >
+                            {"📎"}
+                        </button>
+                        <div class="relative">
+                            {
+                                if self.recorder.is_some() {
+                                    html! {
+                                        <div class="absolute bottom-full right-0 mb-2 p-2 flex flex-col items-center gap-1 bg-white dark:bg-gray-800 rounded-lg shadow">
+                                            <canvas ref={self.waveform_canvas.clone()} width="200" height="48" class="w-48 h-12"></canvas>
+                                            <span class="text-xs text-gray-500 dark:text-gray-400 whitespace-nowrap">{t("voice.release")}</span>
+                                        </div>
+                                    }
+                                } else {
+                                    html! {}
+                                }
+                            }
+                            <button
+                                type="button"
+                                onpointerdown={ctx.link().callback(|_| Msg::Record(true))}
+                                onpointerup={ctx.link().callback(|_| Msg::Record(false))}
+                                onpointerleave={ctx.link().batch_callback(move |_| recording.then_some(Msg::Record(false)))}
+                                title={t("composer.record")}
+                                class={classes!(
+                                    "p-2", "text-xl", "rounded-full", "select-none", "touch-none",
+                                    if recording { "bg-red-200 dark:bg-red-900 animate-pulse" } else { "hover:bg-gray-200 dark:hover:bg-gray-700" }
+                                )}
+                            >
+                                {"🎤"}
+                            </button>
+                        </div>
+                        <div class="relative ml-3">
+                            {
+                                if self.slow_down.is_some() {
+                                    html! {
+                                        <div class="absolute bottom-full right-0 mb-2 px-2 py-1 text-xs text-white bg-gray-800 rounded shadow whitespace-nowrap">
+                                            {t("composer.slow_down")}
+                                        </div>
+                                    }
+                                } else {
+                                    html! {}
+                                }
+                            }
+                            <button
+                                onclick={submit}
+                                disabled={self.input_chars > self.max_chars || self.connection_failed() || self.slow_down.is_some()}
+                                class="p-3 bg-blue-600 hover:bg-blue-700 text-white rounded-full disabled:opacity-50 disabled:cursor-not-allowed"
+                            >
+                                <svg viewBox="0 0 24 24" xmlns="http://www.w3.org/2000/svg" class="w-5 h-5 fill-current">
+                                    <path d="M0 0h24v24H0z" fill="none"></path>
+                                    <path d="M2.01 21L23 12 2.01 3 2 10l15 2-15 2z"></path>
+                                </svg>
+                            </button>
+                        </div>
+                    </div>
+                    {
+                        // Only shown once the limit gets close.
+                        if self.input_chars * 5 > self.max_chars * 4 {
+                            html! {
+                                <div class={classes!(
+                                    "px-6", "pb-1", "text-right", "text-xs", "bg-gray-50", "dark:bg-gray-800",
+                                    if self.input_chars > self.max_chars { "text-red-600 font-semibold" } else { "text-gray-500 dark:text-gray-400" }
+                                )}>
+                                    {format!("{}/{}", self.input_chars, self.max_chars)}
+                                </div>
+                            }
+                        } else {
+                            html! {}
+                        }
+                    }
+                </div>
+                {
+                    if self.profile_open {
+                        html! {
+                            <ProfilePanel
+                                name={current_user.clone()}
+                                avatar={self.avatar_style.clone()}
+                                on_save={ctx.link().callback(|(name, avatar)| Msg::SaveProfile(name, avatar))}
+                                on_close={ctx.link().callback(|_| Msg::ShowProfile(false))}
+                            />
+                        }
+                    } else {
+                        html! {}
+                    }
+                }
+                {
+                    if self.settings_open {
+                        html! {
+                            <Settings
+                                prefs={self.notify}
+                                on_change={ctx.link().callback(Msg::SetNotificationPrefs)}
+                                blocked={self.blocked_names()}
+                                on_unblock={ctx.link().callback(|name| Msg::Block(name, false))}
+                                on_close={ctx.link().callback(|_| Msg::ShowSettings(false))}
+                            />
+                        }
+                    } else {
+                        html! {}
+                    }
+                }
+                {
+                    if self.user_search_open {
+                        html! {
+                            <UserSearch
+                                results={self.found_users.clone()}
+                                more={self.more_users}
+                                loading={self.searching_users}
+                                room={self.room.clone()}
+                                on_search={ctx.link().callback(|(query, offset)| Msg::SearchUsers { query, offset })}
+                                on_dm={ctx.link().batch_callback(|peer| vec![Msg::ShowUserSearch(false), Msg::SelectThread(Some(peer))])}
+                                on_invite={ctx.link().callback(Msg::InviteUser)}
+                                on_close={ctx.link().callback(|_| Msg::ShowUserSearch(false))}
+                            />
+                        }
+                    } else {
+                        html! {}
+                    }
+                }
+                {
+                    if self.room_dialog_open {
+                        html! {
+                            <RoomDialog
+                                rooms={self.rooms.clone()}
+                                current={self.room.clone()}
+                                error={self.room_error.clone()}
+                                on_create={ctx.link().callback(Msg::CreateRoom)}
+                                on_join={ctx.link().callback(|(room, invite)| Msg::EnterRoom { room, invite })}
+                                on_close={ctx.link().callback(|_| Msg::ShowRoomDialog(false))}
+                            />
+                        }
+                    } else {
+                        html! {}
+                    }
+                }
+                { self.view_call(ctx) }
+                { self.view_user_menu(ctx) }
+                { self.view_user_card(ctx) }
+                {
+                    if self.mentions_open {
+                        html! {
+                            <MentionsInbox
+                                mentions={self.mentions.clone()}
+                                on_open={ctx.link().callback(|(room, id)| Msg::OpenMention { room, id })}
+                                on_close={ctx.link().callback(|_| Msg::ShowMentions(false))}
+                            />
+                        }
+                    } else {
+                        html! {}
+                    }
+                }
+                {
+                    if self.gifs_open {
+                        html! {
+                            <GifPicker
+                                on_pick={ctx.link().callback(Msg::PickGif)}
+                                on_close={ctx.link().callback(|_| Msg::ShowGifs(false))}
+                            />
+                        }
+                    } else {
+                        html! {}
+                    }
+                }
+                {
+                    match &self.forwarding {
+                        Some(message) => html! {
+                            <ForwardDialog
+                                rooms={self.rooms.clone()}
+                                contacts={self.contacts(&current_user)}
+                                excerpt={excerpt(split_forwarded(&message.message).map_or(message.message.as_str(), |(_, body)| body))}
+                                on_forward={ctx.link().callback(Msg::ForwardTo)}
+                                on_close={ctx.link().callback(|_| Msg::Forward(None))}
+                            />
+                        },
+                        None => html! {},
+                    }
+                }
+            </div>
+        };
+        html! {
+            <ContextProvider<Language> context={self.language}>{chat}</ContextProvider<Language>>
+        }
+    }
+}
+
+impl Chat {
+    /// Queues a frame for the server. Returns `false` if it couldn't even be
+    /// handed to the websocket service.
+    fn send(&self, message: &WebSocketMessage) -> bool {
+        match self
+            .wss
+            .tx
+            .clone()
+            .try_send(serde_json::to_string(message).unwrap())
+        {
+            Ok(_) => true,
+            Err(e) => {
+                log::debug!("error sending to channel: {:?}", e);
+                false
+            }
+        }
+    }
+
+    /// Sends `text` to the open DM thread or the current room and scrolls to
+    /// the bottom. Returns `false` if it couldn't be queued; messages sent
+    /// while offline are counted as pending.
+    fn send_message(&mut self, ctx: &Context<Self>, text: String) -> bool {
+        // Nothing would ever pick it up.
+        if self.connection_failed() {
+            return false;
+        }
+        let encrypt_for = self
+            .active_dm
+            .clone()
+            .filter(|peer| self.shared_keys.contains_key(peer));
+        if let Some(peer) = encrypt_for {
+            self.send_encrypted(ctx, peer, text);
+        } else {
+            let message = match &self.active_dm {
+                Some(peer) => WebSocketMessage {
+                    data: Some(text),
+                    to: Some(peer.clone()),
+                    ..WebSocketMessage::new(MsgTypes::DirectMessage)
+                },
+                None => WebSocketMessage {
+                    data: Some(text),
+                    room: Some(self.room.clone()),
+                    reply_to: self.replying_to.as_ref().map(|(id, _)| *id),
+                    ..WebSocketMessage::new(MsgTypes::Message)
+                },
+            };
+            if !self.send(&message) {
+                return false;
+            }
+            if message.reply_to.is_some() {
+                self.replying_to = None;
+            }
+        }
+        // Asked now rather than on load, when it's clear I'm chatting.
+        if !std::mem::replace(&mut self.permission_asked, true)
+            && !self.notify.desktop_enabled
+            && notify::permission_undecided()
+        {
+            let link = ctx.link().clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                // Saying no is left at that.
+                if notify::request_permission().await {
+                    link.send_message(Msg::DesktopPermission(true));
+                }
+            });
+        }
+        // Whatever I was reading, I want to see what I just said.
+        if !self.is_connected() {
+            self.pending = (self.pending + 1).min(OUTBOX_CAPACITY);
+        }
+        true
+    }
+
+    /// Encrypts `text` with the key shared with `peer`; it goes out as
+    /// `Msg::SendEncrypted` once that's done.
+    fn send_encrypted(&self, ctx: &Context<Self>, peer: String, text: String) {
+        let key = self.shared_keys[&peer].clone();
+        let link = ctx.link().clone();
+        wasm_bindgen_futures::spawn_local(async move {
+            let data = crypto::encrypt(&key, &text)
+                .await
+                .map_err(|e| log::warn!("dm: couldn't encrypt a message: {:?}", e))
+                .ok();
+            link.send_message(Msg::SendEncrypted { to: peer, data });
+        });
+    }
+
+    /// Opens an encrypted direct message with the key shared with the other
+    /// side, then hands it on as `Msg::Decrypted`.
+    fn decrypt(&self, ctx: &Context<Self>, to: Option<String>, mut message: MessageData) {
+        let key = self
+            .shared_keys
+            .get(&dm_peer(ctx, to.as_ref(), &message))
+            .cloned();
+        let link = ctx.link().clone();
+        wasm_bindgen_futures::spawn_local(async move {
+            let opened = match key {
+                Some(key) => crypto::decrypt(&key, &message.message).await,
+                None => Err("no key shared with the sender".into()),
+            };
+            message.message = opened.unwrap_or_else(|e| {
+                log::warn!("dm: couldn't decrypt a message: {:?}", e);
+                t("dm.undecryptable").to_string()
+            });
+            link.send_message(Msg::Decrypted { to, message });
+        });
+    }
+
+    /// Opens the microphone and camera for the call with `peer` and makes
+    /// my offer, or my answer to their `offer`; the result comes back as
+    /// `Msg::CallReady`.
+    fn open_call(&self, ctx: &Context<Self>, peer: String, offer: Option<String>) {
+        let on_candidate = ctx.link().callback(Msg::CallCandidate);
+        let on_stream = ctx.link().callback(Msg::RemoteStream);
+        let link = ctx.link().clone();
+        wasm_bindgen_futures::spawn_local(async move {
+            let result = match Connection::open(on_candidate, on_stream).await {
+                Ok(connection) => {
+                    let sdp = match &offer {
+                        Some(offer) => connection.answer(offer).await,
+                        None => connection.offer().await,
+                    };
+                    match sdp {
+                        Ok(sdp) => Ok((connection, sdp)),
+                        Err(e) => {
+                            connection.hang_up();
+                            Err(format!("{:?}", e))
+                        }
+                    }
+                }
+                Err(e) => Err(format!("{:?}", e)),
+            };
+            link.send_message(Msg::CallReady { peer, result });
+        });
+    }
+
+    /// Sends a call frame of type `kind` to `peer`.
+    fn send_call_signal(&self, kind: MsgTypes, peer: &str, signal: CallSignal) {
+        self.send(&WebSocketMessage {
+            data: Some(serde_json::to_string(&signal).unwrap()),
+            to: Some(peer.to_string()),
+            ..WebSocketMessage::new(kind)
+        });
+    }
+
+    /// Works out the key shared with `peer` from the public key they
+    /// announced, once I have a key pair of my own.
+    fn derive_key(&self, ctx: &Context<Self>, peer: String) {
+        let (mine, public) = match (&self.keys, self.peer_keys.get(&peer)) {
+            (Some(mine), Some(public)) => (mine.clone(), public.clone()),
+            _ => return,
+        };
+        let link = ctx.link().clone();
+        wasm_bindgen_futures::spawn_local(async move {
+            match crypto::derive(&mine, &public).await {
+                Ok(key) => link.send_message(Msg::KeyDerived { peer, public, key }),
+                Err(e) => log::warn!("dm: no shared key with {}: {:?}", peer, e),
+            }
+        });
+    }
+
+    /// Files a direct message under the thread with the other side and
+    /// alerts me to it if it's from them.
+    fn receive_direct_message(
+        &mut self,
+        ctx: &Context<Self>,
+        to: Option<String>,
+        message: MessageData,
+    ) -> bool {
+        let from_self = message.from == current_username(ctx);
+        let peer = dm_peer(ctx, to.as_ref(), &message);
+        if self.blocked.contains(&message.from) {
+            // Kept, in case they're unblocked, but without a word.
+            self.dm_threads.entry(peer).or_default().push(message);
+            return false;
+        }
+        if self.active_dm.as_ref() == Some(&peer) {
+            self.on_new_message(from_self);
+        }
+        if !from_self {
+            let mentioned = mentions::mentions(&message.message, &current_username(ctx));
+            let thread = Some(peer.clone());
+            self.alert(
+                &message,
+                &t_args("notify.direct", &[&message.from]),
+                mentioned,
+                ctx.link().callback(move |_| Msg::ShowNotified {
+                    peer: thread.clone(),
+                    id: None,
+                }),
+            );
+            if self.active_dm.as_ref() != Some(&peer) {
+                self.dm_unread.insert(peer.clone());
+            }
+            if self.away() {
+                self.tab_badge.increment();
+            }
+        }
+        self.dm_threads.entry(peer).or_default().push(message);
+        true
+    }
+
+    /// Shows `error` in the banner, unless it's already the latest one shown.
+    /// Returns whether a re-render is needed.
+    fn report_error(&mut self, error: &str) -> bool {
+        if self.errors.last().map(String::as_str) == Some(error) {
+            return false;
+        }
+        self.errors.push(error.to_string());
+        true
+    }
+
+    /// Announces `username` to the server as a member of the current room.
+    /// The frame is also handed to the service as its hello, so the server
+    /// hears it again first thing after every reconnect.
+    fn register(&mut self, username: String) {
+        let register = self.set_hello(username);
+        self.history_requested = false;
+        self.loading_older = false;
+        if self.is_connected() && self.send(&register) {
+            log::debug!("message sent successfully");
+        }
+    }
+
+    /// Carries out a slash command typed into the message box. Mistakes are
+    /// reported in the error banner without bothering the server.
+    fn run_command(&mut self, ctx: &Context<Self>, command: Command) -> bool {
+        match command {
+            Command::Me(action) => {
+                self.send_message(ctx, format!("/me {}", replace_shortcodes(&action)));
+                !self.is_connected()
+            }
+            Command::Clear => {
+                match &self.active_dm {
+                    Some(peer) => {
+                        if let Some(thread) = self.dm_threads.get_mut(peer) {
+                            thread.clear();
+                        }
+                    }
+                    None => {
+                        self.messages.clear();
+                        self.history_len = 0;
+                        self.unseen = 0;
+                        history::save(
+                            history::key(&self.room, &current_username(ctx)),
+                            &self.messages,
+                        );
+                    }
+                }
+                true
+            }
+            Command::Nick(name) => {
+                let current = current_username(ctx);
+                if name == current {
+                    return false;
+                }
+                if let Err(e) = self.check_name(&name, &current) {
+                    return self.report_error(&e);
+                }
+                self.rename(ctx, name);
+                true
+            }
+            Command::Usage(usage) => self.report_error(&t_args("error.usage", &[&usage])),
+            Command::Unknown(name) => self.report_error(&t_args("error.unknown_command", &[&name])),
+        }
+    }
+
+    /// Hands the service the Register frame for `username` as its hello,
+    /// and returns it.
+    fn set_hello(&self, username: String) -> WebSocketMessage {
+        let (timezone, locale) = time::local_zone();
+        let register = WebSocketMessage {
+            data: Some(username),
+            room: Some(self.room.clone()),
+            public_key: self.keys.as_ref().map(|keys| keys.public.clone()),
+            invite: self.invites.get(&self.room).cloned(),
+            token: self.staff_token.clone(),
+            timezone: Some(timezone),
+            locale: Some(locale),
+            ..WebSocketMessage::new(MsgTypes::Register)
+        };
+        self.wss
+            .set_hello(serde_json::to_string(&register).unwrap());
+        register
+    }
+
+    /// Whether `name` would do as a new name for `current`.
+    fn check_name(&self, name: &str, current: &str) -> Result<(), String> {
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            return Err(t("error.name_invalid").to_string());
+        }
+        if name != current && self.users.iter().any(|u| u.name == name) {
+            return Err(t_args("error.name_taken", &[&name]));
+        }
+        Ok(())
+    }
+
+    /// Switches to `name` and tells the room, along with my avatar style.
+    /// The hello is updated too, so a reconnect doesn't bring the old name
+    /// back.
+    fn rename(&mut self, ctx: &Context<Self>, name: String) {
+        if let Some((user, _)) = ctx.link().context::<User>(Callback::noop()) {
+            *user.username.borrow_mut() = name.clone();
+        }
+        self.set_hello(name.clone());
+        self.send_profile(name);
+    }
+
+    fn send_profile(&self, name: String) {
+        let profile = ProfileData {
+            previous: None,
+            name,
+            avatar: self.avatar_style.clone(),
+            role: UserRole::User,
+            timezone: String::new(),
+            locale: String::new(),
+        };
+        self.send(&WebSocketMessage {
+            data: Some(serde_json::to_string(&profile).unwrap()),
+            ..WebSocketMessage::new(MsgTypes::ProfileUpdate)
+        });
+    }
+
+    /// The `@name` being typed at the caret of `input`, if it could still
+    /// become the name of someone else in the room.
+    fn find_mention(&self, input: &HtmlTextAreaElement, current: &str) -> Option<MentionState> {
+        let text = input.value();
+        let caret = byte_offset(&text, input.selection_start().ok().flatten()?);
+        let (token, query) = mentions::find_mention_token(&text, caret)?;
+        let prefix = query.to_lowercase();
+        let candidates: Vec<String> = self
+            .users
+            .iter()
+            .map(|u| &u.name)
+            .filter(|name| *name != current && name.to_lowercase().starts_with(&prefix))
+            .take(MAX_MENTION_SUGGESTIONS)
+            .cloned()
+            .collect();
+        if candidates.is_empty() {
+            return None;
+        }
+        Some(MentionState {
+            start: text[..token.start].encode_utf16().count() as u32,
+            end: text[..token.end].encode_utf16().count() as u32,
+            query: query.to_string(),
+            candidates,
+            selected_idx: 0,
+        })
+    }
+
+    /// Recounts the characters in the message box. Returns whether the
+    /// count changed.
+    fn count_input(&mut self) -> bool {
+        let chars = self
+            .chat_input
+            .cast::<HtmlTextAreaElement>()
+            .map_or(0, |input| input.value().chars().count());
+        std::mem::replace(&mut self.input_chars, chars) != chars
+    }
+
+    /// Shrinks the message box back to one line after sending. Returns
+    /// whether it had grown.
+    fn reset_input_rows(&mut self) -> bool {
+        std::mem::replace(&mut self.input_rows, 1) != 1
+    }
+
+    fn is_connected(&self) -> bool {
+        self.connection == ReconnectState::Connected
+    }
+
+    /// Whether the service gave up, as opposed to still (re)connecting,
+    /// when messages wait in its outbox.
+    fn connection_failed(&self) -> bool {
+        matches!(self.connection, ReconnectState::Failed(_))
+    }
+
+    /// Reads the current room's stored history and joins the room once it's
+    /// in, see `Msg::HistoryLoaded`.
+    fn load_history(&self, ctx: &Context<Self>) {
+        let room = self.room.clone();
+        let key = history::key(&room, &current_username(ctx));
+        let link = ctx.link().clone();
+        wasm_bindgen_futures::spawn_local(async move {
+            let messages = history::load(&key).await;
+            link.send_message(Msg::HistoryLoaded { room, messages });
+        });
+    }
+
+    /// Adds "bob joined the room" / "carol left the room" lines for a
+    /// change in the member list.
+    fn announce_membership(&mut self, change: &MembershipChange, current_user: &str) {
+        let joined = change.joined.iter().map(|u| t_args("notice.joined", &[u]));
+        let left = change.left.iter().map(|u| t_args("notice.left", &[u]));
+        let notices: Vec<String> = joined.chain(left).collect();
+        if notices.is_empty() {
+            return;
+        }
+        self.messages
+            .extend(notices.into_iter().map(MessageData::system));
+        history::save(history::key(&self.room, current_user), &self.messages);
+        if self.active_dm.is_none() && self.at_bottom {
+            self.jump_to_bottom();
+        }
+    }
+
+    /// Shows `delta` next to the online count, added to one still showing.
+    fn show_member_delta(&mut self, ctx: &Context<Self>, delta: isize) {
+        let shown = self.member_delta.take().map_or(0, |(shown, _)| shown);
+        let total = shown + delta;
+        if total == 0 {
+            return;
+        }
+        let link = ctx.link().clone();
+        let timeout = Timeout::new(MEMBER_DELTA_MS, move || {
+            link.send_message(Msg::ClearMemberDelta)
+        });
+        self.member_delta = Some((total, timeout));
+    }
+
+    /// Folds the room history sent by the server in with what we already
+    /// have, dropping anything we've got already, and keeps it above the
+    /// live messages.
+    fn merge_history(&mut self, fetched: Vec<MessageData>, current_user: &str) {
+        let live = self
+            .messages
+            .split_off(self.history_len.min(self.messages.len()));
+        let history = std::mem::take(&mut self.messages);
+        for message in &fetched {
+            self.recent.insert(message.dedup_key());
+        }
+        (self.messages, self.history_len) = dedup::merge_history(history, fetched, live);
+        history::save(history::key(&self.room, current_user), &self.messages);
+        if self.active_dm.is_none() && self.at_bottom {
+            self.jump_to_bottom();
+        }
+    }
+
+    /// Picks up where I left off in the room, `cursor` being the last
+    /// message I had read: what came after is marked as new and counted,
+    /// and the list opens at the first of it instead of the bottom.
+    fn restore_read_position(&mut self, cursor: u64, current_user: &str) {
+        self.last_read_sent = self.last_read_sent.max(cursor);
+        let unread: Vec<&MessageData> = self
+            .messages
+            .iter()
+            .filter(|m| m.id.is_some_and(|id| id > cursor))
+            .filter(|m| m.kind != MessageKind::System && !m.deleted)
+            .filter(|m| m.from != current_user && !self.blocked.contains(&m.from))
+            .collect();
+        let first = match unread.first() {
+            Some(first) => first.dedup_key(),
+            None => return,
+        };
+        self.unseen = unread.len();
+        self.unread_marker = Some(first);
+        self.unread_timeout = None;
+        self.at_bottom = false;
+        self.scroll_pending = false;
+        self.unread_scroll_pending = true;
+    }
+
+    /// Asks the server for a page of the current room's history: the
+    /// latest messages, or those older than message `before`.
+    fn request_history(&self, before: Option<u64>) -> bool {
+        let query = HistoryQuery {
+            before,
+            limit: HISTORY_PAGE,
+        };
+        self.send(&WebSocketMessage {
+            data: Some(serde_json::to_string(&query).unwrap()),
+            room: Some(self.room.clone()),
+            ..WebSocketMessage::new(MsgTypes::History)
+        })
+    }
+
+    /// Asks for the page before the oldest room message loaded, unless one
+    /// is on its way already or the server said there is nothing older.
+    /// Returns whether a request went out.
+    fn load_older(&mut self) -> bool {
+        if self.loading_older
+            || !self.history_more
+            || self.active_dm.is_some()
+            || !self.is_connected()
+        {
+            return false;
+        }
+        let oldest = match self.messages.iter().find_map(|m| m.id) {
+            Some(id) => id,
+            None => return false,
+        };
+        self.loading_older = self.request_history(Some(oldest));
+        self.loading_older
+    }
+
+    /// Puts a page of older messages from the server above everything
+    /// loaded so far, minus any we have already.
+    fn prepend_history(&mut self, fetched: Vec<MessageData>, current_user: &str) {
+        let known: HashSet<String> = self.messages.iter().map(MessageData::dedup_key).collect();
+        let mut page: Vec<MessageData> = fetched
+            .into_iter()
+            .filter(|m| {
+                let key = m.dedup_key();
+                self.recent.insert(key.clone());
+                !known.contains(&key)
+            })
+            .collect();
+        self.history_len += page.len();
+        page.append(&mut self.messages);
+        self.messages = page;
+        history::save(history::key(&self.room, current_user), &self.messages);
+    }
+
+    /// Starts watching room messages that aren't being watched yet.
+    fn observe_messages(&self) {
+        let (observer, list) = match (
+            &self.read_observer,
+            self.message_list.cast::<web_sys::Element>(),
+        ) {
+            (Some(observer), Some(list)) => (observer, list),
+            _ => return,
+        };
+        if let Ok(nodes) = list.query_selector_all("[data-id]") {
+            for i in 0..nodes.length() {
+                if let Some(node) = nodes.item(i) {
+                    observer.observe(node.unchecked_ref());
+                }
+            }
+        }
+    }
+
+    /// Who has read how far: each reader is listed under the latest
+    /// message they've seen.
+    fn receipts<'a>(&'a self, current_user: &str) -> HashMap<u64, Vec<&'a String>> {
+        let shown: Vec<u64> = self
+            .visible_messages()
+            .into_iter()
+            .filter_map(|(_, m)| m.id)
+            .collect();
+        let mut receipts: HashMap<u64, Vec<&String>> = HashMap::new();
+        for (reader, read) in &self.read_up_to {
+            if reader == current_user {
+                continue;
+            }
+            if let Some(id) = shown.iter().rev().find(|id| *id <= read) {
+                receipts.entry(*id).or_default().push(reader);
+            }
+        }
+        for readers in receipts.values_mut() {
+            readers.sort();
+        }
+        receipts
+    }
+
+    /// The avatar `name` is shown with: theirs if they're in the room, the
+    /// default one otherwise.
+    fn avatar_of(&self, name: &str) -> String {
+        match self.users.iter().find(|u| u.name == name) {
+            Some(u) => u.avatar.clone(),
+            None => avatar_url(AVATAR_STYLES[0], name),
+        }
+    }
+
+    /// Online users first, then busy, away and offline, keeping the
+    /// server's order otherwise.
+    fn sort_users(&mut self) {
+        self.users.sort_by_key(|u| match u.status {
+            PresenceStatus::Online => 0,
+            PresenceStatus::Busy => 1,
+            PresenceStatus::Away => 2,
+            PresenceStatus::Offline => 3,
+        });
+    }
+
+    /// The status others should see for me.
+    fn my_status(&self) -> PresenceStatus {
+        match self.chosen_status {
+            PresenceStatus::Online if page_hidden() => PresenceStatus::Away,
+            chosen => chosen,
+        }
+    }
+
+    fn send_presence(&self, ctx: &Context<Self>) {
+        let presence = PresenceData {
+            name: current_username(ctx),
+            status: self.my_status(),
+        };
+        self.send(&WebSocketMessage {
+            data: Some(serde_json::to_string(&presence).unwrap()),
+            ..WebSocketMessage::new(MsgTypes::Presence)
+        });
+    }
+
+    /// Keeps the view pinned to the newest message if the user is already
+    /// looking at the bottom (or sent it themselves), otherwise counts it
+    /// towards the "new messages" badge.
+    fn on_new_message(&mut self, from_self: bool) {
+        if self.at_bottom || from_self {
+            self.jump_to_bottom();
+        } else {
+            self.unseen += 1;
+        }
+    }
+
+    /// Raises the alerts the user opted into for `m`: the chime if the tab
+    /// is hidden or the list is scrolled up, a desktop notification if the
+    /// tab is hidden. Mentions always alert; with `mentions_only` nothing
+    /// else does.
+    fn alert(&mut self, m: &MessageData, title: &str, mentioned: bool, on_click: Callback<()>) {
+        let hidden = page_hidden();
+        let wanted = |unseen: bool| mentioned || (unseen && !self.notify.mentions_only);
+        let (chime, desktop) = (wanted(hidden || !self.at_bottom), wanted(hidden));
+        if self.notify.sound_enabled && chime {
+            if let Some(sound) = &mut self.sound {
+                sound.play();
+            }
+        }
+        if self.notify.desktop_enabled && desktop {
+            let body = if m.file.is_some() {
+                t_args("notify.shared", &[&m.message])
+            } else if classify_message(&m.message) == MessageContent::Image {
+                t("notify.image").to_string()
+            } else {
+                excerpt(&m.message)
+            };
+            notify::show(title, &body, &m.from, &self.avatar_of(&m.from), on_click);
+        }
+    }
+
+    /// Whether I'm not looking at the chat: the tab is hidden or another
+    /// window has focus.
+    fn away(&self) -> bool {
+        !self.focused || page_hidden()
+    }
+
+    /// Leaving drops the divider from an earlier absence; coming back
+    /// starts clearing the current one, and the tab's count if I'm at the
+    /// bottom. Returns whether a re-render is
+    /// needed.
+    fn on_focus_change(&mut self, ctx: &Context<Self>) -> bool {
+        if self.away() {
+            self.read_timeout = None;
+            self.unread_timeout = None;
+            return self.unread_marker.take().is_some();
+        }
+        if self.at_bottom {
+            self.tab_badge.clear();
+        }
+        self.start_unread_countdown(ctx);
+        self.start_read_dwell(ctx);
+        false
+    }
+
+    /// Waits to see whether `read_pending` stays on screen long enough to
+    /// count as read, unless the tab is in the background.
+    fn start_read_dwell(&mut self, ctx: &Context<Self>) {
+        if self.read_pending <= self.last_read_sent || self.read_timeout.is_some() || self.away() {
+            return;
+        }
+        let link = ctx.link().clone();
+        self.read_timeout = Some(Timeout::new(READ_DWELL_MS, move || {
+            link.send_message(Msg::ReadDwelled)
+        }));
+    }
+
+    /// Whether message `id` is at least partly visible in the list.
+    fn on_screen(&self, id: u64) -> bool {
+        let list = match self.message_list.cast::<web_sys::Element>() {
+            Some(list) => list,
+            None => return false,
+        };
+        let selector = format!("[data-id=\"{}\"]", id);
+        match list.query_selector(&selector).ok().flatten() {
+            Some(message) => {
+                let (rect, view) = (
+                    message.get_bounding_client_rect(),
+                    list.get_bounding_client_rect(),
+                );
+                rect.bottom() > view.top() && rect.top() < view.bottom()
+            }
+            None => false,
+        }
+    }
+
+    /// Schedules the divider to go away once I'm back and it has been on
+    /// screen, or scrolled past.
+    fn start_unread_countdown(&mut self, ctx: &Context<Self>) {
+        if self.unread_marker.is_none() || self.unread_timeout.is_some() || self.away() {
+            return;
+        }
+        let (divider, list) = match (
+            self.unread_divider.cast::<web_sys::Element>(),
+            self.message_list.cast::<web_sys::Element>(),
+        ) {
+            (Some(divider), Some(list)) => (divider, list),
+            _ => return,
+        };
+        if divider.get_bounding_client_rect().top() > list.get_bounding_client_rect().bottom() {
+            return;
+        }
+        let link = ctx.link().clone();
+        self.unread_timeout = Some(Timeout::new(UNREAD_DIVIDER_MS, move || {
+            link.send_message(Msg::ClearUnreadDivider)
+        }));
+    }
+
+    fn jump_to_bottom(&mut self) {
+        self.scroll_pending = true;
+        self.at_bottom = true;
+        self.unseen = 0;
+    }
+
+    /// The thread currently on screen: the public room, or the open DM.
+    fn current_messages(&self) -> &[MessageData] {
+        match &self.active_dm {
+            Some(peer) => self.dm_threads.get(peer).map(Vec::as_slice).unwrap_or(&[]),
+            None => &self.messages,
+        }
+    }
+
+    /// For each message of the current thread, whether it continues a group
+    /// started by an earlier one, see `group_messages`.
+    fn follow_ups(&self) -> Vec<bool> {
+        let messages = self.current_messages();
+        let mut follow_ups = vec![false; messages.len()];
+        for group in group_messages(messages) {
+            for i in group.messages.skip(1) {
+                follow_ups[i] = true;
+            }
+        }
+        follow_ups
+    }
+
+    /// The current thread without blocked users' messages, and in the room
+    /// only `active_filter`'s. Each comes with its position in the full
+    /// thread.
+    fn shown_messages(&self) -> impl Iterator<Item = (usize, &MessageData)> {
+        let from = self
+            .active_filter
+            .as_ref()
+            .filter(|_| self.active_dm.is_none());
+        self.current_messages()
+            .iter()
+            .enumerate()
+            .filter(move |(_, m)| from.is_none_or(|from| m.from == *from))
+            .filter(|(_, m)| !self.blocked.contains(&m.from))
+    }
+
+    /// `shown_messages`, narrowed down to the search results if there's a
+    /// query.
+    fn visible_messages(&self) -> Vec<(usize, &MessageData)> {
+        let query = self.search_query.trim();
+        self.shown_messages()
+            .filter(|(_, m)| {
+                query.is_empty()
+                    || search::contains(&m.from, query)
+                    || search::contains(&m.message, query)
+            })
+            .collect()
+    }
+
+    /// Open DM threads, sorted so the tab order stays stable.
+    fn dm_peers(&self) -> Vec<&String> {
+        let mut peers: Vec<&String> = self.dm_threads.keys().collect();
+        peers.sort();
+        peers
+    }
+
+    #[allow(clippy::unnecessary_operation)]
+    fn view_message(
+        &self,
+        ctx: &Context<Self>,
+        m: &MessageData,
+        current_user: &str,
+        receipts: &HashMap<u64, Vec<&String>>,
+        continued: bool,
+        active_result: bool,
+    ) -> Html {
+        if m.kind == MessageKind::System {
+            return html! {
+                <div class="text-center text-xs italic text-gray-400">{m.message.clone()}</div>
+            };
+        }
+        let is_self = m.from == current_user;
+        let flashing = m.id.is_some() && m.id == self.flashing;
+        let pinned = m.id.is_some() && self.pinned.iter().any(|p| p.id == m.id);
+        if m.deleted || m.hidden {
+            return html! {
+                <div class={classes!("flex", "flex-col", if is_self { "items-end" } else { "items-start" }, continued.then_some("!mt-1"), flashing.then_some("animate-flash"))} data-id={m.id.map(|id| id.to_string())}>
+                    <div class="px-3 py-2 rounded-lg text-xs italic text-gray-400 border border-dashed border-gray-300 dark:border-gray-600">
+                        {t(if m.deleted { "message.deleted" } else { "message.hidden" })}
+                    </div>
+                </div>
+            };
+        }
+        let sender = self
+            .users
+            .iter()
+            .find(|u| u.name == m.from)
+            .filter(|_| !is_self);
+        let content = split_forwarded(&m.message).map_or(m.message.as_str(), |(_, body)| body);
+        let is_image = classify_message(content) == MessageContent::Image;
+        let editable = match m.id {
+            Some(id) if is_self && !is_image && self.active_dm.is_none() => Some(id),
+            _ => None,
+        };
+        let quote = m
+            .reply_to
+            .map(|id| match self.messages.iter().find(|q| q.id == Some(id)) {
+                Some(quoted) => Quote::Found {
+                    id,
+                    from: quoted.from.clone(),
+                    text: self.quoted_text(id),
+                },
+                None => Quote::Missing,
+            });
+        let read = m.id.filter(|_| is_self).map(|id| {
+            self.read_up_to
+                .iter()
+                .any(|(reader, read)| reader != current_user && *read >= id)
+        });
+
+        let row_class = match (is_self, continued) {
+            (true, false) => "items-end",
+            (false, false) => "items-start",
+            // Pulled up close to the message it follows.
+            (true, true) => "items-end !mt-1",
+            (false, true) => "items-start !mt-1",
+        };
+
+        html! {
+            <div class={classes!("group", "flex", "flex-col", row_class, flashing.then_some("animate-flash"))} data-id={m.id.map(|id| id.to_string())}>
+                <MessageBubble
+                    message={m.clone()}
+                    current_user={current_user.to_string()}
+                    {continued}
+                    {active_result}
+                    search_query={self.search_query.clone()}
+                    avatar={sender.map(|u| u.avatar.clone())}
+                    avatar_title={sender.and_then(local_time)}
+                    {quote}
+                    file={m.file.as_ref().map(|file_id| self.view_file(file_id, &m.message))}
+                    {pinned}
+                    {read}
+                    editing={editable.is_some() && editable == self.editing}
+                    edit_input={self.edit_input.clone()}
+                    on_edit={editable.map(|id| ctx.link().callback(move |start: bool| Msg::EditMessage(start.then_some(id))))}
+                    on_submit_edit={ctx.link().callback(|_| Msg::SubmitEdit)}
+                    on_jump={ctx.link().callback(Msg::JumpTo)}
+                    on_user_menu={(!is_self).then(|| user_menu(ctx, &m.from))}
+                />
+                { self.view_previews(m) }
+                { self.view_reactions(ctx, m, current_user) }
+                {
+                    match m.id.and_then(|id| receipts.get(&id)) {
+                        Some(readers) => html! {
+                            <div class="flex -space-x-1 mt-1">
+                                {
+                                    readers.iter().map(|reader| html! {
+                                        <img
+                                            class="w-4 h-4 rounded-full ring-1 ring-white"
+                                            src={self.avatar_of(reader)}
+                                            alt={reader.to_string()}
+                                            title={t_args("message.seen_by", &[reader])}
+                                        />
+                                    }).collect::<Html>()
+                                }
+                            </div>
+                        },
+                        _ => html! {},
+                    }
+                }
+            </div>
+        }
+    }
+
+    /// The starred messages in the sidebar, newest first. Clicking one
+    /// scrolls to it.
+    fn view_favorites(&self, ctx: &Context<Self>) -> Html {
+        if self.favorites.is_empty() {
+            return html! {
+                <div class="mx-3 px-3 text-xs text-gray-500 dark:text-gray-400">{t("sidebar.no_favorites")}</div>
+            };
+        }
+        self.favorites
+            .iter()
+            .rev()
+            .filter_map(|favorite| Some((favorite, favorite.message.id?)))
+            .map(|(favorite, id)| {
+                let room = favorite.room.clone();
+                let unstar = favorite.clone();
+                html! {
+                    <div class="group flex items-start mx-3 my-1 px-3 py-1 rounded-lg text-xs text-blue-900 hover:bg-blue-200 dark:text-gray-200 dark:hover:bg-gray-700">
+                        <button
+                            class="grow min-w-0 text-left"
+                            title={t("pins.show")}
+                            onclick={ctx.link().callback(move |_| Msg::ShowRoomMessage { room: room.clone(), id })}
+                        >
+                            <div class="font-semibold truncate">{format!("{} · #{}", favorite.message.from, favorite.room)}</div>
+                            <div class="truncate text-gray-600 dark:text-gray-300">{excerpt(&favorite.message.message)}</div>
+                        </button>
+                        <button
+                            class="ml-1 text-gray-400 hover:text-red-600 opacity-0 group-hover:opacity-100"
+                            title={t("message.unfavorite")}
+                            onclick={ctx.link().callback(move |_| Msg::ToggleFavorite(unstar.clone()))}
+                        >
+                            {"✕"}
+                        </button>
+                    </div>
+                }
+            })
+            .collect()
+    }
+
+    /// Block and unblock for the user right-clicked, where they were, and
+    /// kick and ban if I outrank them.
+    fn view_user_menu(&self, ctx: &Context<Self>) -> Html {
+        let menu = match &self.user_menu {
+            Some(menu) => menu,
+            None => return html! {},
+        };
+        let blocked = self.blocked.contains(&menu.name);
+        let name = menu.name.clone();
+        let my_role = self.role_of(&current_username(ctx));
+        let mut actions: Vec<(&str, Option<u32>)> = vec![];
+        if my_role > self.role_of(&menu.name) {
+            if my_role >= UserRole::Moderator {
+                actions.push((t("moderate.kick"), None));
+            }
+            if my_role == UserRole::Admin {
+                actions.extend(
+                    BAN_DURATIONS
+                        .iter()
+                        .map(|(minutes, key)| (t(key), Some(*minutes))),
+                );
+            }
+        }
+        let close = ctx.link().callback(|e: MouseEvent| {
+            e.prevent_default();
+            Msg::ShowUserMenu(None)
+        });
+        html! {
+            <div class="fixed inset-0 z-30" onclick={close.clone()} oncontextmenu={close}>
+                <div
+                    class="fixed py-1 min-w-[10rem] rounded-md shadow-lg bg-white dark:bg-gray-800 dark:text-gray-100 border border-gray-200 dark:border-gray-700"
+                    style={format!("left: {}px; top: {}px", menu.x, menu.y)}
+                >
+                    <div class="px-3 py-1 text-xs text-gray-500 dark:text-gray-400 truncate">{&menu.name}</div>
+                    <button
+                        type="button"
+                        onclick={ctx.link().callback(move |_| Msg::Block(name.clone(), !blocked))}
+                        class={classes!("w-full", "px-3", "py-1", "text-left", "text-sm", "hover:bg-gray-100", "dark:hover:bg-gray-700", (!blocked).then_some("text-red-600"))}
+                    >
+                        {if blocked { t("block.unblock_user") } else { t("block.block_user") }}
+                    </button>
+                    {
+                        if actions.is_empty() {
+                            html! {}
+                        } else {
+                            html! {
+                                <div class="mt-1 pt-1 border-t border-gray-200 dark:border-gray-700">
+                                    {
+                                        actions.into_iter().map(|(label, duration_minutes)| {
+                                            let action = ModerationData {
+                                                target: menu.name.clone(),
+                                                duration_minutes,
+                                            };
+                                            html! {
+                                                <button
+                                                    type="button"
+                                                    onclick={ctx.link().callback(move |_| Msg::Moderate(action.clone()))}
+                                                    class="w-full px-3 py-1 text-left text-sm text-red-600 hover:bg-gray-100 dark:hover:bg-gray-700"
+                                                >
+                                                    {label}
+                                                </button>
+                                            }
+                                        }).collect::<Html>()
+                                    }
+                                </div>
+                            }
+                        }
+                    }
+                </div>
+            </div>
+        }
+    }
 
-                <div class="grow h-screen flex flex-col bg-white">
-                    <div class="w-full h-14 border-b-2 border-blue-200">
-                        <div class="text-xl p-3 font-semibold text-blue-700">{"💬 Chat Room"}</div>
-                    </div>
+    /// The card of the user clicked in the sidebar, if one is open.
+    #[allow(clippy::unnecessary_operation)]
+    fn view_user_card(&self, ctx: &Context<Self>) -> Html {
+        let card = match &self.user_card {
+            Some(card) => card,
+            None => return html! {},
+        };
+        let user = match self.users.iter().find(|u| u.name == card.name) {
+            Some(user) => user,
+            None => return html! {},
+        };
+        let (dm, mention, block) = (card.name.clone(), card.name.clone(), card.name.clone());
+        html! {
+            <UserPopover
+                name={user.name.clone()}
+                avatar={user.avatar.clone()}
+                online_since={user.online_since}
+                blocked={self.blocked.contains(&user.name)}
+                x={card.x}
+                y={card.y}
+                on_message={ctx.link().batch_callback(move |_| vec![Msg::ShowUserCard(None), Msg::SelectThread(Some(dm.clone()))])}
+                on_mention={ctx.link().batch_callback(move |_| vec![Msg::ShowUserCard(None), Msg::InsertText(format!("@{} ", mention))])}
+                on_block={ctx.link().callback(move |blocked| Msg::Block(block.clone(), blocked))}
+                on_close={ctx.link().callback(|_| Msg::ShowUserCard(None))}
+            />
+        }
+    }
 
-                    <div class="w-full grow overflow-auto px-6 py-4 space-y-4">
-                        {
-                            self.messages.iter().map(|m| {
-                                let is_self = m.from == current_user;
+    /// Copies the invite to the private room I'm in, if I have it.
+    fn view_invite_button(&self, ctx: &Context<Self>) -> Html {
+        let invite = match self.invites.get(&self.room) {
+            Some(invite) => invite.clone(),
+            None => return html! {},
+        };
+        let key = format!("invite:{}", self.room);
+        let copied = self
+            .copied
+            .as_ref()
+            .is_some_and(|(copied, ok)| *copied == key && *ok);
+        let onclick = ctx.link().callback(move |e: MouseEvent| {
+            // Not a click on the tab itself.
+            e.stop_propagation();
+            Msg::CopyMessage {
+                key: key.clone(),
+                text: invite.clone(),
+            }
+        });
+        html! {
+            <button
+                {onclick}
+                class="ml-1 px-1 rounded text-xs hover:bg-gray-100 dark:hover:bg-gray-700"
+                title={t("room.copy_invite")}
+            >
+                {if copied { "✓" } else { "🔗" }}
+            </button>
+        }
+    }
 
-                                let bubble_class = if is_self {
-                                    "ml-auto bg-blue-200 text-right rounded-tl-lg rounded-bl-lg rounded-br-lg"
-                                } else {
-                                    "mr-auto bg-gray-100 text-left rounded-tr-lg rounded-bl-lg rounded-br-lg"
-                                };
+    /// The role `name` has, as far as the server told.
+    fn role_of(&self, name: &str) -> UserRole {
+        self.users
+            .iter()
+            .find(|u| u.name == name)
+            .map(|u| u.role)
+            .unwrap_or_default()
+    }
 
-                                html! {
-                                    <div class={format!("flex items-end max-w-[60%] p-2 {}", bubble_class)}>
-                                        {
-                                            if !is_self {
-                                                if let Some(u) = self.users.iter().find(|u| u.name == m.from) {
+    /// Who I can forward a message to: the room's members and anyone I
+    /// have a DM thread with, except me and whoever I blocked.
+    fn contacts(&self, current_user: &str) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .users
+            .iter()
+            .map(|u| &u.name)
+            .chain(self.dm_threads.keys())
+            .filter(|name| *name != current_user && !self.blocked.contains(*name))
+            .cloned()
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// What I blocked, sorted for the settings.
+    fn blocked_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.blocked.iter().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// The incoming call banner, or the call itself once it's under way.
+    #[allow(clippy::unnecessary_operation)]
+    fn view_call(&self, ctx: &Context<Self>) -> Html {
+        let call = match &self.call {
+            Some(call) => call,
+            None => return html! {},
+        };
+        let hang_up = ctx.link().callback(|_| Msg::EndCall);
+        if let CallState::Ringing(_) = call.state {
+            return html! {
+                <div class="fixed top-4 left-1/2 -translate-x-1/2 z-30 flex items-center gap-3 px-4 py-3 rounded-lg shadow-xl bg-white dark:bg-gray-800 dark:text-gray-100">
+                    <Avatar class="w-10 h-10" src={self.avatar_of(&call.peer)} alt="avatar"/>
+                    <span class="font-semibold">{t_args("call.incoming", &[&call.peer])}</span>
+                    <button
+                        onclick={ctx.link().callback(|_| Msg::AcceptCall)}
+                        class="px-3 py-1 rounded-md bg-green-600 hover:bg-green-700 text-white"
+                    >
+                        {t("call.accept")}
+                    </button>
+                    <button onclick={hang_up} class="px-3 py-1 rounded-md bg-red-600 hover:bg-red-700 text-white">
+                        {t("call.decline")}
+                    </button>
+                </div>
+            };
+        }
+        let status = match call.state {
+            CallState::Calling => t_args("call.calling", &[&call.peer]),
+            CallState::Active => t_args("call.active", &[&call.peer]),
+            _ => t("call.connecting").to_string(),
+        };
+        html! {
+            <div class="fixed top-4 left-1/2 -translate-x-1/2 z-30 w-96 rounded-lg shadow-xl bg-gray-900 text-white overflow-hidden">
+                <div class="relative bg-black aspect-video">
+                    <video ref={self.remote_video.clone()} class="w-full h-full object-cover"></video>
+                    <video
+                        ref={self.local_video.clone()}
+                        class="absolute bottom-2 right-2 w-24 rounded border border-gray-700 object-cover"
+                    ></video>
+                </div>
+                <div class="flex items-center justify-between px-4 py-2">
+                    <span class="text-sm">{status}</span>
+                    <button onclick={hang_up} class="px-3 py-1 rounded-md bg-red-600 hover:bg-red-700 text-white">
+                        {t("call.hang_up")}
+                    </button>
+                </div>
+            </div>
+        }
+    }
+
+    /// The strip under the header showing the latest pin, which unfolds
+    /// into all of them. Clicking a pin scrolls to the message. Pins are
+    /// full copies, so they show even when the message isn't loaded.
+    fn view_pins(&self, ctx: &Context<Self>) -> Html {
+        let latest = match self.pinned.last() {
+            Some(latest) if self.active_dm.is_none() => latest,
+            _ => return html! {},
+        };
+        let open = self.pins_open;
+        let may_pin = self.role_of(&current_username(ctx)) >= UserRole::Moderator;
+        html! {
+            <div class="w-full border-b border-blue-100 bg-blue-50 text-xs dark:border-gray-700 dark:bg-gray-800">
+                <button
+                    onclick={ctx.link().callback(move |_| Msg::ShowPins(!open))}
+                    class="w-full flex items-center gap-2 px-6 py-1 text-left hover:bg-blue-100 dark:hover:bg-gray-700"
+                    title={t("pins.title")}
+                >
+                    <span class="shrink-0 font-semibold">{format!("📌 {}", t_args("pins.count", &[&self.pinned.len()]))}</span>
+                    {
+                        if open {
+                            html! { <span class="grow"></span> }
+                        } else {
+                            html! {
+                                <span class="grow truncate text-gray-600 dark:text-gray-300">
+                                    {format!("{}: {}", latest.from, pin_excerpt(latest))}
+                                </span>
+                            }
+                        }
+                    }
+                    <span class="shrink-0 text-gray-400">{if open { "▴" } else { "▾" }}</span>
+                </button>
+                {
+                    if open {
+                        html! {
+                            <ul class="max-h-60 overflow-y-auto">
+                                {
+                                    self.pinned.iter().rev().filter_map(|pin| Some((pin, pin.id?))).map(|(pin, id)| html! {
+                                        <li class="flex items-start gap-2 px-6 py-2 border-t border-blue-100 dark:border-gray-700">
+                                            <button
+                                                class="grow text-left hover:text-blue-600"
+                                                title={t("pins.show")}
+                                                onclick={ctx.link().callback(move |_| Msg::JumpTo(id))}
+                                            >
+                                                <div class="font-semibold">{pin.from.clone()}</div>
+                                                <div class="line-clamp-2 break-words text-gray-600 dark:text-gray-300">{pin_excerpt(pin)}</div>
+                                            </button>
+                                            {
+                                                if may_pin {
                                                     html! {
-                                                        <img class="w-8 h-8 rounded-full mr-2" src={u.avatar.clone()} alt="avatar"/>
+                                                        <button
+                                                            class="px-1 text-gray-400 hover:text-red-600"
+                                                            title={t("message.unpin")}
+                                                            onclick={ctx.link().callback(move |_| Msg::SetPinned { id, pinned: false })}
+                                                        >
+                                                            {"✕"}
+                                                        </button>
                                                     }
                                                 } else {
                                                     html! {}
                                                 }
-                                            } else {
-                                                html! {}
                                             }
-                                        }
-                                        <div class="text-sm">
-                                            <div class="font-semibold text-blue-800">{m.from.clone()}</div>
-                                            <div class="text-xs text-gray-700 mt-1">
-                                                {
-                                                    if m.message.ends_with(".gif") {
-                                                        html! {
-                                                            <img class="mt-2 max-w-full rounded-md" src={m.message.clone()} />
-                                                        }
-                                                    } else {
-                                                        html! {
-                                                            { m.message.clone() }
-                                                        }
-                                                    }
-                                                }
-                                            </div>
-                                        </div>
-                                    </div>
+                                        </li>
+                                    }).collect::<Html>()
                                 }
-                            }).collect::<Html>()
+                            </ul>
                         }
-                    </div>
+                    } else {
+                        html! {}
+                    }
+                }
+            </div>
+        }
+    }
 
-                    <div class="w-full h-16 flex px-4 py-2 items-center border-t-2 border-blue-100 bg-gray-50">
-                        <input
-                            ref={self.chat_input.clone()}
-                            type="text"
-                            placeholder="Type a message..."
-                            class="flex-grow py-2 px-4 bg-white border border-gray-300 rounded-full outline-none focus:ring-2 focus:ring-blue-300"
-                            required=true
+    /// A shared file: a progress bar while it arrives, then a download
+    /// link, the picture itself for images, or a player for audio.
+    fn view_file(&self, file_id: &str, name: &str) -> Html {
+        match self.transfers.get(file_id) {
+            Some(FileTransfer {
+                url: Some(url),
+                mime,
+                ..
+            }) if mime.starts_with("image/") => html! {
+                <a href={url.clone()} download={name.to_string()} title={t_args("file.download", &[&name])}>
+                    <img class="mt-2 max-w-full rounded-md" src={url.clone()} alt={name.to_string()} />
+                </a>
+            },
+            Some(FileTransfer {
+                url: Some(url),
+                mime,
+                ..
+            }) if mime.starts_with("audio/") => html! {
+                <div class="mt-1 flex items-center gap-2">
+                    <span title={name.to_string()}>{"🎤"}</span>
+                    <audio controls=true preload="metadata" src={url.clone()} class="h-8 w-56"></audio>
+                </div>
+            },
+            Some(FileTransfer { url: Some(url), .. }) => html! {
+                <a
+                    href={url.clone()}
+                    download={name.to_string()}
+                    class="mt-1 inline-flex items-center gap-1 px-2 py-1 rounded bg-white text-blue-700 hover:underline dark:bg-gray-700 dark:text-blue-300"
+                >
+                    {"📄 "}{name}
+                </a>
+            },
+            Some(transfer) => html! {
+                <div class="mt-1 w-48">
+                    <div class="truncate">{"📄 "}{name}</div>
+                    <div class="h-1.5 mt-1 rounded bg-gray-300 dark:bg-gray-600 overflow-hidden">
+                        <div
+                            class="h-full bg-blue-600 transition-all"
+                            style={format!("width: {}%", transfer.received * 100 / transfer.total)}
                         />
-                        <button onclick={submit} class="ml-3 p-3 bg-blue-600 hover:bg-blue-700 text-white rounded-full">
-                            <svg viewBox="0 0 24 24" xmlns="http://www.w3.org/2000/svg" class="w-5 h-5 fill-current">
-                                <path d="M0 0h24v24H0z" fill="none"></path>
-                                <path d="M2.01 21L23 12 2.01 3 2 10l15 2-15 2z"></path>
-                            </svg>
-                        </button>
                     </div>
                 </div>
+            },
+            None => html! {
+                <div class="mt-1 italic text-gray-500 dark:text-gray-400">
+                    {format!("📄 {} {}", name, t("file.unavailable"))}
+                </div>
+            },
+        }
+    }
+
+    /// Dropdown for picking my own status, shown under my name.
+    fn view_status_picker(&self, ctx: &Context<Self>) -> Html {
+        const CHOICES: [PresenceStatus; 3] = [
+            PresenceStatus::Online,
+            PresenceStatus::Away,
+            PresenceStatus::Busy,
+        ];
+        let onchange = ctx.link().batch_callback(|e: Event| {
+            let index = e
+                .target_unchecked_into::<HtmlSelectElement>()
+                .selected_index();
+            CHOICES.get(index as usize).copied().map(Msg::SetStatus)
+        });
+        html! {
+            <select
+                {onchange}
+                // Picking a status shouldn't open the profile editor.
+                onclick={Callback::from(|e: MouseEvent| e.stop_propagation())}
+                title={t("status.set")}
+                class="text-xs bg-transparent text-gray-500 dark:text-gray-300 outline-none cursor-pointer"
+            >
+                {
+                    CHOICES.into_iter().map(|status| {
+                        html! {
+                            <option selected={status == self.chosen_status}>
+                                {presence_label(status)}
+                            </option>
+                        }
+                    }).collect::<Html>()
+                }
+            </select>
+        }
+    }
+
+    /// "12 online" in the header, with a "+1" or "−1" rising off it for a
+    /// while when people join or leave.
+    fn view_online_count(&self) -> Html {
+        html! {
+            <span class="relative mr-1 px-2 rounded-full text-xs whitespace-nowrap bg-green-100 text-green-800 dark:bg-green-900/40 dark:text-green-300">
+                {t_args("header.online", &[&self.users.len()])}
+                {
+                    match &self.member_delta {
+                        Some((delta, _)) => html! {
+                            // Keyed by the number, so the animation starts over when it changes.
+                            <span
+                                key={delta.to_string()}
+                                class={classes!("absolute", "-top-3", "right-0", "text-[10px]", "font-semibold", "animate-delta", if *delta > 0 { "text-green-600" } else { "text-red-600" })}
+                            >
+                                { if *delta > 0 { format!("+{}", delta) } else { format!("−{}", -delta) } }
+                            </span>
+                        },
+                        None => html! {},
+                    }
+                }
+            </span>
+        }
+    }
+
+    /// The ⬇ button in the header and, while it's open, the formats the
+    /// thread on screen can be downloaded in.
+    fn view_export_menu(&self, ctx: &Context<Self>) -> Html {
+        let open = self.export_open;
+        html! {
+            <div class="relative">
+                <button
+                    onclick={ctx.link().callback(move |_| Msg::ShowExport(!open))}
+                    class={classes!("p-1", "rounded", "text-sm", "hover:bg-gray-100", "dark:hover:bg-gray-700", open.then_some("bg-gray-100 dark:bg-gray-700"))}
+                    title={t("header.export")}
+                >
+                    {"⬇"}
+                </button>
+                {
+                    if open {
+                        html! {
+                            <>
+                                <div class="fixed inset-0 z-20" onclick={ctx.link().callback(|_| Msg::ShowExport(false))} />
+                                <div class="absolute right-0 top-full mt-1 z-30 w-44 py-1 rounded-md shadow-lg bg-white dark:bg-gray-800 border border-gray-200 dark:border-gray-700">
+                                    {
+                                        ExportFormat::ALL.into_iter().map(|format| {
+                                            html! {
+                                                <button
+                                                    type="button"
+                                                    onclick={ctx.link().callback(move |_| Msg::Export(format))}
+                                                    class="block w-full px-3 py-1 text-left text-sm hover:bg-gray-100 dark:hover:bg-gray-700"
+                                                >
+                                                    {t(format.label_key())}
+                                                </button>
+                                            }
+                                        }).collect::<Html>()
+                                    }
+                                </div>
+                            </>
+                        }
+                    } else {
+                        html! {}
+                    }
+                }
+            </div>
+        }
+    }
+
+    fn view_language_picker(&self, ctx: &Context<Self>) -> Html {
+        let onchange = ctx.link().batch_callback(|e: Event| {
+            let index = e
+                .target_unchecked_into::<HtmlSelectElement>()
+                .selected_index();
+            Language::ALL
+                .get(index as usize)
+                .copied()
+                .map(Msg::SetLanguage)
+        });
+        html! {
+            <select
+                {onchange}
+                title={t("header.language")}
+                class="ml-1 text-xs bg-transparent text-gray-500 dark:text-gray-300 outline-none cursor-pointer"
+            >
+                {
+                    Language::ALL.into_iter().map(|language| {
+                        html! {
+                            <option selected={language == self.language}>{language.name()}</option>
+                        }
+                    }).collect::<Html>()
+                }
+            </select>
+        }
+    }
+
+    /// Preview cards for the first `MAX_PREVIEWS` links in `m`. Links whose
+    /// preview failed only show as links in the text.
+    fn view_previews(&self, m: &MessageData) -> Html {
+        preview_links(m)
+            .into_iter()
+            .map(|url| match self.previews.get(url) {
+                Some(PreviewState::Loading) => html! {
+                    <div class="mt-1 w-72 p-2 rounded-lg border border-gray-200 text-xs text-gray-400 animate-pulse dark:border-gray-700">
+                        {t("message.loading_preview")}
+                    </div>
+                },
+                Some(PreviewState::Loaded(preview)) => html! {
+                    <a
+                        href={url.to_string()}
+                        target="_blank"
+                        rel="noopener noreferrer"
+                        class="mt-1 flex w-72 overflow-hidden rounded-lg border border-gray-200 bg-white hover:bg-gray-50 dark:border-gray-700 dark:bg-gray-800 dark:hover:bg-gray-700"
+                    >
+                        {
+                            // Held to the same rules as images in messages.
+                            match preview.thumbnail.as_deref().and_then(safe_image_url) {
+                                Some(src) => html! {
+                                    <img
+                                        class="w-20 h-20 flex-none object-cover"
+                                        {src}
+                                        alt=""
+                                        referrerpolicy="no-referrer"
+                                        loading="lazy"
+                                    />
+                                },
+                                None => html! {},
+                            }
+                        }
+                        <div class="p-2 min-w-0 text-xs">
+                            {
+                                match &preview.provider_name {
+                                    Some(provider) => html! { <div class="text-gray-400">{provider.clone()}</div> },
+                                    None => html! {},
+                                }
+                            }
+                            <div class="font-semibold truncate text-gray-800 dark:text-gray-100">
+                                {preview.title.clone().unwrap_or_default()}
+                            </div>
+                            {
+                                match &preview.description {
+                                    Some(description) => html! {
+                                        <div class="line-clamp-2 text-gray-600 dark:text-gray-300">{description.clone()}</div>
+                                    },
+                                    None => html! {},
+                                }
+                            }
+                        </div>
+                    </a>
+                },
+                _ => html! {},
+            })
+            .collect()
+    }
+
+    /// Starts fetching previews for links on screen that haven't been
+    /// looked up yet. Links in direct messages stay between the two
+    /// people, so those are never looked up.
+    fn fetch_previews(&mut self, ctx: &Context<Self>) {
+        if self.active_dm.is_some() || !preview::enabled() {
+            return;
+        }
+        let mut wanted = vec![];
+        for m in self.current_messages() {
+            for url in preview_links(m) {
+                if !self.previews.contains_key(url) && !wanted.iter().any(|w| w == url) {
+                    wanted.push(url.to_string());
+                }
+            }
+        }
+        for url in wanted {
+            self.previews.insert(url.clone(), PreviewState::Loading);
+            let link = ctx.link().clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                let preview = match preview::fetch(&url).await {
+                    Ok(preview) => Some(preview),
+                    Err(e) => {
+                        log::debug!("no preview for {}: {}", url, e);
+                        None
+                    }
+                };
+                link.send_message(Msg::PreviewLoaded { url, preview });
+            });
+        }
+    }
+
+    /// A one-line excerpt of room message `id`, for quoting it.
+    fn quoted_text(&self, id: u64) -> String {
+        match self.messages.iter().find(|m| m.id == Some(id)) {
+            Some(message) if message.deleted => t("message.deleted").to_string(),
+            Some(message) if message.hidden => t("message.hidden").to_string(),
+            Some(message) => excerpt(&message.message),
+            None => String::new(),
+        }
+    }
+
+    /// Button copying the text of `m`, with how that went for a moment
+    /// after clicking it.
+    fn view_copy_button(&self, ctx: &Context<Self>, m: &MessageData) -> Html {
+        let key = m.dedup_key();
+        let label = match &self.copied {
+            Some((copied, true)) if *copied == key => t("message.copied"),
+            Some((copied, false)) if *copied == key => t("message.copy_failed"),
+            _ => t("message.copy"),
+        };
+        let text = m.message.clone();
+        html! {
+            <button
+                class="px-1 text-xs text-gray-500 hover:text-blue-600"
+                title={t("message.copy_hint")}
+                onclick={ctx.link().callback(move |_| Msg::CopyMessage { key: key.clone(), text: text.clone() })}
+            >
+                {format!("⧉ {}", label)}
+            </button>
+        }
+    }
+
+    /// Pill badges for the reactions on `m`, plus the quick-reaction bar
+    /// revealed on hover. Only messages with a server id can be reacted to;
+    /// others just get the copy button on hover.
+    fn view_reactions(&self, ctx: &Context<Self>, m: &MessageData, current_user: &str) -> Html {
+        let id = match m.id {
+            Some(id) if self.active_dm.is_none() => id,
+            _ => {
+                return html! {
+                    <div class="hidden group-hover:flex w-fit mt-1 px-1 rounded-full bg-white dark:bg-gray-700 shadow">
+                        { self.view_copy_button(ctx, m) }
+                        { view_forward_button(ctx, m) }
+                    </div>
+                }
+            }
+        };
+        let from = m.from.clone();
+        let my_role = self.role_of(current_user);
+        // The server holds everyone to the same rules.
+        let may_pin = my_role >= UserRole::Moderator;
+        let may_delete = m.from == current_user || my_role == UserRole::Admin;
+        let pinned = self.pinned.iter().any(|p| p.id == Some(id));
+        let confirming = self.confirm_delete == Some(id);
+        let starred = self.favorites.iter().any(|f| f.is(&self.room, Some(id)));
+        let favorite = Favorite {
+            room: self.room.clone(),
+            message: m.clone(),
+        };
+        let reporting = self
+            .reporting
+            .and_then(|(reporting, picked)| (reporting == id).then_some(picked));
+        let react = |emoji: &str| {
+            let emoji = emoji.to_string();
+            ctx.link().callback(move |_| Msg::React {
+                id,
+                emoji: emoji.clone(),
+            })
+        };
+
+        let mut reactions: Vec<(&String, &Vec<String>)> = m
+            .reactions
+            .iter()
+            .filter(|(_, users)| !users.is_empty())
+            .collect();
+        reactions.sort_by_key(|(emoji, _)| (reaction_order(emoji), emoji.as_str()));
+
+        html! {
+            <div class="flex flex-wrap items-center gap-1 mt-1">
+                {
+                    reactions.into_iter().map(|(emoji, users)| {
+                        let class = if users.iter().any(|u| u == current_user) {
+                            "px-2 rounded-full text-xs border border-blue-400 bg-blue-50"
+                        } else {
+                            "px-2 rounded-full text-xs border border-gray-200 bg-white hover:bg-gray-50 dark:border-gray-600 dark:bg-gray-800 dark:hover:bg-gray-700"
+                        };
+                        html! {
+                            <button {class} title={users.join(", ")} onclick={react(emoji)}>
+                                {format!("{} {}", emoji, users.len())}
+                            </button>
+                        }
+                    }).collect::<Html>()
+                }
+                <div class={classes!(
+                    if confirming || reporting.is_some() { "flex" } else { "hidden group-hover:flex" },
+                    "gap-1", "px-1", "rounded-full", "bg-white", "dark:bg-gray-700", "shadow"
+                )}>
+                    {
+                        QUICK_REACTIONS.iter().map(|emoji| html! {
+                            <button class="hover:scale-125 transition-transform" onclick={react(emoji)}>
+                                {*emoji}
+                            </button>
+                        }).collect::<Html>()
+                    }
+                    {
+                        if m.from == current_user && classify_message(&m.message) != MessageContent::Image {
+                            html! {
+                                <button
+                                    class="px-1 text-xs text-gray-500 hover:text-blue-600"
+                                    title={t("message.edit_hint")}
+                                    onclick={ctx.link().callback(move |_| Msg::EditMessage(Some(id)))}
+                                >
+                                    {format!("✏️ {}", t("message.edit"))}
+                                </button>
+                            }
+                        } else {
+                            html! {}
+                        }
+                    }
+                    {
+                        if may_delete {
+                            html! {
+                                <>
+                                    {
+                                        if confirming {
+                                            html! {
+                                                <span class="flex items-center gap-1 px-1 text-xs">
+                                                    <span class="text-gray-600 dark:text-gray-300">{t("message.delete_confirm")}</span>
+                                                    <button
+                                                        class="px-1 rounded text-white bg-red-600 hover:bg-red-700"
+                                                        onclick={ctx.link().callback(move |_| Msg::DeleteMessage(id))}
+                                                    >
+                                                        {t("message.delete")}
+                                                    </button>
+                                                    <button
+                                                        class="px-1 text-gray-500 hover:text-blue-600"
+                                                        onclick={ctx.link().callback(|_| Msg::ConfirmDelete(None))}
+                                                    >
+                                                        {t("common.cancel")}
+                                                    </button>
+                                                </span>
+                                            }
+                                        } else {
+                                            html! {
+                                                <button
+                                                    class="px-1 text-xs text-gray-500 hover:text-red-600"
+                                                    title={t("message.delete")}
+                                                    onclick={ctx.link().callback(move |_| Msg::ConfirmDelete(Some(id)))}
+                                                >
+                                                    {"🗑"}
+                                                </button>
+                                            }
+                                        }
+                                    }
+                                </>
+                            }
+                        } else {
+                            html! {}
+                        }
+                    }
+                    <button
+                        class={classes!("px-1", "text-xs", if starred { "text-yellow-500" } else { "text-gray-500 hover:text-yellow-500" })}
+                        title={t(if starred { "message.unfavorite" } else { "message.favorite" })}
+                        onclick={ctx.link().callback(move |_| Msg::ToggleFavorite(favorite.clone()))}
+                    >
+                        {if starred { "★" } else { "☆" }}
+                    </button>
+                    {
+                        if may_pin {
+                            html! {
+                                <button
+                                    class="px-1 text-xs text-gray-500 hover:text-blue-600"
+                                    title={t(if pinned { "message.unpin" } else { "message.pin" })}
+                                    onclick={ctx.link().callback(move |_| Msg::SetPinned { id, pinned: !pinned })}
+                                >
+                                    {format!("📌 {}", t(if pinned { "message.unpin" } else { "message.pin" }))}
+                                </button>
+                            }
+                        } else {
+                            html! {}
+                        }
+                    }
+                    <button
+                        class="px-1 text-xs text-gray-500 hover:text-blue-600"
+                        title={t("message.reply")}
+                        onclick={ctx.link().callback(move |_| Msg::ReplyTo(Some((id, from.clone()))))}
+                    >
+                        {format!("↩ {}", t("message.reply"))}
+                    </button>
+                    { self.view_copy_button(ctx, m) }
+                    { view_forward_button(ctx, m) }
+                    {
+                        if m.from == current_user {
+                            html! {}
+                        } else if self.reported.contains(&id) {
+                            html! {
+                                <span class="px-1 text-xs text-gray-400">{format!("⚑ {}", t("message.reported"))}</span>
+                            }
+                        } else {
+                            html! {
+                                <span class="relative">
+                                    <button
+                                        class="px-1 text-xs text-gray-500 hover:text-red-600"
+                                        title={t("message.report")}
+                                        onclick={ctx.link().callback(move |_| Msg::Report(reporting.is_none().then_some(id)))}
+                                    >
+                                        {format!("⚑ {}", t("message.report"))}
+                                    </button>
+                                    {
+                                        match reporting {
+                                            Some(picked) => view_report_popover(ctx, id, picked),
+                                            None => html! {},
+                                        }
+                                    }
+                                </span>
+                            }
+                        }
+                    }
+                </div>
+            </div>
+        }
+    }
+}
+
+fn presence_dot_class(status: PresenceStatus) -> &'static str {
+    match status {
+        PresenceStatus::Online => "bg-green-500",
+        PresenceStatus::Away => "bg-yellow-400",
+        PresenceStatus::Busy => "bg-red-500",
+        PresenceStatus::Offline => "bg-gray-400",
+    }
+}
+
+fn presence_label(status: PresenceStatus) -> &'static str {
+    match status {
+        PresenceStatus::Online => t("status.online"),
+        PresenceStatus::Away => t("status.away"),
+        PresenceStatus::Busy => t("status.busy"),
+        PresenceStatus::Offline => t("status.offline"),
+    }
+}
+
+fn connection_dot_class(connection: &ReconnectState) -> &'static str {
+    match connection {
+        ReconnectState::Connected => "bg-green-500",
+        ReconnectState::Connecting | ReconnectState::Reconnecting { .. } => "bg-yellow-400",
+        ReconnectState::Failed(_) => "bg-red-500",
+    }
+}
+
+fn connection_label(connection: &ReconnectState) -> &'static str {
+    match connection {
+        ReconnectState::Connected => t("connection.connected"),
+        ReconnectState::Connecting => t("connection.connecting"),
+        ReconnectState::Reconnecting { .. } => t("connection.reconnecting"),
+        ReconnectState::Failed(_) => t("connection.disconnected"),
+    }
+}
+
+/// Sort key putting the quick reactions first, in their usual order.
+fn reaction_order(emoji: &str) -> usize {
+    QUICK_REACTIONS
+        .iter()
+        .position(|quick| *quick == emoji)
+        .unwrap_or(QUICK_REACTIONS.len())
+}
+
+/// "Alice is typing…", "Alice and Bob are typing…" or "3 people are
+/// typing…".
+fn typing_notice(typists: &HashMap<String, f64>) -> Option<String> {
+    let mut names: Vec<&String> = typists.keys().collect();
+    names.sort();
+    match names.as_slice() {
+        [] => None,
+        [one] => Some(t_args("typing.one", &[one])),
+        [first, second] => Some(t_args("typing.two", &[first, second])),
+        many => Some(t_args("typing.many", &[&many.len()])),
+    }
+}
+
+/// Maps the keyboard shortcuts to what they do: Enter sends (Shift+Enter
+/// starts a new line instead, and Enter with Ctrl, Alt or Cmd is left
+/// alone for now), Escape closes the profile editor and
+/// Ctrl/Cmd+K opens the search box.
+fn shortcut(e: &KeyboardEvent) -> Option<Msg> {
+    match e.key().as_str() {
+        "Enter"
+            if !e.shift_key()
+                && !e.ctrl_key()
+                && !e.alt_key()
+                && !e.meta_key()
+                && !e.is_composing() =>
+        {
+            e.target()
+                .and_then(|target| target.dyn_into::<HtmlTextAreaElement>().ok())
+                .map(|_| Msg::SubmitMessage)
+        }
+        "Escape" => Some(Msg::CloseOverlays),
+        "k" | "K" if e.ctrl_key() || e.meta_key() => Some(Msg::FocusSearch),
+        _ => None,
+    }
+}
+
+/// The dot marking a DM with messages I haven't opened yet.
+fn unread_dot(unread: bool) -> Html {
+    if unread {
+        html! {
+            <span class="inline-block w-2 h-2 ml-2 mb-0.5 rounded-full bg-blue-600" title={t("dm.unread")} />
+        }
+    } else {
+        html! {}
+    }
+}
+
+/// Tells whether direct messages in a thread are end-to-end encrypted.
+fn lock_badge(encrypted: bool) -> Html {
+    let (icon, title) = if encrypted {
+        ("🔒", t("dm.encrypted"))
+    } else {
+        ("🔓", t("dm.unencrypted"))
+    };
+    html! {
+        <span class="ml-1 text-sm" title={title}>{icon}</span>
+    }
+}
+
+/// The difference between the previous member list and the new one,
+/// leaving me out: everyone joined in the order listed, everyone left by
+/// name.
+fn membership_change<'a>(
+    before: &'a HashMap<String, UserProfile>,
+    after: &'a [String],
+    current_user: &str,
+) -> MembershipChange<'a> {
+    let joined = after
+        .iter()
+        .filter(|u| !before.contains_key(*u) && *u != current_user)
+        .collect();
+    // Sorted, as the map hands them out in no particular order.
+    let mut left: Vec<&String> = before.keys().filter(|u| !after.contains(u)).collect();
+    left.sort();
+    MembershipChange { joined, left }
+}
+
+/// The users whose name contains `filter`, ignoring case; everyone for a
+/// blank one.
+fn filter_users<'a>(users: &'a [UserProfile], filter: &str) -> Vec<&'a UserProfile> {
+    let filter = filter.trim().to_lowercase();
+    users
+        .iter()
+        .filter(|u| u.name.to_lowercase().contains(&filter))
+        .collect()
+}
+
+/// Whether the window is narrower than `MOBILE_WIDTH_PX`.
+fn narrow_window() -> bool {
+    web_sys::window()
+        .and_then(|w| w.inner_width().ok())
+        .and_then(|width| width.as_f64())
+        .is_some_and(|width| width < MOBILE_WIDTH_PX)
+}
+
+fn tab_class(active: bool) -> &'static str {
+    if active {
+        "px-3 py-2 text-lg font-semibold text-blue-700 dark:text-blue-300 border-b-2 border-blue-600 cursor-pointer"
+    } else {
+        "px-3 py-2 text-lg text-gray-500 hover:text-blue-700 dark:text-gray-400 dark:hover:text-blue-300 cursor-pointer"
+    }
+}
+
+/// Reads `file` in the background and hands it to `on_load` as a
+/// base64 `data:` URL.
+fn read_as_data_url(file: &Blob, on_load: Callback<String>) -> Result<(), JsValue> {
+    let reader = web_sys::FileReader::new()?;
+    let loaded = reader.clone();
+    let onload = Closure::once_into_js(move || {
+        if let Some(url) = loaded.result().ok().and_then(|r| r.as_string()) {
+            on_load.emit(url);
+        }
+    });
+    reader.set_onload(Some(onload.unchecked_ref()));
+    reader.read_as_data_url(file)
+}
+
+/// The links in `m` that get a preview card. Messages that are shown as
+/// an image don't get any.
+fn preview_links(m: &MessageData) -> Vec<&str> {
+    if m.kind == MessageKind::System
+        || m.deleted
+        || m.file.is_some()
+        || classify_message(&m.message) == MessageContent::Image
+    {
+        return vec![];
+    }
+    split_into_segments(&m.message)
+        .into_iter()
+        .filter_map(|segment| match segment {
+            Segment::Link(url) => Some(url),
+            Segment::Text(_) => None,
+        })
+        .take(MAX_PREVIEWS)
+        .collect()
+}
+
+/// The first line of `message`, cut to `QUOTE_CHARS`, or a stand-in for
+/// an image.
+fn excerpt(message: &str) -> String {
+    if classify_message(message) == MessageContent::Image {
+        return "🖼 Image".to_string();
+    }
+    let line = message.lines().next().unwrap_or_default();
+    match line.char_indices().nth(QUOTE_CHARS) {
+        Some((end, _)) => format!("{}…", &line[..end]),
+        None if line.len() < message.trim_end().len() => format!("{}…", line),
+        None => line.to_string(),
+    }
+}
+
+/// What the pins show of `pin`, which can't be much if it was hidden.
+fn pin_excerpt(pin: &MessageData) -> String {
+    if pin.hidden {
+        return t("message.hidden").to_string();
+    }
+    excerpt(&pin.message)
+}
+
+/// The most characters a message may have: `maxMessageLength` in the
+/// global `YEWCHAT_CONFIG`, or `DEFAULT_MAX_MESSAGE_CHARS`.
+fn max_message_chars() -> usize {
+    let config = match js_sys::Reflect::get(&js_sys::global(), &"YEWCHAT_CONFIG".into()) {
+        Ok(config) if config.is_object() => config,
+        _ => return DEFAULT_MAX_MESSAGE_CHARS,
+    };
+    js_sys::Reflect::get(&config, &"maxMessageLength".into())
+        .ok()
+        .and_then(|max| max.as_f64())
+        .filter(|max| *max >= 1.0)
+        .map_or(DEFAULT_MAX_MESSAGE_CHARS, |max| max as usize)
+}
+
+/// Byte offset in `text` of the UTF-16 `offset` the DOM reports carets in.
+fn byte_offset(text: &str, offset: u32) -> usize {
+    let mut units = 0;
+    for (i, c) in text.char_indices() {
+        if units >= offset as usize {
+            return i;
+        }
+        units += c.len_utf16();
+    }
+    text.len()
+}
+
+fn page_hidden() -> bool {
+    web_sys::window()
+        .and_then(|w| w.document())
+        .is_some_and(|d| d.hidden())
+}
+
+/// The other side of a direct message; `to` is only set on my own, as
+/// echoed back by the server.
+fn dm_peer(ctx: &Context<Chat>, to: Option<&String>, message: &MessageData) -> String {
+    if message.from == current_username(ctx) {
+        to.cloned().unwrap_or_default()
+    } else {
+        message.from.clone()
+    }
+}
+
+/// The message in `messages` with server id `id`.
+fn find_message(messages: &mut [MessageData], id: u64) -> Option<&mut MessageData> {
+    messages.iter_mut().find(|m| m.id == Some(id))
+}
+
+/// Popover for reporting room message `id`, with the category `picked`
+/// so far.
+fn view_report_popover(ctx: &Context<Chat>, id: u64, picked: Option<ReportCategory>) -> Html {
+    let label = |category| match category {
+        ReportCategory::Spam => t("report.spam"),
+        ReportCategory::Harassment => t("report.harassment"),
+        ReportCategory::Inappropriate => t("report.inappropriate"),
+    };
+    let name = format!("report-{}", id);
+    html! {
+        <div class="absolute bottom-full right-0 mb-2 w-56 p-3 z-20 text-xs text-left bg-white dark:bg-gray-800 rounded-lg shadow-lg border border-gray-200 dark:border-gray-700">
+            <div class="font-semibold mb-2">{t("report.title")}</div>
+            {
+                ReportCategory::ALL.into_iter().map(|category| html! {
+                    <label class="flex items-center gap-2 py-0.5 cursor-pointer">
+                        <input
+                            type="radio"
+                            name={name.clone()}
+                            checked={picked == Some(category)}
+                            onchange={ctx.link().callback(move |_| Msg::PickReportCategory(category))}
+                        />
+                        {label(category)}
+                    </label>
+                }).collect::<Html>()
+            }
+            <div class="flex justify-end gap-1 mt-2">
+                <button
+                    class="px-2 py-1 rounded text-gray-500 hover:bg-gray-100 dark:hover:bg-gray-700"
+                    onclick={ctx.link().callback(|_| Msg::Report(None))}
+                >
+                    {t("common.cancel")}
+                </button>
+                <button
+                    class="px-2 py-1 rounded text-white bg-red-600 hover:bg-red-700 disabled:opacity-50 disabled:cursor-not-allowed"
+                    disabled={picked.is_none()}
+                    onclick={ctx.link().callback(|_| Msg::SubmitReport)}
+                >
+                    {t("report.send")}
+                </button>
             </div>
+        </div>
+    }
+}
+
+/// Button opening the forward dialog for `m`. Shared files only exist as
+/// the pieces that were sent, so they can't be forwarded.
+fn view_forward_button(ctx: &Context<Chat>, m: &MessageData) -> Html {
+    if m.file.is_some() {
+        return html! {};
+    }
+    let message = m.clone();
+    html! {
+        <button
+            class="px-1 text-xs text-gray-500 hover:text-blue-600"
+            title={t("message.forward")}
+            onclick={ctx.link().callback(move |_| Msg::Forward(Some(message.clone())))}
+        >
+            {format!("↪ {}", t("message.forward"))}
+        </button>
+    }
+}
+
+/// Opens the menu for `name` where the pointer is, instead of the
+/// browser's own.
+fn user_menu(ctx: &Context<Chat>, name: &str) -> Callback<MouseEvent> {
+    let name = name.to_string();
+    ctx.link().callback(move |e: MouseEvent| {
+        e.prevent_default();
+        e.stop_propagation();
+        Msg::ShowUserMenu(Some(UserMenu {
+            name: name.clone(),
+            x: e.client_x(),
+            y: e.client_y(),
+        }))
+    })
+}
+
+/// Opens `name`'s card beside their entry in the sidebar, kept inside the
+/// window.
+fn user_card(ctx: &Context<Chat>, name: &str) -> Callback<MouseEvent> {
+    let name = name.to_string();
+    ctx.link().callback(move |e: MouseEvent| {
+        // Events are delegated, so the entry is found from what was clicked.
+        let entry = e
+            .target()
+            .and_then(|target| target.dyn_into::<web_sys::Element>().ok())
+            .and_then(|target| target.closest("[data-user-card]").ok().flatten());
+        let (x, y) = match entry {
+            Some(entry) => {
+                let rect = entry.get_bounding_client_rect();
+                (rect.right() as i32 + 8, rect.top() as i32)
+            }
+            None => (e.client_x(), e.client_y()),
+        };
+        let window = web_sys::window();
+        let size = |length: Option<JsValue>| length.and_then(|l| l.as_f64()).unwrap_or(0.0) as i32;
+        let width = size(window.as_ref().and_then(|w| w.inner_width().ok()));
+        let height = size(window.as_ref().and_then(|w| w.inner_height().ok()));
+        Msg::ShowUserCard(Some(UserMenu {
+            name: name.clone(),
+            x: x.min(width - POPOVER_WIDTH_PX - 8).max(8),
+            y: y.min(height - POPOVER_HEIGHT_PX - 8).max(8),
+        }))
+    })
+}
+
+/// "Their time: 21:05 (Asia/Jakarta, id-ID)" for `user`'s avatar, if
+/// their time zone is known.
+fn local_time(user: &UserProfile) -> Option<String> {
+    if user.timezone.is_empty() {
+        return None;
+    }
+    let now = time::now_in(&user.timezone)?;
+    let zone = if user.locale.is_empty() {
+        user.timezone.clone()
+    } else {
+        format!("{}, {}", user.timezone, user.locale)
+    };
+    Some(t_args("user.local_time", &[&now, &zone]))
+}
+
+/// Adds a candidate from the other side of the call, in the background.
+fn add_candidate(connection: Connection, candidate: IceCandidateData) {
+    wasm_bindgen_futures::spawn_local(async move {
+        if let Err(e) = connection.add_candidate(&candidate).await {
+            log::debug!("call: couldn't add a candidate: {:?}", e);
         }
+    });
+}
+
+/// Plays `stream` in the `<video>` behind `video`, unless it already is.
+/// My own is muted so I don't hear myself.
+fn attach_stream(video: &NodeRef, stream: Option<&MediaStream>, muted: bool) {
+    let video = match video.cast::<HtmlVideoElement>() {
+        Some(video) => video,
+        None => return,
+    };
+    if video.src_object().as_ref() == stream {
+        return;
     }
-}
\ No newline at end of file
+    video.set_muted(muted);
+    video.set_src_object(stream);
+    if stream.is_some() {
+        let _ = video.play();
+    }
+}
+
+fn current_username(ctx: &Context<Chat>) -> String {
+    ctx.link()
+        .context::<User>(Callback::noop())
+        .map(|(user, _)| user.username.borrow().clone())
+        .unwrap_or_default()
+}
@@ -0,0 +1,227 @@
+use gloo_timers::callback::Timeout;
+use js_sys::{Function, Promise, Reflect};
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::{spawn_local, JsFuture};
+use web_sys::Element;
+use yew::context::ContextHandle;
+use yew::prelude::*;
+
+use crate::utils::highlight::{tokenize, Language, TokenKind};
+use crate::utils::translations::{self, t, t_args};
+
+/// Longer blocks are folded down to about this many lines until expanded.
+const FOLDED_LINES: usize = 12;
+/// How long the copy button says how copying went.
+const COPIED_MS: u32 = 1_500;
+
+pub enum Msg {
+    Copy,
+    /// Copying finished, successfully or not.
+    Copied(bool),
+    ResetCopied,
+    ToggleExpanded,
+    LanguageChanged,
+}
+
+#[derive(Properties, PartialEq)]
+pub struct Props {
+    /// Info string of the fence, e.g. "rust"; empty for indented blocks.
+    pub info: String,
+    pub code: String,
+}
+
+/// A code block from a message, with a copy button, and folded when long.
+/// highlight.js colors it when the page has loaded it; otherwise languages
+/// `utils::highlight` knows get colored.
+pub struct CodeBlock {
+    /// Whether highlight.js is on the page, checked once on creation.
+    hljs: Option<JsValue>,
+    code_ref: NodeRef,
+    /// The code highlight.js last colored. Its nodes are left to it rather
+    /// than rendered by Yew.
+    highlighted: Option<String>,
+    expanded: bool,
+    /// Outcome of the last copy, shown on the button for a moment.
+    copied: Option<bool>,
+    copied_reset: Option<Timeout>,
+    /// Renders the labels again when the UI language changes.
+    _language: Option<ContextHandle<translations::Language>>,
+}
+
+impl Component for CodeBlock {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        let language = ctx
+            .link()
+            .context(ctx.link().callback(|_| Msg::LanguageChanged));
+        Self {
+            hljs: hljs(),
+            code_ref: NodeRef::default(),
+            highlighted: None,
+            expanded: false,
+            copied: None,
+            copied_reset: None,
+            _language: language.map(|(_, handle)| handle),
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::Copy => {
+                let code = ctx.props().code.clone();
+                let link = ctx.link().clone();
+                spawn_local(async move {
+                    let copied = copy_to_clipboard(&code).await;
+                    if let Err(e) = &copied {
+                        log::warn!("failed to copy code: {:?}", e);
+                    }
+                    link.send_message(Msg::Copied(copied.is_ok()));
+                });
+                false
+            }
+            Msg::Copied(ok) => {
+                self.copied = Some(ok);
+                let link = ctx.link().clone();
+                self.copied_reset = Some(Timeout::new(COPIED_MS, move || {
+                    link.send_message(Msg::ResetCopied)
+                }));
+                true
+            }
+            Msg::ResetCopied => {
+                self.copied_reset = None;
+                self.copied.take().is_some()
+            }
+            Msg::ToggleExpanded => {
+                self.expanded = !self.expanded;
+                true
+            }
+            Msg::LanguageChanged => true,
+        }
+    }
+
+    fn rendered(&mut self, ctx: &Context<Self>, _first_render: bool) {
+        let (hljs, code) = match (&self.hljs, self.code_ref.cast::<Element>()) {
+            (Some(hljs), Some(code)) => (hljs, code),
+            _ => return,
+        };
+        let source = &ctx.props().code;
+        if self.highlighted.as_ref() == Some(source) {
+            return;
+        }
+        code.set_text_content(Some(source));
+        // highlight.js skips elements it has colored before.
+        let _ = code.remove_attribute("data-highlighted");
+        if let Err(e) = highlight_element(hljs, &code) {
+            log::warn!("highlight.js failed: {:?}", e);
+        }
+        self.highlighted = Some(source.clone());
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let Props { info, code } = ctx.props();
+        let lines = code.lines().count();
+        let foldable = lines > FOLDED_LINES;
+        let language = info.split_whitespace().next().unwrap_or_default();
+
+        html! {
+            <div class="relative">
+                <button
+                    type="button"
+                    onclick={ctx.link().callback(|_| Msg::Copy)}
+                    class="absolute top-1 right-1 px-2 py-0.5 rounded text-[10px] bg-slate-600 text-slate-100 opacity-70 hover:opacity-100"
+                    title={t("code.copy_hint")}
+                >
+                    {
+                        match self.copied {
+                            Some(true) => t("code.copied"),
+                            Some(false) => t("code.copy_failed"),
+                            None => t("code.copy"),
+                        }
+                    }
+                </button>
+                <pre class={classes!((foldable && !self.expanded).then_some("max-h-48 overflow-hidden"))}>
+                    {
+                        if self.hljs.is_some() {
+                            html! {
+                                <code
+                                    ref={self.code_ref.clone()}
+                                    class={(!language.is_empty()).then(|| format!("language-{}", language))}
+                                />
+                            }
+                        } else {
+                            html! {
+                                <code>
+                                    {
+                                        tokenize(code, Language::from_info(info)).into_iter().map(|token| match token_class(token.kind) {
+                                            Some(class) => html! { <span {class}>{token.text}</span> },
+                                            None => Html::from(token.text.to_string()),
+                                        }).collect::<Html>()
+                                    }
+                                </code>
+                            }
+                        }
+                    }
+                </pre>
+                {
+                    if foldable {
+                        html! {
+                            <button
+                                type="button"
+                                onclick={ctx.link().callback(|_| Msg::ToggleExpanded)}
+                                class="mt-1 text-[11px] text-blue-600 hover:underline dark:text-blue-400"
+                            >
+                                {if self.expanded { t("code.show_less").to_string() } else { t_args("code.show_all", &[&lines]) }}
+                            </button>
+                        }
+                    } else {
+                        html! {}
+                    }
+                }
+            </div>
+        }
+    }
+}
+
+/// Colors for the tokens, readable on the dark background code blocks
+/// have in either theme.
+fn token_class(kind: TokenKind) -> Option<&'static str> {
+    match kind {
+        TokenKind::Plain => None,
+        TokenKind::Keyword => Some("text-pink-300"),
+        TokenKind::Literal => Some("text-sky-300"),
+        TokenKind::String => Some("text-green-300"),
+        TokenKind::Number => Some("text-amber-300"),
+        TokenKind::Comment => Some("italic text-slate-400"),
+    }
+}
+
+/// highlight.js's global `hljs`, if the page loaded it.
+fn hljs() -> Option<JsValue> {
+    let hljs = Reflect::get(&js_sys::global(), &JsValue::from_str("hljs")).ok()?;
+    (!hljs.is_undefined() && !hljs.is_null()).then_some(hljs)
+}
+
+/// `hljs.highlightElement(element)`.
+fn highlight_element(hljs: &JsValue, element: &Element) -> Result<(), JsValue> {
+    let highlight: Function =
+        Reflect::get(hljs, &JsValue::from_str("highlightElement"))?.dyn_into()?;
+    highlight.call1(hljs, element)?;
+    Ok(())
+}
+
+/// `navigator.clipboard.writeText(text)`, looked up dynamically: the web-sys
+/// bindings for the Clipboard API need `web_sys_unstable_apis` in the
+/// versions this builds with.
+pub async fn copy_to_clipboard(text: &str) -> Result<(), JsValue> {
+    let window = web_sys::window().ok_or_else(|| JsValue::from_str("no window"))?;
+    let clipboard = Reflect::get(&window.navigator(), &JsValue::from_str("clipboard"))?;
+    let write_text: Function =
+        Reflect::get(&clipboard, &JsValue::from_str("writeText"))?.dyn_into()?;
+    let written: Promise = write_text
+        .call1(&clipboard, &JsValue::from_str(text))?
+        .dyn_into()?;
+    JsFuture::from(written).await?;
+    Ok(())
+}
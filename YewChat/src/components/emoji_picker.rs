@@ -0,0 +1,267 @@
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::JsCast;
+use web_sys::{Event, HtmlInputElement, KeyboardEvent, Node};
+use yew::context::ContextHandle;
+use yew::prelude::*;
+
+use crate::utils::shortcodes;
+use crate::utils::translations::{self, t};
+
+const CATEGORIES: &[(&str, &str, &[&str])] = &[
+    (
+        "😀",
+        "emoji.smileys",
+        &[
+            "😀", "😃", "😄", "😁", "😆", "😅", "😂", "🤣", "😊", "😇", "🙂", "🙃", "😉", "😌",
+            "😍", "🥰", "😘", "😋", "😛", "😜", "🤪", "😎", "🤓", "🥳", "😏", "😒", "😞", "😔",
+            "😢", "😭", "😤", "😠", "😡", "🤯", "😳", "😱", "🤔", "🤫", "😴", "🤗",
+        ],
+    ),
+    (
+        "👍",
+        "emoji.gestures",
+        &[
+            "👍", "👎", "👌", "✌️", "🤞", "🤟", "🤘", "👋", "🤙", "👏", "🙌", "👐", "🙏", "🤝",
+            "💪", "👀", "👉", "👈", "👆", "👇", "✋", "🤚", "🖐️", "✊",
+        ],
+    ),
+    (
+        "🐶",
+        "emoji.animals",
+        &[
+            "🐶", "🐱", "🐭", "🐹", "🐰", "🦊", "🐻", "🐼", "🐨", "🐯", "🦁", "🐮", "🐷", "🐸",
+            "🐵", "🐔", "🐧", "🐦", "🦀", "🐙", "🐢", "🐍", "🦄", "🐝",
+        ],
+    ),
+    (
+        "🍕",
+        "emoji.food",
+        &[
+            "🍏", "🍎", "🍌", "🍉", "🍇", "🍓", "🍒", "🍑", "🥑", "🌶️", "🌽", "🥕", "🍞", "🧀",
+            "🍔", "🍟", "🍕", "🌮", "🍣", "🍜", "🍩", "🍪", "🎂", "☕",
+        ],
+    ),
+    (
+        "❤️",
+        "emoji.symbols",
+        &[
+            "❤️", "🧡", "💛", "💚", "💙", "💜", "🖤", "💔", "💯", "✨", "🔥", "⭐", "🎉", "🎊",
+            "✅", "❌", "❓", "❗", "💤", "💬", "🦀", "🚀", "⚡", "🌈",
+        ],
+    ),
+];
+
+pub enum Msg {
+    Toggle,
+    Close,
+    SelectCategory(usize),
+    Filter(String),
+    /// Picked an emoji; `keep_open` when Shift was held, to insert several.
+    Pick {
+        emoji: &'static str,
+        keep_open: bool,
+    },
+    LanguageChanged,
+}
+
+#[derive(Properties, PartialEq)]
+pub struct Props {
+    /// Called with the emoji the user picked.
+    pub on_select: Callback<String>,
+}
+
+/// A document event handler.
+type Listener = Closure<dyn Fn(Event)>;
+
+/// A 😀 button that toggles a grid of emoji grouped by category, with a
+/// filter over category and shortcode names. Closes on Escape, on a click
+/// outside of it, and after picking unless Shift is held.
+pub struct EmojiPicker {
+    open: bool,
+    category: usize,
+    filter: String,
+    root: NodeRef,
+    /// Document `mousedown` and `keydown` handlers, removed again in
+    /// `destroy`.
+    listeners: Vec<(&'static str, Listener)>,
+    /// Renders the labels again when the UI language changes.
+    _language: Option<ContextHandle<translations::Language>>,
+}
+
+impl Component for EmojiPicker {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        let root = NodeRef::default();
+        let outside_click = {
+            let (link, root) = (ctx.link().clone(), root.clone());
+            Closure::wrap(Box::new(move |e: Event| {
+                let target = e.target().and_then(|t| t.dyn_into::<Node>().ok());
+                let inside = root
+                    .get()
+                    .is_some_and(|root| root.contains(target.as_ref()));
+                if !inside {
+                    link.send_message(Msg::Close);
+                }
+            }) as Box<dyn Fn(Event)>)
+        };
+        let escape = {
+            let link = ctx.link().clone();
+            Closure::wrap(Box::new(move |e: Event| {
+                if e.dyn_ref::<KeyboardEvent>()
+                    .map(KeyboardEvent::key)
+                    .as_deref()
+                    == Some("Escape")
+                {
+                    link.send_message(Msg::Close);
+                }
+            }) as Box<dyn Fn(Event)>)
+        };
+        let listeners = vec![("mousedown", outside_click), ("keydown", escape)];
+        if let Some(document) = web_sys::window().and_then(|w| w.document()) {
+            for (event, listener) in &listeners {
+                let _ = document
+                    .add_event_listener_with_callback(event, listener.as_ref().unchecked_ref());
+            }
+        }
+
+        let language = ctx
+            .link()
+            .context(ctx.link().callback(|_| Msg::LanguageChanged));
+
+        Self {
+            open: false,
+            category: 0,
+            filter: String::new(),
+            root,
+            listeners,
+            _language: language.map(|(_, handle)| handle),
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::Toggle => {
+                self.open = !self.open;
+                true
+            }
+            Msg::Close => std::mem::take(&mut self.open),
+            Msg::SelectCategory(category) => {
+                self.category = category;
+                self.filter.clear();
+                true
+            }
+            Msg::Filter(filter) => {
+                self.filter = filter;
+                true
+            }
+            Msg::Pick { emoji, keep_open } => {
+                ctx.props().on_select.emit(emoji.to_string());
+                !keep_open && std::mem::take(&mut self.open)
+            }
+            Msg::LanguageChanged => true,
+        }
+    }
+
+    fn destroy(&mut self, _ctx: &Context<Self>) {
+        if let Some(document) = web_sys::window().and_then(|w| w.document()) {
+            for (event, listener) in &self.listeners {
+                let _ = document
+                    .remove_event_listener_with_callback(event, listener.as_ref().unchecked_ref());
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let toggle = ctx.link().callback(|_| Msg::Toggle);
+        let filter = ctx.link().callback(|e: InputEvent| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            Msg::Filter(input.value())
+        });
+        let query = self.filter.trim().to_lowercase();
+        let emojis: Vec<&'static str> = if query.is_empty() {
+            CATEGORIES[self.category].2.to_vec()
+        } else {
+            let mut found: Vec<&'static str> = vec![];
+            for (_, name, emojis) in CATEGORIES {
+                let category_matches = t(name).to_lowercase().contains(&query);
+                for emoji in emojis.iter() {
+                    let matches = category_matches
+                        || shortcodes::names_of(emoji).any(|code| code.contains(&query));
+                    if matches && !found.contains(emoji) {
+                        found.push(emoji);
+                    }
+                }
+            }
+            found
+        };
+
+        html! {
+            <div class="relative" ref={self.root.clone()}>
+                <button type="button" onclick={toggle} title={t("emoji.button")} class="ml-3 p-2 text-xl rounded-full hover:bg-gray-200 dark:hover:bg-gray-700">
+                    {"😀"}
+                </button>
+                {
+                    if self.open {
+                        html! {
+                            <div class="absolute bottom-14 right-0 w-72 bg-white border border-gray-200 rounded-lg shadow-lg z-10 dark:bg-gray-800 dark:border-gray-700">
+                                <input
+                                    type="text"
+                                    placeholder={t("emoji.search")}
+                                    value={self.filter.clone()}
+                                    oninput={filter}
+                                    class="block w-full px-3 py-2 text-sm bg-transparent border-b border-gray-200 outline-none dark:border-gray-700 dark:text-gray-100"
+                                />
+                                <div class="flex border-b border-gray-200 dark:border-gray-700">
+                                    {
+                                        CATEGORIES.iter().enumerate().map(|(i, (icon, name, _))| {
+                                            let class = if i == self.category && query.is_empty() {
+                                                "flex-1 p-2 text-lg bg-blue-100 dark:bg-blue-900"
+                                            } else {
+                                                "flex-1 p-2 text-lg hover:bg-gray-100 dark:hover:bg-gray-700"
+                                            };
+                                            html! {
+                                                <button type="button" {class} title={t(name)} onclick={ctx.link().callback(move |_| Msg::SelectCategory(i))}>
+                                                    {*icon}
+                                                </button>
+                                            }
+                                        }).collect::<Html>()
+                                    }
+                                </div>
+                                <div class="grid grid-cols-8 gap-1 p-2 h-48 overflow-y-auto">
+                                    {
+                                        emojis.iter().map(|&emoji| {
+                                            let title = shortcodes::names_of(emoji).next().map(|name| format!(":{}:", name));
+                                            html! {
+                                                <button
+                                                    type="button"
+                                                    {title}
+                                                    class="text-xl rounded hover:bg-gray-100 dark:hover:bg-gray-700"
+                                                    // Keep focus, and with it the caret, in the message box.
+                                                    onmousedown={Callback::from(|e: MouseEvent| e.prevent_default())}
+                                                    onclick={ctx.link().callback(move |e: MouseEvent| Msg::Pick { emoji, keep_open: e.shift_key() })}
+                                                >
+                                                    {emoji}
+                                                </button>
+                                            }
+                                        }).collect::<Html>()
+                                    }
+                                    {
+                                        if emojis.is_empty() {
+                                            html! { <div class="col-span-8 p-2 text-sm text-center text-gray-500">{t("emoji.none")}</div> }
+                                        } else {
+                                            html! {}
+                                        }
+                                    }
+                                </div>
+                            </div>
+                        }
+                    } else {
+                        html! {}
+                    }
+                }
+            </div>
+        }
+    }
+}
@@ -0,0 +1,127 @@
+use yew::prelude::*;
+
+use crate::utils::translations::t;
+
+/// Where a message can be forwarded to.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Destination {
+    Room(String),
+    /// The DM thread with this user.
+    User(String),
+}
+
+pub enum Msg {
+    Select(Destination),
+    Forward,
+}
+
+#[derive(Properties, PartialEq)]
+pub struct Props {
+    /// Rooms the server knows about.
+    pub rooms: Vec<String>,
+    /// Who else is around to get it as a DM.
+    pub contacts: Vec<String>,
+    /// The start of the message being forwarded, as a reminder.
+    pub excerpt: String,
+    pub on_forward: Callback<Destination>,
+    pub on_close: Callback<()>,
+}
+
+/// Modal for picking the room or person to forward a message to.
+pub struct ForwardDialog {
+    selected: Option<Destination>,
+}
+
+impl Component for ForwardDialog {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        Self { selected: None }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::Select(destination) => {
+                self.selected = Some(destination);
+                true
+            }
+            Msg::Forward => {
+                if let Some(destination) = self.selected.take() {
+                    ctx.props().on_forward.emit(destination);
+                }
+                false
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let props = ctx.props();
+        let close = props.on_close.reform(|_| ());
+        let option = |destination: Destination, label: String| {
+            let class = if self.selected.as_ref() == Some(&destination) {
+                "px-3 py-1 bg-blue-600 text-white cursor-pointer"
+            } else {
+                "px-3 py-1 hover:bg-blue-100 dark:hover:bg-gray-700 cursor-pointer"
+            };
+            // Double-clicking picks and forwards in one go.
+            let pick = destination.clone();
+            html! {
+                <div
+                    {class}
+                    onclick={ctx.link().callback(move |_| Msg::Select(destination.clone()))}
+                    ondblclick={ctx.link().batch_callback(move |_| vec![Msg::Select(pick.clone()), Msg::Forward])}
+                >
+                    {label}
+                </div>
+            }
+        };
+
+        html! {
+            <div class="fixed inset-0 z-20 flex items-center justify-center bg-black/40" onclick={close.clone()}>
+                <div
+                    class="w-96 bg-white dark:bg-gray-800 dark:text-gray-100 rounded-lg shadow-xl p-6"
+                    onclick={Callback::from(|e: MouseEvent| e.stop_propagation())}
+                >
+                    <div class="text-lg font-semibold text-blue-800 dark:text-blue-300 mb-2">{t("forward.title")}</div>
+                    <div class="mb-4 border-l-2 border-gray-400 pl-2 text-xs text-gray-500 dark:text-gray-400 truncate">
+                        {&props.excerpt}
+                    </div>
+                    <div class="max-h-72 overflow-auto rounded-md border border-gray-200 dark:border-gray-700">
+                        <div class="px-3 py-1 text-xs font-semibold text-gray-500 dark:text-gray-400">{t("forward.rooms")}</div>
+                        {
+                            props.rooms.iter().map(|room| {
+                                option(Destination::Room(room.clone()), format!("# {}", room))
+                            }).collect::<Html>()
+                        }
+                        <div class="px-3 py-1 text-xs font-semibold text-gray-500 dark:text-gray-400">{t("forward.people")}</div>
+                        {
+                            if props.contacts.is_empty() {
+                                html! {
+                                    <div class="px-3 py-1 text-sm text-gray-500 dark:text-gray-400">{t("forward.nobody")}</div>
+                                }
+                            } else {
+                                props.contacts.iter().map(|name| {
+                                    option(Destination::User(name.clone()), format!("@ {}", name))
+                                }).collect::<Html>()
+                            }
+                        }
+                    </div>
+                    <div class="flex justify-end gap-2 mt-6">
+                        <button type="button" onclick={close} class="px-4 py-2 rounded-md text-gray-600 hover:bg-gray-100">
+                            {t("common.cancel")}
+                        </button>
+                        <button
+                            type="button"
+                            disabled={self.selected.is_none()}
+                            onclick={ctx.link().callback(|_| Msg::Forward)}
+                            class="px-4 py-2 rounded-md bg-blue-600 hover:bg-blue-700 text-white disabled:opacity-50 disabled:cursor-not-allowed"
+                        >
+                            {t("forward.send")}
+                        </button>
+                    </div>
+                </div>
+            </div>
+        }
+    }
+}
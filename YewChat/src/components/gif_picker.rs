@@ -0,0 +1,169 @@
+use gloo_timers::callback::Timeout;
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+
+use crate::services::gifs::{self, Gif};
+use crate::utils::translations::t;
+
+/// How long the search box has to be left alone before searching, so
+/// typing doesn't send a request per key.
+const SEARCH_DEBOUNCE_MS: u32 = 400;
+
+pub enum Msg {
+    Input(String),
+    Search,
+    /// Results for the query given, which may be outdated by now.
+    Found(String, Result<Vec<Gif>, String>),
+}
+
+#[derive(Properties, PartialEq)]
+pub struct Props {
+    /// Gets the URL of the GIF picked.
+    pub on_pick: Callback<String>,
+    pub on_close: Callback<()>,
+}
+
+enum Results {
+    /// Nothing searched for yet.
+    None,
+    Loading,
+    Loaded(Vec<Gif>),
+    Failed,
+}
+
+/// Modal for searching GIFs and picking one.
+pub struct GifPicker {
+    query_input: NodeRef,
+    query: String,
+    results: Results,
+    debounce: Option<Timeout>,
+}
+
+impl Component for GifPicker {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        Self {
+            query_input: NodeRef::default(),
+            query: String::new(),
+            results: Results::None,
+            debounce: None,
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::Input(query) => {
+                self.query = query;
+                let link = ctx.link().clone();
+                self.debounce = Some(Timeout::new(SEARCH_DEBOUNCE_MS, move || {
+                    link.send_message(Msg::Search)
+                }));
+                false
+            }
+            Msg::Search => {
+                self.debounce = None;
+                let query = self.query.trim().to_string();
+                if query.is_empty() {
+                    self.results = Results::None;
+                    return true;
+                }
+                self.results = Results::Loading;
+                let link = ctx.link().clone();
+                wasm_bindgen_futures::spawn_local(async move {
+                    let found = gifs::search(&query).await;
+                    link.send_message(Msg::Found(query, found));
+                });
+                true
+            }
+            Msg::Found(query, found) => {
+                if query != self.query.trim() {
+                    return false;
+                }
+                self.results = match found {
+                    Ok(gifs) => Results::Loaded(gifs),
+                    Err(e) => {
+                        log::warn!("GIF search failed: {}", e);
+                        Results::Failed
+                    }
+                };
+                true
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let props = ctx.props();
+        let close = props.on_close.reform(|_| ());
+        let oninput = ctx.link().callback(|e: InputEvent| {
+            Msg::Input(e.target_unchecked_into::<HtmlInputElement>().value())
+        });
+        let note = |text: &str| {
+            html! {
+                <div class="py-8 text-center text-sm text-gray-500 dark:text-gray-400">{text.to_string()}</div>
+            }
+        };
+
+        html! {
+            <div class="fixed inset-0 z-20 flex items-center justify-center bg-black/40" onclick={close.clone()}>
+                <div
+                    class="w-[32rem] max-w-full bg-white dark:bg-gray-800 dark:text-gray-100 rounded-lg shadow-xl p-6"
+                    onclick={Callback::from(|e: MouseEvent| e.stop_propagation())}
+                >
+                    <div class="text-lg font-semibold text-blue-800 dark:text-blue-300 mb-4">{t("gif.title")}</div>
+                    <input
+                        ref={self.query_input.clone()}
+                        type="search"
+                        value={self.query.clone()}
+                        placeholder={t("gif.placeholder")}
+                        {oninput}
+                        class="w-full py-2 px-3 border border-gray-300 dark:border-gray-600 dark:bg-gray-900 rounded-md outline-none focus:ring-2 focus:ring-blue-300"
+                    />
+                    <div class="mt-4 h-80 overflow-auto">
+                        {
+                            match &self.results {
+                                Results::None => note(t("gif.hint")),
+                                Results::Loading => note(t("gif.searching")),
+                                Results::Failed => note(t("gif.failed")),
+                                Results::Loaded(gifs) if gifs.is_empty() => note(t("gif.none")),
+                                Results::Loaded(gifs) => html! {
+                                    <div class="grid grid-cols-3 gap-2">
+                                        {
+                                            gifs.iter().map(|gif| {
+                                                let url = gif.url.clone();
+                                                html! {
+                                                    <img
+                                                        class="w-full h-24 object-cover rounded-md cursor-pointer hover:ring-2 hover:ring-blue-400"
+                                                        src={gif.preview_url.clone()}
+                                                        referrerpolicy="no-referrer"
+                                                        loading="lazy"
+                                                        onclick={props.on_pick.reform(move |_| url.clone())}
+                                                    />
+                                                }
+                                            }).collect::<Html>()
+                                        }
+                                    </div>
+                                },
+                            }
+                        }
+                    </div>
+                    <div class="flex items-center justify-between mt-4">
+                        <span class="text-[10px] text-gray-400">{t("gif.powered_by")}</span>
+                        <button type="button" onclick={close} class="px-4 py-2 rounded-md text-gray-600 hover:bg-gray-100">
+                            {t("common.cancel")}
+                        </button>
+                    </div>
+                </div>
+            </div>
+        }
+    }
+
+    fn rendered(&mut self, _ctx: &Context<Self>, first_render: bool) {
+        if first_render {
+            if let Some(input) = self.query_input.cast::<HtmlInputElement>() {
+                let _ = input.focus();
+            }
+        }
+    }
+}
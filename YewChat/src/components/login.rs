@@ -3,12 +3,14 @@ use yew::functional::*;
 use yew::prelude::*;
 use yew_router::prelude::*;
 
+use crate::utils::translations::t;
 use crate::Route;
 use crate::User;
 
 #[function_component(Login)]
 pub fn login() -> Html {
-    let username = use_state(|| String::new());
+    let username = use_state(String::new);
+    let token = use_state(String::new);
     let user = use_context::<User>().expect("No context found.");
 
     let oninput = {
@@ -20,20 +22,38 @@ pub fn login() -> Html {
         })
     };
 
+    let ontokeninput = {
+        let token = token.clone();
+
+        Callback::from(move |e: InputEvent| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            token.set(input.value());
+        })
+    };
+
     let onclick = {
         let username = username.clone();
         let user = user.clone();
-        Callback::from(move |_| *user.username.borrow_mut() = (*username).clone())
+        Callback::from(move |_| {
+            *user.username.borrow_mut() = (*username).clone();
+            *user.token.borrow_mut() = (*token).clone();
+        })
+    };
+
+    #[allow(clippy::unnecessary_operation)]
+    let go = html! {
+        <Link<Route> to={Route::Chat}> <button {onclick} disabled={username.is_empty()} class="px-8 rounded-r-lg bg-violet-600	  text-white font-bold p-4 uppercase border-violet-600 border-t border-b border-r" >{t("login.go")}</button></Link<Route>>
     };
 
     html! {
-       <div class="bg-gray-800 flex w-screen">
+       <div class="bg-gray-100 dark:bg-gray-800 flex w-screen">
             <div class="container mx-auto flex flex-col justify-center items-center">
                 <form class="m-4 flex">
-                    <input {oninput} class="rounded-l-lg p-4 border-t mr-0 border-b border-l text-gray-800 border-gray-200 bg-white" placeholder="Username" />
-                    <Link<Route> to={Route::Chat}> <button {onclick} disabled={username.len()<1} class="px-8 rounded-r-lg bg-violet-600	  text-white font-bold p-4 uppercase border-violet-600 border-t border-b border-r" >{"Go Chatting!"}</button></Link<Route>>
+                    <input {oninput} class="rounded-l-lg p-4 border-t mr-0 border-b border-l text-gray-800 border-gray-200 bg-white dark:text-gray-100 dark:border-gray-600 dark:bg-gray-700" placeholder={t("login.username")} />
+                    <input type="password" oninput={ontokeninput} class="w-40 p-4 border-t border-b border-l text-gray-800 border-gray-200 bg-white dark:text-gray-100 dark:border-gray-600 dark:bg-gray-700" placeholder={t("login.token")} />
+                    {go}
                 </form>
             </div>
         </div>
     }
-}
\ No newline at end of file
+}
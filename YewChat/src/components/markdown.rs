@@ -0,0 +1,184 @@
+use pulldown_cmark::{CodeBlockKind, CowStr, Event, Options, Parser, Tag};
+use yew::prelude::*;
+
+use crate::components::code_block::CodeBlock;
+use crate::utils::content::safe_image_url;
+use crate::utils::linkify::{split_into_segments, Segment};
+use crate::utils::mentions;
+
+/// Renders a message body written in Markdown as Yew nodes. Raw HTML in the
+/// source comes out as text and links only go to web or mail addresses, so
+/// nothing typed into a message ever becomes live markup. Bare web addresses
+/// become links, and mentions of `me` stand out as pills.
+pub fn render_markdown(source: &str, me: &str) -> Html {
+    // Children collected so far for every element still open, the message
+    // itself at the bottom.
+    let mut open: Vec<Vec<Html>> = vec![vec![]];
+    // Text not turned into nodes yet. The parser can hand one run of text
+    // over in pieces, which would cut links and mentions apart.
+    let mut text = String::new();
+    // Links, images and code blocks open, whose text is left alone.
+    let mut verbatim = 0;
+    for event in Parser::new_ext(source, Options::ENABLE_STRIKETHROUGH) {
+        if let Event::Text(more) = &event {
+            text.push_str(more);
+            continue;
+        }
+        if let Event::End(Tag::CodeBlock(kind)) = &event {
+            // The block's whole text is still pending, as it holds nothing
+            // but text.
+            let info = match kind {
+                CodeBlockKind::Fenced(info) => info.to_string(),
+                CodeBlockKind::Indented => String::new(),
+            };
+            let code = std::mem::take(&mut text).trim_end_matches('\n').to_string();
+            verbatim -= 1;
+            open.pop();
+            if let Some(children) = open.last_mut() {
+                children.push(html! { <CodeBlock {info} {code} /> });
+            }
+            continue;
+        }
+        flush_text(&mut open, &mut text, me, verbatim == 0);
+        let node = match event {
+            Event::Start(tag) => {
+                if is_verbatim(&tag) {
+                    verbatim += 1;
+                }
+                open.push(vec![]);
+                continue;
+            }
+            Event::End(tag) => {
+                if is_verbatim(&tag) {
+                    verbatim -= 1;
+                }
+                element(tag, open.pop().unwrap_or_default())
+            }
+            Event::Html(text) => Html::from(text.into_string()),
+            Event::Code(code) => html! { <code>{code.into_string()}</code> },
+            // Line breaks typed with Shift+Enter are kept.
+            Event::SoftBreak | Event::HardBreak => html! { <br/> },
+            Event::Rule => html! { <hr/> },
+            _ => continue,
+        };
+        if let Some(children) = open.last_mut() {
+            children.push(node);
+        }
+    }
+    flush_text(&mut open, &mut text, me, true);
+
+    html! {
+        <div class="markdown break-words">{ for open.into_iter().flatten() }</div>
+    }
+}
+
+fn is_verbatim(tag: &Tag) -> bool {
+    matches!(tag, Tag::Link(..) | Tag::Image(..) | Tag::CodeBlock(_))
+}
+
+/// Adds the pending `text` to the innermost open element, linkified and
+/// with mentions of `me` marked when `rich`.
+fn flush_text(open: &mut [Vec<Html>], text: &mut String, me: &str, rich: bool) {
+    if text.is_empty() {
+        return;
+    }
+    let text = std::mem::take(text);
+    let node = if rich {
+        html! {
+            <>
+                {
+                    for split_into_segments(&text).into_iter().map(|segment| match segment {
+                        Segment::Text(text) => with_mentions(text, me),
+                        Segment::Link(url) => html! {
+                            <a href={url.to_string()} target="_blank" rel="noopener noreferrer">{url}</a>
+                        },
+                    })
+                }
+            </>
+        }
+    } else {
+        Html::from(text)
+    };
+    if let Some(children) = open.last_mut() {
+        children.push(node);
+    }
+}
+
+fn element(tag: Tag, children: Vec<Html>) -> Html {
+    let children = children.into_iter();
+    match tag {
+        Tag::Paragraph => html! { <p>{ for children }</p> },
+        Tag::Heading(level, ..) => html! { <@{level.to_string()}>{ for children }</@> },
+        Tag::BlockQuote => html! { <blockquote>{ for children }</blockquote> },
+        Tag::List(Some(start)) => html! { <ol start={start.to_string()}>{ for children }</ol> },
+        Tag::List(None) => html! { <ul>{ for children }</ul> },
+        Tag::Item => html! { <li>{ for children }</li> },
+        Tag::Emphasis => html! { <em>{ for children }</em> },
+        Tag::Strong => html! { <strong>{ for children }</strong> },
+        Tag::Strikethrough => html! { <del>{ for children }</del> },
+        Tag::Link(_, dest, title) => html! {
+            <a
+                href={safe_url(dest).into_string()}
+                title={(!title.is_empty()).then(|| title.into_string())}
+                target="_blank"
+                rel="noopener noreferrer"
+            >
+                { for children }
+            </a>
+        },
+        // The children are the alt text, which has no place to go as nodes;
+        // images that aren't safe to load become a link to them instead.
+        Tag::Image(_, dest, title) => match safe_image_url(&dest) {
+            Some(src) => html! {
+                <img
+                    {src}
+                    alt={title.into_string()}
+                    referrerpolicy="no-referrer"
+                    loading="lazy"
+                    class="max-w-full rounded-md"
+                />
+            },
+            None => html! {
+                <a href={safe_url(dest.clone()).into_string()} target="_blank" rel="noopener noreferrer">
+                    {
+                        if children.as_slice().is_empty() {
+                            html! { dest.into_string() }
+                        } else {
+                            html! { for children }
+                        }
+                    }
+                </a>
+            },
+        },
+        _ => html! { <>{ for children }</> },
+    }
+}
+
+fn with_mentions(text: &str, me: &str) -> Html {
+    let mut nodes = vec![];
+    let mut last = 0;
+    for (start, end) in mentions::mention_ranges(text, me) {
+        nodes.push(Html::from(text[last..start].to_string()));
+        nodes.push(html! {
+            <span class="px-1 rounded bg-yellow-200 text-gray-900 font-semibold">{&text[start..end]}</span>
+        });
+        last = end;
+    }
+    nodes.push(Html::from(text[last..].to_string()));
+    html! { <>{ for nodes }</> }
+}
+
+/// Drops `javascript:` and similar pseudo-URLs from links and images.
+fn safe_url(dest: CowStr) -> CowStr {
+    let scheme = dest
+        .split(':')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    if dest.contains(':') && !matches!(scheme.as_str(), "http" | "https" | "mailto") {
+        CowStr::Borrowed("#")
+    } else {
+        dest
+    }
+}
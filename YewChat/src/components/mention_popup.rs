@@ -0,0 +1,63 @@
+use yew::prelude::*;
+
+use crate::components::avatar::Avatar;
+
+/// Someone the `@name` being typed could be about.
+#[derive(Clone, PartialEq)]
+pub struct Candidate {
+    pub name: String,
+    pub avatar: String,
+}
+
+#[derive(Properties, PartialEq)]
+pub struct Props {
+    pub candidates: Vec<Candidate>,
+    /// What has been typed after the `@` so far.
+    pub query: String,
+    /// Index into `candidates` of the highlighted one.
+    pub selected: usize,
+    /// Called with the name clicked.
+    pub on_select: Callback<String>,
+}
+
+/// Suggestions for the `@name` being typed in the message box, shown above
+/// it. Moving through them with the keyboard is left to the message box,
+/// which keeps focus the whole time.
+#[function_component(MentionPopup)]
+pub fn mention_popup(props: &Props) -> Html {
+    let typed = props.query.chars().count();
+    html! {
+        <ul class="absolute bottom-16 left-4 w-56 py-1 bg-white border border-gray-200 rounded-lg shadow-lg z-10 dark:bg-gray-800 dark:border-gray-700">
+            {
+                props.candidates.iter().enumerate().map(|(i, candidate)| {
+                    let class = if i == props.selected {
+                        "flex items-center gap-2 px-3 py-1 text-sm cursor-pointer bg-blue-100 dark:bg-blue-900"
+                    } else {
+                        "flex items-center gap-2 px-3 py-1 text-sm cursor-pointer hover:bg-gray-100 dark:hover:bg-gray-700"
+                    };
+                    // The part already typed is the prefix that matched.
+                    let split = candidate
+                        .name
+                        .char_indices()
+                        .nth(typed)
+                        .map_or(candidate.name.len(), |(i, _)| i);
+                    let (matched, rest) = candidate.name.split_at(split);
+                    let name = candidate.name.clone();
+                    #[allow(clippy::unnecessary_operation)]
+                    let avatar = html! { <Avatar class="w-5 h-5" src={candidate.avatar.clone()} /> };
+                    html! {
+                        <li
+                            {class}
+                            // Keep focus, and with it the caret, in the message box.
+                            onmousedown={Callback::from(|e: MouseEvent| e.prevent_default())}
+                            onclick={props.on_select.reform(move |_| name.clone())}
+                        >
+                            {avatar}
+                            <span>{"@"}<span class="font-semibold">{matched}</span>{rest}</span>
+                        </li>
+                    }
+                }).collect::<Html>()
+            }
+        </ul>
+    }
+}
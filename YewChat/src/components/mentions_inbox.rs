@@ -0,0 +1,71 @@
+use yew::prelude::*;
+
+use crate::utils::time;
+use crate::utils::translations::t;
+
+/// A room message that mentioned me.
+#[derive(Clone, PartialEq)]
+pub struct Mention {
+    pub room: String,
+    pub id: u64,
+    pub from: String,
+    pub sent_at: Option<u64>,
+    /// The start of the message.
+    pub excerpt: String,
+    /// Whether I've jumped to it from here.
+    pub read: bool,
+}
+
+#[derive(Properties, PartialEq)]
+pub struct Props {
+    /// Oldest first; listed the other way round.
+    pub mentions: Vec<Mention>,
+    /// Called with the room and id of the mention clicked.
+    pub on_open: Callback<(String, u64)>,
+    pub on_close: Callback<()>,
+}
+
+/// The messages that mentioned me since logging in, dropped down from the
+/// header's @ button.
+#[function_component(MentionsInbox)]
+pub fn mentions_inbox(props: &Props) -> Html {
+    let close = props.on_close.reform(|_| ());
+    html! {
+        <div class="fixed inset-0 z-30" onclick={close}>
+            <div
+                class="absolute right-4 top-14 w-80 max-w-[calc(100%-2rem)] max-h-96 overflow-auto py-1 rounded-md shadow-lg bg-white dark:bg-gray-800 dark:text-gray-100 border border-gray-200 dark:border-gray-700"
+                onclick={Callback::from(|e: MouseEvent| e.stop_propagation())}
+            >
+                <div class="px-3 py-1 text-sm font-semibold text-blue-800 dark:text-blue-300">{t("mentions.title")}</div>
+                {
+                    if props.mentions.is_empty() {
+                        html! {
+                            <div class="px-3 py-4 text-center text-xs text-gray-500 dark:text-gray-400">{t("mentions.none")}</div>
+                        }
+                    } else {
+                        props.mentions.iter().rev().map(|mention| {
+                            let target = (mention.room.clone(), mention.id);
+                            html! {
+                                <button
+                                    type="button"
+                                    class={classes!("block", "w-full", "px-3", "py-2", "text-left", "text-xs", "hover:bg-gray-100", "dark:hover:bg-gray-700", (!mention.read).then_some("bg-yellow-50 dark:bg-yellow-900/30"))}
+                                    title={t("mentions.show")}
+                                    onclick={props.on_open.reform(move |_| target.clone())}
+                                >
+                                    <div class="flex items-center gap-1">
+                                        <span class="font-semibold truncate">{&mention.from}</span>
+                                        <span class="text-gray-500 dark:text-gray-400 truncate">{format!("#{}", mention.room)}</span>
+                                        <span class="ml-auto shrink-0 text-gray-400">
+                                            {mention.sent_at.map(time::short_label).unwrap_or_default()}
+                                        </span>
+                                    </div>
+                                    <div class="truncate text-gray-600 dark:text-gray-300">{&mention.excerpt}</div>
+                                </button>
+                            }
+                        }).collect::<Html>()
+                    }
+                }
+            </div>
+        </div>
+    }
+}
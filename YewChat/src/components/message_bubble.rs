@@ -0,0 +1,308 @@
+use yew::prelude::*;
+
+use crate::components::avatar::Avatar;
+use crate::components::markdown::render_markdown;
+use crate::services::protocol::MessageData;
+use crate::utils::commands;
+use crate::utils::content::{classify_message, safe_image_url, split_forwarded, MessageContent};
+use crate::utils::mentions;
+use crate::utils::search;
+use crate::utils::shortcodes::replace_shortcodes;
+use crate::utils::time;
+use crate::utils::translations::{t, t_args};
+
+/// Longer messages, which only a misbehaving client sends, are cut
+/// short when shown.
+const MAX_RENDERED_CHARS: usize = 10_000;
+
+/// The message a reply is to.
+#[derive(Clone, PartialEq)]
+pub enum Quote {
+    Found {
+        id: u64,
+        from: String,
+        text: String,
+    },
+    /// Deleted from the history, or from before it starts.
+    Missing,
+}
+
+#[derive(Properties, PartialEq)]
+pub struct Props {
+    pub message: MessageData,
+    pub current_user: String,
+    /// Follows a message from the same sender, so the name and avatar are
+    /// left out.
+    #[prop_or_default]
+    pub continued: bool,
+    /// The search result currently jumped to.
+    #[prop_or_default]
+    pub active_result: bool,
+    /// Marked in the text instead of rendering Markdown, when not empty.
+    #[prop_or_default]
+    pub search_query: String,
+    /// The sender's avatar, for someone else who is in the room.
+    #[prop_or_default]
+    pub avatar: Option<String>,
+    #[prop_or_default]
+    pub avatar_title: Option<String>,
+    #[prop_or_default]
+    pub quote: Option<Quote>,
+    /// Shown in place of the text, for a file message.
+    #[prop_or_default]
+    pub file: Option<Html>,
+    #[prop_or_default]
+    pub pinned: bool,
+    /// Whether anyone else has read it, for the user's own messages.
+    #[prop_or_default]
+    pub read: Option<bool>,
+    #[prop_or_default]
+    pub editing: bool,
+    #[prop_or_default]
+    pub edit_input: NodeRef,
+    /// Called with whether to start or stop editing; none if the message
+    /// can't be edited.
+    #[prop_or_default]
+    pub on_edit: Option<Callback<bool>>,
+    #[prop_or_default]
+    pub on_submit_edit: Callback<()>,
+    /// Called with the id of the quoted message when it's clicked.
+    pub on_jump: Callback<u64>,
+    /// Opens the menu for the sender, on others' messages.
+    #[prop_or_default]
+    pub on_user_menu: Option<Callback<MouseEvent>>,
+}
+
+/// A message as shown in the conversation: who sent it, what it's a reply
+/// to, its text and when it was sent. Deleted, hidden and system messages
+/// are left to the caller, as are previews and reactions below it.
+#[function_component(MessageBubble)]
+pub fn message_bubble(props: &Props) -> Html {
+    let m = &props.message;
+    let query = &props.search_query;
+    let is_self = m.from == props.current_user;
+    let forward = split_forwarded(&m.message);
+    let content = forward.map_or(m.message.as_str(), |(_, body)| body);
+    let is_image = classify_message(content) == MessageContent::Image;
+
+    let bubble_class = if is_self {
+        "ml-auto bg-blue-200 dark:bg-blue-900 text-right rounded-tl-lg rounded-bl-lg rounded-br-lg"
+    } else if mentions::mentions(&m.message, &props.current_user) {
+        "mr-auto bg-gray-100 dark:bg-gray-800 text-left rounded-tr-lg rounded-bl-lg rounded-br-lg ring-2 ring-yellow-300 dark:ring-yellow-600"
+    } else {
+        "mr-auto bg-gray-100 dark:bg-gray-800 text-left rounded-tr-lg rounded-bl-lg rounded-br-lg"
+    };
+
+    #[allow(clippy::unnecessary_operation)]
+    let avatar = match &props.avatar {
+        // Keeps follow-ups lined up with the first message.
+        Some(_) if props.continued => html! {
+            <div class="w-8 mr-2 flex-none" />
+        },
+        Some(avatar) => html! {
+            <Avatar class="w-8 h-8 mr-2" src={avatar.clone()} alt="avatar" title={props.avatar_title.clone()} oncontextmenu={props.on_user_menu.clone()}/>
+        },
+        None => html! {},
+    };
+
+    html! {
+        <div
+            class={classes!("flex", "items-end", "max-w-[85%]", "sm:max-w-[60%]", "p-2", bubble_class, props.active_result.then_some("outline outline-2 outline-orange-400"))}
+            ondblclick={props.on_edit.clone().map(|on_edit| Callback::from(move |_| on_edit.emit(true)))}
+        >
+            {avatar}
+            <div class="text-sm">
+                {
+                    match &props.on_edit {
+                        Some(on_edit) if props.editing => {
+                            let onkeydown = {
+                                let on_edit = on_edit.clone();
+                                let on_submit_edit = props.on_submit_edit.clone();
+                                Callback::from(move |e: KeyboardEvent| match e.key().as_str() {
+                                    "Enter" if !e.is_composing() => on_submit_edit.emit(()),
+                                    "Escape" => {
+                                        // Only cancels the edit, not a reply being written.
+                                        e.stop_propagation();
+                                        on_edit.emit(false);
+                                    }
+                                    _ => {}
+                                })
+                            };
+                            let onblur = on_edit.reform(|_| false);
+                            html! {
+                                <input
+                                    ref={props.edit_input.clone()}
+                                    type="text"
+                                    value={m.message.clone()}
+                                    {onkeydown}
+                                    {onblur}
+                                    class="w-64 px-2 py-1 text-xs rounded bg-white text-gray-800 outline-none dark:bg-gray-700 dark:text-gray-100"
+                                />
+                            }
+                        }
+                        _ => match commands::action_text(&m.message) {
+                            Some(action) => {
+                                let (action, truncated) = clip(action);
+                                html! {
+                                    <div class="italic whitespace-pre-wrap break-words text-gray-700 dark:text-gray-300">
+                                        {"* "}{highlight(&m.from, query)}{" "}{highlight(&replace_shortcodes(action), query)}
+                                        { view_truncated(truncated) }
+                                    </div>
+                                }
+                            }
+                            None => html! {
+                                <>
+                                    {
+                                        if props.continued {
+                                            html! {}
+                                        } else {
+                                            html! {
+                                                <div
+                                                    class="font-semibold text-blue-800 dark:text-blue-300"
+                                                    oncontextmenu={props.on_user_menu.clone()}
+                                                >
+                                                    {highlight(&m.from, query)}
+                                                </div>
+                                            }
+                                        }
+                                    }
+                                    {
+                                        match forward {
+                                            Some((author, _)) => html! {
+                                                <div class="mt-1 italic text-[11px] text-gray-500 dark:text-gray-400 text-left">
+                                                    {format!("↪ {}", t_args("message.forwarded", &[&author]))}
+                                                </div>
+                                            },
+                                            None => html! {},
+                                        }
+                                    }
+                                    { view_quote(props) }
+                                    <div class={if props.continued { "text-xs text-gray-700 dark:text-gray-200" } else { "text-xs text-gray-700 dark:text-gray-200 mt-1" }}>
+                                        {
+                                            if let Some(file) = &props.file {
+                                                file.clone()
+                                            } else if is_image {
+                                                html! {
+                                                    <img
+                                                        class="mt-2 max-w-full rounded-md"
+                                                        src={safe_image_url(content)}
+                                                        referrerpolicy="no-referrer"
+                                                        loading="lazy"
+                                                    />
+                                                }
+                                            } else {
+                                                view_text(content, query, &props.current_user)
+                                            }
+                                        }
+                                    </div>
+                                </>
+                            },
+                        },
+                    }
+                }
+                <div class="text-[10px] text-gray-500 dark:text-gray-400 mt-1">
+                    {
+                        match m.sent_at {
+                            Some(sent_at) => html! {
+                                <span title={time::full_label(sent_at)}>{time::short_label(sent_at)}</span>
+                            },
+                            None => html! {},
+                        }
+                    }
+                    { if m.edited { format!(" ({})", t("message.edited")) } else { String::new() } }
+                    {
+                        if props.pinned {
+                            html! { <span class="ml-1" title={t("message.pinned")}>{"📌"}</span> }
+                        } else {
+                            html! {}
+                        }
+                    }
+                    {
+                        match props.read {
+                            Some(true) => html! { <span class="ml-1 text-blue-500" title={t("message.read")}>{"✓✓"}</span> },
+                            Some(false) => html! { <span class="ml-1" title={t("message.sent")}>{"✓"}</span> },
+                            None => html! {},
+                        }
+                    }
+                </div>
+            </div>
+        </div>
+    }
+}
+
+fn view_quote(props: &Props) -> Html {
+    match &props.quote {
+        Some(Quote::Found { id, from, text }) => {
+            let id = *id;
+            html! {
+                <div
+                    class="mt-1 border-l-2 border-gray-400 pl-2 text-[11px] text-gray-500 dark:text-gray-400 text-left cursor-pointer hover:text-gray-700 dark:hover:text-gray-200"
+                    title={t("message.show_original")}
+                    onclick={props.on_jump.reform(move |_| id)}
+                >
+                    <span class="font-semibold">{format!("{}: ", from)}</span>
+                    {text.clone()}
+                </div>
+            }
+        }
+        Some(Quote::Missing) => html! {
+            <div class="mt-1 border-l-2 border-gray-400 pl-2 text-[11px] text-gray-500 dark:text-gray-400 text-left italic">
+                {t("message.original_missing")}
+            </div>
+        },
+        None => html! {},
+    }
+}
+
+/// The text of a message: Markdown, or plain with the matches of `query`
+/// marked while searching.
+fn view_text(content: &str, query: &str, current_user: &str) -> Html {
+    let (text, truncated) = clip(content);
+    let body = if !query.is_empty() {
+        html! {
+            <div class="whitespace-pre-wrap break-words">
+                {highlight(text, query)}
+            </div>
+        }
+    } else {
+        render_markdown(&replace_shortcodes(text), current_user)
+    };
+    html! {
+        <>
+            {body}
+            { view_truncated(truncated) }
+        </>
+    }
+}
+
+/// Says the text was cut short, if it was.
+fn view_truncated(truncated: bool) -> Html {
+    if truncated {
+        html! {
+            <div class="mt-1 italic text-[10px] text-gray-500 dark:text-gray-400">{t("message.truncated")}</div>
+        }
+    } else {
+        html! {}
+    }
+}
+
+/// `text` with every match of `query` wrapped in a highlighted span.
+fn highlight(text: &str, query: &str) -> Html {
+    let mut parts = vec![];
+    let mut last = 0;
+    for (start, end) in search::match_ranges(text, query.trim()) {
+        parts.push(html! { {&text[last..start]} });
+        parts.push(html! { <span class="bg-yellow-200 dark:bg-yellow-700 rounded-sm">{&text[start..end]}</span> });
+        last = end;
+    }
+    parts.push(html! { {&text[last..]} });
+    parts.into_iter().collect()
+}
+
+/// `message` cut to `MAX_RENDERED_CHARS`, and whether anything was cut.
+fn clip(message: &str) -> (&str, bool) {
+    match message.char_indices().nth(MAX_RENDERED_CHARS) {
+        Some((end, _)) => (&message[..end], true),
+        None => (message, false),
+    }
+}
@@ -1,2 +1,16 @@
+pub mod avatar;
 pub mod chat;
-pub mod login;
\ No newline at end of file
+pub mod code_block;
+pub mod emoji_picker;
+pub mod forward_dialog;
+pub mod gif_picker;
+pub mod login;
+pub mod markdown;
+pub mod mention_popup;
+pub mod mentions_inbox;
+pub mod message_bubble;
+pub mod profile_panel;
+pub mod room_dialog;
+pub mod settings;
+pub mod user_popover;
+pub mod user_search;
\ No newline at end of file
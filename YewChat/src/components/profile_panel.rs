@@ -0,0 +1,117 @@
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+
+use crate::components::avatar::Avatar;
+use crate::utils::translations::t;
+
+/// DiceBear collections offered for avatars, the default first.
+pub const AVATAR_STYLES: &[&str] = &[
+    "adventurer-neutral",
+    "adventurer",
+    "avataaars",
+    "big-smile",
+    "bottts",
+    "identicon",
+    "micah",
+    "pixel-art",
+];
+
+pub fn avatar_url(style: &str, name: &str) -> String {
+    format!("https://avatars.dicebear.com/api/{}/{}.svg", style, name)
+}
+
+pub enum Msg {
+    Pick(&'static str),
+    Save,
+}
+
+#[derive(Properties, PartialEq)]
+pub struct Props {
+    pub name: String,
+    /// Current avatar style, one of `AVATAR_STYLES`.
+    pub avatar: String,
+    /// Called with the new name and avatar style.
+    pub on_save: Callback<(String, String)>,
+    pub on_close: Callback<()>,
+}
+
+/// Modal for changing my display name and avatar.
+pub struct ProfilePanel {
+    name_input: NodeRef,
+    avatar: String,
+}
+
+impl Component for ProfilePanel {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        Self {
+            name_input: NodeRef::default(),
+            avatar: ctx.props().avatar.clone(),
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::Pick(style) => {
+                self.avatar = style.to_string();
+                true
+            }
+            Msg::Save => {
+                if let Some(input) = self.name_input.cast::<HtmlInputElement>() {
+                    let name = input.value().trim().to_string();
+                    ctx.props().on_save.emit((name, self.avatar.clone()));
+                }
+                false
+            }
+        }
+    }
+
+    #[allow(clippy::unnecessary_operation)]
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let close = ctx.props().on_close.reform(|_| ());
+        let name = &ctx.props().name;
+
+        html! {
+            <div class="fixed inset-0 z-20 flex items-center justify-center bg-black/40">
+                <div class="w-96 bg-white dark:bg-gray-800 dark:text-gray-100 rounded-lg shadow-xl p-6">
+                    <div class="text-lg font-semibold text-blue-800 dark:text-blue-300 mb-4">{t("profile.title")}</div>
+                    <label class="block text-sm text-gray-600 mb-1">{t("profile.name")}</label>
+                    <input
+                        ref={self.name_input.clone()}
+                        type="text"
+                        value={name.clone()}
+                        class="w-full py-2 px-3 mb-4 border border-gray-300 dark:bg-gray-900 dark:border-gray-600 rounded-md outline-none focus:ring-2 focus:ring-blue-300"
+                    />
+                    <div class="text-sm text-gray-600 mb-1">{t("profile.avatar")}</div>
+                    <div class="grid grid-cols-4 gap-2 mb-6">
+                        {
+                            AVATAR_STYLES.iter().map(|style| {
+                                let style: &'static str = style;
+                                let class = if self.avatar == style {
+                                    "p-1 rounded-lg ring-2 ring-blue-500"
+                                } else {
+                                    "p-1 rounded-lg hover:bg-gray-100"
+                                };
+                                html! {
+                                    <button type="button" {class} title={style} onclick={ctx.link().callback(move |_| Msg::Pick(style))}>
+                                        <Avatar class="w-14 h-14 mx-auto" src={avatar_url(style, name)} alt={style}/>
+                                    </button>
+                                }
+                            }).collect::<Html>()
+                        }
+                    </div>
+                    <div class="flex justify-end gap-2">
+                        <button type="button" onclick={close} class="px-4 py-2 rounded-md text-gray-600 hover:bg-gray-100">
+                            {t("common.cancel")}
+                        </button>
+                        <button type="button" onclick={ctx.link().callback(|_| Msg::Save)} class="px-4 py-2 rounded-md bg-blue-600 hover:bg-blue-700 text-white">
+                            {t("common.save")}
+                        </button>
+                    </div>
+                </div>
+            </div>
+        }
+    }
+}
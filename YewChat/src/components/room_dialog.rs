@@ -0,0 +1,312 @@
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+
+use crate::services::protocol::RoomData;
+use crate::utils::translations::{t, t_args};
+
+/// Room names the server accepts, in characters.
+const NAME_LEN: std::ops::RangeInclusive<usize> = 3..=32;
+/// Longest description the server accepts, in characters.
+const MAX_DESCRIPTION: usize = 200;
+/// Fewest members a room can be limited to.
+const MIN_MEMBERS: u32 = 2;
+
+/// Whether `name` would do for a room: 3 to 32 lowercase letters, digits or
+/// dashes. The server holds new rooms to the same rule.
+pub fn valid_room_name(name: &str) -> bool {
+    NAME_LEN.contains(&name.chars().count())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+}
+
+pub enum Msg {
+    Input(String),
+    Description(String),
+    Private(bool),
+    MaxMembers(String),
+    Invite(String),
+    Create,
+    /// Join the named room, or the one typed in with `None`.
+    Join(Option<String>),
+}
+
+#[derive(Properties, PartialEq)]
+pub struct Props {
+    /// Rooms the server knows about.
+    pub rooms: Vec<String>,
+    pub current: String,
+    /// Why the server turned down the last request, if it did.
+    pub error: Option<String>,
+    pub on_create: Callback<RoomData>,
+    /// Gets the room to join, and the invite if it's private.
+    pub on_join: Callback<(String, Option<String>)>,
+    pub on_close: Callback<()>,
+}
+
+/// Modal for creating a room or joining one, either picked from the list
+/// or typed in, with an invite for private rooms.
+pub struct RoomDialog {
+    name_input: NodeRef,
+    name: String,
+    description: String,
+    private: bool,
+    /// As typed; empty for no limit.
+    max_members: String,
+    invite: String,
+    /// What's wrong with the last attempt, shown under the name.
+    error: Option<String>,
+}
+
+impl Component for RoomDialog {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        Self {
+            name_input: NodeRef::default(),
+            name: String::new(),
+            description: String::new(),
+            private: false,
+            max_members: String::new(),
+            invite: String::new(),
+            error: ctx.props().error.clone(),
+        }
+    }
+
+    fn changed(&mut self, ctx: &Context<Self>) -> bool {
+        if ctx.props().error.is_some() {
+            self.error = ctx.props().error.clone();
+        }
+        true
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        let props = ctx.props();
+        match msg {
+            Msg::Input(name) => {
+                self.name = name;
+                self.error = None;
+                true
+            }
+            Msg::Description(description) => {
+                self.description = description;
+                false
+            }
+            Msg::Private(private) => {
+                self.private = private;
+                true
+            }
+            Msg::MaxMembers(max_members) => {
+                self.max_members = max_members;
+                self.error = None;
+                true
+            }
+            Msg::Invite(invite) => {
+                self.invite = invite;
+                self.error = None;
+                true
+            }
+            Msg::Create => {
+                let name = self.name.trim();
+                let max_members = match self.max_members.trim() {
+                    "" => Ok(None),
+                    typed => match typed.parse::<u32>() {
+                        Ok(n) if n >= MIN_MEMBERS => Ok(Some(n)),
+                        _ => Err(()),
+                    },
+                };
+                self.error = if !valid_room_name(name) {
+                    Some(t("room.invalid").to_string())
+                } else if props.rooms.iter().any(|room| room == name) {
+                    Some(t_args("room.exists", &[&name]))
+                } else if let Ok(max_members) = max_members {
+                    props.on_create.emit(RoomData {
+                        name: name.to_string(),
+                        description: self.description.trim().to_string(),
+                        private: self.private,
+                        max_members,
+                        invite: None,
+                    });
+                    None
+                } else {
+                    Some(t_args("room.max_members_invalid", &[&MIN_MEMBERS]))
+                };
+                true
+            }
+            Msg::Join(Some(room)) => {
+                props.on_join.emit((room, None));
+                false
+            }
+            Msg::Join(None) => {
+                let name = self.name.trim();
+                let invite = Some(self.invite.trim().to_string()).filter(|i| !i.is_empty());
+                self.error = if !valid_room_name(name) {
+                    Some(t("room.invalid").to_string())
+                } else if invite.is_none() && !props.rooms.iter().any(|room| room == name) {
+                    Some(t_args("room.not_found", &[&name]))
+                } else {
+                    props.on_join.emit((name.to_string(), invite));
+                    None
+                };
+                true
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let props = ctx.props();
+        let close = props.on_close.reform(|_| ());
+        let name = self.name.trim();
+        // Enter joins a room that exists or that there's an invite for,
+        // and creates one that doesn't.
+        let joining = !self.invite.trim().is_empty() || props.rooms.iter().any(|room| room == name);
+        let input = |msg: fn(String) -> Msg| {
+            ctx.link().callback(move |e: InputEvent| {
+                msg(e.target_unchecked_into::<HtmlInputElement>().value())
+            })
+        };
+        let onkeydown = ctx.link().batch_callback(move |e: KeyboardEvent| {
+            (e.key() == "Enter").then_some(if joining {
+                Msg::Join(None)
+            } else {
+                Msg::Create
+            })
+        });
+        let field = "w-full py-2 px-3 border border-gray-300 dark:border-gray-600 dark:bg-gray-900 rounded-md outline-none focus:ring-2 focus:ring-blue-300";
+        let matching: Vec<&String> = props
+            .rooms
+            .iter()
+            .filter(|room| room.contains(name))
+            .collect();
+
+        html! {
+            <div class="fixed inset-0 z-20 flex items-center justify-center bg-black/40" onclick={close.clone()}>
+                <div
+                    class="w-96 bg-white dark:bg-gray-800 dark:text-gray-100 rounded-lg shadow-xl p-6"
+                    onclick={Callback::from(|e: MouseEvent| e.stop_propagation())}
+                >
+                    <div class="text-lg font-semibold text-blue-800 dark:text-blue-300 mb-4">{t("room.title")}</div>
+                    <label class="block text-sm text-gray-600 mb-1">{t("room.name")}</label>
+                    <input
+                        ref={self.name_input.clone()}
+                        type="text"
+                        value={self.name.clone()}
+                        maxlength={NAME_LEN.end().to_string()}
+                        placeholder="study-group"
+                        oninput={input(Msg::Input)}
+                        onkeydown={onkeydown.clone()}
+                        class={classes!(
+                            "w-full", "py-2", "px-3", "border", "dark:bg-gray-900", "rounded-md", "outline-none", "focus:ring-2",
+                            if self.error.is_some() {
+                                classes!("border-red-400", "focus:ring-red-300")
+                            } else {
+                                classes!("border-gray-300", "dark:border-gray-600", "focus:ring-blue-300")
+                            }
+                        )}
+                    />
+                    {
+                        match &self.error {
+                            Some(error) => html! {
+                                <div class="mt-1 text-xs text-red-600">{error}</div>
+                            },
+                            None => html! {
+                                <div class="mt-1 text-xs text-gray-500 dark:text-gray-400">{t("room.hint")}</div>
+                            },
+                        }
+                    }
+                    <label class="block mt-4 text-sm text-gray-600 mb-1">{t("room.description")}</label>
+                    <input
+                        type="text"
+                        value={self.description.clone()}
+                        maxlength={MAX_DESCRIPTION.to_string()}
+                        placeholder={t("room.description_placeholder")}
+                        oninput={input(Msg::Description)}
+                        class={field}
+                    />
+                    <div class="flex items-end gap-4 mt-4">
+                        <label class="flex items-center gap-2 py-2 text-sm text-gray-600 dark:text-gray-300" title={t("room.private_hint")}>
+                            <input
+                                type="checkbox"
+                                checked={self.private}
+                                onchange={ctx.link().callback(|e: Event| Msg::Private(e.target_unchecked_into::<HtmlInputElement>().checked()))}
+                            />
+                            {t("room.private")}
+                        </label>
+                        <div class="ml-auto w-32">
+                            <label class="block text-sm text-gray-600 mb-1">{t("room.max_members")}</label>
+                            <input
+                                type="number"
+                                min={MIN_MEMBERS.to_string()}
+                                value={self.max_members.clone()}
+                                placeholder={t("room.no_limit")}
+                                oninput={input(Msg::MaxMembers)}
+                                class={field}
+                            />
+                        </div>
+                    </div>
+                    <label class="block mt-4 text-sm text-gray-600 mb-1">{t("room.invite")}</label>
+                    <input
+                        type="text"
+                        value={self.invite.clone()}
+                        placeholder={t("room.invite_placeholder")}
+                        oninput={input(Msg::Invite)}
+                        {onkeydown}
+                        class={field}
+                    />
+                    <div class="mt-4 text-sm text-gray-600 mb-1">{t("room.known")}</div>
+                    <div class="max-h-48 overflow-auto rounded-md border border-gray-200 dark:border-gray-700">
+                        {
+                            if matching.is_empty() {
+                                html! {
+                                    <div class="px-3 py-2 text-sm text-gray-500 dark:text-gray-400">{t("room.none")}</div>
+                                }
+                            } else {
+                                matching.into_iter().map(|room| {
+                                    let target = room.clone();
+                                    let class = if *room == props.current {
+                                        "px-3 py-1 bg-blue-600 text-white cursor-pointer"
+                                    } else {
+                                        "px-3 py-1 hover:bg-blue-100 dark:hover:bg-gray-700 cursor-pointer"
+                                    };
+                                    html! {
+                                        <div {class} onclick={ctx.link().callback(move |_| Msg::Join(Some(target.clone())))}>
+                                            {format!("# {}", room)}
+                                        </div>
+                                    }
+                                }).collect::<Html>()
+                            }
+                        }
+                    </div>
+                    <div class="flex justify-end gap-2 mt-6">
+                        <button type="button" onclick={close} class="px-4 py-2 rounded-md text-gray-600 hover:bg-gray-100">
+                            {t("common.cancel")}
+                        </button>
+                        <button
+                            type="button"
+                            onclick={ctx.link().callback(|_| Msg::Join(None))}
+                            class="px-4 py-2 rounded-md border border-blue-600 text-blue-700 hover:bg-blue-50 dark:text-blue-300 dark:hover:bg-gray-700"
+                        >
+                            {t("room.join")}
+                        </button>
+                        <button
+                            type="button"
+                            onclick={ctx.link().callback(|_| Msg::Create)}
+                            class="px-4 py-2 rounded-md bg-blue-600 hover:bg-blue-700 text-white"
+                        >
+                            {t("room.create")}
+                        </button>
+                    </div>
+                </div>
+            </div>
+        }
+    }
+
+    fn rendered(&mut self, _ctx: &Context<Self>, first_render: bool) {
+        if first_render {
+            if let Some(input) = self.name_input.cast::<HtmlInputElement>() {
+                let _ = input.focus();
+            }
+        }
+    }
+}
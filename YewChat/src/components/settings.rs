@@ -0,0 +1,89 @@
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+
+use crate::services::notify::NotificationPrefs;
+use crate::utils::translations::{t, t_args};
+
+#[derive(Properties, PartialEq)]
+pub struct Props {
+    pub prefs: NotificationPrefs,
+    /// Called with the preferences as changed by a checkbox.
+    pub on_change: Callback<NotificationPrefs>,
+    /// Users I blocked, in display order.
+    pub blocked: Vec<String>,
+    pub on_unblock: Callback<String>,
+    pub on_close: Callback<()>,
+}
+
+/// Modal with the notification preferences and the people I blocked.
+/// Every change is handed back right away; there's nothing to save.
+#[function_component(Settings)]
+pub fn settings(props: &Props) -> Html {
+    let prefs = props.prefs;
+    let checkbox = |label: &str, checked: bool, set: fn(&mut NotificationPrefs, bool)| {
+        let onchange = props.on_change.reform(move |e: Event| {
+            let mut prefs = prefs;
+            set(
+                &mut prefs,
+                e.target_unchecked_into::<HtmlInputElement>().checked(),
+            );
+            prefs
+        });
+        html! {
+            <label class="flex items-center gap-3 py-2 text-sm cursor-pointer">
+                <input type="checkbox" class="w-4 h-4 accent-blue-600" {checked} {onchange} />
+                {label.to_string()}
+            </label>
+        }
+    };
+
+    html! {
+        <div class="fixed inset-0 z-20 flex items-center justify-center bg-black/40">
+            <div class="w-96 bg-white dark:bg-gray-800 dark:text-gray-100 rounded-lg shadow-xl p-6">
+                <div class="text-lg font-semibold text-blue-800 dark:text-blue-300 mb-1">{t("settings.title")}</div>
+                <div class="text-xs text-gray-500 dark:text-gray-400 mb-3">
+                    {t("settings.hint")}
+                </div>
+                { checkbox(t("settings.sound"), prefs.sound_enabled, |p, on| p.sound_enabled = on) }
+                { checkbox(t("settings.desktop"), prefs.desktop_enabled, |p, on| p.desktop_enabled = on) }
+                { checkbox(t("settings.mentions_only"), prefs.mentions_only, |p, on| p.mentions_only = on) }
+                <div class="mt-4 mb-1 text-sm font-semibold text-blue-800 dark:text-blue-300">{t("settings.blocked")}</div>
+                <div class="max-h-40 overflow-auto">
+                    {
+                        if props.blocked.is_empty() {
+                            html! {
+                                <div class="py-1 text-xs text-gray-500 dark:text-gray-400">{t("settings.blocked_none")}</div>
+                            }
+                        } else {
+                            props.blocked.iter().map(|name| {
+                                let target = name.clone();
+                                html! {
+                                    <div class="flex items-center justify-between py-1 text-sm">
+                                        <span class="truncate">{name}</span>
+                                        <button
+                                            type="button"
+                                            onclick={props.on_unblock.reform(move |_| target.clone())}
+                                            class="px-2 py-0.5 rounded text-xs text-blue-700 hover:bg-blue-50 dark:text-blue-300 dark:hover:bg-gray-700"
+                                            title={t_args("block.unblock", &[name])}
+                                        >
+                                            {t("settings.unblock")}
+                                        </button>
+                                    </div>
+                                }
+                            }).collect::<Html>()
+                        }
+                    }
+                </div>
+                <div class="flex justify-end mt-4">
+                    <button
+                        type="button"
+                        onclick={props.on_close.reform(|_| ())}
+                        class="px-4 py-2 rounded-md bg-blue-600 hover:bg-blue-700 text-white"
+                    >
+                        {t("common.done")}
+                    </button>
+                </div>
+            </div>
+        </div>
+    }
+}
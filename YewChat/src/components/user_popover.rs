@@ -0,0 +1,78 @@
+use yew::prelude::*;
+
+use crate::components::avatar::Avatar;
+use crate::utils::time;
+use crate::utils::translations::{t, t_args};
+
+/// Size of the popover, in px, for keeping it on screen. It's about this
+/// tall.
+pub const POPOVER_WIDTH_PX: i32 = 256;
+pub const POPOVER_HEIGHT_PX: i32 = 240;
+
+#[derive(Properties, PartialEq)]
+pub struct Props {
+    pub name: String,
+    pub avatar: String,
+    /// When they showed up in the room, if it was after me.
+    pub online_since: Option<u64>,
+    /// Whether I've blocked them.
+    pub blocked: bool,
+    /// Where the top left corner goes, in px from the window's.
+    pub x: i32,
+    pub y: i32,
+    pub on_message: Callback<()>,
+    /// Puts "@name " in the message box.
+    pub on_mention: Callback<()>,
+    /// Called with whether to block them from now on.
+    pub on_block: Callback<bool>,
+    pub on_close: Callback<()>,
+}
+
+/// Someone's card, opened next to them in the sidebar: who they are, and
+/// what I can do with them.
+#[function_component(UserPopover)]
+pub fn user_popover(props: &Props) -> Html {
+    let close = props.on_close.reform(|_| ());
+    let blocked = props.blocked;
+    let action = "grow px-2 py-1 rounded-md text-sm bg-gray-100 hover:bg-gray-200 dark:bg-gray-700 dark:hover:bg-gray-600";
+    #[allow(clippy::unnecessary_operation)]
+    let avatar = html! { <Avatar class="w-20 h-20" src={props.avatar.clone()} alt="avatar" /> };
+    html! {
+        // Above the sidebar drawer of the phone layout.
+        <div class="fixed inset-0 z-50" onclick={close}>
+            <div
+                class="fixed p-4 rounded-lg shadow-xl bg-white dark:bg-gray-800 dark:text-gray-100 border border-gray-200 dark:border-gray-700"
+                style={format!("left: {}px; top: {}px; width: {}px", props.x, props.y, POPOVER_WIDTH_PX)}
+                onclick={Callback::from(|e: MouseEvent| e.stop_propagation())}
+            >
+                <div class="flex flex-col items-center text-center">
+                    {avatar}
+                    <div class="mt-2 font-semibold truncate max-w-full">{&props.name}</div>
+                    <div class="text-xs text-gray-500 dark:text-gray-400">
+                        {
+                            match props.online_since {
+                                Some(since) => t_args("user_card.online_since", &[&time::short_label(since)]),
+                                None => t("user_card.here_before_me").to_string(),
+                            }
+                        }
+                    </div>
+                </div>
+                <div class="flex gap-2 mt-4">
+                    <button type="button" class={action} onclick={props.on_message.reform(|_| ())}>
+                        {t("user_card.message")}
+                    </button>
+                    <button type="button" class={action} onclick={props.on_mention.reform(|_| ())}>
+                        {t("user_card.mention")}
+                    </button>
+                </div>
+                <button
+                    type="button"
+                    onclick={props.on_block.reform(move |_| !blocked)}
+                    class={classes!("w-full", "mt-2", "px-2", "py-1", "rounded-md", "text-sm", "hover:bg-gray-100", "dark:hover:bg-gray-700", (!blocked).then_some("text-red-600"))}
+                >
+                    {if blocked { t("block.unblock_user") } else { t("block.block_user") }}
+                </button>
+            </div>
+        </div>
+    }
+}
@@ -0,0 +1,190 @@
+use gloo_timers::callback::Timeout;
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+
+use crate::utils::translations::{t, t_args};
+
+/// How long typing has to pause before the server is asked, in ms.
+const DEBOUNCE_MS: u32 = 300;
+
+pub enum Msg {
+    Input(String),
+    /// The debounce ran out: look for the query.
+    Search,
+    /// Ask for the page after the results shown.
+    More,
+}
+
+#[derive(Properties, PartialEq)]
+pub struct Props {
+    /// Who matched so far, in the order the server sent them.
+    pub results: Vec<String>,
+    /// Whether the server has more after `results`.
+    pub more: bool,
+    /// Whether a search is on its way to the server or back.
+    pub loading: bool,
+    /// The room "Invite to room" invites to.
+    pub room: String,
+    /// Called with the trimmed query and how many matches to skip.
+    pub on_search: Callback<(String, usize)>,
+    pub on_dm: Callback<String>,
+    pub on_invite: Callback<String>,
+    pub on_close: Callback<()>,
+}
+
+/// Modal for finding someone in any room, not just the one I'm in, to
+/// message them or invite them over.
+pub struct UserSearch {
+    input: NodeRef,
+    query: String,
+    /// Pending until typing pauses.
+    debounce: Option<Timeout>,
+}
+
+impl Component for UserSearch {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        Self {
+            input: NodeRef::default(),
+            query: String::new(),
+            debounce: None,
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::Input(query) => {
+                self.query = query;
+                self.debounce = if self.query.trim().is_empty() {
+                    None
+                } else {
+                    let link = ctx.link().clone();
+                    Some(Timeout::new(DEBOUNCE_MS, move || {
+                        link.send_message(Msg::Search)
+                    }))
+                };
+                true
+            }
+            Msg::Search => {
+                self.debounce = None;
+                ctx.props()
+                    .on_search
+                    .emit((self.query.trim().to_string(), 0));
+                true
+            }
+            Msg::More => {
+                let props = ctx.props();
+                props
+                    .on_search
+                    .emit((self.query.trim().to_string(), props.results.len()));
+                false
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let props = ctx.props();
+        let close = props.on_close.reform(|_| ());
+        let query = self.query.trim();
+        let waiting = self.debounce.is_some() || props.loading;
+        let action = "shrink-0 px-2 py-1 rounded text-xs text-blue-700 dark:text-blue-300 hover:bg-blue-100 dark:hover:bg-gray-700";
+
+        html! {
+            <div class="fixed inset-0 z-20 flex items-center justify-center bg-black/40" onclick={close.clone()}>
+                <div
+                    class="w-96 max-w-[calc(100%-2rem)] bg-white dark:bg-gray-800 dark:text-gray-100 rounded-lg shadow-xl p-6"
+                    onclick={Callback::from(|e: MouseEvent| e.stop_propagation())}
+                >
+                    <div class="text-lg font-semibold text-blue-800 dark:text-blue-300 mb-4">{t("search_users.title")}</div>
+                    <div class="relative">
+                        <input
+                            ref={self.input.clone()}
+                            type="search"
+                            value={self.query.clone()}
+                            placeholder={t("search_users.placeholder")}
+                            oninput={ctx.link().callback(|e: InputEvent| Msg::Input(e.target_unchecked_into::<HtmlInputElement>().value()))}
+                            class="w-full py-2 pl-3 pr-9 border border-gray-300 dark:border-gray-600 dark:bg-gray-900 rounded-md outline-none focus:ring-2 focus:ring-blue-300"
+                        />
+                        {
+                            if waiting {
+                                html! {
+                                    <div class="absolute right-3 top-1/2 -translate-y-1/2 w-4 h-4 rounded-full border-2 border-blue-300 border-t-transparent animate-spin"></div>
+                                }
+                            } else {
+                                html! {}
+                            }
+                        }
+                    </div>
+                    <div class="mt-4 max-h-72 overflow-auto rounded-md border border-gray-200 dark:border-gray-700">
+                        {
+                            if query.is_empty() {
+                                html! {
+                                    <div class="px-3 py-4 text-center text-sm text-gray-500 dark:text-gray-400">{t("search_users.hint")}</div>
+                                }
+                            } else if props.results.is_empty() && !waiting {
+                                html! {
+                                    <div class="px-3 py-4 text-center text-sm text-gray-500 dark:text-gray-400">{t_args("search_users.none", &[&query])}</div>
+                                }
+                            } else {
+                                props.results.iter().map(|name| {
+                                    let (dm, invite) = (name.clone(), name.clone());
+                                    html! {
+                                        <div class="flex items-center gap-1 px-3 py-1">
+                                            <span class="grow truncate text-sm">{name}</span>
+                                            <button
+                                                type="button"
+                                                class={action}
+                                                title={t_args("search_users.invite_title", &[&props.room])}
+                                                onclick={props.on_invite.reform(move |_| invite.clone())}
+                                            >
+                                                {t("search_users.invite")}
+                                            </button>
+                                            <button
+                                                type="button"
+                                                class={action}
+                                                onclick={props.on_dm.reform(move |_| dm.clone())}
+                                            >
+                                                {t("search_users.dm")}
+                                            </button>
+                                        </div>
+                                    }
+                                }).collect::<Html>()
+                            }
+                        }
+                        {
+                            if props.more && !query.is_empty() {
+                                html! {
+                                    <button
+                                        type="button"
+                                        disabled={waiting}
+                                        onclick={ctx.link().callback(|_| Msg::More)}
+                                        class="block w-full px-3 py-2 text-sm text-blue-700 dark:text-blue-300 hover:bg-gray-100 dark:hover:bg-gray-700 disabled:opacity-50"
+                                    >
+                                        {t("search_users.more")}
+                                    </button>
+                                }
+                            } else {
+                                html! {}
+                            }
+                        }
+                    </div>
+                    <div class="flex justify-end mt-6">
+                        <button type="button" onclick={close} class="px-4 py-2 rounded-md text-gray-600 hover:bg-gray-100">
+                            {t("common.done")}
+                        </button>
+                    </div>
+                </div>
+            </div>
+        }
+    }
+
+    fn rendered(&mut self, _ctx: &Context<Self>, first_render: bool) {
+        if first_render {
+            if let Some(input) = self.input.cast::<HtmlInputElement>() {
+                let _ = input.focus();
+            }
+        }
+    }
+}
@@ -2,6 +2,7 @@
 
 mod components;
 mod services;
+mod utils;
 
 use std::cell::RefCell;
 use std::rc::Rc;
@@ -9,10 +10,14 @@ use std::rc::Rc;
 use wasm_bindgen::prelude::*;
 use yew::functional::*;
 use yew::prelude::*;
+use yew_agent::use_bridge;
 use yew_router::prelude::*;
 
 use components::chat::Chat;
 use components::login::Login;
+use services::event_bus::{BusEvent, EventBus};
+use utils::theme::Theme;
+use utils::translations::t;
 
 // When the `wee_alloc` feature is enabled, this uses `wee_alloc` as the global
 // allocator.
@@ -38,32 +43,58 @@ pub type User = Rc<UserInner>;
 #[derive(Debug, PartialEq)]
 pub struct UserInner {
     pub username: RefCell<String>,
+    /// Lets the server give the roles it has for `username`; empty for
+    /// everyone else.
+    pub token: RefCell<String>,
 }
 
+/// The theme shown, and setting it. Provided at the root, so switching
+/// re-renders every page in place rather than recreating it.
+pub type ThemeContext = UseStateHandle<Theme>;
+
 #[function_component(Main)]
 fn main() -> Html {
     let ctx = use_state(|| {
         Rc::new(UserInner {
             username: RefCell::new("initial".into()),
+            token: RefCell::default(),
         })
     });
+    let theme = use_state(Theme::load);
+    // The tab is titled after the room being shown.
+    let _bus = use_bridge::<EventBus, _>(|event| {
+        if let BusEvent::RoomChanged(room) = event {
+            if let Some(document) = web_sys::window().and_then(|w| w.document()) {
+                document.set_title(&format!("#{} · YewChat", room));
+            }
+        }
+    });
 
+    view((*ctx).clone(), theme)
+}
+
+/// The providers and router every page sits in.
+#[allow(clippy::unnecessary_operation)]
+fn view(user: User, theme: ThemeContext) -> Html {
     html! {
-        <ContextProvider<User> context={(*ctx).clone()}>
-            <BrowserRouter>
-                <div class="flex w-screen h-screen">
-                    <Switch<Route> render={Switch::render(switch)}/>
-                </div>
-            </BrowserRouter>
+        <ContextProvider<User> context={user}>
+            <ContextProvider<ThemeContext> context={theme.clone()}>
+                <BrowserRouter>
+                    <div class={classes!("flex", "w-screen", "h-dvh", theme.class())}>
+                        <Switch<Route> render={Switch::render(switch)}/>
+                    </div>
+                </BrowserRouter>
+            </ContextProvider<ThemeContext>>
         </ContextProvider<User>>
     }
 }
 
+#[allow(clippy::let_unit_value)]
 fn switch(selected_route: &Route) -> Html {
     match selected_route {
         Route::Login => html! {<Login />},
         Route::Chat => html! {<Chat/>},
-        Route::NotFound => html! {<h1>{"404 baby"}</h1>},
+        Route::NotFound => html! {<h1>{t("page.not_found")}</h1>},
     }
 }
 
@@ -72,4 +103,4 @@ pub fn run_app() -> Result<(), JsValue> {
     wasm_logger::init(wasm_logger::Config::default());
     yew::start_app::<Main>();
     Ok(())
-}
\ No newline at end of file
+}
@@ -0,0 +1,37 @@
+//! People I don't want to hear from. The list lives in this browser's
+//! `localStorage`; the server never learns about it, and blocked users
+//! can't tell.
+
+use std::collections::HashSet;
+
+use web_sys::Storage;
+
+/// Where the blocked names are kept, as a JSON array.
+const BLOCKED_KEY: &str = "yewchat.blocked.v1";
+
+/// The names blocked in this browser, if any.
+pub fn load() -> HashSet<String> {
+    let json = match storage().and_then(|s| s.get_item(BLOCKED_KEY).ok().flatten()) {
+        Some(json) => json,
+        None => return HashSet::new(),
+    };
+    serde_json::from_str(&json).unwrap_or_else(|e| {
+        log::warn!("blocklist: discarding unreadable list: {}", e);
+        HashSet::new()
+    })
+}
+
+pub fn save(blocked: &HashSet<String>) {
+    let storage = match storage() {
+        Some(storage) => storage,
+        None => return,
+    };
+    let json = serde_json::to_string(blocked).unwrap();
+    if let Err(e) = storage.set_item(BLOCKED_KEY, &json) {
+        log::warn!("blocklist: failed to save: {:?}", e);
+    }
+}
+
+fn storage() -> Option<Storage> {
+    web_sys::window()?.local_storage().ok().flatten()
+}
@@ -0,0 +1,160 @@
+//! Audio/video calls between two users over WebRTC. The offer, the answer
+//! and the ICE candidates travel through the chat server as `CallOffer`,
+//! `CallAnswer` and `IceCandidate` frames; the media itself goes straight
+//! from one browser to the other.
+
+use std::rc::Rc;
+
+use js_sys::{Array, Object, Reflect};
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{
+    MediaStream, MediaStreamTrack, RtcPeerConnection, RtcPeerConnectionIceEvent, RtcTrackEvent,
+};
+use yew::Callback;
+
+use crate::services::protocol::IceCandidateData;
+
+/// Public STUN server that tells each side how the other can reach it.
+const STUN_URL: &str = "stun:stun.l.google.com:19302";
+
+type Listeners = (
+    Closure<dyn FnMut(RtcPeerConnectionIceEvent)>,
+    Closure<dyn FnMut(RtcTrackEvent)>,
+);
+
+/// My side of a call: my microphone (and camera, if there is one) and the
+/// connection to the other side. Clones share the connection.
+#[derive(Clone)]
+pub struct Connection {
+    pc: RtcPeerConnection,
+    /// What my microphone and camera pick up, for a preview.
+    pub local: MediaStream,
+    _listeners: Rc<Listeners>,
+}
+
+impl Connection {
+    /// Asks for the microphone and camera and sets up a connection that
+    /// hands the ICE candidates it finds to `on_candidate` and the other
+    /// side's media to `on_stream`.
+    pub async fn open(
+        on_candidate: Callback<IceCandidateData>,
+        on_stream: Callback<MediaStream>,
+    ) -> Result<Self, JsValue> {
+        let local = user_media().await?;
+        let server = dict(&[("urls", STUN_URL.into())]);
+        let config = dict(&[("iceServers", Array::of1(&server).into())]);
+        let pc = RtcPeerConnection::new_with_configuration(config.unchecked_ref())?;
+        for track in local.get_tracks().iter() {
+            pc.add_track(track.unchecked_ref(), &local, &Array::new());
+        }
+
+        let on_ice = Closure::wrap(Box::new(move |e: RtcPeerConnectionIceEvent| {
+            // No candidate marks the end of them, which the other side
+            // doesn't need to hear about.
+            if let Some(candidate) = e.candidate() {
+                on_candidate.emit(IceCandidateData {
+                    candidate: candidate.candidate(),
+                    sdp_mid: candidate.sdp_mid(),
+                    sdp_m_line_index: candidate.sdp_m_line_index(),
+                });
+            }
+        }) as Box<dyn FnMut(_)>);
+        pc.set_onicecandidate(Some(on_ice.as_ref().unchecked_ref()));
+        let on_track = Closure::wrap(Box::new(move |e: RtcTrackEvent| {
+            if let Ok(stream) = e.streams().get(0).dyn_into::<MediaStream>() {
+                on_stream.emit(stream);
+            }
+        }) as Box<dyn FnMut(_)>);
+        pc.set_ontrack(Some(on_track.as_ref().unchecked_ref()));
+
+        Ok(Self {
+            pc,
+            local,
+            _listeners: Rc::new((on_ice, on_track)),
+        })
+    }
+
+    /// Starts a call: the SDP offer to send to the other side.
+    pub async fn offer(&self) -> Result<String, JsValue> {
+        let offer = JsFuture::from(self.pc.create_offer()).await?;
+        self.describe_locally(offer).await
+    }
+
+    /// Takes up the other side's `offer`: the SDP answer to send back.
+    pub async fn answer(&self, offer: &str) -> Result<String, JsValue> {
+        self.describe_remote("offer", offer).await?;
+        let answer = JsFuture::from(self.pc.create_answer()).await?;
+        self.describe_locally(answer).await
+    }
+
+    /// Completes a call I started with the other side's `answer`.
+    pub async fn accept(&self, answer: &str) -> Result<(), JsValue> {
+        self.describe_remote("answer", answer).await
+    }
+
+    /// Adds a candidate the other side found. Only works once its offer or
+    /// answer is in.
+    pub async fn add_candidate(&self, candidate: &IceCandidateData) -> Result<(), JsValue> {
+        let init = dict(&[
+            ("candidate", candidate.candidate.as_str().into()),
+            ("sdpMid", candidate.sdp_mid.as_deref().into()),
+            ("sdpMLineIndex", candidate.sdp_m_line_index.into()),
+        ]);
+        JsFuture::from(
+            self.pc
+                .add_ice_candidate_with_opt_rtc_ice_candidate_init(Some(init.unchecked_ref())),
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Closes the connection and lets go of the microphone and camera.
+    pub fn hang_up(&self) {
+        self.pc.close();
+        for track in self.local.get_tracks().iter() {
+            track.unchecked_into::<MediaStreamTrack>().stop();
+        }
+    }
+
+    async fn describe_locally(&self, description: JsValue) -> Result<String, JsValue> {
+        JsFuture::from(self.pc.set_local_description(description.unchecked_ref())).await?;
+        Ok(Reflect::get(&description, &"sdp".into())?
+            .as_string()
+            .unwrap_or_default())
+    }
+
+    async fn describe_remote(&self, kind: &str, sdp: &str) -> Result<(), JsValue> {
+        let description = dict(&[("type", kind.into()), ("sdp", sdp.into())]);
+        JsFuture::from(self.pc.set_remote_description(description.unchecked_ref())).await?;
+        Ok(())
+    }
+}
+
+/// My microphone and camera, or just the microphone if there's no camera.
+async fn user_media() -> Result<MediaStream, JsValue> {
+    let devices = web_sys::window()
+        .ok_or("no window")?
+        .navigator()
+        .media_devices()?;
+    let mut error = JsValue::UNDEFINED;
+    for video in [true, false] {
+        let constraints = dict(&[("audio", true.into()), ("video", video.into())]);
+        let request = devices.get_user_media_with_constraints(constraints.unchecked_ref())?;
+        match JsFuture::from(request).await {
+            Ok(stream) => return Ok(stream.unchecked_into()),
+            Err(e) => error = e,
+        }
+    }
+    Err(error)
+}
+
+/// A plain object with `entries`, for the dictionaries WebRTC takes.
+fn dict(entries: &[(&str, JsValue)]) -> Object {
+    let object = Object::new();
+    for (key, value) in entries {
+        let _ = Reflect::set(&object, &(*key).into(), value);
+    }
+    object
+}
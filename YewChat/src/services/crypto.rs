@@ -0,0 +1,130 @@
+//! End-to-end encryption of direct messages. Everyone gets an ECDH key
+//! pair on P-256 and announces the public half when registering; the key
+//! agreed with a peer encrypts what is said to them with AES-GCM.
+//!
+//! Browsers only offer `crypto.subtle` on secure origins (https or
+//! localhost), so everything here can fail, and DMs then go out as before.
+
+use js_sys::{Array, ArrayBuffer, Reflect, Uint8Array};
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{
+    AesDerivedKeyParams, AesGcmParams, CryptoKey, EcKeyGenParams, EcdhKeyDeriveParams, SubtleCrypto,
+};
+
+const CURVE: &str = "P-256";
+/// AES-GCM nonce length recommended by the spec, in bytes.
+const IV_LEN: usize = 12;
+
+/// My key pair for this session. A new one is made on every page load.
+#[derive(Clone, Debug)]
+pub struct KeyPair {
+    private: CryptoKey,
+    /// The public key as raw bytes in base64, ready to go on the wire.
+    pub public: String,
+}
+
+/// Makes a fresh key pair.
+pub async fn generate() -> Result<KeyPair, JsValue> {
+    let subtle = subtle()?;
+    let usages = Array::of1(&"deriveKey".into());
+    let pair = JsFuture::from(subtle.generate_key_with_object(
+        &EcKeyGenParams::new("ECDH", CURVE),
+        false,
+        &usages,
+    )?)
+    .await?;
+    let private: CryptoKey = Reflect::get(&pair, &"privateKey".into())?.unchecked_into();
+    let public: CryptoKey = Reflect::get(&pair, &"publicKey".into())?.unchecked_into();
+    let raw: ArrayBuffer = JsFuture::from(subtle.export_key("raw", &public)?)
+        .await?
+        .unchecked_into();
+    Ok(KeyPair {
+        private,
+        public: to_base64(&Uint8Array::new(&raw).to_vec())?,
+    })
+}
+
+/// The AES-GCM key shared with the owner of `peer_public`, a public key as
+/// sent by `generate`.
+pub async fn derive(mine: &KeyPair, peer_public: &str) -> Result<CryptoKey, JsValue> {
+    let subtle = subtle()?;
+    let raw = Uint8Array::from(from_base64(peer_public)?.as_slice());
+    // The same name and curve the pair was made with.
+    let params = EcKeyGenParams::new("ECDH", CURVE);
+    let peer: CryptoKey =
+        JsFuture::from(subtle.import_key_with_object("raw", &raw, &params, true, &Array::new())?)
+            .await?
+            .unchecked_into();
+    let usages = Array::of2(&"encrypt".into(), &"decrypt".into());
+    let key = JsFuture::from(subtle.derive_key_with_object_and_object(
+        &EcdhKeyDeriveParams::new("ECDH", &peer),
+        &mine.private,
+        &AesDerivedKeyParams::new("AES-GCM", 256),
+        false,
+        &usages,
+    )?)
+    .await?;
+    Ok(key.unchecked_into())
+}
+
+/// Encrypts `text` with `key`, as base64 of a random nonce followed by the
+/// ciphertext.
+pub async fn encrypt(key: &CryptoKey, text: &str) -> Result<String, JsValue> {
+    let mut iv = [0u8; IV_LEN];
+    web_sys::window()
+        .ok_or("no window")?
+        .crypto()?
+        .get_random_values_with_u8_array(&mut iv)?;
+    let params = AesGcmParams::new("AES-GCM", &Uint8Array::from(&iv[..]));
+    let sealed: ArrayBuffer = JsFuture::from(subtle()?.encrypt_with_object_and_buffer_source(
+        &params,
+        key,
+        &Uint8Array::from(text.as_bytes()),
+    )?)
+    .await?
+    .unchecked_into();
+    let mut bytes = iv.to_vec();
+    bytes.extend(Uint8Array::new(&sealed).to_vec());
+    to_base64(&bytes)
+}
+
+/// Reverses `encrypt`. Fails if `data` was tampered with or encrypted with
+/// another key.
+pub async fn decrypt(key: &CryptoKey, data: &str) -> Result<String, JsValue> {
+    let bytes = from_base64(data)?;
+    if bytes.len() < IV_LEN {
+        return Err("ciphertext too short".into());
+    }
+    let (iv, sealed) = bytes.split_at(IV_LEN);
+    let params = AesGcmParams::new("AES-GCM", &Uint8Array::from(iv));
+    let opened: ArrayBuffer = JsFuture::from(subtle()?.decrypt_with_object_and_buffer_source(
+        &params,
+        key,
+        &Uint8Array::from(sealed),
+    )?)
+    .await?
+    .unchecked_into();
+    String::from_utf8(Uint8Array::new(&opened).to_vec()).map_err(|e| e.to_string().into())
+}
+
+/// `crypto.subtle`, which is missing outside secure contexts.
+fn subtle() -> Result<SubtleCrypto, JsValue> {
+    let crypto = web_sys::window().ok_or("no window")?.crypto()?;
+    let subtle = Reflect::get(&crypto, &"subtle".into())?;
+    if subtle.is_undefined() {
+        return Err("crypto.subtle is unavailable; is the page served securely?".into());
+    }
+    Ok(subtle.unchecked_into())
+}
+
+fn to_base64(bytes: &[u8]) -> Result<String, JsValue> {
+    let binary: String = bytes.iter().map(|&b| b as char).collect();
+    web_sys::window().ok_or("no window")?.btoa(&binary)
+}
+
+fn from_base64(data: &str) -> Result<Vec<u8>, JsValue> {
+    let binary = web_sys::window().ok_or("no window")?.atob(data)?;
+    // atob only ever gives back characters up to U+00FF, one per byte.
+    Ok(binary.chars().map(|c| c as u8).collect())
+}
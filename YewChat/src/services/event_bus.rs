@@ -1,10 +1,119 @@
-use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use yew_agent::{Agent, AgentLink, Context, HandlerId};
 
-#[derive(Serialize, Deserialize, Debug)]
-pub enum Request {
-    EventBusMsg(String),
+use crate::services::protocol::{
+    CallSignal, EditData, FileChunk, LinkPreviewData, MessageData, ModerationData, NameReserved,
+    PresenceData, ProfileData, PublicKeyData, ReadReceipt, RoomData, RoomError, UserSearchResults,
+};
+use crate::services::websocket::ConnectionStatus;
+
+/// Everything the websocket service has to tell the UI, already decoded,
+/// and what components tell each other without involving the server.
+#[derive(Debug, Clone)]
+pub enum BusEvent {
+    /// Members of `room`, or of the sender's room for servers that don't
+    /// tag the list.
+    Users {
+        room: Option<String>,
+        users: Vec<String>,
+    },
+    Message {
+        room: Option<String>,
+        message: MessageData,
+    },
+    /// `to` is only set on the copy echoed back to the sender.
+    DirectMessage {
+        to: Option<String>,
+        message: MessageData,
+    },
+    /// Reactions on message `id` changed to `reactions`.
+    Reactions {
+        room: Option<String>,
+        id: u64,
+        reactions: HashMap<String, Vec<String>>,
+    },
+    /// The sender of a room message changed its text.
+    Edited {
+        room: Option<String>,
+        edit: EditData,
+    },
+    /// The sender of room message `id` deleted it.
+    Deleted {
+        room: Option<String>,
+        id: u64,
+    },
+    /// Room message `id` was reported enough times to be hidden.
+    Hidden {
+        room: Option<String>,
+        id: u64,
+    },
+    /// `message` was pinned in `room`.
+    Pinned {
+        room: Option<String>,
+        message: MessageData,
+    },
+    Unpinned {
+        room: Option<String>,
+        id: u64,
+    },
+    LinkPreview(LinkPreviewData),
+    /// A piece of a file or voice message someone is sharing in `room`.
+    FileChunk {
+        room: Option<String>,
+        chunk: FileChunk,
+    },
+    /// A moderator disconnected `target`, and banned them if there's a
+    /// `duration_minutes`.
+    Moderated {
+        room: Option<String>,
+        action: ModerationData,
+    },
+    Typing(String),
+    Presence(PresenceData),
+    Profile(ProfileData),
+    /// Someone is calling me.
+    CallOffer(CallSignal),
+    /// The user I called picked up.
+    CallAnswer(CallSignal),
+    IceCandidate(CallSignal),
+    /// The other side declined, cancelled or hung up the call.
+    CallEnd(CallSignal),
+    /// Someone announced the key to encrypt direct messages to them with.
+    PublicKey(PublicKeyData),
+    /// A page of `room`'s messages, oldest first: the latest ones, or those
+    /// older than message `before`. `more` tells whether older ones exist.
+    History {
+        room: Option<String>,
+        before: Option<u64>,
+        more: bool,
+        messages: Vec<MessageData>,
+    },
+    Read {
+        room: Option<String>,
+        receipt: ReadReceipt,
+    },
+    /// The last message I had read in each room, by room name, as the
+    /// server remembers it.
+    ReadSync(HashMap<String, u64>),
+    Rooms(Vec<String>),
+    /// The room I asked for was created.
+    RoomCreated(RoomData),
+    /// The settings of the room just joined.
+    RoomInfo(RoomData),
+    RoomError(RoomError),
+    /// The name I registered as, or renamed to, is kept for staff.
+    NameReserved(NameReserved),
+    /// A page of the users matching a search of mine.
+    UserSearchResults(UserSearchResults),
+    ConnectionChanged(ConnectionStatus),
+    /// Round-trip time of the latest heartbeat, in milliseconds.
+    Latency(u32),
+    /// A frame from the server that couldn't be decoded.
+    Unreadable,
+    /// A binary frame from the server, such as a piece of a file.
+    Binary(Vec<u8>),
+    /// Published by the chat rather than the server: the room now shown.
+    RoomChanged(String),
 }
 
 pub struct EventBus {
@@ -15,8 +124,8 @@ pub struct EventBus {
 impl Agent for EventBus {
     type Reach = Context<Self>;
     type Message = ();
-    type Input = Request;
-    type Output = String;
+    type Input = BusEvent;
+    type Output = BusEvent;
 
     fn create(link: AgentLink<Self>) -> Self {
         Self {
@@ -27,13 +136,10 @@ impl Agent for EventBus {
 
     fn update(&mut self, _msg: Self::Message) {}
 
-    fn handle_input(&mut self, msg: Self::Input, _id: HandlerId) {
-        match msg {
-            Request::EventBusMsg(s) => {
-                for sub in self.subscribers.iter() {
-                    self.link.respond(*sub, s.clone())
-                }
-            }
+    /// Hands `event` to every subscriber but the one that published it.
+    fn handle_input(&mut self, event: Self::Input, id: HandlerId) {
+        for sub in self.subscribers.iter().filter(|sub| **sub != id) {
+            self.link.respond(*sub, event.clone())
         }
     }
 
@@ -44,4 +150,4 @@ impl Agent for EventBus {
     fn disconnected(&mut self, id: HandlerId) {
         self.subscribers.remove(&id);
     }
-}
\ No newline at end of file
+}
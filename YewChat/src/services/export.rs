@@ -0,0 +1,163 @@
+//! Saving the thread on screen as a file: a plain-text log to read, or the
+//! messages as JSON to process further. Neither has more of a hidden
+//! message than the view shows.
+
+use gloo_timers::callback::Timeout;
+use js_sys::{Array, Object, Reflect};
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{Blob, HtmlAnchorElement, Url};
+
+use crate::services::protocol::{MessageData, MessageKind};
+use crate::utils::time;
+
+/// How long the browser gets to start the download before its `blob:` URL
+/// is revoked, in ms.
+const REVOKE_AFTER_MS: u32 = 1_000;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExportFormat {
+    Text,
+    Json,
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 2] = [ExportFormat::Text, ExportFormat::Json];
+
+    /// Translation key of its name in the export menu.
+    pub fn label_key(self) -> &'static str {
+        match self {
+            ExportFormat::Text => "export.text",
+            ExportFormat::Json => "export.json",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Text => "txt",
+            ExportFormat::Json => "json",
+        }
+    }
+
+    fn mime(self) -> &'static str {
+        match self {
+            ExportFormat::Text => "text/plain;charset=utf-8",
+            ExportFormat::Json => "application/json",
+        }
+    }
+
+    /// `messages` written out in this format.
+    pub fn export(self, messages: &[MessageData]) -> String {
+        match self {
+            ExportFormat::Text => export_as_text(messages),
+            ExportFormat::Json => export_as_json(messages),
+        }
+    }
+}
+
+/// One line per message, "[time] name: text", with the lines of longer
+/// messages indented under it and notices marked with a `*`.
+pub fn export_as_text(messages: &[MessageData]) -> String {
+    let mut log = String::new();
+    for m in messages {
+        let stamp = m.sent_at.map(time::full_label).unwrap_or_default();
+        let body = if m.hidden {
+            "[hidden]".to_string()
+        } else if m.file.is_some() {
+            format!("[file] {}", m.message)
+        } else {
+            m.message.clone()
+        };
+        let mut lines = body.lines();
+        let first = lines.next().unwrap_or_default();
+        match m.kind {
+            MessageKind::System => log.push_str(&format!("[{}] * {}", stamp, first)),
+            MessageKind::Chat => log.push_str(&format!("[{}] {}: {}", stamp, m.from, first)),
+        }
+        for line in lines {
+            log.push_str("\n    ");
+            log.push_str(line);
+        }
+        if m.edited && !m.deleted {
+            log.push_str(" (edited)");
+        }
+        log.push('\n');
+    }
+    log
+}
+
+/// The messages as they came from the server, as a JSON array, with what
+/// hidden ones said left out.
+pub fn export_as_json(messages: &[MessageData]) -> String {
+    let messages: Vec<MessageData> = messages
+        .iter()
+        .map(|m| {
+            let mut m = m.clone();
+            if m.hidden {
+                m.message.clear();
+                m.file = None;
+            }
+            m
+        })
+        .collect();
+    serde_json::to_string_pretty(&messages).unwrap()
+}
+
+/// Has the browser save `contents` as `file_name`.
+pub fn download(file_name: &str, format: ExportFormat, contents: &str) -> Result<(), JsValue> {
+    let options = Object::new();
+    Reflect::set(&options, &"type".into(), &format.mime().into())?;
+    let blob = Blob::new_with_str_sequence_and_options(
+        &Array::of1(&contents.into()),
+        options.unchecked_ref(),
+    )?;
+    let url = Url::create_object_url_with_blob(&blob)?;
+
+    let document = web_sys::window()
+        .and_then(|w| w.document())
+        .ok_or("no document")?;
+    let link: HtmlAnchorElement = document.create_element("a")?.unchecked_into();
+    link.set_href(&url);
+    link.set_download(file_name);
+    // Some browsers only follow links that are in the page.
+    let body = document.body().ok_or("no body")?;
+    body.append_child(&link)?;
+    link.click();
+    link.remove();
+
+    Timeout::new(REVOKE_AFTER_MS, move || {
+        let _ = Url::revoke_object_url(&url);
+    })
+    .forget();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(json: &str) -> MessageData {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn hidden_messages_are_left_out_of_the_text() {
+        let messages = [
+            message(r#"{"id":1,"from":"ana","message":"hi"}"#),
+            message(r#"{"id":2,"from":"budi","message":"rude","hidden":true}"#),
+        ];
+        assert_eq!(export_as_text(&messages), "[] ana: hi\n[] budi: [hidden]\n");
+    }
+
+    #[test]
+    fn hidden_messages_are_left_out_of_the_json() {
+        let messages = [
+            message(r#"{"id":1,"from":"ana","message":"hi"}"#),
+            message(r#"{"id":2,"from":"budi","message":"rude.png","file":"f1","hidden":true}"#),
+        ];
+        let exported: Vec<MessageData> = serde_json::from_str(&export_as_json(&messages)).unwrap();
+        assert_eq!(exported[0], messages[0]);
+        assert_eq!(exported[1].message, "");
+        assert_eq!(exported[1].file, None);
+        assert!(exported[1].hidden);
+    }
+}
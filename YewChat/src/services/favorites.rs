@@ -0,0 +1,52 @@
+//! Room messages I've starred to find again. Like the blocklist they live
+//! in this browser's `localStorage`, as copies, so they stay readable after
+//! the message has dropped out of the room's history.
+
+use serde::{Deserialize, Serialize};
+use web_sys::Storage;
+
+use crate::services::protocol::MessageData;
+
+/// Where the favorites are kept, as a JSON array.
+const FAVORITES_KEY: &str = "yewchat.favorites.v1";
+
+/// A starred message and the room it was said in.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Favorite {
+    pub room: String,
+    pub message: MessageData,
+}
+
+impl Favorite {
+    /// Whether this is room message `id` of `room`.
+    pub fn is(&self, room: &str, id: Option<u64>) -> bool {
+        self.room == room && id.is_some() && self.message.id == id
+    }
+}
+
+/// The favorites saved in this browser, oldest first.
+pub fn load() -> Vec<Favorite> {
+    let json = match storage().and_then(|s| s.get_item(FAVORITES_KEY).ok().flatten()) {
+        Some(json) => json,
+        None => return vec![],
+    };
+    serde_json::from_str(&json).unwrap_or_else(|e| {
+        log::warn!("favorites: discarding unreadable list: {}", e);
+        vec![]
+    })
+}
+
+pub fn save(favorites: &[Favorite]) {
+    let storage = match storage() {
+        Some(storage) => storage,
+        None => return,
+    };
+    let json = serde_json::to_string(favorites).unwrap();
+    if let Err(e) = storage.set_item(FAVORITES_KEY, &json) {
+        log::warn!("favorites: failed to save: {:?}", e);
+    }
+}
+
+fn storage() -> Option<Storage> {
+    web_sys::window()?.local_storage().ok().flatten()
+}
@@ -0,0 +1,78 @@
+//! GIF search. Queries go to a proxy holding the Giphy API key, by default
+//! the chat server's `/gifs` endpoint, which answers with
+//! `{ "data": [{ "url": ..., "preview_url": ... }] }`.
+
+use reqwasm::http::Request;
+use serde::Deserialize;
+use wasm_bindgen::JsValue;
+
+use crate::services::websocket;
+
+/// `<meta name="gif-search-url" content="...">` overrides the chat server.
+/// `{query}` stands for the search terms.
+const ENDPOINT_META_SELECTOR: &str = "meta[name=gif-search-url]";
+/// As does `gifSearchUrl` in this global config object.
+const ENDPOINT_GLOBAL_CONFIG: &str = "YEWCHAT_CONFIG";
+/// Path of the proxy on the chat server.
+const SERVER_PATH: &str = "/gifs?q={query}";
+
+/// One search result.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct Gif {
+    /// The GIF itself, what gets sent.
+    pub url: String,
+    /// A smaller version for the picker.
+    pub preview_url: String,
+}
+
+#[derive(Deserialize)]
+struct Results {
+    data: Vec<Gif>,
+}
+
+/// GIFs matching `query`, best first.
+pub async fn search(query: &str) -> Result<Vec<Gif>, String> {
+    let terms = String::from(js_sys::encode_uri_component(query));
+    let response = Request::get(&endpoint().replace("{query}", &terms))
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    if !response.ok() {
+        return Err(format!("HTTP {}", response.status()));
+    }
+    let results: Results = response.json().await.map_err(|e| e.to_string())?;
+    Ok(results.data)
+}
+
+/// The search endpoint, first match wins: the `<meta>` tag, the global
+/// config, the `GIF_SEARCH_URL` environment variable at build time, or the
+/// chat server over http(s).
+fn endpoint() -> String {
+    let from_meta = web_sys::window()
+        .and_then(|w| w.document())
+        .and_then(|doc| doc.query_selector(ENDPOINT_META_SELECTOR).ok().flatten())
+        .and_then(|meta| meta.get_attribute("content"));
+
+    let from_global = || {
+        let config =
+            js_sys::Reflect::get(&js_sys::global(), &ENDPOINT_GLOBAL_CONFIG.into()).ok()?;
+        if config.is_undefined() || config.is_null() {
+            return None;
+        }
+        js_sys::Reflect::get(&config, &JsValue::from_str("gifSearchUrl"))
+            .ok()?
+            .as_string()
+    };
+
+    from_meta
+        .or_else(from_global)
+        .or_else(|| option_env!("GIF_SEARCH_URL").map(String::from))
+        .map(|url| url.trim().to_string())
+        .filter(|url| !url.is_empty())
+        .unwrap_or_else(|| {
+            // ws://host:port becomes http://host:port, wss:// https://.
+            let server = websocket::resolve_url();
+            let server = server.strip_prefix("ws").unwrap_or(&server);
+            format!("http{}{}", server.trim_end_matches('/'), SERVER_PATH)
+        })
+}
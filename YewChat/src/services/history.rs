@@ -0,0 +1,233 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use gloo_timers::callback::Timeout;
+use js_sys::{Array, Promise};
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::{spawn_local, JsFuture};
+use web_sys::{IdbDatabase, IdbKeyRange, IdbRequest, IdbTransaction, IdbTransactionMode};
+
+use crate::services::protocol::MessageData;
+
+const DB_NAME: &str = "yewchat";
+const DB_VERSION: u32 = 2;
+/// Object store holding one JSON-encoded message per record, see
+/// `record_key`.
+const STORE: &str = "messages";
+/// Where version 1 kept each history as a single list. It's only a cache,
+/// so it's dropped rather than converted.
+const OLD_STORE: &str = "history";
+/// Only the most recent messages of a room are kept on disk.
+const HISTORY_LIMIT: usize = 200;
+/// How long changes are collected before they're written, in ms.
+const SAVE_DEBOUNCE_MS: u32 = 1_000;
+/// Separates the history key from the message's in a record key. Sorts
+/// below anything printable, so a history's records form one key range.
+const KEY_SEPARATOR: char = '\u{1f}';
+
+thread_local! {
+    /// Opened on first use and kept open, unless another tab needs to
+    /// upgrade the database.
+    static DB: RefCell<Option<IdbDatabase>> = const { RefCell::new(None) };
+    /// What each history has on disk, or will once `PENDING` is written:
+    /// its messages by `MessageData::dedup_key`.
+    static STORED: RefCell<HashMap<String, HashMap<String, MessageData>>> =
+        RefCell::new(HashMap::new());
+    /// Records to write on the next flush, by record key: the JSON to put,
+    /// or `None` to delete it.
+    static PENDING: RefCell<HashMap<String, Option<String>>> = RefCell::new(HashMap::new());
+    static FLUSH: RefCell<Option<Timeout>> = const { RefCell::new(None) };
+}
+
+/// Key a room's history is stored under. Histories are per user so that
+/// logging in under another name on the same browser starts fresh.
+pub fn key(room: &str, username: &str) -> String {
+    format!("{}/{}", username, room)
+}
+
+/// Reads the history stored under `key`, oldest first. Anything going
+/// wrong (no IndexedDB, corrupt entry, ...) is logged and treated as an
+/// empty history.
+pub async fn load(key: &str) -> Vec<MessageData> {
+    match get_all(key).await {
+        Ok(messages) => {
+            let stored = messages
+                .iter()
+                .map(|m| (m.dedup_key(), m.clone()))
+                .collect();
+            STORED.with(|s| s.borrow_mut().insert(key.to_string(), stored));
+            messages
+        }
+        Err(e) => {
+            log::warn!("history: failed to load {}: {:?}", key, e);
+            vec![]
+        }
+    }
+}
+
+/// Stores the last `HISTORY_LIMIT` of `messages` under `key`. Only the
+/// messages that changed since the last save are encoded, and they're
+/// written in the background a little later, together with whatever else
+/// changed meanwhile; failures are only logged.
+pub fn save(key: String, messages: &[MessageData]) {
+    let start = messages.len().saturating_sub(HISTORY_LIMIT);
+    let mut writes = Vec::new();
+    STORED.with(|stored| {
+        let mut stored = stored.borrow_mut();
+        let stored = stored.entry(key.clone()).or_default();
+        let mut kept = HashMap::with_capacity(stored.len());
+        for message in &messages[start..] {
+            let id = message.dedup_key();
+            match stored.remove(&id) {
+                Some(previous) if previous == *message => {
+                    kept.insert(id, previous);
+                }
+                _ => match serde_json::to_string(message) {
+                    Ok(json) => {
+                        writes.push((record_key(&key, &id), Some(json)));
+                        kept.insert(id, message.clone());
+                    }
+                    Err(e) => log::warn!("history: failed to encode {}: {}", id, e),
+                },
+            }
+        }
+        // Whatever is left has dropped out of the window.
+        writes.extend(stored.keys().map(|id| (record_key(&key, id), None)));
+        *stored = kept;
+    });
+    if writes.is_empty() {
+        return;
+    }
+    PENDING.with(|pending| pending.borrow_mut().extend(writes));
+    FLUSH.with(|flush| {
+        flush.borrow_mut().get_or_insert_with(|| {
+            Timeout::new(SAVE_DEBOUNCE_MS, || {
+                FLUSH.with(|flush| flush.borrow_mut().take());
+                spawn_local(async {
+                    if let Err(e) = flush_pending().await {
+                        log::warn!("history: failed to save: {:?}", e);
+                        // Unsure what made it to disk; the next save of
+                        // each history writes all of it again.
+                        STORED.with(|stored| stored.borrow_mut().clear());
+                    }
+                });
+            })
+        });
+    });
+}
+
+fn record_key(key: &str, id: &str) -> String {
+    format!("{}{}{}", key, KEY_SEPARATOR, id)
+}
+
+async fn get_all(key: &str) -> Result<Vec<MessageData>, JsValue> {
+    let db = database().await?;
+    let store = db.transaction_with_str(STORE)?.object_store(STORE)?;
+    let prefix = format!("{}{}", key, KEY_SEPARATOR);
+    // The character after the separator.
+    let end = format!("{}{}", key, '\u{20}');
+    let range = IdbKeyRange::bound_with_lower_open_and_upper_open(
+        &prefix.into(),
+        &end.into(),
+        false,
+        true,
+    )?;
+    let values: Array = wait(&store.get_all_with_key(&range)?)
+        .await?
+        .unchecked_into();
+    let mut messages: Vec<MessageData> = values
+        .iter()
+        .filter_map(|value| {
+            let json = value.as_string()?;
+            serde_json::from_str(&json)
+                .map_err(|e| log::warn!("history: discarding unreadable entry in {}: {}", key, e))
+                .ok()
+        })
+        .collect();
+    messages.sort_by_key(|m| m.sent_at.unwrap_or_default());
+    Ok(messages)
+}
+
+/// Writes out everything pending, in one transaction.
+async fn flush_pending() -> Result<(), JsValue> {
+    let pending = PENDING.with(|pending| std::mem::take(&mut *pending.borrow_mut()));
+    if pending.is_empty() {
+        return Ok(());
+    }
+    let db = database().await?;
+    let transaction = db.transaction_with_str_and_mode(STORE, IdbTransactionMode::Readwrite)?;
+    let store = transaction.object_store(STORE)?;
+    for (record, json) in pending {
+        let record = JsValue::from(record);
+        match json {
+            Some(json) => store.put_with_key(&JsValue::from(json), &record)?,
+            None => store.delete(&record)?,
+        };
+    }
+    committed(&transaction).await
+}
+
+/// The open database, opening it first if need be.
+async fn database() -> Result<IdbDatabase, JsValue> {
+    if let Some(db) = DB.with(|db| db.borrow().clone()) {
+        return Ok(db);
+    }
+    let db = open().await?;
+    // Lets another tab with a newer version upgrade; the next use reopens.
+    let closing = db.clone();
+    let on_version_change = Closure::once_into_js(move || {
+        closing.close();
+        DB.with(|db| db.borrow_mut().take());
+    });
+    db.set_onversionchange(Some(on_version_change.unchecked_ref()));
+    DB.with(|cached| *cached.borrow_mut() = Some(db.clone()));
+    Ok(db)
+}
+
+async fn open() -> Result<IdbDatabase, JsValue> {
+    let factory = web_sys::window()
+        .ok_or_else(|| JsValue::from_str("no window"))?
+        .indexed_db()?
+        .ok_or_else(|| JsValue::from_str("IndexedDB is not available"))?;
+    let request = factory.open_with_u32(DB_NAME, DB_VERSION)?;
+
+    // Only fires when the database doesn't exist yet or is older.
+    let upgrading = request.clone();
+    let on_upgrade = Closure::once_into_js(move || {
+        let db = upgrading
+            .result()
+            .map(JsCast::unchecked_into::<IdbDatabase>);
+        let created = db.and_then(|db| {
+            // Not there on a fresh database, which is fine.
+            let _ = db.delete_object_store(OLD_STORE);
+            db.create_object_store(STORE)
+        });
+        if let Err(e) = created {
+            log::warn!("history: failed to create store: {:?}", e);
+        }
+    });
+    request.set_onupgradeneeded(Some(on_upgrade.unchecked_ref()));
+
+    Ok(wait(&request).await?.unchecked_into())
+}
+
+/// Resolves once `request` succeeds, with its result.
+async fn wait(request: &IdbRequest) -> Result<JsValue, JsValue> {
+    let promise = Promise::new(&mut |resolve, reject| {
+        request.set_onsuccess(Some(&resolve));
+        request.set_onerror(Some(&reject));
+    });
+    JsFuture::from(promise).await?;
+    request.result()
+}
+
+/// Resolves once `transaction` has been committed.
+async fn committed(transaction: &IdbTransaction) -> Result<(), JsValue> {
+    let promise = Promise::new(&mut |resolve, reject| {
+        transaction.set_oncomplete(Some(&resolve));
+        transaction.set_onerror(Some(&reject));
+        transaction.set_onabort(Some(&reject));
+    });
+    JsFuture::from(promise).await.map(|_| ())
+}
@@ -1,2 +1,14 @@
 pub mod websocket;
-pub mod event_bus;
\ No newline at end of file
+pub mod blocklist;
+pub mod call;
+pub mod crypto;
+pub mod event_bus;
+pub mod export;
+pub mod favorites;
+pub mod gifs;
+pub mod history;
+pub mod notify;
+pub mod preview;
+pub mod protocol;
+pub mod unread;
+pub mod voice;
\ No newline at end of file
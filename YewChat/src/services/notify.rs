@@ -0,0 +1,231 @@
+use std::cell::Cell;
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{
+    HtmlAudioElement, Notification, NotificationOptions, NotificationPermission, Storage,
+};
+use yew::Callback;
+
+/// Where `NotificationPrefs` are kept, as JSON. The version goes up when
+/// the format changes in a way older entries can't be read as.
+const PREFS_KEY: &str = "yewchat.notify.v1";
+/// Where the two switches lived before `PREFS_KEY`, as "on"/"off".
+const LEGACY_SOUND_KEY: &str = "yewchat.notify.sound";
+const LEGACY_DESKTOP_KEY: &str = "yewchat.notify.desktop";
+
+/// Which alerts to raise for messages arriving while the tab is hidden.
+/// Everything is off until the user turns it on.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct NotificationPrefs {
+    pub sound_enabled: bool,
+    pub desktop_enabled: bool,
+    /// Only alert for messages that mention me.
+    pub mentions_only: bool,
+}
+
+impl NotificationPrefs {
+    /// Reads the preferences saved in `localStorage`, if any.
+    pub fn load() -> Self {
+        let storage = match storage() {
+            Some(storage) => storage,
+            None => return Self::default(),
+        };
+        if let Some(json) = storage.get_item(PREFS_KEY).ok().flatten() {
+            return serde_json::from_str(&json).unwrap_or_else(|e| {
+                log::warn!("notify: discarding unreadable preferences: {}", e);
+                Self::default()
+            });
+        }
+        let enabled = |key| {
+            storage
+                .get_item(key)
+                .ok()
+                .flatten()
+                .is_some_and(|value| value == "on")
+        };
+        Self {
+            sound_enabled: enabled(LEGACY_SOUND_KEY),
+            desktop_enabled: enabled(LEGACY_DESKTOP_KEY),
+            mentions_only: false,
+        }
+    }
+
+    pub fn save(&self) {
+        let storage = match storage() {
+            Some(storage) => storage,
+            None => return,
+        };
+        let json = serde_json::to_string(self).unwrap();
+        if let Err(e) = storage.set_item(PREFS_KEY, &json) {
+            log::warn!("notify: failed to save preferences: {:?}", e);
+        }
+    }
+}
+
+fn storage() -> Option<Storage> {
+    web_sys::window()?.local_storage().ok().flatten()
+}
+
+/// The chime, served next to `index.html`.
+const SOUND_URL: &str = "notification.wav";
+/// However many messages come in, the chime plays at most once this often.
+const SOUND_INTERVAL_MS: f64 = 2_000.0;
+/// Interactions that let a page play audio from then on.
+const UNLOCK_EVENTS: [&str; 2] = ["pointerdown", "keydown"];
+
+/// The notification chime. Browsers only let a page play audio once the
+/// user has interacted with it, so it stays silent until the first click
+/// or key press, which also primes the element for later.
+pub struct Sound {
+    audio: HtmlAudioElement,
+    unlocked: Rc<Cell<bool>>,
+    /// When it last played, in epoch ms.
+    last_played: f64,
+    on_interaction: Closure<dyn Fn()>,
+}
+
+impl Sound {
+    pub fn new() -> Result<Self, JsValue> {
+        let audio = HtmlAudioElement::new_with_src(SOUND_URL)?;
+        audio.set_preload("auto");
+        let unlocked = Rc::new(Cell::new(false));
+
+        let (element, flag) = (audio.clone(), unlocked.clone());
+        let on_interaction = Closure::wrap(Box::new(move || {
+            if flag.replace(true) {
+                return;
+            }
+            // Playing it muted for a moment is what counts as a user
+            // started playback.
+            element.set_muted(true);
+            if let Ok(promise) = element.play() {
+                let element = element.clone();
+                wasm_bindgen_futures::spawn_local(async move {
+                    let _ = JsFuture::from(promise).await;
+                    element.pause().ok();
+                    element.set_current_time(0.0);
+                    element.set_muted(false);
+                });
+            }
+        }) as Box<dyn Fn()>);
+        if let Some(window) = web_sys::window() {
+            for event in UNLOCK_EVENTS {
+                let _ = window.add_event_listener_with_callback(
+                    event,
+                    on_interaction.as_ref().unchecked_ref(),
+                );
+            }
+        }
+
+        Ok(Self {
+            audio,
+            unlocked,
+            last_played: f64::NEG_INFINITY,
+            on_interaction,
+        })
+    }
+
+    /// Plays the chime, unless the page may not play audio yet or it
+    /// played less than `SOUND_INTERVAL_MS` ago.
+    pub fn play(&mut self) {
+        let now = js_sys::Date::now();
+        if !self.unlocked.get() || now - self.last_played < SOUND_INTERVAL_MS {
+            return;
+        }
+        self.last_played = now;
+        self.audio.set_current_time(0.0);
+        match self.audio.play() {
+            Ok(promise) => wasm_bindgen_futures::spawn_local(async move {
+                if let Err(e) = JsFuture::from(promise).await {
+                    log::warn!("notify: failed to play sound: {:?}", e);
+                }
+            }),
+            Err(e) => log::warn!("notify: failed to play sound: {:?}", e),
+        }
+    }
+}
+
+impl Drop for Sound {
+    fn drop(&mut self) {
+        if let Some(window) = web_sys::window() {
+            for event in UNLOCK_EVENTS {
+                let _ = window.remove_event_listener_with_callback(
+                    event,
+                    self.on_interaction.as_ref().unchecked_ref(),
+                );
+            }
+        }
+    }
+}
+
+/// Whether the browser has the Notification API at all; touching it
+/// where it doesn't throws.
+fn supported() -> bool {
+    js_sys::Reflect::has(&js_sys::global(), &"Notification".into()).unwrap_or(false)
+}
+
+/// Whether the browser lets us show desktop notifications.
+pub fn desktop_allowed() -> bool {
+    supported() && Notification::permission() == NotificationPermission::Granted
+}
+
+/// Whether the user has yet to be asked about desktop notifications.
+pub fn permission_undecided() -> bool {
+    supported() && Notification::permission() == NotificationPermission::Default
+}
+
+/// Asks the user for permission to show desktop notifications, unless
+/// they've answered already. Resolves to whether it was granted.
+pub async fn request_permission() -> bool {
+    if !permission_undecided() {
+        return desktop_allowed();
+    }
+    match Notification::request_permission() {
+        Ok(promise) => match JsFuture::from(promise).await {
+            Ok(answer) => answer.as_string().as_deref() == Some("granted"),
+            Err(e) => {
+                log::warn!("notify: permission request failed: {:?}", e);
+                false
+            }
+        },
+        Err(e) => {
+            log::warn!("notify: notifications not supported: {:?}", e);
+            false
+        }
+    }
+}
+
+/// Shows a desktop notification with `icon`, if allowed. `tag` makes a
+/// newer notification replace an older one with the same tag. Clicking it
+/// brings the chat window forward and calls `on_click`.
+pub fn show(title: &str, body: &str, tag: &str, icon: &str, on_click: Callback<()>) {
+    if !desktop_allowed() {
+        return;
+    }
+    let options = NotificationOptions::new();
+    options.set_body(body);
+    options.set_tag(tag);
+    options.set_icon(icon);
+    let notification = match Notification::new_with_options(title, &options) {
+        Ok(notification) => notification,
+        Err(e) => {
+            log::warn!("notify: failed to show notification: {:?}", e);
+            return;
+        }
+    };
+    let shown = notification.clone();
+    // Freed once clicked; one that never is stays around, which is small.
+    let onclick = Closure::once_into_js(move || {
+        if let Some(window) = web_sys::window() {
+            let _ = window.focus();
+        }
+        shown.close();
+        on_click.emit(());
+    });
+    notification.set_onclick(Some(onclick.unchecked_ref()));
+}
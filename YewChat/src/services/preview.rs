@@ -0,0 +1,103 @@
+use futures::future::{self, Either};
+use gloo_timers::future::TimeoutFuture;
+use reqwasm::http::Request;
+use serde::Deserialize;
+use wasm_bindgen::JsValue;
+
+use crate::services::protocol::LinkPreviewData;
+
+/// `<meta name="link-preview-url" content="...">` sets the oEmbed endpoint
+/// previews are fetched from, `{url}` standing for the link. Without one,
+/// links aren't sent anywhere.
+const ENDPOINT_META_SELECTOR: &str = "meta[name=link-preview-url]";
+/// As does `linkPreviewUrl` in this global config object.
+const ENDPOINT_GLOBAL_CONFIG: &str = "YEWCHAT_CONFIG";
+/// Previews that take longer than this are given up on.
+const TIMEOUT_MS: u32 = 5_000;
+
+/// What an oEmbed-style endpoint has to say about a link.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct Preview {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    #[serde(rename = "thumbnail_url", alias = "image")]
+    pub thumbnail: Option<String>,
+    pub provider_name: Option<String>,
+}
+
+impl From<LinkPreviewData> for Preview {
+    fn from(data: LinkPreviewData) -> Self {
+        Self {
+            title: Some(data.title),
+            description: data.description,
+            thumbnail: data.image_url,
+            provider_name: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PreviewState {
+    Loading,
+    Loaded(Preview),
+    /// Shown as the plain link only.
+    Failed,
+}
+
+/// Whether an endpoint is configured to fetch previews from.
+pub fn enabled() -> bool {
+    endpoint().is_some()
+}
+
+/// Fetches the preview of `url`. Errors, answers without a title and
+/// answers slower than `TIMEOUT_MS` all count as failures, as does there
+/// being no endpoint.
+pub async fn fetch(url: &str) -> Result<Preview, String> {
+    match future::select(Box::pin(request(url)), TimeoutFuture::new(TIMEOUT_MS)).await {
+        Either::Left((result, _)) => result,
+        Either::Right(_) => Err("timed out".to_string()),
+    }
+}
+
+async fn request(url: &str) -> Result<Preview, String> {
+    let endpoint = endpoint().ok_or("no preview endpoint configured")?;
+    let link = String::from(js_sys::encode_uri_component(url));
+    let response = Request::get(&endpoint.replace("{url}", &link))
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    if !response.ok() {
+        return Err(format!("HTTP {}", response.status()));
+    }
+    let preview: Preview = response.json().await.map_err(|e| e.to_string())?;
+    match preview.title {
+        Some(_) => Ok(preview),
+        None => Err("no title".to_string()),
+    }
+}
+
+/// The preview endpoint, first match wins: the `<meta>` tag, the global
+/// config or the `LINK_PREVIEW_URL` environment variable at build time.
+fn endpoint() -> Option<String> {
+    let from_meta = web_sys::window()
+        .and_then(|w| w.document())
+        .and_then(|doc| doc.query_selector(ENDPOINT_META_SELECTOR).ok().flatten())
+        .and_then(|meta| meta.get_attribute("content"));
+
+    let from_global = || {
+        let config =
+            js_sys::Reflect::get(&js_sys::global(), &ENDPOINT_GLOBAL_CONFIG.into()).ok()?;
+        if config.is_undefined() || config.is_null() {
+            return None;
+        }
+        js_sys::Reflect::get(&config, &JsValue::from_str("linkPreviewUrl"))
+            .ok()?
+            .as_string()
+    };
+
+    from_meta
+        .or_else(from_global)
+        .or_else(|| option_env!("LINK_PREVIEW_URL").map(String::from))
+        .map(|url| url.trim().to_string())
+        .filter(|url| !url.is_empty())
+}
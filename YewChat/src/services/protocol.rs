@@ -0,0 +1,531 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::utils::time;
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct MessageData {
+    /// Assigned by the server to room messages, so they can be reacted to.
+    #[serde(default)]
+    pub id: Option<u64>,
+    pub from: String,
+    pub message: String,
+    /// When the server saw the message, in epoch ms; sent as that or as an
+    /// RFC 3339 string. Servers that don't stamp messages, or stamp them
+    /// unreadably, get the time it reached us instead, see
+    /// `parse_message_data`.
+    #[serde(
+        rename = "time",
+        default,
+        deserialize_with = "time::deserialize_timestamp"
+    )]
+    pub sent_at: Option<u64>,
+    /// Emoji → users who reacted with it.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub reactions: HashMap<String, Vec<String>>,
+    #[serde(default)]
+    pub kind: MessageKind,
+    /// Id of the room message this one answers.
+    #[serde(rename = "replyTo", default, skip_serializing_if = "Option::is_none")]
+    pub reply_to: Option<u64>,
+    /// Whether the sender changed the text after sending it.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub edited: bool,
+    /// The message was deleted; what's left of it stands in its place.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub deleted: bool,
+    /// Reported by enough people to be hidden until a moderator has
+    /// looked at it.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub hidden: bool,
+    /// Id of the file this message shares, see `FileChunk`. `message` is
+    /// its name then.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    /// Whether a direct message came encrypted, see `crate::services::crypto`.
+    /// On arrival `message` is still the ciphertext.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub encrypted: bool,
+}
+
+impl MessageData {
+    /// Identifies a message for duplicate suppression: its server id, or for
+    /// servers that don't assign ids, the sender and text within the same
+    /// minute.
+    pub fn dedup_key(&self) -> String {
+        if let Some(file) = &self.file {
+            return format!("file:{}", file);
+        }
+        match self.id {
+            Some(id) => format!("id:{}", id),
+            None => format!(
+                "{}:{}:{}",
+                self.from,
+                self.sent_at.unwrap_or_default() / 60_000,
+                self.message
+            ),
+        }
+    }
+
+    /// A notice generated on this side, e.g. someone joining the room.
+    pub fn system(message: String) -> Self {
+        Self {
+            id: None,
+            from: String::new(),
+            message,
+            sent_at: Some(js_sys::Date::now() as u64),
+            reactions: HashMap::new(),
+            kind: MessageKind::System,
+            reply_to: None,
+            edited: false,
+            deleted: false,
+            hidden: false,
+            file: None,
+            encrypted: false,
+        }
+    }
+
+    /// The message announcing a file `from` started sending to the room.
+    pub fn file(from: String, name: String, file_id: String) -> Self {
+        Self {
+            id: None,
+            from,
+            message: name,
+            sent_at: Some(js_sys::Date::now() as u64),
+            reactions: HashMap::new(),
+            kind: MessageKind::Chat,
+            reply_to: None,
+            edited: false,
+            deleted: false,
+            hidden: false,
+            file: Some(file_id),
+            encrypted: false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MessageKind {
+    /// Something a user wrote.
+    #[default]
+    Chat,
+    /// A notice about the room itself, rendered apart from the bubbles.
+    System,
+}
+
+/// Payload of a `Reaction` frame sent to the server. Reacting twice with the
+/// same emoji takes the reaction back.
+#[derive(Serialize)]
+pub struct ReactionData {
+    pub id: u64,
+    pub emoji: String,
+    pub from: String,
+}
+
+/// Payload of a `Reaction` frame from the server: the full, updated set of
+/// reactions on a message.
+#[derive(Deserialize)]
+pub struct ReactionUpdate {
+    pub id: u64,
+    #[serde(default)]
+    pub reactions: HashMap<String, Vec<String>>,
+}
+
+/// Payload of an `Edit` frame, in either direction: room message `id` now
+/// reads `message`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct EditData {
+    pub id: u64,
+    pub message: String,
+}
+
+/// Payload of a `LinkPreview` frame: Open Graph details the server fetched
+/// for `url`, a link in room message `id`.
+#[derive(Clone, Debug, Deserialize)]
+pub struct LinkPreviewData {
+    pub id: u64,
+    pub url: String,
+    pub title: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub image_url: Option<String>,
+}
+
+/// Payload of a `FileChunk` or `Audio` frame: piece `chunk_index` of the
+/// `total_chunks` base64 pieces file `file_id` was cut into. Every piece
+/// carries the file's name and type, so it doesn't matter which arrives
+/// first. The server fills in `from` when relaying it to the room.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct FileChunk {
+    pub file_id: String,
+    pub chunk_index: usize,
+    pub total_chunks: usize,
+    pub data: String,
+    pub name: String,
+    pub mime: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub from: String,
+}
+
+/// Payload of a `Delete` or `Unpin` frame, in either direction, and of a
+/// `Pin` frame to the server: the room message it is about. The server
+/// broadcasts a pinned message as a whole `MessageData`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct MessageId {
+    pub id: u64,
+}
+
+/// What's wrong with a reported message.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportCategory {
+    Spam,
+    Harassment,
+    /// Inappropriate content.
+    Inappropriate,
+}
+
+impl ReportCategory {
+    pub const ALL: [Self; 3] = [Self::Spam, Self::Harassment, Self::Inappropriate];
+}
+
+/// Payload of a `Report` frame: `reporter` flags room message
+/// `message_id` to the moderators.
+#[derive(Serialize)]
+pub struct ReportData {
+    pub message_id: u64,
+    pub reporter: String,
+    pub category: ReportCategory,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PresenceStatus {
+    /// Idle: the tab is in the background, or chosen by hand.
+    Away,
+    /// Chosen by hand, to say one doesn't want to be disturbed.
+    Busy,
+    Offline,
+    /// Also what statuses this client doesn't know about map to.
+    #[serde(other)]
+    Online,
+}
+
+/// Payload of a `Presence` frame, in either direction.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PresenceData {
+    pub name: String,
+    pub status: PresenceStatus,
+}
+
+/// Payload of a `Read` frame: `name` has seen every room message up to and
+/// including `id`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ReadReceipt {
+    pub name: String,
+    pub id: u64,
+}
+
+/// Payload of a `ProfileUpdate` frame. Sent with my new `name` and avatar
+/// style; broadcast with `previous` set to the name it replaces, and the
+/// user's role, time zone and locale.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ProfileData {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous: Option<String>,
+    pub name: String,
+    /// DiceBear collection the avatar is drawn from. Empty if the server
+    /// only announces the role of someone who kept the default.
+    #[serde(default)]
+    pub avatar: String,
+    /// Given out by the server, so never sent.
+    #[serde(default, skip_serializing)]
+    pub role: UserRole,
+    /// IANA time zone, e.g. "Asia/Jakarta", as told on `Register`; empty
+    /// if it wasn't.
+    #[serde(default, skip_serializing)]
+    pub timezone: String,
+    /// BCP 47 locale, e.g. "id-ID", as told on `Register`.
+    #[serde(default, skip_serializing)]
+    pub locale: String,
+}
+
+/// What a user may do to others, lowest first.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UserRole {
+    #[default]
+    User,
+    /// May kick users out.
+    Moderator,
+    /// May also ban them for a while.
+    Admin,
+}
+
+/// Payload of `Kick` and `Ban` frames, and of the `UserKicked` and
+/// `UserBanned` frames the server answers them with.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ModerationData {
+    pub target: String,
+    /// How long a ban lasts; a kick has none.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_minutes: Option<u32>,
+}
+
+/// Payload of a `PublicKey` frame from the server: the key `name`
+/// registered with.
+#[derive(Clone, Debug, Deserialize)]
+pub struct PublicKeyData {
+    pub name: String,
+    pub key: String,
+}
+
+/// Why the server turned down a `CreateRoom` or `Register` frame.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RoomErrorReason {
+    /// There already is a room by that name.
+    Exists,
+    NotFound,
+    /// The name breaks the rules in `components::room_dialog::valid_room_name`,
+    /// or the settings don't hold up.
+    Invalid,
+    /// The room is private, and the invite was missing or wrong.
+    Private,
+    /// The room has as many members as it allows.
+    Full,
+}
+
+/// A room's settings: the payload of a `CreateRoom` frame, and of the
+/// `RoomCreated` and `RoomInfo` frames the server answers with.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct RoomData {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+    /// Unlisted, and only joined with the invite.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub private: bool,
+    /// How many may be in the room at once, if there's a limit.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_members: Option<u32>,
+    /// What a private room is joined with. Only its creator is sent it,
+    /// with `RoomCreated`, to hand out.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub invite: Option<String>,
+}
+
+/// Payload of a `SearchUsers` frame: who to look for among everyone
+/// connected, and how many matches to skip.
+#[derive(Clone, Debug, Serialize)]
+pub struct UserSearchData {
+    pub query: String,
+    pub offset: usize,
+}
+
+/// Payload of the `SearchResults` frame answering a `SearchUsers` one: a
+/// page of the nicks matching `query`, sorted, starting at `offset`.
+#[derive(Clone, Debug, Deserialize)]
+pub struct UserSearchResults {
+    pub query: String,
+    pub offset: usize,
+    pub users: Vec<String>,
+    /// Whether there are more after these.
+    #[serde(default)]
+    pub more: bool,
+}
+
+/// Payload of a `NameReserved` frame.
+#[derive(Clone, Debug, Deserialize)]
+pub struct NameReserved {
+    pub name: String,
+    /// The name I keep, when it was a rename that was turned down.
+    #[serde(default)]
+    pub previous: Option<String>,
+}
+
+/// Payload of a `RoomError` frame.
+#[derive(Clone, Debug, Deserialize)]
+pub struct RoomError {
+    pub room: String,
+    pub reason: RoomErrorReason,
+}
+
+/// An ICE candidate, as `RTCIceCandidate.toJSON()` gives it.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IceCandidateData {
+    pub candidate: String,
+    pub sdp_mid: Option<String>,
+    pub sdp_m_line_index: Option<u16>,
+}
+
+/// Payload of the call frames. They only go to the other side of the
+/// call, with `from` filled in by the server on the way.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct CallSignal {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub from: String,
+    /// SDP of a `CallOffer` or `CallAnswer`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sdp: Option<String>,
+    /// Of an `IceCandidate`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub candidate: Option<IceCandidateData>,
+}
+
+/// Payload of a `History` request: up to `limit` of the room's messages
+/// older than message `before`, or the latest ones without a cursor.
+#[derive(Serialize)]
+pub struct HistoryQuery {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub before: Option<u64>,
+    pub limit: usize,
+}
+
+/// Payload of a `History` reply, describing the page in its `data_array`.
+#[derive(Debug, Default, Deserialize)]
+pub struct HistoryPage {
+    /// The cursor the page was asked for with.
+    #[serde(default)]
+    pub before: Option<u64>,
+    /// Whether the server has messages older than this page.
+    #[serde(default)]
+    pub more: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MsgTypes {
+    Users,
+    Register,
+    Message,
+    Typing,
+    DirectMessage,
+    Ping,
+    Pong,
+    Rooms,
+    Reaction,
+    Presence,
+    Read,
+    /// Sent by the server on registering: how far I had read in each room,
+    /// as a map of room name to message id.
+    ReadSync,
+    /// Asks for, or carries, a room's recent messages.
+    History,
+    ProfileUpdate,
+    Edit,
+    Delete,
+    Pin,
+    Unpin,
+    /// Pushed by the server some time after a message with links.
+    LinkPreview,
+    /// A piece of a shared file, relayed to the whole room.
+    FileChunk,
+    /// A piece of a voice message, see `crate::services::voice`. Travels
+    /// like a `FileChunk`.
+    Audio,
+    /// Someone's key for encrypting direct messages to them.
+    PublicKey,
+    /// Asks for a new room, see `RoomData`.
+    CreateRoom,
+    /// Sent to me once the room I asked for exists, with its `RoomData`.
+    RoomCreated,
+    /// Sent on registering: the `RoomData` of the room joined, without the
+    /// invite.
+    RoomInfo,
+    /// Sent instead when a room couldn't be created or joined.
+    RoomError,
+    /// Calls the user in `to`, see `crate::services::call`.
+    CallOffer,
+    CallAnswer,
+    IceCandidate,
+    /// Declines, cancels or hangs up a call.
+    CallEnd,
+    /// Reports a room message, see `ReportData`.
+    Report,
+    /// Sent by the server once a room message has been reported enough
+    /// to be hidden, with its `MessageId`.
+    MessageHidden,
+    /// Asks the server to disconnect a user, see `ModerationData`. Only
+    /// moderators and admins may.
+    Kick,
+    /// Also keeps them from coming back for a while. Only admins may.
+    Ban,
+    /// Sent by the server to the room of a user who was kicked, or banned.
+    /// The banned also get it on trying to register again.
+    UserKicked,
+    UserBanned,
+    /// Looks for users in every room, see `UserSearchData`.
+    SearchUsers,
+    /// Sent by the server in answer, see `UserSearchResults`.
+    SearchResults,
+    /// Sent instead when I register as, or rename to, someone given a role
+    /// without the staff key, see `NameReserved`.
+    NameReserved,
+}
+
+/// A frame as it goes over the wire, in either direction.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebSocketMessage {
+    pub message_type: MsgTypes,
+    pub data_array: Option<Vec<String>>,
+    pub data: Option<String>,
+    /// Recipient of a `DirectMessage` or call frame.
+    pub to: Option<String>,
+    /// Room a `Register`, `Message`, `Reaction`, `Edit`, `Delete`, `Pin`,
+    /// `Unpin`, `FileChunk`, `Audio` or `Users` frame belongs to.
+    pub room: Option<String>,
+    /// Id of the message a `Message` frame replies to.
+    pub reply_to: Option<u64>,
+    /// My key for encrypted direct messages, on a `Register` frame.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_key: Option<String>,
+    /// Invite to the private room a `Register` frame joins.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub invite: Option<String>,
+    /// My time zone and locale, on a `Register` frame, see `ProfileData`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locale: Option<String>,
+    /// Whether the `data` of a `DirectMessage` is encrypted.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub encrypted: bool,
+    /// The staff key, on a `Register` frame for a name given a role.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+}
+
+impl WebSocketMessage {
+    pub fn new(message_type: MsgTypes) -> Self {
+        Self {
+            message_type,
+            data_array: None,
+            data: None,
+            to: None,
+            room: None,
+            reply_to: None,
+            public_key: None,
+            invite: None,
+            timezone: None,
+            locale: None,
+            encrypted: false,
+            token: None,
+        }
+    }
+}
+
+/// Decodes the `data` payload of a `Message`/`DirectMessage` frame.
+pub fn parse_message_data(data: Option<&str>) -> Result<MessageData, String> {
+    let data = data.ok_or_else(|| "missing data".to_string())?;
+    let mut message: MessageData = serde_json::from_str(data).map_err(|e| e.to_string())?;
+    message
+        .sent_at
+        .get_or_insert_with(|| js_sys::Date::now() as u64);
+    Ok(message)
+}
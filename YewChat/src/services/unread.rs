@@ -0,0 +1,155 @@
+//! The unread count in the browser tab while I'm away: "(3) #general ·
+//! YewChat" as the title and a red badge drawn onto the favicon.
+
+use js_sys::Reflect;
+use wasm_bindgen::JsCast;
+use web_sys::{CanvasRenderingContext2d, Document, Element, HtmlCanvasElement, HtmlImageElement};
+
+/// Size the favicon is drawn at, in pixels.
+const ICON_PX: u32 = 32;
+const BADGE_COLOR: &str = "#dc2626";
+/// Where browsers look for an icon when the page doesn't name one.
+const DEFAULT_FAVICON: &str = "/favicon.ico";
+
+/// Owns the page's own title and favicon while a count is shown, and puts
+/// them back when it's cleared or dropped.
+pub struct TabBadge {
+    /// The title as it was when counting started.
+    title: String,
+    /// `href` of the page's `<link rel="icon">`, `None` if it has none and
+    /// the one showing the badge is ours.
+    favicon: Option<String>,
+    /// The page's icon, drawn under the badge once it has loaded.
+    icon: Option<HtmlImageElement>,
+    count: usize,
+}
+
+impl TabBadge {
+    pub fn new() -> Self {
+        let favicon = document()
+            .as_ref()
+            .and_then(icon_link)
+            .and_then(|link| link.get_attribute("href"));
+        let icon = HtmlImageElement::new().ok();
+        if let Some(image) = &icon {
+            // Lets an icon from another origin be drawn onto the canvas, if
+            // that origin allows it.
+            image.set_cross_origin(Some("anonymous"));
+            image.set_src(favicon.as_deref().unwrap_or(DEFAULT_FAVICON));
+        }
+        Self {
+            title: String::new(),
+            favicon,
+            icon,
+            count: 0,
+        }
+    }
+
+    /// Counts one more message.
+    pub fn increment(&mut self) {
+        let document = match document() {
+            Some(document) => document,
+            None => return,
+        };
+        // The title follows the room, so it's only known now.
+        if self.count == 0 {
+            self.title = document.title();
+        }
+        self.count += 1;
+        document.set_title(&format!("({}) {}", self.count, self.title));
+        let loaded = self
+            .icon
+            .as_ref()
+            .filter(|i| i.complete() && i.natural_width() > 0);
+        // A cross-origin icon that didn't allow it taints the canvas; then
+        // the badge goes on its own.
+        let drawn = self.draw(loaded).or_else(|| self.draw(None));
+        if let Some(url) = drawn {
+            if let Some(link) = icon_link(&document).or_else(|| add_icon_link(&document)) {
+                let _ = link.set_attribute("href", &url);
+            }
+        }
+    }
+
+    /// Back to the page's own title and favicon.
+    pub fn clear(&mut self) {
+        if std::mem::take(&mut self.count) == 0 {
+            return;
+        }
+        let document = match document() {
+            Some(document) => document,
+            None => return,
+        };
+        document.set_title(&self.title);
+        if let Some(link) = icon_link(&document) {
+            match &self.favicon {
+                Some(href) => {
+                    let _ = link.set_attribute("href", href);
+                }
+                None => link.remove(),
+            }
+        }
+    }
+
+    /// `icon`, if any, with the badge on it, as a `data:` URL.
+    fn draw(&self, icon: Option<&HtmlImageElement>) -> Option<String> {
+        let canvas = document()?
+            .create_element("canvas")
+            .ok()?
+            .dyn_into::<HtmlCanvasElement>()
+            .ok()?;
+        canvas.set_width(ICON_PX);
+        canvas.set_height(ICON_PX);
+        let context = canvas
+            .get_context("2d")
+            .ok()
+            .flatten()?
+            .dyn_into::<CanvasRenderingContext2d>()
+            .ok()?;
+        let size = f64::from(ICON_PX);
+
+        if let Some(icon) = icon {
+            let _ = context
+                .draw_image_with_html_image_element_and_dw_and_dh(icon, 0.0, 0.0, size, size);
+        }
+        let radius = size * 0.3;
+        let (x, y) = (size - radius, radius);
+        let _ = Reflect::set(&context, &"fillStyle".into(), &BADGE_COLOR.into());
+        context.begin_path();
+        let _ = context.arc(x, y, radius, 0.0, std::f64::consts::TAU);
+        context.fill();
+
+        let label = if self.count > 9 {
+            "9+".to_string()
+        } else {
+            self.count.to_string()
+        };
+        let _ = Reflect::set(&context, &"fillStyle".into(), &"#ffffff".into());
+        let _ = Reflect::set(&context, &"font".into(), &"bold 14px sans-serif".into());
+        let _ = Reflect::set(&context, &"textAlign".into(), &"center".into());
+        let _ = Reflect::set(&context, &"textBaseline".into(), &"middle".into());
+        let _ = context.fill_text(&label, x, y + 1.0);
+        canvas.to_data_url().ok()
+    }
+}
+
+impl Drop for TabBadge {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+fn document() -> Option<Document> {
+    web_sys::window()?.document()
+}
+
+fn icon_link(document: &Document) -> Option<Element> {
+    document.query_selector("link[rel~=icon]").ok().flatten()
+}
+
+fn add_icon_link(document: &Document) -> Option<Element> {
+    let link = document.create_element("link").ok()?;
+    link.set_attribute("rel", "icon").ok()?;
+    document.head()?.append_child(&link).ok()?;
+    Some(link)
+}
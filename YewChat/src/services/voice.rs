@@ -0,0 +1,137 @@
+//! Voice messages: recording one from the microphone while the button is
+//! held, and turning a received one back into something `<audio>` plays.
+//! They travel in pieces like shared files, as `Audio` frames.
+
+use js_sys::{Array, Object, Reflect, Uint8Array};
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{
+    AnalyserNode, AudioContext, Blob, BlobEvent, CanvasRenderingContext2d, HtmlCanvasElement,
+    MediaRecorder, MediaStream, MediaStreamTrack, Url,
+};
+use yew::Callback;
+
+/// Samples the waveform is drawn from; a power of two, as the analyser
+/// wants.
+const FFT_SIZE: u32 = 1024;
+const WAVEFORM_COLOR: &str = "#2563eb";
+
+/// A recording in progress.
+pub struct Recorder {
+    recorder: MediaRecorder,
+    stream: MediaStream,
+    audio: AudioContext,
+    analyser: AnalyserNode,
+    /// When it started, in epoch ms.
+    started: f64,
+    _on_data: Closure<dyn FnMut(BlobEvent)>,
+}
+
+impl Recorder {
+    /// Asks for the microphone and starts recording. Once stopped, the
+    /// recording goes to `on_done`.
+    pub async fn start(on_done: Callback<Blob>) -> Result<Self, JsValue> {
+        let devices = web_sys::window()
+            .ok_or("no window")?
+            .navigator()
+            .media_devices()?;
+        let constraints = Object::new();
+        Reflect::set(&constraints, &"audio".into(), &true.into())?;
+        let stream: MediaStream =
+            JsFuture::from(devices.get_user_media_with_constraints(constraints.unchecked_ref())?)
+                .await?
+                .unchecked_into();
+
+        let audio = AudioContext::new()?;
+        let analyser = audio.create_analyser()?;
+        analyser.set_fft_size(FFT_SIZE);
+        audio
+            .create_media_stream_source(&stream)?
+            .connect_with_audio_node(&analyser)?;
+
+        let recorder = MediaRecorder::new_with_media_stream(&stream)?;
+        // Without a timeslice the whole recording comes in one piece, on stop.
+        let on_data = Closure::wrap(Box::new(move |e: BlobEvent| {
+            if let Some(blob) = e.data().filter(|blob| blob.size() > 0.0) {
+                on_done.emit(blob);
+            }
+        }) as Box<dyn FnMut(_)>);
+        recorder.set_ondataavailable(Some(on_data.as_ref().unchecked_ref()));
+        recorder.start()?;
+
+        Ok(Self {
+            recorder,
+            stream,
+            audio,
+            analyser,
+            started: js_sys::Date::now(),
+            _on_data: on_data,
+        })
+    }
+
+    /// How long it has been recording, in ms.
+    pub fn elapsed_ms(&self) -> f64 {
+        js_sys::Date::now() - self.started
+    }
+
+    /// Stops recording and lets go of the microphone. The recording only
+    /// goes to `on_done` if it's to be `kept`.
+    pub fn stop(self, kept: bool) {
+        if !kept {
+            self.recorder.set_ondataavailable(None);
+        }
+        let _ = self.recorder.stop();
+        for track in self.stream.get_tracks().iter() {
+            track.unchecked_into::<MediaStreamTrack>().stop();
+        }
+        let _ = self.audio.close();
+    }
+
+    /// Draws what the microphone is picking up right now on `canvas`.
+    pub fn draw(&self, canvas: &HtmlCanvasElement) {
+        let context = match canvas
+            .get_context("2d")
+            .ok()
+            .flatten()
+            .and_then(|c| c.dyn_into::<CanvasRenderingContext2d>().ok())
+        {
+            Some(context) => context,
+            None => return,
+        };
+        let mut samples = vec![0u8; FFT_SIZE as usize];
+        self.analyser.get_byte_time_domain_data(&mut samples);
+
+        let (width, height) = (f64::from(canvas.width()), f64::from(canvas.height()));
+        context.clear_rect(0.0, 0.0, width, height);
+        let _ = Reflect::set(&context, &"strokeStyle".into(), &WAVEFORM_COLOR.into());
+        context.set_line_width(2.0);
+        context.begin_path();
+        let step = width / samples.len() as f64;
+        for (i, sample) in samples.iter().enumerate() {
+            // 128 is silence.
+            let y = f64::from(*sample) / 255.0 * height;
+            if i == 0 {
+                context.move_to(0.0, y);
+            } else {
+                context.line_to(i as f64 * step, y);
+            }
+        }
+        context.stroke();
+    }
+}
+
+/// A `blob:` URL for audio of type `mime` given as base64 `data`. Revoke
+/// it with `Url::revoke_object_url` once it's no longer shown.
+pub fn blob_url(mime: &str, data: &str) -> Result<String, JsValue> {
+    let binary = web_sys::window().ok_or("no window")?.atob(data)?;
+    // atob only ever gives back characters up to U+00FF, one per byte.
+    let bytes: Vec<u8> = binary.chars().map(|c| c as u8).collect();
+    let options = Object::new();
+    Reflect::set(&options, &"type".into(), &mime.into())?;
+    let blob = Blob::new_with_buffer_source_sequence_and_options(
+        &Array::of1(&Uint8Array::from(bytes.as_slice())),
+        options.unchecked_ref(),
+    )?;
+    Url::create_object_url_with_blob(&blob)
+}
@@ -1,53 +1,770 @@
-use futures::{channel::mpsc::Sender, SinkExt, StreamExt};
-use reqwasm::websocket::{futures::WebSocket, Message};
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::Poll;
 
+use futures::channel::mpsc::{Receiver, SendError, Sender};
+use futures::future::Fuse;
+use futures::{future, FutureExt, Sink, SinkExt, StreamExt};
+use gloo_timers::future::{IntervalStream, TimeoutFuture};
+use reqwasm::websocket::{futures::WebSocket, Message, State};
+use serde::de::DeserializeOwned;
+
+use wasm_bindgen::JsValue;
 use wasm_bindgen_futures::spawn_local;
-use yew_agent::Dispatched;
+use yew_agent::{Dispatched, Dispatcher};
+
+use crate::services::event_bus::{BusEvent, EventBus};
+use crate::services::protocol::{
+    parse_message_data, CallSignal, EditData, FileChunk, HistoryPage, LinkPreviewData, MessageId,
+    ModerationData, MsgTypes, NameReserved, PresenceData, ProfileData, PublicKeyData,
+    ReactionUpdate, ReadReceipt, RoomData, RoomError, UserSearchResults, WebSocketMessage,
+};
+use crate::utils::translations::{t, t_args};
 
-use crate::services::event_bus::{EventBus, Request};
+const DEFAULT_WS_URL: &str = "ws://127.0.0.1:8080";
+/// Query parameter that overrides the endpoint, e.g. `?ws_url=wss://chat.example`.
+const URL_QUERY_PARAM: &str = "ws_url";
+/// `<meta name="websocket-url" content="...">` in the served page.
+const URL_META_SELECTOR: &str = "meta[name=websocket-url]";
+/// `window.YEWCHAT_CONFIG = { websocketUrl: "..." }` set before the app loads.
+const URL_GLOBAL_CONFIG: &str = "YEWCHAT_CONFIG";
+const INITIAL_BACKOFF_MS: u32 = 1_000;
+const MAX_BACKOFF_MS: u32 = 30_000;
+const HEARTBEAT_INTERVAL_MS: u32 = 20_000;
+const PONG_TIMEOUT_MS: u32 = 10_000;
+/// How often a hold is checked on, see `WebsocketService::hold`.
+const HOLD_CHECK_MS: u32 = 1_000;
+/// Maximum number of messages, text or binary, buffered while
+/// disconnected. Once full, the oldest queued message is dropped to make
+/// room.
+pub const OUTBOX_CAPACITY: usize = 100;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ConnectionStatus {
+    Connected,
+    /// The socket dropped; the next attempt to re-open it starts in
+    /// `delay_ms`.
+    Reconnecting {
+        attempt: u32,
+        delay_ms: u32,
+    },
+    /// The endpoint can't be connected to at all (e.g. a malformed URL), so
+    /// the service has given up, or it's holding off after a kick or ban.
+    Failed(String),
+}
 
 pub struct WebsocketService {
     pub tx: Sender<String>,
+    /// Like `tx`, for binary frames, see `send_binary`.
+    tx_binary: Sender<Vec<u8>>,
+    hello: Rc<RefCell<Option<String>>>,
+    /// When reconnecting may resume, in ms since the epoch; infinite until
+    /// the next hello. See `hold`.
+    held_until: Rc<Cell<Option<f64>>>,
 }
 
 impl WebsocketService {
-    pub fn new() -> Self {
-        let ws = WebSocket::open("ws://127.0.0.1:8080").unwrap();
+    /// Opens a connection to `url` in the background. Incoming frames are
+    /// decoded and published on the `EventBus`, along with a
+    /// `ConnectionChanged` every time the socket connects (after the outbox
+    /// has been flushed) or drops.
+    pub fn new(url: String) -> Self {
+        let (in_tx, in_rx) = futures::channel::mpsc::channel::<String>(1000);
+        let (binary_tx, binary_rx) = futures::channel::mpsc::channel::<Vec<u8>>(1000);
+        let hello = Rc::new(RefCell::new(None));
+        let held_until = Rc::new(Cell::new(None));
 
-        let (mut write, mut read) = ws.split();
+        let worker = Worker {
+            url,
+            in_rx,
+            binary_rx,
+            event_bus: EventBus::dispatcher(),
+            reconnect_attempts: 0,
+            hello: hello.clone(),
+            held_until: held_until.clone(),
+            outbox: VecDeque::new(),
+            heartbeat_supported: false,
+        };
+        spawn_local(worker.run());
+
+        Self {
+            tx: in_tx,
+            tx_binary: binary_tx,
+            hello,
+            held_until,
+        }
+    }
 
-        let (in_tx, mut in_rx) = futures::channel::mpsc::channel::<String>(1000);
-        let mut event_bus = EventBus::dispatcher();
+    /// Queues `data` to go out as a binary frame, which spares file and
+    /// audio pieces the base64 and JSON around them. Fails only if the
+    /// queue is full or the connection has been given up on.
+    // Nothing in the chat sends binary frames yet.
+    #[allow(dead_code)]
+    pub fn send_binary(&self, data: &[u8]) -> Result<(), SendError> {
+        self.tx_binary
+            .clone()
+            .try_send(data.to_vec())
+            .map_err(|e| e.into_send_error())
+    }
+
+    /// Sets the frame sent first thing on every (re)connect, ahead of
+    /// anything queued while offline, so that the server knows who we are
+    /// before it sees our messages.
+    pub fn set_hello(&self, frame: String) {
+        *self.hello.borrow_mut() = Some(frame);
+        // Logging in again lifts a kick, but not a ban.
+        if self.held_until.get().is_some_and(f64::is_infinite) {
+            self.held_until.set(None);
+        }
+    }
 
-        spawn_local(async move {
-            while let Some(s) = in_rx.next().await {
-                log::debug!("got event from channel! {}", s);
-                write.send(Message::Text(s)).await.unwrap();
+    /// Stops re-opening the connection the server is about to close on us
+    /// after a kick or ban. A ban is waited out until `until` (ms since the
+    /// epoch); a kick also drops the hello, and lasts until the next one is
+    /// set. Whatever is sent meanwhile is queued as usual.
+    pub fn hold(&self, until: Option<f64>) {
+        if until.is_none() {
+            self.hello.borrow_mut().take();
+        }
+        self.held_until.set(Some(until.unwrap_or(f64::INFINITY)));
+    }
+}
+
+enum Session {
+    /// The socket closed or failed and should be re-opened.
+    Disconnected,
+    /// The sending half was dropped, nobody is listening anymore.
+    Finished,
+}
+
+struct Worker {
+    url: String,
+    in_rx: Receiver<String>,
+    binary_rx: Receiver<Vec<u8>>,
+    event_bus: Dispatcher<EventBus>,
+    /// Number of consecutive failed connection attempts, reset to 0 once a
+    /// connection is established.
+    reconnect_attempts: u32,
+    hello: Rc<RefCell<Option<String>>>,
+    held_until: Rc<Cell<Option<f64>>>,
+    outbox: VecDeque<Message>,
+    /// Whether the server has ever answered a ping. Older servers don't know
+    /// about heartbeats, so a missing pong only counts as a dead connection
+    /// once we've seen the server reply at least once.
+    heartbeat_supported: bool,
+}
+
+impl Worker {
+    /// Keeps a socket open for as long as the service's senders are alive,
+    /// re-opening it with exponential backoff whenever it drops. Messages sent
+    /// while disconnected are queued in the outbox and flushed in order once
+    /// the next connection opens.
+    async fn run(mut self) {
+        if !(self.url.starts_with("ws://") || self.url.starts_with("wss://")) {
+            log::error!("ws: refusing to open {}", self.url);
+            let error = t_args("error.invalid_server_address", &[&self.url]);
+            self.set_status(ConnectionStatus::Failed(error));
+            return;
+        }
+
+        loop {
+            if !self.wait_out_hold().await {
+                return;
             }
-        });
+            match WebSocket::open(&self.url) {
+                Ok(ws) => {
+                    if let Session::Finished = self.session(ws).await {
+                        log::debug!("WebsocketService dropped, stopping");
+                        return;
+                    }
+                }
+                Err(e) => {
+                    // `open` only fails synchronously for URLs the browser
+                    // refuses outright; retrying won't help.
+                    log::error!("ws: failed to open {}: {:?}", self.url, e);
+                    let error = t_args("connection.failed", &[&self.url, &e]);
+                    self.set_status(ConnectionStatus::Failed(error));
+                    return;
+                }
+            }
+            self.reconnect_attempts += 1;
+            let attempt = self.reconnect_attempts;
+            let delay = backoff_delay(attempt);
+            log::debug!("reconnecting in {}ms (attempt {})", delay, attempt);
+            self.set_status(ConnectionStatus::Reconnecting {
+                attempt,
+                delay_ms: delay,
+            });
 
-        spawn_local(async move {
-            while let Some(msg) = read.next().await {
-                match msg {
-                    Ok(Message::Text(data)) => {
-                        log::debug!("from websocket: {}", data);
-                        event_bus.send(Request::EventBusMsg(data));
+            if !self.idle(delay).await {
+                return;
+            }
+        }
+    }
+
+    /// Waits `ms`, queueing whatever is sent meanwhile. False if the
+    /// service was dropped.
+    async fn idle(&mut self, ms: u32) -> bool {
+        let delay = TimeoutFuture::new(ms).fuse();
+        futures::pin_mut!(delay);
+        loop {
+            futures::select! {
+                _ = delay => return true,
+                out = self.in_rx.next() => match out {
+                    Some(s) => self.enqueue(Message::Text(s)),
+                    None => return false,
+                },
+                out = self.binary_rx.next() => match out {
+                    Some(b) => self.enqueue(Message::Bytes(b)),
+                    None => return false,
+                },
+            }
+        }
+    }
+
+    /// Waits for a kick or ban to be over, see `WebsocketService::hold`.
+    /// False if the service was dropped meanwhile.
+    async fn wait_out_hold(&mut self) -> bool {
+        let mut shown = None;
+        while let Some(until) = self.held_until.get() {
+            let left = until - js_sys::Date::now();
+            if left <= 0.0 {
+                self.held_until.set(None);
+                break;
+            }
+            let reason = if until.is_finite() {
+                t_args("error.banned", &[&(left / 60_000.0).ceil()])
+            } else {
+                t("error.kicked").to_string()
+            };
+            if shown.as_ref() != Some(&reason) {
+                self.set_status(ConnectionStatus::Failed(reason.clone()));
+                shown = Some(reason);
+            }
+            if !self.idle(HOLD_CHECK_MS).await {
+                return false;
+            }
+        }
+        true
+    }
+
+    async fn session(&mut self, mut ws: WebSocket) -> Session {
+        // Wait until the socket either opens or reports an error/close.
+        future::poll_fn(|cx| {
+            if Pin::new(&mut ws).poll_ready(cx).is_ready() {
+                return Poll::Ready(());
+            }
+            Pin::new(&mut ws).poll_next_unpin(cx).map(|_| ())
+        })
+        .await;
+        if !matches!(ws.state(), State::Open) {
+            return Session::Disconnected;
+        }
+        log::debug!("WebSocket Opened");
+        self.reconnect_attempts = 0;
+
+        let (mut write, mut read) = ws.split();
+
+        let hello = self.hello.borrow().clone();
+        if let Some(hello) = hello {
+            if let Err(e) = write.send(Message::Text(hello)).await {
+                log::error!("ws: {:?}", e);
+                return Session::Disconnected;
+            }
+        }
+        while let Some(message) = self.outbox.pop_front() {
+            if let Err(e) = write.send(message.clone()).await {
+                log::error!("ws: {:?}", e);
+                self.outbox.push_front(message);
+                return Session::Disconnected;
+            }
+        }
+        self.set_status(ConnectionStatus::Connected);
+
+        let mut heartbeat = IntervalStream::new(HEARTBEAT_INTERVAL_MS).fuse();
+        let mut pong_deadline = Fuse::<TimeoutFuture>::terminated();
+
+        loop {
+            futures::select! {
+                _ = heartbeat.next() => {
+                    // Pings carry our clock (epoch ms), which the pong echoes
+                    // back.
+                    let ping = WebSocketMessage {
+                        data: Some(js_sys::Date::now().to_string()),
+                        ..WebSocketMessage::new(MsgTypes::Ping)
+                    };
+                    let ping = serde_json::to_string(&ping).unwrap();
+                    if let Err(e) = write.send(Message::Text(ping)).await {
+                        log::error!("ws: {:?}", e);
+                        return Session::Disconnected;
+                    }
+                    pong_deadline = TimeoutFuture::new(PONG_TIMEOUT_MS).fuse();
+                },
+                _ = pong_deadline => {
+                    if self.heartbeat_supported {
+                        log::warn!("ws: no pong within {}ms, reconnecting", PONG_TIMEOUT_MS);
+                        return Session::Disconnected;
+                    }
+                },
+                out = self.in_rx.next() => match out {
+                    Some(s) => {
+                        log::debug!("got event from channel! {}", s);
+                        if let Err(e) = write.send(Message::Text(s.clone())).await {
+                            log::error!("ws: {:?}", e);
+                            self.enqueue(Message::Text(s));
+                            return Session::Disconnected;
+                        }
                     }
-                    Ok(Message::Bytes(b)) => {
-                        let decoded = std::str::from_utf8(&b);
-                        if let Ok(val) = decoded {
-                            log::debug!("from websocket: {}", val);
-                            event_bus.send(Request::EventBusMsg(val.into()));
+                    None => return Session::Finished,
+                },
+                out = self.binary_rx.next() => match out {
+                    Some(b) => {
+                        log::debug!("got {} bytes from channel", b.len());
+                        if let Err(e) = write.send(Message::Bytes(b.clone())).await {
+                            log::error!("ws: {:?}", e);
+                            self.enqueue(Message::Bytes(b));
+                            return Session::Disconnected;
+                        }
+                    }
+                    None => return Session::Finished,
+                },
+                msg = read.next().fuse() => match msg {
+                    Some(Ok(Message::Text(data))) => {
+                        log::debug!("from websocket: {}", data);
+                        match self.decode(&data) {
+                            Some(WebSocketMessage { message_type: MsgTypes::Pong, data, .. }) => {
+                                pong_deadline = Fuse::terminated();
+                                self.heartbeat_supported = true;
+                                self.on_pong(data);
+                            }
+                            Some(WebSocketMessage { message_type: MsgTypes::Ping, data, .. }) => {
+                                let pong = WebSocketMessage {
+                                    data,
+                                    ..WebSocketMessage::new(MsgTypes::Pong)
+                                };
+                                let pong = serde_json::to_string(&pong).unwrap();
+                                if let Err(e) = write.send(Message::Text(pong)).await {
+                                    log::error!("ws: {:?}", e);
+                                    return Session::Disconnected;
+                                }
+                            }
+                            Some(frame) => self.publish(frame),
+                            None => {}
                         }
                     }
-                    Err(e) => {
+                    Some(Ok(Message::Bytes(b))) => {
+                        log::debug!("from websocket: {} bytes", b.len());
+                        self.event_bus.send(BusEvent::Binary(b));
+                    }
+                    Some(Err(e)) => {
                         log::error!("ws: {:?}", e)
                     }
+                    None => {
+                        log::debug!("WebSocket Closed");
+                        return Session::Disconnected;
+                    }
+                },
+            }
+        }
+    }
+
+    /// Publishes the round-trip time for a pong. Pongs whose payload isn't
+    /// one of our timestamps are ignored.
+    fn on_pong(&mut self, data: Option<String>) {
+        let sent_at = match data.and_then(|d| d.parse::<f64>().ok()) {
+            Some(sent_at) => sent_at,
+            None => {
+                log::debug!("ws: ignoring pong without a timestamp");
+                return;
+            }
+        };
+        let rtt = (js_sys::Date::now() - sent_at).max(0.0);
+        self.event_bus.send(BusEvent::Latency(rtt as u32));
+    }
+
+    /// Parses a frame, reporting it on the bus if it can't be read.
+    fn decode(&mut self, data: &str) -> Option<WebSocketMessage> {
+        let frame = parse_frame(data);
+        if frame.is_none() {
+            self.event_bus.send(BusEvent::Unreadable);
+        }
+        frame
+    }
+
+    /// Sends a frame meant for the UI out as a `BusEvent`.
+    fn publish(&mut self, frame: WebSocketMessage) {
+        if let Some(event) = to_event(frame) {
+            self.event_bus.send(event);
+        }
+    }
+
+    fn set_status(&mut self, status: ConnectionStatus) {
+        self.event_bus.send(BusEvent::ConnectionChanged(status));
+    }
+
+    fn enqueue(&mut self, message: Message) {
+        if self.outbox.len() >= OUTBOX_CAPACITY {
+            if let Some(dropped) = self.outbox.pop_front() {
+                log::warn!("outbox full, dropping oldest message: {:?}", dropped);
+            }
+        }
+        self.outbox.push_back(message);
+    }
+}
+
+/// Parses a frame, logging it if it can't be read.
+fn parse_frame(data: &str) -> Option<WebSocketMessage> {
+    match serde_json::from_str(data) {
+        Ok(frame) => Some(frame),
+        Err(e) => {
+            log::warn!("ws: unreadable frame ({}): {}", e, data);
+            None
+        }
+    }
+}
+
+/// The `BusEvent` for a frame, `Unreadable` if its payload can't be
+/// decoded; `None` for frames that aren't meant for the UI.
+fn to_event(frame: WebSocketMessage) -> Option<BusEvent> {
+    let event = match frame.message_type {
+        MsgTypes::Users => BusEvent::Users {
+            room: frame.room,
+            users: frame.data_array.unwrap_or_default(),
+        },
+        MsgTypes::Rooms => BusEvent::Rooms(frame.data_array.unwrap_or_default()),
+        MsgTypes::RoomCreated | MsgTypes::RoomInfo => match payload::<RoomData>(&frame) {
+            Some(room) => match frame.message_type {
+                MsgTypes::RoomCreated => BusEvent::RoomCreated(room),
+                _ => BusEvent::RoomInfo(room),
+            },
+            None => BusEvent::Unreadable,
+        },
+        MsgTypes::RoomError => match payload::<RoomError>(&frame) {
+            Some(error) => BusEvent::RoomError(error),
+            None => BusEvent::Unreadable,
+        },
+        MsgTypes::NameReserved => match payload::<NameReserved>(&frame) {
+            Some(reserved) => BusEvent::NameReserved(reserved),
+            None => BusEvent::Unreadable,
+        },
+        MsgTypes::SearchResults => match payload::<UserSearchResults>(&frame) {
+            Some(results) => BusEvent::UserSearchResults(results),
+            None => BusEvent::Unreadable,
+        },
+        MsgTypes::Typing => BusEvent::Typing(frame.data.unwrap_or_default()),
+        MsgTypes::Message | MsgTypes::DirectMessage => {
+            match parse_message_data(frame.data.as_deref()) {
+                Ok(message) => match frame.message_type {
+                    MsgTypes::Message => BusEvent::Message {
+                        room: frame.room,
+                        message,
+                    },
+                    _ => BusEvent::DirectMessage {
+                        to: frame.to,
+                        message,
+                    },
+                },
+                Err(e) => {
+                    log::warn!("ws: unreadable message payload ({}): {:?}", e, frame.data);
+                    BusEvent::Unreadable
                 }
             }
-            log::debug!("WebSocket Closed");
-        });
+        }
+        MsgTypes::History => {
+            // Servers without paging send everything at once, untagged.
+            let page: HistoryPage = match frame.data {
+                Some(_) => payload(&frame).unwrap_or_default(),
+                None => HistoryPage::default(),
+            };
+            BusEvent::History {
+                room: frame.room,
+                before: page.before,
+                more: page.more,
+                messages: frame
+                    .data_array
+                    .unwrap_or_default()
+                    .iter()
+                    .filter_map(|data| match parse_message_data(Some(data)) {
+                        Ok(message) => Some(message),
+                        Err(e) => {
+                            log::warn!("ws: skipping unreadable history entry ({}): {}", e, data);
+                            None
+                        }
+                    })
+                    .collect(),
+            }
+        }
+        MsgTypes::Reaction => match payload::<ReactionUpdate>(&frame) {
+            Some(update) => BusEvent::Reactions {
+                room: frame.room,
+                id: update.id,
+                reactions: update.reactions,
+            },
+            None => BusEvent::Unreadable,
+        },
+        MsgTypes::Edit => match payload::<EditData>(&frame) {
+            Some(edit) => BusEvent::Edited {
+                room: frame.room,
+                edit,
+            },
+            None => BusEvent::Unreadable,
+        },
+        MsgTypes::Delete => match payload::<MessageId>(&frame) {
+            Some(delete) => BusEvent::Deleted {
+                room: frame.room,
+                id: delete.id,
+            },
+            None => BusEvent::Unreadable,
+        },
+        MsgTypes::MessageHidden => match payload::<MessageId>(&frame) {
+            Some(hidden) => BusEvent::Hidden {
+                room: frame.room,
+                id: hidden.id,
+            },
+            None => BusEvent::Unreadable,
+        },
+        MsgTypes::Pin => match parse_message_data(frame.data.as_deref()) {
+            Ok(message) => BusEvent::Pinned {
+                room: frame.room,
+                message,
+            },
+            Err(_) => BusEvent::Unreadable,
+        },
+        MsgTypes::Unpin => match payload::<MessageId>(&frame) {
+            Some(unpin) => BusEvent::Unpinned {
+                room: frame.room,
+                id: unpin.id,
+            },
+            None => BusEvent::Unreadable,
+        },
+        MsgTypes::LinkPreview => match payload::<LinkPreviewData>(&frame) {
+            Some(preview) => BusEvent::LinkPreview(preview),
+            None => BusEvent::Unreadable,
+        },
+        MsgTypes::FileChunk | MsgTypes::Audio => match payload::<FileChunk>(&frame) {
+            Some(chunk) => BusEvent::FileChunk {
+                room: frame.room,
+                chunk,
+            },
+            None => BusEvent::Unreadable,
+        },
+        MsgTypes::Presence => match payload::<PresenceData>(&frame) {
+            Some(presence) => BusEvent::Presence(presence),
+            None => BusEvent::Unreadable,
+        },
+        MsgTypes::UserKicked | MsgTypes::UserBanned => match payload::<ModerationData>(&frame) {
+            Some(action) => BusEvent::Moderated {
+                room: frame.room,
+                action,
+            },
+            None => BusEvent::Unreadable,
+        },
+        MsgTypes::ProfileUpdate => match payload::<ProfileData>(&frame) {
+            Some(profile) => BusEvent::Profile(profile),
+            None => BusEvent::Unreadable,
+        },
+        MsgTypes::CallOffer | MsgTypes::CallAnswer | MsgTypes::IceCandidate | MsgTypes::CallEnd => {
+            match payload::<CallSignal>(&frame) {
+                Some(signal) => match frame.message_type {
+                    MsgTypes::CallOffer => BusEvent::CallOffer(signal),
+                    MsgTypes::CallAnswer => BusEvent::CallAnswer(signal),
+                    MsgTypes::IceCandidate => BusEvent::IceCandidate(signal),
+                    _ => BusEvent::CallEnd(signal),
+                },
+                None => BusEvent::Unreadable,
+            }
+        }
+        MsgTypes::PublicKey => match payload::<PublicKeyData>(&frame) {
+            Some(key) => BusEvent::PublicKey(key),
+            None => BusEvent::Unreadable,
+        },
+        MsgTypes::Read => match payload::<ReadReceipt>(&frame) {
+            Some(receipt) => BusEvent::Read {
+                room: frame.room,
+                receipt,
+            },
+            None => BusEvent::Unreadable,
+        },
+        MsgTypes::ReadSync => match payload(&frame) {
+            Some(cursors) => BusEvent::ReadSync(cursors),
+            None => BusEvent::Unreadable,
+        },
+        MsgTypes::Register
+        | MsgTypes::Ping
+        | MsgTypes::Pong
+        | MsgTypes::Report
+        | MsgTypes::Kick
+        | MsgTypes::Ban
+        | MsgTypes::CreateRoom
+        | MsgTypes::SearchUsers => return None,
+    };
+    Some(event)
+}
 
-        Self { tx: in_tx }
+/// Decodes the JSON carried in a frame's `data`.
+fn payload<T: DeserializeOwned>(frame: &WebSocketMessage) -> Option<T> {
+    match frame.data.as_deref().map(serde_json::from_str) {
+        Some(Ok(payload)) => Some(payload),
+        Some(Err(e)) => {
+            log::warn!(
+                "ws: unreadable {:?} payload ({}): {:?}",
+                frame.message_type,
+                e,
+                frame.data
+            );
+            None
+        }
+        None => {
+            log::warn!("ws: {:?} frame without a payload", frame.message_type);
+            None
+        }
     }
-}
\ No newline at end of file
+}
+
+/// 1s, 2s, 4s, ... capped at `MAX_BACKOFF_MS`.
+fn backoff_delay(attempt: u32) -> u32 {
+    let exp = attempt.saturating_sub(1).min(16);
+    INITIAL_BACKOFF_MS
+        .saturating_mul(1 << exp)
+        .min(MAX_BACKOFF_MS)
+}
+
+/// Picks the server endpoint from, in order: the `ws_url` query parameter,
+/// the `websocket-url` meta tag, `window.YEWCHAT_CONFIG.websocketUrl`, the
+/// `WEBSOCKET_URL` build-time variable, and finally localhost. `ws://` is
+/// upgraded to `wss://` when the page itself is served over https.
+pub fn resolve_url() -> String {
+    let window = web_sys::window();
+
+    let from_query = window
+        .as_ref()
+        .and_then(|w| w.location().search().ok())
+        .and_then(|search| web_sys::UrlSearchParams::new_with_str(&search).ok())
+        .and_then(|params| params.get(URL_QUERY_PARAM));
+
+    let from_meta = || {
+        window
+            .as_ref()
+            .and_then(|w| w.document())
+            .and_then(|doc| doc.query_selector(URL_META_SELECTOR).ok().flatten())
+            .and_then(|meta| meta.get_attribute("content"))
+    };
+
+    let from_global = || {
+        let config = js_sys::Reflect::get(&js_sys::global(), &URL_GLOBAL_CONFIG.into()).ok()?;
+        if config.is_undefined() || config.is_null() {
+            return None;
+        }
+        js_sys::Reflect::get(&config, &JsValue::from_str("websocketUrl"))
+            .ok()?
+            .as_string()
+    };
+
+    let url = from_query
+        .or_else(from_meta)
+        .or_else(from_global)
+        .or_else(|| option_env!("WEBSOCKET_URL").map(String::from))
+        .map(|url| url.trim().to_string())
+        .filter(|url| !url.is_empty())
+        .unwrap_or_else(|| DEFAULT_WS_URL.to_string());
+
+    let served_over_https =
+        window.and_then(|w| w.location().protocol().ok()).as_deref() == Some("https:");
+    match url.strip_prefix("ws://") {
+        Some(rest) if served_over_https => format!("wss://{}", rest),
+        _ => url,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(json: &str) -> WebSocketMessage {
+        parse_frame(json).expect("a frame")
+    }
+
+    #[test]
+    fn garbage_is_not_a_frame() {
+        assert!(parse_frame("not json").is_none());
+        assert!(parse_frame(r#"{"messageType":"nonsense"}"#).is_none());
+    }
+
+    #[test]
+    fn message_without_data_is_unreadable() {
+        let event = to_event(frame(r#"{"messageType":"message"}"#));
+        assert!(matches!(event, Some(BusEvent::Unreadable)));
+    }
+
+    #[test]
+    fn data_that_is_not_a_message_is_unreadable() {
+        let event = to_event(frame(r#"{"messageType":"message","data":"[1,2]"}"#));
+        assert!(matches!(event, Some(BusEvent::Unreadable)));
+        let event = to_event(frame(r#"{"messageType":"directmessage","data":"{oops"}"#));
+        assert!(matches!(event, Some(BusEvent::Unreadable)));
+    }
+
+    #[test]
+    fn users_go_out_with_their_room() {
+        let event = to_event(frame(
+            r#"{"messageType":"users","room":"general","dataArray":["ana","budi"]}"#,
+        ));
+        match event {
+            Some(BusEvent::Users { room, users }) => {
+                assert_eq!(room.as_deref(), Some("general"));
+                assert_eq!(users, ["ana", "budi"]);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn messages_are_decoded_once() {
+        let event = to_event(frame(
+            r#"{"messageType":"message","room":"general","data":"{\"id\":7,\"from\":\"ana\",\"message\":\"hi\",\"time\":1}"}"#,
+        ));
+        match event {
+            Some(BusEvent::Message { room, message }) => {
+                assert_eq!(room.as_deref(), Some("general"));
+                assert_eq!(message.id, Some(7));
+                assert_eq!(message.from, "ana");
+                assert_eq!(message.message, "hi");
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+
+        let event = to_event(frame(
+            r#"{"messageType":"directmessage","to":"budi","data":"{\"from\":\"ana\",\"message\":\"psst\",\"time\":1}"}"#,
+        ));
+        match event {
+            Some(BusEvent::DirectMessage { to, message }) => {
+                assert_eq!(to.as_deref(), Some("budi"));
+                assert_eq!(message.message, "psst");
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn history_skips_unreadable_entries() {
+        let event = to_event(frame(
+            r#"{"messageType":"history","data":"{\"more\":true}","dataArray":["{\"from\":\"ana\",\"message\":\"a\",\"time\":1}","nope"]}"#,
+        ));
+        match event {
+            Some(BusEvent::History { more, messages, .. }) => {
+                assert!(more);
+                assert_eq!(messages.len(), 1);
+                assert_eq!(messages[0].message, "a");
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn frames_for_the_server_are_not_published() {
+        assert!(to_event(frame(r#"{"messageType":"register","data":"ana"}"#)).is_none());
+        assert!(to_event(frame(r#"{"messageType":"ping","data":"1"}"#)).is_none());
+    }
+}
@@ -0,0 +1,45 @@
+/// A slash command typed into the message box.
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    /// `/me waves`: an action rather than something said.
+    Me(String),
+    /// `/clear`: empty the thread on screen, locally only.
+    Clear,
+    /// `/nick bob`: change my name.
+    Nick(String),
+    /// A known command without the argument it needs; carries its usage.
+    Usage(&'static str),
+    /// Any other `/word`.
+    Unknown(String),
+}
+
+/// Parses `input` as a command. Returns `None` for ordinary messages,
+/// including ones escaped with a double slash (`//shrug`).
+pub fn parse_command(input: &str) -> Option<Command> {
+    let input = input.trim();
+    let rest = input.strip_prefix('/')?;
+    if rest.starts_with('/') {
+        return None;
+    }
+    let (name, args) = match rest.split_once(char::is_whitespace) {
+        Some((name, args)) => (name, args.trim()),
+        None => (rest, ""),
+    };
+
+    let command = match name.to_lowercase().as_str() {
+        "me" if args.is_empty() => Command::Usage("/me <action>"),
+        "me" => Command::Me(args.to_string()),
+        "clear" => Command::Clear,
+        "nick" if args.is_empty() || args.contains(char::is_whitespace) => {
+            Command::Usage("/nick <name>")
+        }
+        "nick" => Command::Nick(args.to_string()),
+        _ => Command::Unknown(name.to_string()),
+    };
+    Some(command)
+}
+
+/// The text of a `/me` message as it goes over the wire, if `message` is one.
+pub fn action_text(message: &str) -> Option<&str> {
+    message.strip_prefix("/me ")
+}
@@ -0,0 +1,150 @@
+use std::collections::{HashSet, VecDeque};
+
+use crate::services::protocol::MessageData;
+
+/// Remembers the most recent `capacity` keys, to spot repeats.
+pub struct RecentKeys {
+    capacity: usize,
+    order: VecDeque<String>,
+    seen: HashSet<String>,
+}
+
+impl RecentKeys {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            order: VecDeque::with_capacity(capacity),
+            seen: HashSet::with_capacity(capacity),
+        }
+    }
+
+    /// Records `key`, returning `false` if it was already among the recent
+    /// ones.
+    pub fn insert(&mut self, key: String) -> bool {
+        if self.seen.contains(&key) {
+            return false;
+        }
+        if self.order.len() >= self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.seen.remove(&oldest);
+            }
+        }
+        self.seen.insert(key.clone());
+        self.order.push_back(key);
+        true
+    }
+}
+
+/// Adds a freshly fetched page of history to the `history` already shown,
+/// in time order and without repeats, followed by the `live` messages
+/// that came in meanwhile and aren't in the page. Returns the messages and
+/// how many of them are history.
+pub fn merge_history(
+    mut history: Vec<MessageData>,
+    fetched: Vec<MessageData>,
+    live: Vec<MessageData>,
+) -> (Vec<MessageData>, usize) {
+    let mut keys: HashSet<String> = history.iter().map(MessageData::dedup_key).collect();
+    for message in fetched {
+        if keys.insert(message.dedup_key()) {
+            history.push(message);
+        }
+    }
+    history.sort_by_key(|m| m.sent_at.unwrap_or_default());
+
+    let history_len = history.len();
+    history.extend(live.into_iter().filter(|m| !keys.contains(&m.dedup_key())));
+    (history, history_len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(json: &str) -> MessageData {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn texts(messages: &[MessageData]) -> Vec<&str> {
+        messages.iter().map(|m| m.message.as_str()).collect()
+    }
+
+    #[test]
+    fn duplicate_ids_are_repeats() {
+        let mut recent = RecentKeys::new(10);
+        let first = message(r#"{"id":1,"from":"ana","message":"hi","time":1000}"#);
+        // Redelivered, even if the copy differs in the details.
+        let again = message(r#"{"id":1,"from":"ana","message":"hi!","time":2000}"#);
+        let other = message(r#"{"id":2,"from":"ana","message":"hi","time":1000}"#);
+        assert!(recent.insert(first.dedup_key()));
+        assert!(!recent.insert(again.dedup_key()));
+        assert!(recent.insert(other.dedup_key()));
+    }
+
+    #[test]
+    fn without_ids_the_same_text_in_the_same_minute_is_a_repeat() {
+        let mut recent = RecentKeys::new(10);
+        let first = message(r#"{"from":"ana","message":"hi","time":60000}"#);
+        let again = message(r#"{"from":"ana","message":"hi","time":119999}"#);
+        let later = message(r#"{"from":"ana","message":"hi","time":120000}"#);
+        let someone_else = message(r#"{"from":"budi","message":"hi","time":60000}"#);
+        assert!(recent.insert(first.dedup_key()));
+        assert!(!recent.insert(again.dedup_key()));
+        assert!(recent.insert(later.dedup_key()));
+        assert!(recent.insert(someone_else.dedup_key()));
+    }
+
+    #[test]
+    fn only_the_most_recent_keys_are_kept() {
+        let mut recent = RecentKeys::new(2);
+        assert!(recent.insert("a".into()));
+        assert!(recent.insert("b".into()));
+        assert!(recent.insert("c".into()));
+        assert!(recent.insert("a".into()));
+        assert!(!recent.insert("c".into()));
+    }
+
+    #[test]
+    fn replayed_history_overlapping_live_messages() {
+        let shown = vec![message(
+            r#"{"id":1,"from":"ana","message":"one","time":1000}"#,
+        )];
+        let live = vec![
+            message(r#"{"id":3,"from":"budi","message":"three","time":3000}"#),
+            message(r#"{"id":4,"from":"ana","message":"four","time":4000}"#),
+        ];
+        // The server replays what it has, up to a message already shown live.
+        let fetched = vec![
+            message(r#"{"id":1,"from":"ana","message":"one","time":1000}"#),
+            message(r#"{"id":2,"from":"ana","message":"two","time":2000}"#),
+            message(r#"{"id":3,"from":"budi","message":"three","time":3000}"#),
+        ];
+        let (messages, history_len) = merge_history(shown, fetched, live);
+        assert_eq!(texts(&messages), ["one", "two", "three", "four"]);
+        assert_eq!(history_len, 3);
+    }
+
+    #[test]
+    fn history_is_put_in_time_order() {
+        let fetched = vec![
+            message(r#"{"id":2,"from":"ana","message":"two","time":2000}"#),
+            message(r#"{"id":1,"from":"ana","message":"one","time":1000}"#),
+        ];
+        let (messages, _) = merge_history(vec![], fetched, vec![]);
+        assert_eq!(texts(&messages), ["one", "two"]);
+    }
+
+    #[test]
+    fn empty_input() {
+        let (messages, history_len) = merge_history(vec![], vec![], vec![]);
+        assert!(messages.is_empty());
+        assert_eq!(history_len, 0);
+
+        let live = vec![message(
+            r#"{"id":1,"from":"ana","message":"one","time":1000}"#,
+        )];
+        let (messages, history_len) = merge_history(vec![], vec![], live);
+        assert_eq!(texts(&messages), ["one"]);
+        assert_eq!(history_len, 0);
+    }
+}
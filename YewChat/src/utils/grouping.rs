@@ -0,0 +1,114 @@
+use std::ops::Range;
+
+use crate::services::protocol::{MessageData, MessageKind};
+use crate::utils::commands;
+
+/// Messages from the same sender at most this far apart (ms) are shown as
+/// one group.
+pub const GROUP_WINDOW_MS: u64 = 3 * 60 * 1000;
+
+/// A run of consecutive messages from one sender, as positions in the
+/// thread. Only the first is shown with the sender's name and avatar.
+#[derive(Debug, PartialEq)]
+pub struct MessageGroup {
+    pub messages: Range<usize>,
+}
+
+/// Splits `messages` into groups. A group ends when the sender changes, at
+/// a gap longer than `GROUP_WINDOW_MS`, and around system notices and
+/// `/me` actions, which always stand alone.
+pub fn group_messages(messages: &[MessageData]) -> Vec<MessageGroup> {
+    let mut groups: Vec<MessageGroup> = vec![];
+    for (i, message) in messages.iter().enumerate() {
+        match groups.last_mut() {
+            Some(group) if continues(&messages[i - 1], message) => group.messages.end = i + 1,
+            _ => groups.push(MessageGroup { messages: i..i + 1 }),
+        }
+    }
+    groups
+}
+
+/// Whether `next` can be shown as a follow-up to `previous`.
+fn continues(previous: &MessageData, next: &MessageData) -> bool {
+    let stands_alone = |m: &MessageData| {
+        m.kind == MessageKind::System || commands::action_text(&m.message).is_some()
+    };
+    if previous.from != next.from || stands_alone(previous) || stands_alone(next) {
+        return false;
+    }
+    match (previous.sent_at, next.sent_at) {
+        (Some(a), Some(b)) => b.saturating_sub(a) <= GROUP_WINDOW_MS,
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(from: &str, sent_at: u64) -> MessageData {
+        serde_json::from_value(serde_json::json!({
+            "from": from,
+            "message": "hi",
+            "time": sent_at,
+        }))
+        .unwrap()
+    }
+
+    fn groups(ranges: &[Range<usize>]) -> Vec<MessageGroup> {
+        ranges
+            .iter()
+            .map(|range| MessageGroup {
+                messages: range.clone(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn same_sender_runs_are_one_group() {
+        let messages = [
+            message("ana", 0),
+            message("ana", 1_000),
+            message("ana", 2_000),
+        ];
+        assert_eq!(
+            group_messages(&messages),
+            vec![MessageGroup { messages: 0..3 }]
+        );
+    }
+
+    #[test]
+    fn a_time_gap_starts_a_new_group() {
+        let messages = [
+            message("ana", 0),
+            message("ana", GROUP_WINDOW_MS),
+            message("ana", 2 * GROUP_WINDOW_MS + 1),
+        ];
+        assert_eq!(group_messages(&messages), groups(&[0..2, 2..3]));
+    }
+
+    #[test]
+    fn a_sender_change_starts_a_new_group() {
+        let messages = [
+            message("ana", 0),
+            message("budi", 1_000),
+            message("ana", 2_000),
+        ];
+        assert_eq!(group_messages(&messages), groups(&[0..1, 1..2, 2..3]));
+    }
+
+    #[test]
+    fn notices_and_actions_stand_alone() {
+        let mut notice = message("ana", 1_000);
+        notice.kind = MessageKind::System;
+        let mut action = message("ana", 2_000);
+        action.message = "/me waves".into();
+        let messages = [message("ana", 0), notice, action, message("ana", 3_000)];
+        assert_eq!(group_messages(&messages), groups(&[0..1, 1..2, 2..3, 3..4]));
+    }
+
+    #[test]
+    fn no_messages_no_groups() {
+        assert!(group_messages(&[]).is_empty());
+    }
+}
@@ -0,0 +1,347 @@
+/// Languages code blocks are highlighted for.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Language {
+    Rust,
+    JavaScript,
+    Python,
+    Json,
+    /// Anything else: no highlighting.
+    Plain,
+}
+
+impl Language {
+    /// The language named by the info string of a fenced code block, as in
+    /// ```` ```rust ````. Only its first word counts.
+    pub fn from_info(info: &str) -> Self {
+        let name = info.split_whitespace().next().unwrap_or_default();
+        match name.to_ascii_lowercase().as_str() {
+            "rust" | "rs" => Self::Rust,
+            "js" | "javascript" | "jsx" | "ts" | "typescript" | "tsx" => Self::JavaScript,
+            "py" | "python" | "python3" => Self::Python,
+            "json" => Self::Json,
+            _ => Self::Plain,
+        }
+    }
+
+    fn keywords(self) -> &'static [&'static str] {
+        match self {
+            Self::Rust => &[
+                "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else",
+                "enum", "extern", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod",
+                "move", "mut", "pub", "ref", "return", "self", "Self", "static", "struct", "super",
+                "trait", "type", "unsafe", "use", "where", "while",
+            ],
+            Self::JavaScript => &[
+                "async",
+                "await",
+                "break",
+                "case",
+                "catch",
+                "class",
+                "const",
+                "continue",
+                "default",
+                "delete",
+                "do",
+                "else",
+                "export",
+                "extends",
+                "finally",
+                "for",
+                "from",
+                "function",
+                "if",
+                "import",
+                "in",
+                "instanceof",
+                "interface",
+                "let",
+                "new",
+                "of",
+                "return",
+                "static",
+                "switch",
+                "this",
+                "throw",
+                "try",
+                "type",
+                "typeof",
+                "var",
+                "void",
+                "while",
+                "yield",
+            ],
+            Self::Python => &[
+                "and", "as", "assert", "async", "await", "break", "class", "continue", "def",
+                "del", "elif", "else", "except", "finally", "for", "from", "global", "if",
+                "import", "in", "is", "lambda", "nonlocal", "not", "or", "pass", "raise", "return",
+                "try", "while", "with", "yield",
+            ],
+            Self::Json | Self::Plain => &[],
+        }
+    }
+
+    fn literals(self) -> &'static [&'static str] {
+        match self {
+            Self::Rust => &["true", "false", "None", "Some", "Ok", "Err"],
+            Self::JavaScript => &["true", "false", "null", "undefined", "NaN"],
+            Self::Python => &["True", "False", "None"],
+            Self::Json => &["true", "false", "null"],
+            Self::Plain => &[],
+        }
+    }
+
+    fn line_comment(self) -> Option<&'static str> {
+        match self {
+            Self::Rust | Self::JavaScript => Some("//"),
+            Self::Python => Some("#"),
+            Self::Json | Self::Plain => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TokenKind {
+    Plain,
+    Keyword,
+    /// `true`, `null`, `None` and the like.
+    Literal,
+    String,
+    Number,
+    Comment,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Token<'a> {
+    pub kind: TokenKind,
+    pub text: &'a str,
+}
+
+/// Splits `code` into tokens to color. This is a rough lexer rather than a
+/// parser: it only has to look right, and it never fails, however odd the
+/// input. Joining the tokens' text gives back `code`.
+pub fn tokenize(code: &str, language: Language) -> Vec<Token<'_>> {
+    let mut tokens: Vec<Token> = vec![];
+    if language == Language::Plain {
+        tokens.push(Token {
+            kind: TokenKind::Plain,
+            text: code,
+        });
+        return tokens;
+    }
+
+    let mut start = 0;
+    while let Some(c) = code[start..].chars().next() {
+        let rest = &code[start..];
+        let (kind, len) = if language.line_comment().is_some_and(|l| rest.starts_with(l)) {
+            (TokenKind::Comment, rest.find('\n').unwrap_or(rest.len()))
+        } else if language != Language::Python && rest.starts_with("/*") {
+            let len = rest[2..].find("*/").map_or(rest.len(), |end| end + 4);
+            (TokenKind::Comment, len)
+        } else if let Some(len) = string_len(rest, language) {
+            (TokenKind::String, len)
+        } else if c.is_ascii_digit() {
+            let len = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '.'))
+                .unwrap_or(rest.len());
+            (TokenKind::Number, len)
+        } else if c.is_alphabetic() || c == '_' {
+            let len = rest
+                .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            let word = &rest[..len];
+            let kind = if language.keywords().contains(&word) {
+                TokenKind::Keyword
+            } else if language.literals().contains(&word) {
+                TokenKind::Literal
+            } else {
+                TokenKind::Plain
+            };
+            (kind, len)
+        } else {
+            (TokenKind::Plain, c.len_utf8())
+        };
+
+        let text = &code[start..start + len];
+        match tokens.last_mut() {
+            // Runs of plain text come out as one token.
+            Some(last) if last.kind == TokenKind::Plain && kind == TokenKind::Plain => {
+                last.text = &code[start - last.text.len()..start + len];
+            }
+            _ => tokens.push(Token { kind, text }),
+        }
+        start += len;
+    }
+    tokens
+}
+
+/// Length of the string literal `text` starts with, if it does. Strings
+/// left open run to the end of the line, or of the code for Python's
+/// triple-quoted ones.
+fn string_len(text: &str, language: Language) -> Option<usize> {
+    if language == Language::Python {
+        for quotes in ["\"\"\"", "'''"] {
+            if let Some(body) = text.strip_prefix(quotes) {
+                return Some(body.find(quotes).map_or(text.len(), |end| end + 6));
+            }
+        }
+    }
+    let quote = text.chars().next()?;
+    let quoted = match language {
+        Language::Rust => quote == '"' || (quote == '\'' && is_char_literal(text)),
+        Language::JavaScript => matches!(quote, '"' | '\'' | '`'),
+        Language::Python => matches!(quote, '"' | '\''),
+        Language::Json => quote == '"',
+        Language::Plain => false,
+    };
+    if !quoted {
+        return None;
+    }
+
+    let mut escaped = false;
+    for (i, c) in text.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            // Template literals and Rust strings may span lines.
+            '\n' if quote != '`' && language != Language::Rust => return Some(i),
+            _ if c == quote => return Some(i + 1),
+            _ => {}
+        }
+    }
+    Some(text.len())
+}
+
+/// Whether the `'` `text` starts with opens a char literal like `'a'` or
+/// `'\n'`, rather than a lifetime.
+fn is_char_literal(text: &str) -> bool {
+    let mut chars = text.chars().skip(1);
+    match chars.next() {
+        Some('\\') => true,
+        Some(_) => chars.next() == Some('\''),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(code: &str, language: Language) -> Vec<(TokenKind, &str)> {
+        let tokens = tokenize(code, language);
+        let joined: String = tokens.iter().map(|token| token.text).collect();
+        assert_eq!(joined, code, "the tokens don't add up to the code");
+        tokens
+            .into_iter()
+            .map(|token| (token.kind, token.text))
+            .collect()
+    }
+
+    #[test]
+    fn words_are_told_apart() {
+        assert_eq!(
+            tokens("let x = None; // done", Language::Rust),
+            vec![
+                (TokenKind::Keyword, "let"),
+                (TokenKind::Plain, " x = "),
+                (TokenKind::Literal, "None"),
+                (TokenKind::Plain, "; "),
+                (TokenKind::Comment, "// done"),
+            ]
+        );
+    }
+
+    #[test]
+    fn an_unterminated_string_runs_to_the_end_of_the_line() {
+        assert_eq!(
+            tokens("x = \"open\ny = 1", Language::Python),
+            vec![
+                (TokenKind::Plain, "x = "),
+                (TokenKind::String, "\"open"),
+                (TokenKind::Plain, "\ny = "),
+                (TokenKind::Number, "1"),
+            ]
+        );
+        assert_eq!(
+            tokens("s = '''open\nstill", Language::Python),
+            vec![
+                (TokenKind::Plain, "s = "),
+                (TokenKind::String, "'''open\nstill"),
+            ]
+        );
+        assert_eq!(
+            tokens("\"open", Language::Json),
+            vec![(TokenKind::String, "\"open")]
+        );
+    }
+
+    #[test]
+    fn an_unterminated_comment_runs_to_the_end() {
+        assert_eq!(
+            tokens("a /* open\nfn", Language::Rust),
+            vec![
+                (TokenKind::Plain, "a "),
+                (TokenKind::Comment, "/* open\nfn")
+            ]
+        );
+        assert_eq!(
+            tokens("/*", Language::JavaScript),
+            vec![(TokenKind::Comment, "/*")]
+        );
+    }
+
+    #[test]
+    fn multi_byte_characters_next_to_delimiters() {
+        assert_eq!(
+            tokens("\"héllo\"é'ü'", Language::Rust),
+            vec![
+                (TokenKind::String, "\"héllo\""),
+                (TokenKind::Plain, "é"),
+                (TokenKind::String, "'ü'"),
+            ]
+        );
+        assert_eq!(
+            tokens("x = `日本`// 語", Language::JavaScript),
+            vec![
+                (TokenKind::Plain, "x = "),
+                (TokenKind::String, "`日本`"),
+                (TokenKind::Comment, "// 語"),
+            ]
+        );
+        assert_eq!(
+            tokens("ß/*ü*/ß", Language::JavaScript),
+            vec![
+                (TokenKind::Plain, "ß"),
+                (TokenKind::Comment, "/*ü*/"),
+                (TokenKind::Plain, "ß"),
+            ]
+        );
+        assert_eq!(
+            tokens("\"€\\", Language::Json),
+            vec![(TokenKind::String, "\"€\\")]
+        );
+    }
+
+    #[test]
+    fn empty_code_has_no_tokens() {
+        for language in [
+            Language::Rust,
+            Language::JavaScript,
+            Language::Python,
+            Language::Json,
+        ] {
+            assert!(tokenize("", language).is_empty(), "{:?}", language);
+        }
+    }
+
+    #[test]
+    fn unknown_languages_are_left_plain() {
+        assert_eq!(Language::from_info("brainfuck"), Language::Plain);
+        assert_eq!(Language::from_info(""), Language::Plain);
+        assert_eq!(Language::from_info("RUST ignore"), Language::Rust);
+        assert_eq!(
+            tokens("let x = \"y\"; // z", Language::Plain),
+            vec![(TokenKind::Plain, "let x = \"y\"; // z")]
+        );
+    }
+}
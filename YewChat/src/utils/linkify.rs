@@ -0,0 +1,146 @@
+/// A piece of message text: either shown as is, or a web address to link.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Segment<'a> {
+    Text(&'a str),
+    Link(&'a str),
+}
+
+/// Splits `text` into plain text and the http(s) URLs in it. Punctuation
+/// right after a URL, as in "see https://example.com!", stays text. Other
+/// schemes, `javascript:` and `data:` included, are never links.
+pub fn split_into_segments(text: &str) -> Vec<Segment<'_>> {
+    let mut segments = vec![];
+    // End of the last link found, and where to look for the next one.
+    let mut last = 0;
+    let mut from = 0;
+    while let Some(start) = url_start(text, from) {
+        let len = url_len(&text[start..]);
+        if len <= scheme_len(&text[start..]).unwrap_or_default() {
+            // Nothing but "https://".
+            from = start + 1;
+            continue;
+        }
+        if last < start {
+            segments.push(Segment::Text(&text[last..start]));
+        }
+        segments.push(Segment::Link(&text[start..start + len]));
+        last = start + len;
+        from = last;
+    }
+    if last < text.len() {
+        segments.push(Segment::Text(&text[last..]));
+    }
+    segments
+}
+
+/// Offset of the first URL at or after `from` that isn't glued to the end
+/// of a word.
+fn url_start(text: &str, from: usize) -> Option<usize> {
+    text[from..]
+        .char_indices()
+        .map(|(i, _)| from + i)
+        .find(|&i| {
+            let starts_word = text[..i]
+                .chars()
+                .next_back()
+                .is_none_or(|c| !c.is_alphanumeric());
+            starts_word && scheme_len(&text[i..]).is_some()
+        })
+}
+
+/// Length of the `http://` or `https://`, in any case, `text` starts with.
+fn scheme_len(text: &str) -> Option<usize> {
+    ["https://", "http://"]
+        .iter()
+        .find(|scheme| {
+            text.get(..scheme.len())
+                .is_some_and(|prefix| prefix.eq_ignore_ascii_case(scheme))
+        })
+        .map(|scheme| scheme.len())
+}
+
+/// Length of the URL `text` starts with.
+fn url_len(text: &str) -> usize {
+    let end = text
+        .find(|c: char| c.is_whitespace() || matches!(c, '<' | '>' | '"'))
+        .unwrap_or(text.len());
+    let mut url = &text[..end];
+    // Punctuation ending a sentence isn't part of the link, and neither is
+    // a closing parenthesis without an opening one, as in "(https://x.y)".
+    loop {
+        let mut trimmed = url.trim_end_matches(['.', ',', '!', '?', ';', ':', '\'', '*', '_']);
+        if trimmed.ends_with(')') && trimmed.matches(')').count() > trimmed.matches('(').count() {
+            trimmed = &trimmed[..trimmed.len() - 1];
+        }
+        if trimmed.len() == url.len() {
+            return url.len();
+        }
+        url = trimmed;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Segment::{Link, Text};
+
+    #[test]
+    fn trailing_punctuation_stays_text() {
+        assert_eq!(
+            split_into_segments("check https://example.com!"),
+            [Text("check "), Link("https://example.com"), Text("!")]
+        );
+        assert_eq!(
+            split_into_segments("(see https://example.com/a_(b)), ok?"),
+            [
+                Text("(see "),
+                Link("https://example.com/a_(b)"),
+                Text("), ok?"),
+            ]
+        );
+        assert_eq!(
+            split_into_segments("https://example.com/path?q=1."),
+            [Link("https://example.com/path?q=1"), Text(".")]
+        );
+    }
+
+    #[test]
+    fn every_url_is_a_link() {
+        assert_eq!(
+            split_into_segments("http://a.example and HTTPS://b.example/x"),
+            [
+                Link("http://a.example"),
+                Text(" and "),
+                Link("HTTPS://b.example/x"),
+            ]
+        );
+        assert_eq!(
+            split_into_segments("https://a.example\nhttps://b.example"),
+            [
+                Link("https://a.example"),
+                Text("\n"),
+                Link("https://b.example"),
+            ]
+        );
+    }
+
+    #[test]
+    fn other_schemes_are_not_links() {
+        for text in [
+            "javascript:alert(1)",
+            "data:text/html;base64,PGI+",
+            "ftp://example.com/file",
+            "mailto:ana@example.com",
+            "xhttps://example.com",
+            "https://",
+        ] {
+            assert_eq!(split_into_segments(text), [Text(text)], "{}", text);
+        }
+    }
+
+    #[test]
+    fn text_without_links_is_one_segment() {
+        assert_eq!(split_into_segments("héllo wörld"), [Text("héllo wörld")]);
+        assert!(split_into_segments("").is_empty());
+    }
+}
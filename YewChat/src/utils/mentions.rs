@@ -0,0 +1,161 @@
+use std::ops::Range;
+
+/// The `@name` token the caret at byte offset `caret` of `value` is in:
+/// its byte range, from the `@` to the end of the word, and the part of
+/// the name before the caret. The `@` has to start a word.
+pub fn find_mention_token(value: &str, caret: usize) -> Option<(Range<usize>, &str)> {
+    let before = value.get(..caret)?;
+    let at = before.rfind('@')?;
+    let query = &before[at + 1..];
+    let starts_word = before[..at]
+        .chars()
+        .next_back()
+        .is_none_or(char::is_whitespace);
+    if !starts_word || query.contains(char::is_whitespace) {
+        return None;
+    }
+    let after = &value[caret..];
+    let end = caret + after.find(char::is_whitespace).unwrap_or(after.len());
+    Some((at..end, query))
+}
+
+/// Name that mentions everyone, as in `@all`.
+const EVERYONE: &str = "all";
+
+/// Byte ranges of the mentions of `name` in `text`: `@name` in any case,
+/// or `@all`. A mention has to start a word and end where the name does,
+/// so `@bo` doesn't count in `@bob` or `mail@bo`.
+pub fn mention_ranges(text: &str, name: &str) -> Vec<(usize, usize)> {
+    text.match_indices('@')
+        .filter(|(start, _)| {
+            text[..*start]
+                .chars()
+                .next_back()
+                .is_none_or(|c| !is_name_char(c))
+        })
+        .filter_map(|(start, _)| {
+            let rest = &text[start + 1..];
+            [name, EVERYONE]
+                .into_iter()
+                .filter(|name| !name.is_empty())
+                .find_map(|name| {
+                    let after = strip_prefix_ignore_case(rest, name)?;
+                    let ends_word = after.chars().next().is_none_or(|c| !is_name_char(c));
+                    ends_word.then(|| (start, text.len() - after.len()))
+                })
+        })
+        .collect()
+}
+
+/// Whether `text` mentions `name`.
+pub fn mentions(text: &str, name: &str) -> bool {
+    !mention_ranges(text, name).is_empty()
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '-' | '@')
+}
+
+/// `text` after `prefix`, if it starts with it in any case.
+fn strip_prefix_ignore_case<'a>(text: &'a str, prefix: &str) -> Option<&'a str> {
+    let mut chars = text.chars();
+    for expected in prefix.chars() {
+        if !chars.next()?.to_lowercase().eq(expected.to_lowercase()) {
+            return None;
+        }
+    }
+    Some(chars.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn token_with_the_caret_mid_word() {
+        let value = "hi @bob there";
+        assert_eq!(find_mention_token(value, 6), Some((3..7, "bo")));
+        assert_eq!(find_mention_token(value, 4), Some((3..7, "")));
+        assert_eq!(find_mention_token(value, 7), Some((3..7, "bob")));
+    }
+
+    #[test]
+    fn no_token_after_whitespace() {
+        assert_eq!(find_mention_token("hi @bob there", 8), None);
+        assert_eq!(find_mention_token("hi @bob ", 8), None);
+        assert_eq!(find_mention_token("hi ", 3), None);
+    }
+
+    #[test]
+    fn token_at_the_start() {
+        assert_eq!(find_mention_token("", 0), None);
+        assert_eq!(find_mention_token("@bob", 0), None);
+        assert_eq!(find_mention_token("@", 1), Some((0..1, "")));
+        assert_eq!(find_mention_token("@bob hi", 2), Some((0..4, "b")));
+    }
+
+    #[test]
+    fn token_in_a_multibyte_string() {
+        let value = "héllo @jörg ✓";
+        let at = value.find('@').unwrap();
+        let caret = at + "@jö".len();
+        assert_eq!(
+            find_mention_token(value, caret),
+            Some((at..at + "@jörg".len(), "jö"))
+        );
+        // Inside the ö.
+        assert_eq!(find_mention_token(value, caret - 1), None);
+        assert_eq!(find_mention_token(value, value.len() + 1), None);
+    }
+
+    #[test]
+    fn the_at_has_to_start_a_word() {
+        assert_eq!(find_mention_token("mail@bob", 8), None);
+        assert_eq!(find_mention_token("(@bob", 5), None);
+    }
+
+    #[test]
+    fn mentions_end_at_word_boundaries() {
+        assert_eq!(mention_ranges("hi @bo!", "bo"), [(3, 6)]);
+        assert_eq!(mention_ranges("@bob", "bo"), []);
+        assert_eq!(mention_ranges("mail@bo", "bo"), []);
+        assert_eq!(mention_ranges("@bo_b @bo-b @bo", "bo"), [(12, 15)]);
+        assert_eq!(mention_ranges("@bo, @bo", "bo"), [(0, 3), (5, 8)]);
+    }
+
+    #[test]
+    fn mentions_ignore_case() {
+        assert_eq!(mention_ranges("hey @BOB", "bob"), [(4, 8)]);
+        assert_eq!(mention_ranges("hey @bob", "Bob"), [(4, 8)]);
+        assert_eq!(mention_ranges("@ALL listen", "bob"), [(0, 4)]);
+        assert!(!mentions("@alligator", "bob"));
+    }
+
+    #[test]
+    fn mentions_of_multibyte_names() {
+        let text = "danke @JÖRG und @jörgen";
+        let start = text.find('@').unwrap();
+        assert_eq!(
+            mention_ranges(text, "jörg"),
+            [(start, start + "@JÖRG".len())]
+        );
+        assert_eq!(
+            mention_ranges("@ünïcode", "ünïcode"),
+            [(0, "@ünïcode".len())]
+        );
+    }
+
+    #[test]
+    fn names_are_matched_literally() {
+        assert_eq!(mention_ranges("@a.b+", "a.b+"), [(0, 5)]);
+        assert_eq!(mention_ranges("@axb", "a.b"), []);
+        assert!(mentions("ping @[x]*", "[x]*"));
+        assert!(!mentions("ping @x", "[x]*"));
+    }
+
+    #[test]
+    fn an_empty_name_only_hears_all() {
+        assert_eq!(mention_ranges("@ hi", ""), []);
+        assert_eq!(mention_ranges("@all", ""), [(0, 4)]);
+    }
+}
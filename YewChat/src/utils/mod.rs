@@ -0,0 +1,13 @@
+pub mod commands;
+pub mod content;
+pub mod dedup;
+pub mod grouping;
+pub mod highlight;
+pub mod linkify;
+pub mod mentions;
+pub mod rate_limit;
+pub mod search;
+pub mod shortcodes;
+pub mod theme;
+pub mod time;
+pub mod translations;
\ No newline at end of file
@@ -0,0 +1,109 @@
+use std::collections::VecDeque;
+
+/// Allows at most `limit` sends in any `window_ms` long stretch of time.
+pub struct RateLimiter {
+    limit: usize,
+    window_ms: f64,
+    /// When the sends still inside the window happened, oldest first.
+    sent: VecDeque<f64>,
+}
+
+impl RateLimiter {
+    pub fn new(limit: usize, window_ms: f64) -> Self {
+        Self {
+            limit,
+            window_ms,
+            sent: VecDeque::with_capacity(limit),
+        }
+    }
+
+    /// Records a send if the limit allows one right now, and says whether
+    /// it did.
+    pub fn try_send(&mut self) -> bool {
+        self.try_send_at(now())
+    }
+
+    /// Milliseconds until `try_send` can succeed again, 0 if it can now.
+    pub fn wait_ms(&self) -> f64 {
+        self.wait_ms_at(now())
+    }
+
+    /// `try_send`, as of `now`.
+    fn try_send_at(&mut self, now: f64) -> bool {
+        while self
+            .sent
+            .front()
+            .is_some_and(|sent| now - sent >= self.window_ms)
+        {
+            self.sent.pop_front();
+        }
+        if self.sent.len() >= self.limit {
+            return false;
+        }
+        self.sent.push_back(now);
+        true
+    }
+
+    /// `wait_ms`, as of `now`.
+    fn wait_ms_at(&self, now: f64) -> f64 {
+        if self.sent.len() < self.limit {
+            return 0.0;
+        }
+        self.sent
+            .front()
+            .map_or(0.0, |oldest| (oldest + self.window_ms - now).max(0.0))
+    }
+}
+
+/// Milliseconds on a clock that doesn't jump when the system time changes.
+fn now() -> f64 {
+    web_sys::window()
+        .and_then(|w| w.performance())
+        .map_or_else(js_sys::Date::now, |performance| performance.now())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_burst_up_to_the_limit_is_allowed() {
+        let mut limiter = RateLimiter::new(3, 1000.0);
+        for at in [0.0, 10.0, 20.0] {
+            assert_eq!(limiter.wait_ms_at(at), 0.0);
+            assert!(limiter.try_send_at(at), "send at {}", at);
+        }
+    }
+
+    #[test]
+    fn the_next_send_is_refused_until_the_window_passes() {
+        let mut limiter = RateLimiter::new(3, 1000.0);
+        for at in [0.0, 10.0, 20.0] {
+            assert!(limiter.try_send_at(at));
+        }
+        assert!(!limiter.try_send_at(30.0));
+        assert_eq!(limiter.wait_ms_at(30.0), 970.0);
+        assert!(!limiter.try_send_at(999.0));
+        assert_eq!(limiter.wait_ms_at(999.0), 1.0);
+    }
+
+    #[test]
+    fn sends_are_allowed_again_after_the_window() {
+        let mut limiter = RateLimiter::new(3, 1000.0);
+        for at in [0.0, 10.0, 20.0] {
+            assert!(limiter.try_send_at(at));
+        }
+        // Only the first send has left the window.
+        assert_eq!(limiter.wait_ms_at(1000.0), 0.0);
+        assert!(limiter.try_send_at(1000.0));
+        assert!(!limiter.try_send_at(1005.0));
+        assert_eq!(limiter.wait_ms_at(1005.0), 5.0);
+        assert!(limiter.try_send_at(1010.0));
+
+        // Long after, the whole burst is there again.
+        for at in [5000.0, 5001.0, 5002.0] {
+            assert!(limiter.try_send_at(at));
+        }
+        assert!(!limiter.try_send_at(5003.0));
+    }
+}
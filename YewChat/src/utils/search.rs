@@ -0,0 +1,46 @@
+/// Byte ranges of the case-insensitive, non-overlapping occurrences of
+/// `query` in `text`.
+pub fn match_ranges(text: &str, query: &str) -> Vec<(usize, usize)> {
+    let query: Vec<char> = query.chars().flat_map(char::to_lowercase).collect();
+    if query.is_empty() {
+        return vec![];
+    }
+
+    let mut ranges = vec![];
+    let mut next = 0;
+    for (start, _) in text.char_indices() {
+        if start < next {
+            continue;
+        }
+        if let Some(len) = match_len(&text[start..], &query) {
+            ranges.push((start, start + len));
+            next = start + len;
+        }
+    }
+    ranges
+}
+
+/// Whether `text` contains `query`, ignoring case.
+pub fn contains(text: &str, query: &str) -> bool {
+    let query: Vec<char> = query.chars().flat_map(char::to_lowercase).collect();
+    text.char_indices()
+        .any(|(start, _)| match_len(&text[start..], &query).is_some())
+}
+
+/// Length in bytes of the prefix of `text` matching the already lowercased
+/// `query`, if there is one.
+fn match_len(text: &str, query: &[char]) -> Option<usize> {
+    let mut rest = query;
+    for (i, c) in text.char_indices() {
+        if rest.is_empty() {
+            return Some(i);
+        }
+        for lower in c.to_lowercase() {
+            match rest.split_first() {
+                Some((q, tail)) if *q == lower => rest = tail,
+                _ => return None,
+            }
+        }
+    }
+    rest.is_empty().then_some(text.len())
+}
@@ -0,0 +1,191 @@
+use std::collections::HashMap;
+
+/// `:name:` shortcodes understood in messages, Slack/GitHub style.
+const SHORTCODES: &[(&str, &str)] = &[
+    ("+1", "👍"),
+    ("-1", "👎"),
+    ("100", "💯"),
+    ("angry", "😠"),
+    ("blush", "😊"),
+    ("broken_heart", "💔"),
+    ("cat", "🐱"),
+    ("check", "✅"),
+    ("clap", "👏"),
+    ("coffee", "☕"),
+    ("confused", "😕"),
+    ("cool", "😎"),
+    ("crab", "🦀"),
+    ("cry", "😢"),
+    ("dog", "🐶"),
+    ("eyes", "👀"),
+    ("fire", "🔥"),
+    ("grin", "😁"),
+    ("grinning", "😀"),
+    ("heart", "❤️"),
+    ("heart_eyes", "😍"),
+    ("hugs", "🤗"),
+    ("innocent", "😇"),
+    ("joy", "😂"),
+    ("kiss", "😘"),
+    ("laughing", "😆"),
+    ("muscle", "💪"),
+    ("neutral_face", "😐"),
+    ("ok_hand", "👌"),
+    ("open_mouth", "😮"),
+    ("party", "🥳"),
+    ("pizza", "🍕"),
+    ("pray", "🙏"),
+    ("question", "❓"),
+    ("rage", "😡"),
+    ("raised_hands", "🙌"),
+    ("rocket", "🚀"),
+    ("rofl", "🤣"),
+    ("scream", "😱"),
+    ("see_no_evil", "🙈"),
+    ("shrug", "🤷"),
+    ("sleeping", "😴"),
+    ("slightly_smiling_face", "🙂"),
+    ("smile", "😄"),
+    ("smiley", "😃"),
+    ("smirk", "😏"),
+    ("sob", "😭"),
+    ("sparkles", "✨"),
+    ("star", "⭐"),
+    ("sunglasses", "😎"),
+    ("sweat_smile", "😅"),
+    ("tada", "🎉"),
+    ("thinking", "🤔"),
+    ("thumbsdown", "👎"),
+    ("thumbsup", "👍"),
+    ("upside_down_face", "🙃"),
+    ("warning", "⚠️"),
+    ("wave", "👋"),
+    ("wink", "😉"),
+    ("x", "❌"),
+    ("yum", "😋"),
+    ("zap", "⚡"),
+    ("zzz", "💤"),
+];
+
+thread_local! {
+    static TABLE: HashMap<&'static str, &'static str> = SHORTCODES.iter().copied().collect();
+}
+
+/// Replaces known `:name:` shortcodes in `text` with their emoji. Unknown
+/// ones are left alone, and so is anything inside `code spans` or fenced
+/// code blocks.
+pub fn replace_shortcodes(text: &str) -> String {
+    let pieces = split_backticks(text);
+    let mut out = String::with_capacity(text.len());
+    let mut i = 0;
+    while i < pieces.len() {
+        let piece = pieces[i];
+        // Code is closed by the same number of backticks that opened it; an
+        // opener without a match is just text.
+        if piece.starts_with('`') {
+            if let Some(close) = (i + 1..pieces.len()).find(|&j| pieces[j] == piece) {
+                pieces[i..=close].iter().for_each(|p| out.push_str(p));
+                i = close + 1;
+                continue;
+            }
+        }
+        expand_into(&mut out, piece);
+        i += 1;
+    }
+    out
+}
+
+/// Shortcode names that stand for `emoji`, e.g. "smile" for 😄.
+pub fn names_of(emoji: &str) -> impl Iterator<Item = &'static str> + '_ {
+    SHORTCODES
+        .iter()
+        .filter(move |(_, e)| *e == emoji)
+        .map(|(name, _)| *name)
+}
+
+/// Cuts `text` into runs of backticks and the text between them.
+fn split_backticks(text: &str) -> Vec<&str> {
+    let mut pieces = vec![];
+    let mut rest = text;
+    while !rest.is_empty() {
+        let ticks = rest.len() - rest.trim_start_matches('`').len();
+        let len = match ticks {
+            0 => rest.find('`').unwrap_or(rest.len()),
+            _ => ticks,
+        };
+        let (piece, tail) = rest.split_at(len);
+        pieces.push(piece);
+        rest = tail;
+    }
+    pieces
+}
+
+fn expand_into(out: &mut String, text: &str) {
+    let mut rest = text;
+    while let Some(start) = rest.find(':') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let len = after
+            .find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '_' | '+' | '-')))
+            .unwrap_or(after.len());
+        let emoji = match after[len..].starts_with(':') {
+            true => TABLE.with(|table| table.get(&after[..len]).copied()),
+            false => None,
+        };
+        match emoji {
+            Some(emoji) => {
+                out.push_str(emoji);
+                rest = &after[len + 1..];
+            }
+            None => {
+                out.push(':');
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn known_codes_are_replaced() {
+        assert_eq!(replace_shortcodes(":smile:"), "😄");
+        assert_eq!(replace_shortcodes("ship it :+1: :rocket:"), "ship it 👍 🚀");
+    }
+
+    #[test]
+    fn unknown_codes_are_left_alone() {
+        assert_eq!(replace_shortcodes(":nope:"), ":nope:");
+        assert_eq!(replace_shortcodes(":smile"), ":smile");
+        assert_eq!(replace_shortcodes(":Smile:"), ":Smile:");
+    }
+
+    #[test]
+    fn lone_colons_are_left_alone() {
+        for text in [":", "::", "a: b", "10:30", "ratio 1:2:3"] {
+            assert_eq!(replace_shortcodes(text), text);
+        }
+    }
+
+    #[test]
+    fn adjacent_codes_are_all_replaced() {
+        assert_eq!(replace_shortcodes(":smile::wave:"), "😄👋");
+        assert_eq!(replace_shortcodes("::smile:"), ":😄");
+        assert_eq!(replace_shortcodes(":nope::wave:"), ":nope:👋");
+    }
+
+    #[test]
+    fn codes_inside_words_are_replaced() {
+        assert_eq!(replace_shortcodes("so:tada:cool"), "so🎉cool");
+        assert_eq!(replace_shortcodes("héé:fire:ü"), "héé🔥ü");
+    }
+
+    #[test]
+    fn code_is_left_alone() {
+        assert_eq!(replace_shortcodes("`:smile:` :smile:"), "`:smile:` 😄");
+        assert_eq!(replace_shortcodes("``:x:`` `:x:"), "``:x:`` `❌");
+    }
+}
@@ -0,0 +1,53 @@
+use web_sys::Storage;
+
+/// Where the chosen theme is kept, "on" for dark and "off" for light.
+const THEME_KEY: &str = "yewchat.dark";
+const PREFERS_DARK: &str = "(prefers-color-scheme: dark)";
+
+/// The colours the UI is shown in. Dark turns on Tailwind's `dark:`
+/// variants for everything under the element with its `class`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Theme {
+    Light,
+    Dark,
+}
+
+impl Theme {
+    /// The theme saved in `localStorage`, or else the system's.
+    pub fn load() -> Self {
+        let saved = storage().and_then(|s| s.get_item(THEME_KEY).ok().flatten());
+        let dark = match saved.as_deref() {
+            Some(value) => value == "on",
+            None => web_sys::window()
+                .and_then(|w| w.match_media(PREFERS_DARK).ok().flatten())
+                .is_some_and(|query| query.matches()),
+        };
+        if dark {
+            Theme::Dark
+        } else {
+            Theme::Light
+        }
+    }
+
+    pub fn save(self) {
+        let value = if self == Theme::Dark { "on" } else { "off" };
+        if let Some(Err(e)) = storage().map(|s| s.set_item(THEME_KEY, value)) {
+            log::warn!("theme: failed to save: {:?}", e);
+        }
+    }
+
+    pub fn toggled(self) -> Self {
+        match self {
+            Theme::Light => Theme::Dark,
+            Theme::Dark => Theme::Light,
+        }
+    }
+
+    pub fn class(self) -> Option<&'static str> {
+        (self == Theme::Dark).then_some("dark")
+    }
+}
+
+fn storage() -> Option<Storage> {
+    web_sys::window()?.local_storage().ok().flatten()
+}
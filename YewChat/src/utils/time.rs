@@ -0,0 +1,373 @@
+use std::cell::{OnceCell, RefCell};
+use std::collections::HashMap;
+
+use js_sys::{Array, Date, Function, Intl, Object, Reflect};
+use serde::{Deserialize, Deserializer};
+use wasm_bindgen::{JsCast, JsValue};
+
+use crate::utils::translations::language;
+
+const DAY_MS: i64 = 86_400_000;
+
+thread_local! {
+    /// `local_zone`, which stays put while the page is open.
+    static ZONE: OnceCell<(String, String)> = const { OnceCell::new() };
+    /// Formatters `format` has made, by `formatter_key`. Making one costs
+    /// far more than using it, and every message shows a couple of times.
+    static FORMATTERS: RefCell<HashMap<String, Intl::DateTimeFormat>> =
+        RefCell::new(HashMap::new());
+}
+
+/// Formats an epoch-millisecond timestamp as the time for today's messages
+/// and as the date and time for older ones, the way my locale writes them.
+/// Timestamps ahead of the local clock (server and client not in sync) are
+/// clamped to "now".
+pub fn short_label(ms: u64) -> String {
+    let now = Date::new_0();
+    let date = local_date(ms, &now);
+    let is_today = local_day(date.get_time() as u64, date.get_timezone_offset())
+        == local_day(now.get_time() as u64, now.get_timezone_offset());
+    let options: &[(&str, &str)] = if is_today {
+        &[("hour", "2-digit"), ("minute", "2-digit")]
+    } else {
+        &[
+            ("month", "short"),
+            ("day", "numeric"),
+            ("hour", "2-digit"),
+            ("minute", "2-digit"),
+        ]
+    };
+    format(&date, options).unwrap_or_default()
+}
+
+/// Formats an epoch-millisecond timestamp with the year and seconds too,
+/// for tooltips.
+pub fn full_label(ms: u64) -> String {
+    let date = local_date(ms, &Date::new_0());
+    let options = [
+        ("year", "numeric"),
+        ("month", "short"),
+        ("day", "numeric"),
+        ("hour", "2-digit"),
+        ("minute", "2-digit"),
+        ("second", "2-digit"),
+    ];
+    format(&date, &options).unwrap_or_default()
+}
+
+/// What time it is now in `timezone`, written my way. `None` for a time
+/// zone this browser doesn't know.
+pub fn now_in(timezone: &str) -> Option<String> {
+    let options = [
+        ("hour", "2-digit"),
+        ("minute", "2-digit"),
+        ("timeZone", timezone),
+    ];
+    format(&Date::new_0(), &options)
+}
+
+/// This browser's time zone and locale, e.g. ("Asia/Jakarta", "id-ID").
+pub fn local_zone() -> (String, String) {
+    ZONE.with(|zone| {
+        zone.get_or_init(|| {
+            let options =
+                Intl::DateTimeFormat::new(&Array::new(), &Object::new()).resolved_options();
+            let get = |key: &str| {
+                Reflect::get(&options, &key.into())
+                    .ok()
+                    .and_then(|value| value.as_string())
+                    .unwrap_or_default()
+            };
+            (get("timeZone"), get("locale"))
+        })
+        .clone()
+    })
+}
+
+/// The browser's locale if it's in the language the UI is shown in, so
+/// that e.g. en-GB keeps its 24-hour clock; otherwise just that language.
+fn display_locale() -> String {
+    let (_, locale) = local_zone();
+    pick_locale(&locale, language().code())
+}
+
+fn pick_locale(browser: &str, language: &str) -> String {
+    if browser.split('-').next() == Some(language) {
+        browser.to_string()
+    } else {
+        language.to_string()
+    }
+}
+
+/// `date` formatted by `Intl.DateTimeFormat` with these options, or `None`
+/// if it rejects them.
+fn format(date: &Date, options: &[(&str, &str)]) -> Option<String> {
+    let formatter = formatter(options)?;
+    formatter
+        .format()
+        .call1(&JsValue::UNDEFINED, date)
+        .ok()?
+        .as_string()
+}
+
+/// Identifies a formatter by what it's made from.
+fn formatter_key(locale: &str, options: &[(&str, &str)]) -> String {
+    let mut key = locale.to_string();
+    for (name, value) in options {
+        key.push_str(&format!(";{}={}", name, value));
+    }
+    key
+}
+
+/// The `Intl.DateTimeFormat` for the display locale and these options,
+/// made the first time it's asked for.
+fn formatter(options: &[(&str, &str)]) -> Option<Intl::DateTimeFormat> {
+    let locale = display_locale();
+    let key = formatter_key(&locale, options);
+    if let Some(formatter) = FORMATTERS.with(|f| f.borrow().get(&key).cloned()) {
+        return Some(formatter);
+    }
+    let object = Object::new();
+    for (key, value) in options {
+        Reflect::set(&object, &(*key).into(), &(*value).into()).ok()?;
+    }
+    // Constructed through `Reflect`, since `Intl::DateTimeFormat::new`
+    // panics on the RangeError an unknown time zone throws.
+    let intl = Reflect::get(&js_sys::global(), &"Intl".into()).ok()?;
+    let constructor: Function = Reflect::get(&intl, &"DateTimeFormat".into())
+        .ok()?
+        .dyn_into()
+        .ok()?;
+    let arguments = Array::of2(&locale.into(), &object);
+    let formatter: Intl::DateTimeFormat = Reflect::construct(&constructor, &arguments)
+        .ok()?
+        .unchecked_into();
+    FORMATTERS.with(|f| f.borrow_mut().insert(key, formatter.clone()));
+    Some(formatter)
+}
+
+/// Reads a timestamp sent as epoch ms or as an RFC 3339 string. One that
+/// can't be read counts as missing.
+pub fn deserialize_timestamp<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<u64>, D::Error> {
+    Ok(
+        match Option::<serde_json::Value>::deserialize(deserializer)? {
+            Some(serde_json::Value::Number(ms)) => ms
+                .as_u64()
+                .or_else(|| ms.as_f64().filter(|ms| *ms >= 0.0).map(|ms| ms as u64)),
+            Some(serde_json::Value::String(text)) => parse_rfc3339(&text),
+            _ => None,
+        },
+    )
+}
+
+/// Epoch ms of an RFC 3339 timestamp such as "2024-03-01T14:32:00+07:00",
+/// `None` if it isn't one or is before 1970.
+pub fn parse_rfc3339(text: &str) -> Option<u64> {
+    let (date, rest) = text.split_once(['T', 't', ' '])?;
+    let mut parts = date.split('-');
+    let year: i64 = digits(parts.next()?, 4)?;
+    let month = digits(parts.next()?, 2)?;
+    let day = digits(parts.next()?, 2)?;
+    if parts.next().is_some() || !(1..=12).contains(&month) {
+        return None;
+    }
+    if day < 1 || day > days_in_month(year, month) {
+        return None;
+    }
+
+    let zone_at = rest.find(['Z', 'z', '+', '-'])?;
+    let (clock, zone) = rest.split_at(zone_at);
+    let (clock, fraction) = match clock.split_once('.') {
+        Some((_, "")) => return None,
+        Some(split) => split,
+        None => (clock, ""),
+    };
+    let mut parts = clock.split(':');
+    let hour = digits(parts.next()?, 2)?;
+    let minute = digits(parts.next()?, 2)?;
+    let second = digits(parts.next()?, 2)?;
+    if parts.next().is_some() || hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+    if !fraction.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let millis = format!("{:0<3}", &fraction[..fraction.len().min(3)])
+        .parse::<i64>()
+        .ok()?;
+
+    let offset_minutes = match zone {
+        "Z" | "z" => 0,
+        _ => {
+            let (hours, minutes) = zone[1..].split_once(':')?;
+            let (hours, minutes): (i64, i64) = (digits(hours, 2)?, digits(minutes, 2)?);
+            if hours > 23 || minutes > 59 {
+                return None;
+            }
+            let offset = hours * 60 + minutes;
+            if zone.starts_with('-') {
+                -offset
+            } else {
+                offset
+            }
+        }
+    };
+
+    let seconds = days_from_civil(year, month, day) * 86_400 + hour * 3_600 + minute * 60 + second
+        - offset_minutes * 60;
+    u64::try_from(seconds * 1_000 + millis).ok()
+}
+
+/// `text` as a number, if it's exactly `len` ASCII digits.
+fn digits(text: &str, len: usize) -> Option<i64> {
+    if text.len() != len || !text.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    text.parse().ok()
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days from 1970-01-01 to the given date in the proleptic Gregorian
+/// calendar, after Howard Hinnant's `days_from_civil`.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// The day `ms` falls on, counted from the epoch, where clocks are
+/// `offset_minutes` behind UTC as `Date.getTimezoneOffset()` has it.
+fn local_day(ms: u64, offset_minutes: f64) -> i64 {
+    let local = ms as i64 - (offset_minutes * 60_000.0) as i64;
+    local.div_euclid(DAY_MS)
+}
+
+fn local_date(ms: u64, now: &Date) -> Date {
+    Date::new(&(ms as f64).min(now.get_time()).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 2024-03-01T00:00:00Z.
+    const MARCH_1: u64 = 1_709_251_200_000;
+    const MINUTE: u64 = 60_000;
+
+    #[test]
+    fn midnight_starts_a_new_day() {
+        let before = MARCH_1 - MINUTE;
+        assert_ne!(local_day(before, 0.0), local_day(MARCH_1, 0.0));
+        assert_eq!(
+            local_day(MARCH_1, 0.0),
+            local_day(MARCH_1 + 1_439 * MINUTE, 0.0)
+        );
+        assert_ne!(
+            local_day(MARCH_1, 0.0),
+            local_day(MARCH_1 + 1_440 * MINUTE, 0.0)
+        );
+    }
+
+    #[test]
+    fn midnight_is_local() {
+        // 23:30 UTC is already tomorrow in Jakarta (UTC+7, offset -420)...
+        let late = MARCH_1 - 30 * MINUTE;
+        assert_eq!(local_day(late, -420.0), local_day(MARCH_1, -420.0));
+        // ...and 00:30 UTC still yesterday in New York (UTC-5, offset 300).
+        let early = MARCH_1 + 30 * MINUTE;
+        assert_eq!(local_day(early, 300.0), local_day(late, 300.0));
+    }
+
+    #[test]
+    fn parses_rfc3339() {
+        assert_eq!(parse_rfc3339("1970-01-01T00:00:00Z"), Some(0));
+        assert_eq!(parse_rfc3339("2024-03-01T00:00:00Z"), Some(MARCH_1));
+        assert_eq!(parse_rfc3339("2024-03-01T07:00:00+07:00"), Some(MARCH_1));
+        assert_eq!(parse_rfc3339("2024-02-29T23:00:00-01:00"), Some(MARCH_1));
+        assert_eq!(
+            parse_rfc3339("2024-03-01t00:00:00.25z"),
+            Some(MARCH_1 + 250)
+        );
+        assert_eq!(
+            parse_rfc3339("2024-03-01 00:00:00.123456Z"),
+            Some(MARCH_1 + 123)
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_strings() {
+        for text in [
+            "",
+            "yesterday",
+            "1709251200000",
+            "2024-03-01",
+            "2024-03-01T00:00:00",
+            "2024-13-01T00:00:00Z",
+            "2023-02-29T00:00:00Z",
+            "2024-03-01T24:00:00Z",
+            "2024-03-01T00:00:00.Z",
+            "2024-3-1T00:00:00Z",
+            "2024-03-01T00:00:00+0700",
+            "1969-12-31T23:59:59Z",
+        ] {
+            assert_eq!(parse_rfc3339(text), None, "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn unreadable_times_count_as_missing() {
+        #[derive(Deserialize)]
+        struct Stamped {
+            #[serde(default, deserialize_with = "deserialize_timestamp")]
+            time: Option<u64>,
+        }
+        let time = |json: &str| serde_json::from_str::<Stamped>(json).unwrap().time;
+        assert_eq!(time(r#"{"time":1709251200000}"#), Some(MARCH_1));
+        assert_eq!(time(r#"{"time":"2024-03-01T00:00:00Z"}"#), Some(MARCH_1));
+        assert_eq!(time(r#"{"time":"soon"}"#), None);
+        assert_eq!(time(r#"{"time":-5}"#), None);
+        assert_eq!(time(r#"{"time":null}"#), None);
+        assert_eq!(time("{}"), None);
+    }
+
+    #[test]
+    fn the_browser_locale_is_kept_in_its_own_language() {
+        assert_eq!(pick_locale("en-GB", "en"), "en-GB");
+        assert_eq!(pick_locale("id-ID", "id"), "id-ID");
+        assert_eq!(pick_locale("en", "en"), "en");
+    }
+
+    #[test]
+    fn another_language_takes_over_the_locale() {
+        assert_eq!(pick_locale("en-GB", "es"), "es");
+        // Not fooled by a language code that's only a prefix.
+        assert_eq!(pick_locale("esu-US", "es"), "es");
+        assert_eq!(pick_locale("", "id"), "id");
+    }
+
+    #[test]
+    fn formatters_differ_by_locale_and_options() {
+        let time = [("hour", "2-digit"), ("minute", "2-digit")];
+        let date = [("month", "short"), ("day", "numeric")];
+        assert_eq!(formatter_key("en-GB", &time), formatter_key("en-GB", &time));
+        assert_ne!(formatter_key("en-GB", &time), formatter_key("en-US", &time));
+        assert_ne!(formatter_key("en-GB", &time), formatter_key("en-GB", &date));
+        // A time zone is an option like any other.
+        let jakarta = [("hour", "2-digit"), ("timeZone", "Asia/Jakarta")];
+        let tokyo = [("hour", "2-digit"), ("timeZone", "Asia/Tokyo")];
+        assert_ne!(formatter_key("en", &jakarta), formatter_key("en", &tokyo));
+    }
+}
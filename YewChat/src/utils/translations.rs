@@ -0,0 +1,893 @@
+use std::cell::Cell;
+use std::fmt::Display;
+
+use web_sys::Storage;
+
+/// Where the chosen language is kept, as its `Language::code`.
+const LANGUAGE_KEY: &str = "yewchat.language";
+
+thread_local! {
+    static CURRENT: Cell<Language> = Cell::new(Language::load());
+}
+
+/// A language the UI can be shown in.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Language {
+    English,
+    Indonesian,
+    Spanish,
+}
+
+impl Language {
+    /// In the order the language selector lists them.
+    pub const ALL: [Language; 3] = [Language::English, Language::Indonesian, Language::Spanish];
+
+    /// ISO 639-1 code, as saved and as set on the page's `lang` attribute.
+    pub fn code(self) -> &'static str {
+        match self {
+            Language::English => "en",
+            Language::Indonesian => "id",
+            Language::Spanish => "es",
+        }
+    }
+
+    /// What the language calls itself, for the selector.
+    pub fn name(self) -> &'static str {
+        match self {
+            Language::English => "English",
+            Language::Indonesian => "Bahasa Indonesia",
+            Language::Spanish => "Español",
+        }
+    }
+
+    /// The language for a code such as "es" or "es-AR", if there's one.
+    fn from_code(code: &str) -> Option<Self> {
+        let primary = code.split('-').next()?.to_lowercase();
+        Self::ALL.into_iter().find(|l| l.code() == primary)
+    }
+
+    /// The language saved in `localStorage`, or else the browser's if the
+    /// UI speaks it, or else English.
+    fn load() -> Self {
+        let saved = storage()
+            .and_then(|s| s.get_item(LANGUAGE_KEY).ok().flatten())
+            .and_then(|code| Self::from_code(&code));
+        let browser = || {
+            web_sys::window()?
+                .navigator()
+                .language()
+                .and_then(|code| Self::from_code(&code))
+        };
+        saved.or_else(browser).unwrap_or(Language::English)
+    }
+
+    pub fn save(self) {
+        if let Some(Err(e)) = storage().map(|s| s.set_item(LANGUAGE_KEY, self.code())) {
+            log::warn!("translations: failed to save language: {:?}", e);
+        }
+    }
+}
+
+fn storage() -> Option<Storage> {
+    web_sys::window()?.local_storage().ok().flatten()
+}
+
+/// The language `t` translates to.
+pub fn language() -> Language {
+    CURRENT.with(Cell::get)
+}
+
+/// Switches every later `t` to `language`. Components only show it once
+/// they render again.
+pub fn set_language(language: Language) {
+    CURRENT.with(|current| current.set(language));
+    let root = web_sys::window()
+        .and_then(|w| w.document())
+        .and_then(|d| d.document_element());
+    if let Some(root) = root {
+        let _ = root.set_attribute("lang", language.code());
+    }
+}
+
+/// The UI string `key` in the current language. Strings a language has
+/// no translation for yet are shown in English.
+pub fn t(key: &str) -> &'static str {
+    let translated = match language() {
+        Language::English => None,
+        Language::Indonesian => indonesian(key),
+        Language::Spanish => spanish(key),
+    };
+    translated.or_else(|| english(key)).unwrap_or_else(|| {
+        log::warn!("translations: no string for {}", key);
+        ""
+    })
+}
+
+/// `t(key)` with each "{}" in it replaced by the next of `args`.
+pub fn t_args(key: &str, args: &[&dyn Display]) -> String {
+    let mut args = args.iter();
+    let mut parts = t(key).split("{}");
+    let mut text = parts.next().unwrap_or_default().to_string();
+    for part in parts {
+        if let Some(arg) = args.next() {
+            text.push_str(&arg.to_string());
+        }
+        text.push_str(part);
+    }
+    text
+}
+
+fn english(key: &str) -> Option<&'static str> {
+    Some(match key {
+        "sidebar.rooms" => "Rooms",
+        "sidebar.users" => "Active Users",
+        "sidebar.filter_users" => "Filter users",
+        "sidebar.no_users_match" => "No users match.",
+        "sidebar.greeting" => "Hi there!",
+        "sidebar.open" => "Rooms and users",
+        "sidebar.close" => "Close",
+        "sidebar.find_users" => "Find people",
+        "search_users.title" => "Find people",
+        "search_users.placeholder" => "Search everyone online",
+        "search_users.hint" => "Type part of a name to look in every room.",
+        "search_users.none" => "Nobody online matches \"{}\".",
+        "search_users.invite" => "Invite to room",
+        "search_users.invite_title" => "Send them a DM inviting them to #{}",
+        "search_users.dm" => "Send DM",
+        "search_users.more" => "Load more",
+        "search_users.invite_message" => "Come join me in #{}!",
+        "search_users.invite_private" => {
+            "Come join me in #{}! It's private; join it from the room dialog with the invite {}."
+        }
+        "sidebar.new_room" => "Create or join a room",
+        "header.mentions" => "Messages mentioning you",
+        "mentions.title" => "Mentions",
+        "mentions.none" => "Nobody has mentioned you yet.",
+        "mentions.show" => "Show in the chat",
+        "header.search" => "Search messages (Ctrl+K)",
+        "header.online" => "{} online",
+        "header.export" => "Export conversation",
+        "export.text" => "Plain text (.txt)",
+        "export.json" => "JSON (.json)",
+        "error.export_failed" => "Couldn't export the conversation.",
+        "header.latency" => "Round-trip latency",
+        "header.light_mode" => "Switch to light mode",
+        "header.dark_mode" => "Switch to dark mode",
+        "header.mute" => "Mute notification sound",
+        "header.unmute" => "Turn on notification sound",
+        "header.profile" => "Edit profile",
+        "header.settings" => "Notification settings",
+        "header.language" => "Language",
+        "common.dismiss" => "Dismiss",
+        "common.cancel" => "Cancel",
+        "common.save" => "Save",
+        "common.done" => "Done",
+        "connection.reconnecting_in" => "Connection lost. Reconnecting in {}s (attempt {})…",
+        "connection.connected" => "Connected",
+        "connection.connecting" => "Connecting…",
+        "connection.reconnecting" => "Reconnecting…",
+        "connection.disconnected" => "Disconnected",
+        "connection.failed" => "Can't connect to {}: {}",
+        "search.placeholder" => "Search messages… (Enter for older, Shift+Enter for newer)",
+        "search.result" => "{} result",
+        "search.results" => "{} results",
+        "search.clear" => "Clear search",
+        "filter.banner" => "Filtered: {}",
+        "filter.clear" => "clear",
+        "filter.hidden" => "{} hidden",
+        "filter.show_only" => "Show only messages from {}",
+        "filter.show_all" => "Show all messages",
+        "list.earlier" => "— earlier messages —",
+        "list.new_messages" => "new messages",
+        "list.jump_one" => "↓ {} new message",
+        "list.jump_many" => "↓ {} new messages",
+        "composer.pending" => "{} message(s) pending, will send when reconnected…",
+        "composer.replying_to" => "Replying to {}: ",
+        "composer.cancel_reply" => "Cancel reply",
+        "composer.placeholder" => "Type a message... (Shift+Enter for a new line)",
+        "composer.attach_image" => "Attach an image",
+        "composer.gif" => "Search GIFs",
+        "composer.share_file" => "Share a file",
+        "composer.record" => "Hold to record a voice message",
+        "voice.release" => "Release to send",
+        "voice.name" => "voice-message",
+        "error.voice_in_rooms" => "Voice messages can only be sent in rooms.",
+        "error.voice_offline" => "Voice messages can only be sent while connected.",
+        "error.voice_failed" => "Couldn't record. Is the microphone allowed?",
+        "error.voice_too_long" => "That voice message is too long to send.",
+        "composer.slow_down" => "Slow down!",
+        "error.unreadable" => "Received an unreadable message from the server.",
+        "error.too_long" => "Messages can be at most {} characters.",
+        "error.images_only" => "Only images can be uploaded.",
+        "error.image_too_large" => "{} is too large, images can be at most {} KB.",
+        "error.image_unreadable" => "Couldn't read that image.",
+        "error.image_type" => "Only PNG, GIF, JPEG and WebP images can be uploaded.",
+        "error.files_in_rooms" => "Files can only be shared in rooms.",
+        "error.files_offline" => "Files can only be shared while connected.",
+        "error.file_too_large" => "{} is too large, files can be at most {} MB.",
+        "error.file_unreadable" => "Couldn't read that file.",
+        "error.file_send" => "Couldn't send {}.",
+        "error.notifications_blocked" => {
+            "Desktop notifications are blocked; allow them in the browser's site settings."
+        }
+        "error.not_loaded" => "That message is older than the ones loaded; scroll up to load more.",
+        "error.usage" => "Usage: {}",
+        "error.unknown_command" => "Unknown command /{}.",
+        "error.name_invalid" => "Names can't be empty or contain spaces.",
+        "error.name_taken" => "The name {} is already taken.",
+        "error.name_reserved" => {
+            "The name {} is kept for staff. Sign in with the staff key to use it."
+        }
+        "error.encrypt_failed" => "Couldn't encrypt the message, so it wasn't sent.",
+        "error.kicked" => "A moderator disconnected you.",
+        "error.invalid_server_address" => {
+            "Invalid server address \"{}\": expected a ws:// or wss:// URL."
+        }
+        "error.banned" => "You are banned for {} more minutes.",
+        "notice.joined" => "{} joined the room",
+        "notice.left" => "{} left the room",
+        "notice.renamed" => "{} is now known as {}",
+        "notice.kicked" => "{} was kicked out",
+        "notice.banned" => "{} was banned for {} minutes",
+        "notify.in_room" => "{} in #{}",
+        "notify.direct" => "{} (direct message)",
+        "dm.unread" => "Unread direct messages",
+        "dm.encrypted" => "End-to-end encrypted",
+        "dm.unencrypted" => "Not encrypted: one of you can't encrypt messages",
+        "dm.undecryptable" => "🔒 This message couldn't be decrypted.",
+        "call.start" => "Call",
+        "call.incoming" => "Incoming call from {}",
+        "call.accept" => "Accept",
+        "call.decline" => "Decline",
+        "call.calling" => "Calling {}…",
+        "call.connecting" => "Connecting…",
+        "call.active" => "In a call with {}",
+        "call.hang_up" => "Hang up",
+        "call.declined" => "{} didn't pick up.",
+        "call.failed" => "Couldn't start the call. Is a microphone allowed?",
+        "notify.shared" => "shared {}",
+        "notify.image" => "sent an image",
+        "message.deleted" => "This message was deleted",
+        "message.original_missing" => "Original message unavailable",
+        "message.show_original" => "Show the original message",
+        "message.truncated" => "message truncated",
+        "message.seen_by" => "Seen by {}",
+        "message.sent" => "Sent",
+        "message.read" => "Read",
+        "message.edit_hint" => "Edit (or double-click the message)",
+        "message.edit" => "Edit",
+        "message.edited" => "edited",
+        "message.delete" => "Delete",
+        "message.delete_confirm" => "Delete this message?",
+        "message.pin" => "Pin",
+        "message.unpin" => "Unpin",
+        "message.pinned" => "Pinned",
+        "message.favorite" => "Save to favorites",
+        "message.unfavorite" => "Remove from favorites",
+        "message.report" => "Report",
+        "message.reported" => "Reported",
+        "message.hidden" => "This message has been reported and hidden.",
+        "report.title" => "Report this message as",
+        "report.spam" => "Spam",
+        "report.harassment" => "Harassment",
+        "report.inappropriate" => "Inappropriate content",
+        "report.send" => "Report",
+        "message.reply" => "Reply",
+        "message.forward" => "Forward",
+        "message.forwarded" => "Forwarded from {}",
+        "forward.title" => "Forward message",
+        "gif.title" => "Search GIFs",
+        "gif.placeholder" => "Search Giphy",
+        "gif.hint" => "Type something to find GIFs.",
+        "gif.searching" => "Searching…",
+        "gif.none" => "No GIFs found.",
+        "gif.failed" => "GIF search isn't available right now.",
+        "gif.powered_by" => "Powered by GIPHY",
+        "forward.rooms" => "Rooms",
+        "forward.people" => "People",
+        "forward.nobody" => "No one else is around.",
+        "forward.send" => "Forward",
+        "error.forward_too_long" => "That message is too long to forward.",
+        "message.copy_hint" => "Copy the text",
+        "message.copy" => "Copy",
+        "message.copied" => "Copied!",
+        "message.copy_failed" => "Copy failed",
+        "message.loading_preview" => "Loading preview…",
+        "pins.title" => "Pinned messages",
+        "pins.count" => "{} pinned",
+        "pins.show" => "Show in the conversation",
+        "file.download" => "Download {}",
+        "file.unavailable" => "(no longer available)",
+        "status.set" => "Set your status",
+        "status.online" => "Online",
+        "status.away" => "Away",
+        "status.busy" => "Busy",
+        "status.offline" => "Offline",
+        "typing.one" => "{} is typing…",
+        "typing.two" => "{} and {} are typing…",
+        "typing.many" => "{} people are typing…",
+        "code.copy_hint" => "Copy to clipboard",
+        "code.copy" => "Copy",
+        "code.copied" => "Copied",
+        "code.copy_failed" => "Copy failed",
+        "code.show_less" => "Show less",
+        "code.show_all" => "Show all {} lines",
+        "emoji.button" => "Emoji",
+        "emoji.search" => "Search emoji",
+        "emoji.none" => "No emoji found",
+        "emoji.smileys" => "Smileys",
+        "emoji.gestures" => "Gestures",
+        "emoji.animals" => "Animals",
+        "emoji.food" => "Food",
+        "emoji.symbols" => "Symbols",
+        "login.username" => "Username",
+        "login.token" => "Staff key (optional)",
+        "login.go" => "Go Chatting!",
+        "profile.title" => "Edit profile",
+        "profile.name" => "Display name",
+        "profile.avatar" => "Avatar",
+        "settings.title" => "Notifications",
+        "settings.hint" => "For messages arriving while the chat is in the background.",
+        "settings.sound" => "Play a sound",
+        "settings.desktop" => "Show desktop notifications",
+        "settings.mentions_only" => "Only for messages that mention me",
+        "block.block_user" => "Block user",
+        "moderate.kick" => "Kick",
+        "moderate.ban_hour" => "Ban for an hour",
+        "moderate.ban_day" => "Ban for a day",
+        "moderate.ban_week" => "Ban for a week",
+        "block.unblock_user" => "Unblock user",
+        "block.unblock" => "Unblock {}",
+        "user_card.online_since" => "Online since {}",
+        "user_card.here_before_me" => "Here since before you came",
+        "user_card.message" => "Message",
+        "user_card.mention" => "Mention",
+        "user.local_time" => "Their time: {} ({})",
+        "sidebar.blocked" => "Blocked",
+        "sidebar.favorites" => "Favorites",
+        "sidebar.no_favorites" => "Star a message to find it here.",
+        "settings.blocked" => "Blocked users",
+        "settings.blocked_none" => "No one is blocked. Right-click a name to block them.",
+        "settings.unblock" => "Unblock",
+        "room.title" => "Create or join a room",
+        "room.name" => "Room name",
+        "room.hint" => "3–32 characters: lowercase letters, digits and dashes.",
+        "room.known" => "Rooms",
+        "room.none" => "No rooms match.",
+        "room.join" => "Join",
+        "room.create" => "Create",
+        "room.invalid" => "Room names are 3–32 lowercase letters, digits or dashes.",
+        "room.exists" => "There is already a room called {}.",
+        "room.not_found" => "There is no room called {}.",
+        "room.description" => "Description",
+        "room.description_placeholder" => "What the room is for (optional)",
+        "room.private" => "Private",
+        "room.private_hint" => "Unlisted; only people with the invite can join.",
+        "room.max_members" => "Max members",
+        "room.no_limit" => "No limit",
+        "room.max_members_invalid" => "A room needs room for at least {} members.",
+        "room.invite" => "Invite code",
+        "room.invite_placeholder" => "To join a private room",
+        "room.private_error" => "#{} is private; you need its invite to join.",
+        "room.full" => "#{} is full.",
+        "room.copy_invite" => "Copy the invite to this room",
+        "page.not_found" => "404 baby",
+        _ => return None,
+    })
+}
+
+fn indonesian(key: &str) -> Option<&'static str> {
+    Some(match key {
+        "sidebar.rooms" => "Ruang",
+        "sidebar.users" => "Pengguna Aktif",
+        "sidebar.filter_users" => "Saring pengguna",
+        "sidebar.no_users_match" => "Tidak ada pengguna yang cocok.",
+        "sidebar.greeting" => "Halo!",
+        "sidebar.open" => "Ruang dan pengguna",
+        "sidebar.close" => "Tutup",
+        "sidebar.find_users" => "Cari orang",
+        "search_users.title" => "Cari orang",
+        "search_users.placeholder" => "Cari semua yang sedang daring",
+        "search_users.hint" => "Ketik sebagian nama untuk mencari di semua ruang.",
+        "search_users.none" => "Tidak ada yang daring dengan nama \"{}\".",
+        "search_users.invite" => "Undang ke ruang",
+        "search_users.invite_title" => "Kirim DM yang mengundangnya ke #{}",
+        "search_users.dm" => "Kirim DM",
+        "search_users.more" => "Muat lebih banyak",
+        "search_users.invite_message" => "Ayo gabung denganku di #{}!",
+        "search_users.invite_private" => "Ayo gabung denganku di #{}! Ruangnya privat; masuk lewat dialog ruang dengan undangan {}.",
+        "sidebar.new_room" => "Buat atau masuk ruang",
+        "header.mentions" => "Pesan yang menyebut Anda",
+        "mentions.title" => "Sebutan",
+        "mentions.none" => "Belum ada yang menyebut Anda.",
+        "mentions.show" => "Tampilkan di obrolan",
+        "header.search" => "Cari pesan (Ctrl+K)",
+        "header.online" => "{} daring",
+        "header.export" => "Ekspor percakapan",
+        "export.text" => "Teks biasa (.txt)",
+        "export.json" => "JSON (.json)",
+        "error.export_failed" => "Tidak dapat mengekspor percakapan.",
+        "header.latency" => "Latensi pulang-pergi",
+        "header.light_mode" => "Beralih ke mode terang",
+        "header.dark_mode" => "Beralih ke mode gelap",
+        "header.mute" => "Bisukan suara notifikasi",
+        "header.unmute" => "Nyalakan suara notifikasi",
+        "header.profile" => "Ubah profil",
+        "header.settings" => "Pengaturan notifikasi",
+        "header.language" => "Bahasa",
+        "common.dismiss" => "Tutup",
+        "common.cancel" => "Batal",
+        "common.save" => "Simpan",
+        "common.done" => "Selesai",
+        "connection.reconnecting_in" => {
+            "Koneksi terputus. Menyambung ulang dalam {} dtk (percobaan ke-{})…"
+        }
+        "connection.connected" => "Terhubung",
+        "connection.connecting" => "Menghubungkan…",
+        "connection.reconnecting" => "Menyambung ulang…",
+        "connection.disconnected" => "Terputus",
+        "connection.failed" => "Tidak dapat terhubung ke {}: {}",
+        "search.placeholder" => {
+            "Cari pesan… (Enter untuk yang lebih lama, Shift+Enter untuk yang lebih baru)"
+        }
+        "search.result" => "{} hasil",
+        "search.results" => "{} hasil",
+        "search.clear" => "Hapus pencarian",
+        "filter.banner" => "Disaring: {}",
+        "filter.clear" => "hapus",
+        "filter.hidden" => "{} tersembunyi",
+        "filter.show_only" => "Tampilkan hanya pesan dari {}",
+        "filter.show_all" => "Tampilkan semua pesan",
+        "list.earlier" => "— pesan sebelumnya —",
+        "list.new_messages" => "pesan baru",
+        "list.jump_one" => "↓ {} pesan baru",
+        "list.jump_many" => "↓ {} pesan baru",
+        "composer.pending" => "{} pesan tertunda, akan dikirim saat tersambung kembali…",
+        "composer.replying_to" => "Membalas {}: ",
+        "composer.cancel_reply" => "Batalkan balasan",
+        "composer.placeholder" => "Ketik pesan... (Shift+Enter untuk baris baru)",
+        "composer.attach_image" => "Lampirkan gambar",
+        "composer.gif" => "Cari GIF",
+        "composer.share_file" => "Bagikan berkas",
+        "composer.record" => "Tahan untuk merekam pesan suara",
+        "voice.release" => "Lepas untuk mengirim",
+        "voice.name" => "pesan-suara",
+        "error.voice_in_rooms" => "Pesan suara hanya dapat dikirim di ruang.",
+        "error.voice_offline" => "Pesan suara hanya dapat dikirim saat tersambung.",
+        "error.voice_failed" => "Tidak bisa merekam. Apakah mikrofon diizinkan?",
+        "error.voice_too_long" => "Pesan suara itu terlalu panjang untuk dikirim.",
+        "composer.slow_down" => "Pelan-pelan!",
+        "error.unreadable" => "Menerima pesan yang tidak dapat dibaca dari server.",
+        "error.too_long" => "Pesan paling panjang {} karakter.",
+        "error.images_only" => "Hanya gambar yang dapat diunggah.",
+        "error.image_too_large" => "{} terlalu besar, gambar paling besar {} KB.",
+        "error.image_unreadable" => "Gambar itu tidak dapat dibaca.",
+        "error.image_type" => "Hanya gambar PNG, GIF, JPEG, dan WebP yang dapat diunggah.",
+        "error.files_in_rooms" => "Berkas hanya dapat dibagikan di ruang.",
+        "error.files_offline" => "Berkas hanya dapat dibagikan saat tersambung.",
+        "error.file_too_large" => "{} terlalu besar, berkas paling besar {} MB.",
+        "error.file_unreadable" => "Berkas itu tidak dapat dibaca.",
+        "error.file_send" => "Gagal mengirim {}.",
+        "error.notifications_blocked" => {
+            "Notifikasi desktop diblokir; izinkan melalui pengaturan situs di peramban."
+        }
+        "error.not_loaded" => {
+            "Pesan itu lebih lama dari yang sudah dimuat; gulir ke atas untuk memuat lebih banyak."
+        }
+        "error.usage" => "Penggunaan: {}",
+        "error.unknown_command" => "Perintah /{} tidak dikenal.",
+        "error.name_invalid" => "Nama tidak boleh kosong atau berisi spasi.",
+        "error.name_taken" => "Nama {} sudah dipakai.",
+        "error.name_reserved" => "Nama {} khusus untuk staf. Masuk dengan kunci staf untuk memakainya.",
+        "error.encrypt_failed" => "Pesan tidak bisa dienkripsi, jadi tidak dikirim.",
+        "error.kicked" => "Moderator memutus sambungan Anda.",
+        "error.invalid_server_address" => "Alamat server \"{}\" tidak valid: harus berupa URL ws:// atau wss://.",
+        "error.banned" => "Anda diblokir selama {} menit lagi.",
+        "notice.joined" => "{} bergabung ke ruang",
+        "notice.left" => "{} meninggalkan ruang",
+        "notice.renamed" => "{} sekarang bernama {}",
+        "notice.kicked" => "{} dikeluarkan",
+        "notice.banned" => "{} diblokir selama {} menit",
+        "notify.in_room" => "{} di #{}",
+        "notify.direct" => "{} (pesan langsung)",
+        "dm.unread" => "Ada pesan langsung yang belum dibaca",
+        "dm.encrypted" => "Terenkripsi end-to-end",
+        "dm.unencrypted" => {
+            "Tidak terenkripsi: salah satu dari kalian tidak bisa mengenkripsi pesan"
+        }
+        "dm.undecryptable" => "🔒 Pesan ini tidak bisa didekripsi.",
+        "call.start" => "Telepon",
+        "call.incoming" => "Panggilan masuk dari {}",
+        "call.accept" => "Terima",
+        "call.decline" => "Tolak",
+        "call.calling" => "Memanggil {}…",
+        "call.connecting" => "Menghubungkan…",
+        "call.active" => "Sedang menelepon {}",
+        "call.hang_up" => "Tutup",
+        "call.declined" => "{} tidak mengangkat.",
+        "call.failed" => "Panggilan tidak bisa dimulai. Apakah mikrofon diizinkan?",
+        "notify.shared" => "membagikan {}",
+        "notify.image" => "mengirim gambar",
+        "message.deleted" => "Pesan ini telah dihapus",
+        "message.original_missing" => "Pesan asli tidak tersedia",
+        "message.show_original" => "Tampilkan pesan asli",
+        "message.truncated" => "pesan dipotong",
+        "message.seen_by" => "Dilihat oleh {}",
+        "message.sent" => "Terkirim",
+        "message.read" => "Dibaca",
+        "message.edit_hint" => "Ubah (atau klik dua kali pesannya)",
+        "message.edited" => "diubah",
+        "message.edit" => "Ubah",
+        "message.delete" => "Hapus",
+        "message.delete_confirm" => "Hapus pesan ini?",
+        "message.pin" => "Sematkan",
+        "message.unpin" => "Lepas sematan",
+        "message.pinned" => "Disematkan",
+        "message.favorite" => "Simpan ke favorit",
+        "message.unfavorite" => "Hapus dari favorit",
+        "message.report" => "Laporkan",
+        "message.reported" => "Dilaporkan",
+        "message.hidden" => "Pesan ini telah dilaporkan dan disembunyikan.",
+        "report.title" => "Laporkan pesan ini sebagai",
+        "report.spam" => "Spam",
+        "report.harassment" => "Pelecehan",
+        "report.inappropriate" => "Konten tidak pantas",
+        "report.send" => "Laporkan",
+        "message.reply" => "Balas",
+        "message.forward" => "Teruskan",
+        "message.forwarded" => "Diteruskan dari {}",
+        "forward.title" => "Teruskan pesan",
+        "gif.title" => "Cari GIF",
+        "gif.placeholder" => "Cari di Giphy",
+        "gif.hint" => "Ketik sesuatu untuk mencari GIF.",
+        "gif.searching" => "Mencari…",
+        "gif.none" => "Tidak ada GIF yang ditemukan.",
+        "gif.failed" => "Pencarian GIF sedang tidak tersedia.",
+        "gif.powered_by" => "Didukung oleh GIPHY",
+        "forward.rooms" => "Ruang",
+        "forward.people" => "Orang",
+        "forward.nobody" => "Tidak ada orang lain di sini.",
+        "forward.send" => "Teruskan",
+        "error.forward_too_long" => "Pesan itu terlalu panjang untuk diteruskan.",
+        "message.copy_hint" => "Salin teksnya",
+        "message.copy" => "Salin",
+        "message.copied" => "Tersalin!",
+        "message.copy_failed" => "Gagal menyalin",
+        "message.loading_preview" => "Memuat pratinjau…",
+        "pins.title" => "Pesan tersemat",
+        "pins.count" => "{} tersemat",
+        "pins.show" => "Tampilkan di percakapan",
+        "file.download" => "Unduh {}",
+        "file.unavailable" => "(sudah tidak tersedia)",
+        "status.set" => "Atur status Anda",
+        "status.online" => "Daring",
+        "status.away" => "Tidak di tempat",
+        "status.busy" => "Sibuk",
+        "status.offline" => "Luring",
+        "typing.one" => "{} sedang mengetik…",
+        "typing.two" => "{} dan {} sedang mengetik…",
+        "typing.many" => "{} orang sedang mengetik…",
+        "code.copy_hint" => "Salin ke papan klip",
+        "code.copy" => "Salin",
+        "code.copied" => "Tersalin",
+        "code.copy_failed" => "Gagal menyalin",
+        "code.show_less" => "Tampilkan lebih sedikit",
+        "code.show_all" => "Tampilkan semua {} baris",
+        "emoji.search" => "Cari emoji",
+        "emoji.none" => "Emoji tidak ditemukan",
+        "emoji.smileys" => "Wajah",
+        "emoji.gestures" => "Gerakan",
+        "emoji.animals" => "Hewan",
+        "emoji.food" => "Makanan",
+        "emoji.symbols" => "Simbol",
+        "login.username" => "Nama pengguna",
+        "login.token" => "Kunci staf (opsional)",
+        "login.go" => "Mulai Mengobrol!",
+        "profile.title" => "Ubah profil",
+        "profile.name" => "Nama tampilan",
+        "settings.title" => "Notifikasi",
+        "settings.hint" => "Untuk pesan yang masuk saat obrolan berada di latar belakang.",
+        "settings.sound" => "Putar suara",
+        "settings.desktop" => "Tampilkan notifikasi desktop",
+        "settings.mentions_only" => "Hanya untuk pesan yang menyebut saya",
+        "block.block_user" => "Blokir pengguna",
+        "moderate.kick" => "Keluarkan",
+        "moderate.ban_hour" => "Blokir selama satu jam",
+        "moderate.ban_day" => "Blokir selama satu hari",
+        "moderate.ban_week" => "Blokir selama satu minggu",
+        "block.unblock_user" => "Buka blokir pengguna",
+        "block.unblock" => "Buka blokir {}",
+        "user_card.online_since" => "Daring sejak {}",
+        "user_card.here_before_me" => "Sudah di sini sebelum kamu datang",
+        "user_card.message" => "Kirim pesan",
+        "user_card.mention" => "Sebut",
+        "user.local_time" => "Waktu setempat: {} ({})",
+        "sidebar.blocked" => "Diblokir",
+        "sidebar.favorites" => "Favorit",
+        "sidebar.no_favorites" => "Beri bintang pada pesan agar muncul di sini.",
+        "settings.blocked" => "Pengguna yang diblokir",
+        "settings.blocked_none" => {
+            "Belum ada yang diblokir. Klik kanan sebuah nama untuk memblokirnya."
+        }
+        "settings.unblock" => "Buka blokir",
+        "room.title" => "Buat atau masuk ruang",
+        "room.name" => "Nama ruang",
+        "room.hint" => "3–32 karakter: huruf kecil, angka, dan tanda hubung.",
+        "room.known" => "Ruang",
+        "room.none" => "Tidak ada ruang yang cocok.",
+        "room.join" => "Masuk",
+        "room.create" => "Buat",
+        "room.invalid" => "Nama ruang harus 3–32 huruf kecil, angka, atau tanda hubung.",
+        "room.exists" => "Ruang {} sudah ada.",
+        "room.not_found" => "Tidak ada ruang bernama {}.",
+        "room.description" => "Deskripsi",
+        "room.description_placeholder" => "Untuk apa ruang ini (opsional)",
+        "room.private" => "Privat",
+        "room.private_hint" => "Tidak tercantum; hanya yang punya undangan yang dapat masuk.",
+        "room.max_members" => "Anggota maksimum",
+        "room.no_limit" => "Tanpa batas",
+        "room.max_members_invalid" => "Ruang harus muat setidaknya {} anggota.",
+        "room.invite" => "Kode undangan",
+        "room.invite_placeholder" => "Untuk masuk ruang privat",
+        "room.private_error" => "#{} privat; Anda perlu undangannya untuk masuk.",
+        "room.full" => "#{} sudah penuh.",
+        "room.copy_invite" => "Salin undangan ke ruang ini",
+        "page.not_found" => "404, halaman tidak ditemukan",
+        _ => return None,
+    })
+}
+
+fn spanish(key: &str) -> Option<&'static str> {
+    Some(match key {
+        "sidebar.rooms" => "Salas",
+        "sidebar.users" => "Usuarios activos",
+        "sidebar.filter_users" => "Filtrar usuarios",
+        "sidebar.no_users_match" => "Ningún usuario coincide.",
+        "sidebar.greeting" => "¡Hola!",
+        "sidebar.open" => "Salas y usuarios",
+        "sidebar.close" => "Cerrar",
+        "sidebar.find_users" => "Buscar personas",
+        "search_users.title" => "Buscar personas",
+        "search_users.placeholder" => "Buscar entre todos los conectados",
+        "search_users.hint" => "Escribe parte de un nombre para buscar en todas las salas.",
+        "search_users.none" => "Nadie conectado coincide con \"{}\".",
+        "search_users.invite" => "Invitar a la sala",
+        "search_users.invite_title" => "Enviarle un DM invitándole a #{}",
+        "search_users.dm" => "Enviar DM",
+        "search_users.more" => "Cargar más",
+        "search_users.invite_message" => "¡Únete a mí en #{}!",
+        "search_users.invite_private" => "¡Únete a mí en #{}! Es privada; entra desde el diálogo de salas con la invitación {}.",
+        "sidebar.new_room" => "Crear o unirse a una sala",
+        "header.mentions" => "Mensajes que te mencionan",
+        "mentions.title" => "Menciones",
+        "mentions.none" => "Nadie te ha mencionado todavía.",
+        "mentions.show" => "Mostrar en el chat",
+        "header.search" => "Buscar mensajes (Ctrl+K)",
+        "header.online" => "{} en línea",
+        "header.export" => "Exportar conversación",
+        "export.text" => "Texto plano (.txt)",
+        "export.json" => "JSON (.json)",
+        "error.export_failed" => "No se pudo exportar la conversación.",
+        "header.latency" => "Latencia de ida y vuelta",
+        "header.light_mode" => "Cambiar a modo claro",
+        "header.dark_mode" => "Cambiar a modo oscuro",
+        "header.mute" => "Silenciar el sonido de notificación",
+        "header.unmute" => "Activar el sonido de notificación",
+        "header.profile" => "Editar perfil",
+        "header.settings" => "Ajustes de notificaciones",
+        "header.language" => "Idioma",
+        "common.dismiss" => "Descartar",
+        "common.cancel" => "Cancelar",
+        "common.save" => "Guardar",
+        "common.done" => "Listo",
+        "connection.reconnecting_in" => {
+            "Se perdió la conexión. Reconectando en {} s (intento {})…"
+        }
+        "connection.connected" => "Conectado",
+        "connection.connecting" => "Conectando…",
+        "connection.reconnecting" => "Reconectando…",
+        "connection.disconnected" => "Desconectado",
+        "connection.failed" => "No se puede conectar a {}: {}",
+        "search.placeholder" => "Buscar mensajes… (Enter para anteriores, Shift+Enter para más recientes)",
+        "search.result" => "{} resultado",
+        "search.results" => "{} resultados",
+        "search.clear" => "Borrar búsqueda",
+        "filter.banner" => "Filtrado: {}",
+        "filter.clear" => "quitar",
+        "filter.hidden" => "{} ocultos",
+        "filter.show_only" => "Mostrar solo los mensajes de {}",
+        "filter.show_all" => "Mostrar todos los mensajes",
+        "list.earlier" => "— mensajes anteriores —",
+        "list.new_messages" => "mensajes nuevos",
+        "list.jump_one" => "↓ {} mensaje nuevo",
+        "list.jump_many" => "↓ {} mensajes nuevos",
+        "composer.pending" => "{} mensaje(s) pendiente(s), se enviarán al reconectar…",
+        "composer.replying_to" => "Respondiendo a {}: ",
+        "composer.cancel_reply" => "Cancelar respuesta",
+        "composer.placeholder" => "Escribe un mensaje... (Shift+Enter para una nueva línea)",
+        "composer.attach_image" => "Adjuntar una imagen",
+        "composer.gif" => "Buscar GIF",
+        "composer.share_file" => "Compartir un archivo",
+        "composer.record" => "Mantén pulsado para grabar un mensaje de voz",
+        "voice.release" => "Suelta para enviar",
+        "voice.name" => "mensaje-de-voz",
+        "error.voice_in_rooms" => "Los mensajes de voz solo se pueden enviar en salas.",
+        "error.voice_offline" => "Los mensajes de voz solo se pueden enviar con conexión.",
+        "error.voice_failed" => "No se pudo grabar. ¿Está permitido el micrófono?",
+        "error.voice_too_long" => "Ese mensaje de voz es demasiado largo para enviarlo.",
+        "composer.slow_down" => "¡Más despacio!",
+        "error.unreadable" => "Se recibió un mensaje ilegible del servidor.",
+        "error.too_long" => "Los mensajes pueden tener como máximo {} caracteres.",
+        "error.images_only" => "Solo se pueden subir imágenes.",
+        "error.image_too_large" => "{} es demasiado grande; las imágenes pueden ocupar como máximo {} KB.",
+        "error.image_unreadable" => "No se pudo leer esa imagen.",
+        "error.image_type" => "Solo se pueden subir imágenes PNG, GIF, JPEG y WebP.",
+        "error.files_in_rooms" => "Los archivos solo se pueden compartir en salas.",
+        "error.files_offline" => "Los archivos solo se pueden compartir con conexión.",
+        "error.file_too_large" => "{} es demasiado grande; los archivos pueden ocupar como máximo {} MB.",
+        "error.file_unreadable" => "No se pudo leer ese archivo.",
+        "error.file_send" => "No se pudo enviar {}.",
+        "error.notifications_blocked" => {
+            "Las notificaciones de escritorio están bloqueadas; permítelas en la configuración del sitio del navegador."
+        }
+        "error.not_loaded" => {
+            "Ese mensaje es anterior a los cargados; desplázate hacia arriba para cargar más."
+        }
+        "error.usage" => "Uso: {}",
+        "error.unknown_command" => "Comando desconocido /{}.",
+        "error.name_invalid" => "Los nombres no pueden estar vacíos ni contener espacios.",
+        "error.name_taken" => "El nombre {} ya está en uso.",
+        "error.name_reserved" => "El nombre {} está reservado para el equipo. Entra con la clave del equipo para usarlo.",
+        "error.encrypt_failed" => "No se pudo cifrar el mensaje, así que no se envió.",
+        "error.kicked" => "Un moderador te desconectó.",
+        "error.invalid_server_address" => "Dirección de servidor \"{}\" no válida: se esperaba una URL ws:// o wss://.",
+        "error.banned" => "Estás bloqueado durante {} minutos más.",
+        "notice.joined" => "{} se unió a la sala",
+        "notice.left" => "{} salió de la sala",
+        "notice.renamed" => "{} ahora se llama {}",
+        "notice.kicked" => "{} fue expulsado",
+        "notice.banned" => "{} fue bloqueado durante {} minutos",
+        "notify.in_room" => "{} en #{}",
+        "notify.direct" => "{} (mensaje directo)",
+        "dm.unread" => "Mensajes directos sin leer",
+        "dm.encrypted" => "Cifrado de extremo a extremo",
+        "dm.unencrypted" => "Sin cifrar: uno de los dos no puede cifrar mensajes",
+        "dm.undecryptable" => "🔒 No se pudo descifrar este mensaje.",
+        "call.start" => "Llamar",
+        "call.incoming" => "Llamada entrante de {}",
+        "call.accept" => "Aceptar",
+        "call.decline" => "Rechazar",
+        "call.calling" => "Llamando a {}…",
+        "call.connecting" => "Conectando…",
+        "call.active" => "En llamada con {}",
+        "call.hang_up" => "Colgar",
+        "call.declined" => "{} no contestó.",
+        "call.failed" => "No se pudo iniciar la llamada. ¿Está permitido el micrófono?",
+        "notify.shared" => "compartió {}",
+        "notify.image" => "envió una imagen",
+        "message.deleted" => "Este mensaje fue eliminado",
+        "message.original_missing" => "Mensaje original no disponible",
+        "message.show_original" => "Mostrar el mensaje original",
+        "message.truncated" => "mensaje recortado",
+        "message.seen_by" => "Visto por {}",
+        "message.sent" => "Enviado",
+        "message.read" => "Leído",
+        "message.edit_hint" => "Editar (o haz doble clic en el mensaje)",
+        "message.edited" => "editado",
+        "message.edit" => "Editar",
+        "message.delete" => "Eliminar",
+        "message.delete_confirm" => "¿Eliminar este mensaje?",
+        "message.pin" => "Fijar",
+        "message.unpin" => "Desfijar",
+        "message.pinned" => "Fijado",
+        "message.favorite" => "Guardar en favoritos",
+        "message.unfavorite" => "Quitar de favoritos",
+        "message.report" => "Denunciar",
+        "message.reported" => "Denunciado",
+        "message.hidden" => "Este mensaje ha sido denunciado y ocultado.",
+        "report.title" => "Denunciar este mensaje como",
+        "report.spam" => "Spam",
+        "report.harassment" => "Acoso",
+        "report.inappropriate" => "Contenido inapropiado",
+        "report.send" => "Denunciar",
+        "message.reply" => "Responder",
+        "message.forward" => "Reenviar",
+        "message.forwarded" => "Reenviado de {}",
+        "forward.title" => "Reenviar mensaje",
+        "gif.title" => "Buscar GIF",
+        "gif.placeholder" => "Buscar en Giphy",
+        "gif.hint" => "Escribe algo para encontrar GIF.",
+        "gif.searching" => "Buscando…",
+        "gif.none" => "No se encontraron GIF.",
+        "gif.failed" => "La búsqueda de GIF no está disponible ahora.",
+        "gif.powered_by" => "Con la tecnología de GIPHY",
+        "forward.rooms" => "Salas",
+        "forward.people" => "Personas",
+        "forward.nobody" => "No hay nadie más por aquí.",
+        "forward.send" => "Reenviar",
+        "error.forward_too_long" => "Ese mensaje es demasiado largo para reenviarlo.",
+        "message.copy_hint" => "Copiar el texto",
+        "message.copy" => "Copiar",
+        "message.copied" => "¡Copiado!",
+        "message.copy_failed" => "Error al copiar",
+        "message.loading_preview" => "Cargando vista previa…",
+        "pins.title" => "Mensajes fijados",
+        "pins.count" => "{} fijados",
+        "pins.show" => "Mostrar en la conversación",
+        "file.download" => "Descargar {}",
+        "file.unavailable" => "(ya no disponible)",
+        "status.set" => "Elige tu estado",
+        "status.online" => "En línea",
+        "status.away" => "Ausente",
+        "status.busy" => "Ocupado",
+        "status.offline" => "Desconectado",
+        "typing.one" => "{} está escribiendo…",
+        "typing.two" => "{} y {} están escribiendo…",
+        "typing.many" => "{} personas están escribiendo…",
+        "code.copy_hint" => "Copiar al portapapeles",
+        "code.copy" => "Copiar",
+        "code.copied" => "Copiado",
+        "code.copy_failed" => "Error al copiar",
+        "code.show_less" => "Mostrar menos",
+        "code.show_all" => "Mostrar las {} líneas",
+        "emoji.search" => "Buscar emoji",
+        "emoji.none" => "No se encontró ningún emoji",
+        "emoji.smileys" => "Caras",
+        "emoji.gestures" => "Gestos",
+        "emoji.animals" => "Animales",
+        "emoji.food" => "Comida",
+        "emoji.symbols" => "Símbolos",
+        "login.username" => "Nombre de usuario",
+        "login.token" => "Clave del equipo (opcional)",
+        "login.go" => "¡A chatear!",
+        "profile.title" => "Editar perfil",
+        "profile.name" => "Nombre visible",
+        "settings.title" => "Notificaciones",
+        "settings.hint" => "Para los mensajes que llegan mientras el chat está en segundo plano.",
+        "settings.sound" => "Reproducir un sonido",
+        "settings.desktop" => "Mostrar notificaciones de escritorio",
+        "settings.mentions_only" => "Solo para mensajes que me mencionan",
+        "block.block_user" => "Bloquear usuario",
+        "moderate.kick" => "Expulsar",
+        "moderate.ban_hour" => "Bloquear durante una hora",
+        "moderate.ban_day" => "Bloquear durante un día",
+        "moderate.ban_week" => "Bloquear durante una semana",
+        "block.unblock_user" => "Desbloquear usuario",
+        "block.unblock" => "Desbloquear a {}",
+        "user_card.online_since" => "En línea desde las {}",
+        "user_card.here_before_me" => "Ya estaba aquí cuando llegaste",
+        "user_card.message" => "Mensaje",
+        "user_card.mention" => "Mencionar",
+        "user.local_time" => "Su hora: {} ({})",
+        "sidebar.blocked" => "Bloqueado",
+        "sidebar.favorites" => "Favoritos",
+        "sidebar.no_favorites" => "Marca un mensaje con una estrella para encontrarlo aquí.",
+        "settings.blocked" => "Usuarios bloqueados",
+        "settings.blocked_none" => "No hay nadie bloqueado. Haz clic derecho en un nombre para bloquearlo.",
+        "settings.unblock" => "Desbloquear",
+        "room.title" => "Crear o unirse a una sala",
+        "room.name" => "Nombre de la sala",
+        "room.hint" => "3–32 caracteres: minúsculas, dígitos y guiones.",
+        "room.known" => "Salas",
+        "room.none" => "Ninguna sala coincide.",
+        "room.join" => "Unirse",
+        "room.create" => "Crear",
+        "room.invalid" => "Los nombres de sala tienen 3–32 minúsculas, dígitos o guiones.",
+        "room.exists" => "Ya existe una sala llamada {}.",
+        "room.not_found" => "No hay ninguna sala llamada {}.",
+        "room.description" => "Descripción",
+        "room.description_placeholder" => "Para qué es la sala (opcional)",
+        "room.private" => "Privada",
+        "room.private_hint" => "No aparece en la lista; solo entra quien tenga la invitación.",
+        "room.max_members" => "Máximo de miembros",
+        "room.no_limit" => "Sin límite",
+        "room.max_members_invalid" => "Una sala necesita espacio para al menos {} miembros.",
+        "room.invite" => "Código de invitación",
+        "room.invite_placeholder" => "Para entrar en una sala privada",
+        "room.private_error" => "#{} es privada; necesitas su invitación para entrar.",
+        "room.full" => "#{} está llena.",
+        "room.copy_invite" => "Copiar la invitación a esta sala",
+        "page.not_found" => "404, página no encontrada",
+        _ => return None,
+    })
+}